@@ -0,0 +1,21 @@
+-- Transactional outbox for real-time events
+-- Rows are written in the same transaction as the change they describe and
+-- delivered to Ably by the background dispatcher, in order per channel.
+
+CREATE TABLE IF NOT EXISTS realtime_outbox (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    channel VARCHAR(255) NOT NULL,
+    event_name VARCHAR(64) NOT NULL,
+    payload JSON NOT NULL,
+    -- pending | delivered | dead
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    last_error TEXT,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    delivered_at TIMESTAMP(3) NULL,
+    -- Head-of-channel lookup: WHERE status = 'pending' GROUP BY channel
+    INDEX idx_outbox_status_channel_id (status, channel, id),
+    -- Retention cleanup: WHERE status = 'delivered' AND delivered_at < ?
+    INDEX idx_outbox_status_delivered_at (status, delivered_at)
+);
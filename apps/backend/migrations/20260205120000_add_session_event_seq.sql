@@ -0,0 +1,12 @@
+-- Per-session event sequence numbers
+-- sessions.event_seq is bumped in the same transaction that enqueues an event,
+-- so sequence numbers are gap-free and ordered per session. Outbox rows keep
+-- session_id/seq and double as the replay log for reconnecting clients.
+
+ALTER TABLE sessions ADD COLUMN event_seq BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE realtime_outbox ADD COLUMN session_id VARCHAR(36) NULL;
+ALTER TABLE realtime_outbox ADD COLUMN seq BIGINT NULL;
+
+-- Replay lookups: WHERE session_id = ? AND seq > ? ORDER BY seq
+CREATE INDEX IF NOT EXISTS idx_outbox_session_seq ON realtime_outbox(session_id, seq);
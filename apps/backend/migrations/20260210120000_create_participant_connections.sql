@@ -0,0 +1,17 @@
+-- Attendance: one row per presence interval of a participant on a session channel
+-- Filled from Ably presence webhooks (enter opens an interval, leave closes it).
+-- left_at IS NULL means the participant is still connected.
+
+CREATE TABLE IF NOT EXISTS participant_connections (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    session_id VARCHAR(36) NOT NULL,
+    participant_id VARCHAR(36) NOT NULL,
+    -- Ably connection id; a participant may be connected from several tabs
+    connection_id VARCHAR(64) NOT NULL,
+    entered_at TIMESTAMP(3) NOT NULL,
+    left_at TIMESTAMP(3) NULL,
+    -- Webhook retries re-deliver the same enter message
+    UNIQUE KEY unique_connection_enter (session_id, participant_id, connection_id, entered_at),
+    -- Stats aggregation: WHERE session_id = ? GROUP BY participant_id
+    INDEX idx_connections_session_participant (session_id, participant_id)
+);
@@ -0,0 +1,25 @@
+-- Refresh tokens and server-side session revocation
+-- A token family is one login (device); every access token carries its id
+-- and is rejected once the family is revoked. Refresh tokens rotate on use
+-- and are stored as SHA-256 hashes; presenting a rotated token again
+-- revokes the whole family.
+
+CREATE TABLE IF NOT EXISTS token_families (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    revoked_at TIMESTAMP(3) NULL,
+    -- Log out all devices: WHERE user_id = ? AND revoked_at IS NULL
+    INDEX idx_token_families_user (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id VARCHAR(36) PRIMARY KEY,
+    family_id VARCHAR(36) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    expires_at TIMESTAMP(3) NOT NULL,
+    used_at TIMESTAMP(3) NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    UNIQUE KEY unique_refresh_token_hash (token_hash),
+    INDEX idx_refresh_tokens_family (family_id)
+);
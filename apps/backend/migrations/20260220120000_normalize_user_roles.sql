@@ -0,0 +1,7 @@
+-- Roles are now an enum: student | teacher | admin
+-- "staff" was the teacher role chosen at registration. Any other value
+-- (including self-assigned "admin") is reset to student; grant admins
+-- afterwards with `cargo run --bin grant_admin -- <email>`.
+
+UPDATE users SET role = 'teacher' WHERE role = 'staff';
+UPDATE users SET role = 'student' WHERE role NOT IN ('student', 'teacher');
@@ -0,0 +1,16 @@
+-- Email verification and password reset
+-- user_tokens holds single-use tokens; the client gets "{id}.{hmac}" and the
+-- row is marked used on redemption. Existing accounts start unverified.
+
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP NULL;
+
+CREATE TABLE IF NOT EXISTS user_tokens (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL,
+    -- password_reset | email_verification
+    purpose VARCHAR(32) NOT NULL,
+    expires_at TIMESTAMP(3) NOT NULL,
+    used_at TIMESTAMP(3) NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    INDEX idx_user_tokens_user_purpose (user_id, purpose)
+);
@@ -0,0 +1,14 @@
+-- Single sign-on identities
+-- Links an OpenID Connect provider account (provider id from OIDC_PROVIDERS,
+-- subject = the ID token `sub`) to a local user. One user may have several.
+
+CREATE TABLE IF NOT EXISTS user_identities (
+    provider VARCHAR(64) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    user_id VARCHAR(36) NOT NULL,
+    -- Email the provider reported when the identity was linked
+    email VARCHAR(255) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (provider, subject),
+    INDEX idx_user_identities_user (user_id)
+);
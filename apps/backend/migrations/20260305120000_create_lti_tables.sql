@@ -0,0 +1,51 @@
+-- LTI 1.3 tool provider
+-- lti_platforms holds LMS registrations (one per issuer + client id).
+-- lti_launch_states carries the OIDC login initiation state/nonce to the
+-- launch and is single-use. Resource links remember which session a course
+-- link opens; lti_participants maps an LMS learner to a stable participant id.
+
+CREATE TABLE IF NOT EXISTS lti_platforms (
+    id VARCHAR(36) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    issuer VARCHAR(255) NOT NULL,
+    client_id VARCHAR(255) NOT NULL,
+    -- NULL accepts launches from any deployment of this client
+    deployment_id VARCHAR(255) NULL,
+    auth_login_url VARCHAR(1024) NOT NULL,
+    auth_token_url VARCHAR(1024) NOT NULL,
+    jwks_url VARCHAR(1024) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_lti_platform_client (issuer, client_id)
+);
+
+CREATE TABLE IF NOT EXISTS lti_launch_states (
+    state VARCHAR(64) PRIMARY KEY,
+    platform_id VARCHAR(36) NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMP(3) NOT NULL,
+    used_at TIMESTAMP(3) NULL,
+    -- Cleanup of stale states
+    INDEX idx_lti_launch_states_expires (expires_at)
+);
+
+CREATE TABLE IF NOT EXISTS lti_resource_links (
+    platform_id VARCHAR(36) NOT NULL,
+    resource_link_id VARCHAR(255) NOT NULL,
+    deployment_id VARCHAR(255) NOT NULL,
+    context_id VARCHAR(255) NULL,
+    session_id VARCHAR(36) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (platform_id, resource_link_id),
+    INDEX idx_lti_resource_links_session (session_id)
+);
+
+CREATE TABLE IF NOT EXISTS lti_participants (
+    session_id VARCHAR(36) NOT NULL,
+    platform_id VARCHAR(36) NOT NULL,
+    -- LMS user id (`sub` of the launch)
+    subject VARCHAR(255) NOT NULL,
+    participant_id VARCHAR(36) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (session_id, platform_id, subject),
+    UNIQUE KEY unique_lti_participant (session_id, participant_id)
+);
@@ -0,0 +1,35 @@
+-- LTI Assignment and Grade Services (AGS) passback
+-- Resource links remember the platform's line item service from the launch
+-- and the line item created for their session. lti_participants records the
+-- course link a learner last launched from, and lti_grade_sync queues one
+-- score per LMS learner when a session is stopped.
+
+ALTER TABLE lti_resource_links
+    -- Line items container of the course (AGS endpoint claim `lineitems`)
+    ADD COLUMN ags_lineitems_url VARCHAR(1024) NULL,
+    -- Gradebook column of the session; from the launch or created on first sync
+    ADD COLUMN line_item_url VARCHAR(1024) NULL;
+
+ALTER TABLE lti_participants
+    ADD COLUMN resource_link_id VARCHAR(255) NULL;
+
+CREATE TABLE IF NOT EXISTS lti_grade_sync (
+    session_id VARCHAR(36) NOT NULL,
+    participant_id VARCHAR(36) NOT NULL,
+    platform_id VARCHAR(36) NOT NULL,
+    resource_link_id VARCHAR(255) NOT NULL,
+    -- LMS user id the score is posted for
+    subject VARCHAR(255) NOT NULL,
+    score_given DOUBLE NOT NULL,
+    score_maximum DOUBLE NOT NULL,
+    -- pending -> synced, or failed once attempts are exhausted
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT NULL,
+    next_attempt_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    synced_at TIMESTAMP(3) NULL,
+    updated_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3) ON UPDATE CURRENT_TIMESTAMP(3),
+    PRIMARY KEY (session_id, participant_id),
+    -- Dispatcher polling for due rows
+    INDEX idx_lti_grade_sync_due (status, next_attempt_at)
+);
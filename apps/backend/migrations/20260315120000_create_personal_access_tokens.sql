@@ -0,0 +1,21 @@
+-- Personal access tokens for scripts and integrations
+-- Sent as `Authorization: Bearer ccp_...`. Only the SHA-256 hash is stored;
+-- the prefix is kept so users can tell their tokens apart. Scopes are a
+-- space-separated list (e.g. "sessions:read stats:read").
+
+CREATE TABLE IF NOT EXISTS personal_access_tokens (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    token_prefix VARCHAR(16) NOT NULL,
+    scopes VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    last_used_at TIMESTAMP(3) NULL,
+    -- NULL never expires
+    expires_at TIMESTAMP(3) NULL,
+    revoked_at TIMESTAMP(3) NULL,
+    UNIQUE KEY unique_personal_access_token_hash (token_hash),
+    -- Token list of a user
+    INDEX idx_personal_access_tokens_user (user_id)
+);
@@ -0,0 +1,14 @@
+-- Per-account login throttling
+-- One row per login email (registered or not, so lockouts do not reveal
+-- which emails exist). Every attempt is counted before the password is
+-- checked; a successful login or a password reset deletes the row.
+
+CREATE TABLE IF NOT EXISTS login_attempts (
+    -- Lowercased email as typed at login
+    email VARCHAR(255) PRIMARY KEY,
+    failed_count INT NOT NULL DEFAULT 0,
+    last_attempt_at TIMESTAMP(3) NOT NULL,
+    locked_until TIMESTAMP(3) NULL,
+    -- Purge of stale rows
+    INDEX idx_login_attempts_last_attempt (last_attempt_at)
+);
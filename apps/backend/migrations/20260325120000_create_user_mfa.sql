@@ -0,0 +1,23 @@
+-- TOTP two-factor authentication
+-- user_mfa holds the authenticator secret (base32). A row without enabled_at
+-- is an enrollment waiting for its first code. last_used_step is the TOTP
+-- time step of the last accepted code, so every code works only once.
+-- Recovery codes are single-use and stored as SHA-256 hashes.
+
+CREATE TABLE IF NOT EXISTS user_mfa (
+    user_id VARCHAR(36) PRIMARY KEY,
+    totp_secret VARCHAR(64) NOT NULL,
+    enabled_at TIMESTAMP(3) NULL,
+    last_used_step BIGINT NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
+);
+
+CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP(3) NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    -- Redemption: WHERE user_id = ? AND code_hash = ? AND used_at IS NULL
+    INDEX idx_mfa_recovery_codes_user (user_id, code_hash)
+);
@@ -0,0 +1,30 @@
+-- Session collaborators
+-- session_members grants other accounts a role on a session: owner, editor,
+-- presenter or viewer. The creator is always an owner and needs no row.
+-- session_invites are pending invitations by email; only the SHA-256 hash
+-- of the emailed token is stored, and accepting one creates the membership.
+
+CREATE TABLE IF NOT EXISTS session_members (
+    session_id VARCHAR(36) NOT NULL,
+    user_id VARCHAR(36) NOT NULL,
+    role VARCHAR(16) NOT NULL,
+    invited_by VARCHAR(36) NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    PRIMARY KEY (session_id, user_id),
+    -- Sessions shared with a user
+    INDEX idx_session_members_user (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS session_invites (
+    id VARCHAR(36) PRIMARY KEY,
+    session_id VARCHAR(36) NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    role VARCHAR(16) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    invited_by VARCHAR(36) NOT NULL,
+    expires_at TIMESTAMP(3) NOT NULL,
+    accepted_at TIMESTAMP(3) NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    UNIQUE KEY unique_session_invite_token_hash (token_hash),
+    INDEX idx_session_invites_session (session_id)
+);
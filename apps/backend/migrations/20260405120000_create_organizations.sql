@@ -0,0 +1,28 @@
+-- Organizations (course teams and departments)
+-- Members see and reuse the organization's sessions; admins manage members
+-- and can hand sessions over to another member. A session belongs to an
+-- organization when sessions.organization_id is set; creator_id stays the
+-- instructor who owns it day to day.
+
+CREATE TABLE IF NOT EXISTS organizations (
+    id VARCHAR(36) PRIMARY KEY,
+    name VARCHAR(200) NOT NULL,
+    created_by VARCHAR(36) NOT NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
+);
+
+CREATE TABLE IF NOT EXISTS organization_members (
+    organization_id VARCHAR(36) NOT NULL,
+    user_id VARCHAR(36) NOT NULL,
+    -- admin or member
+    role VARCHAR(16) NOT NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    PRIMARY KEY (organization_id, user_id),
+    -- Organizations of a user
+    INDEX idx_organization_members_user (user_id)
+);
+
+ALTER TABLE sessions ADD COLUMN organization_id VARCHAR(36) NULL;
+
+-- Organization session library: WHERE organization_id = ?
+CREATE INDEX IF NOT EXISTS idx_sessions_organization ON sessions(organization_id);
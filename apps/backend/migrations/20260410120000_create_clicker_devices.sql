@@ -0,0 +1,23 @@
+-- Paired clicker devices
+-- A presenter starts a pairing, which stores the hash of a short code shown
+-- on their screen (as text and as a link for a QR scanner). The phone trades
+-- the code for a clicker token scoped to that one session; only hashes of
+-- the code and the token are stored. Revoking a device or letting it expire
+-- stops its token from changing the live slide.
+
+CREATE TABLE IF NOT EXISTS clicker_devices (
+    id VARCHAR(36) PRIMARY KEY,
+    session_id VARCHAR(36) NOT NULL,
+    created_by VARCHAR(36) NOT NULL,
+    pairing_code_hash CHAR(64) NULL,
+    pairing_expires_at TIMESTAMP(3) NOT NULL,
+    token_hash CHAR(64) NULL,
+    paired_at TIMESTAMP(3) NULL,
+    expires_at TIMESTAMP(3) NULL,
+    last_used_at TIMESTAMP(3) NULL,
+    revoked_at TIMESTAMP(3) NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    UNIQUE KEY unique_clicker_token_hash (token_hash),
+    INDEX idx_clicker_devices_pairing_code (pairing_code_hash),
+    INDEX idx_clicker_devices_session (session_id)
+);
@@ -0,0 +1,11 @@
+-- Numeric join codes
+-- A live session gets a 6-8 digit code that students type in to join. The
+-- code is set when the session goes live and cleared when it stops, so the
+-- unique key only has to hold among live sessions (NULLs do not collide).
+-- Sessions that are live during this migration get a code on their next
+-- go-live.
+
+ALTER TABLE sessions ADD COLUMN join_code VARCHAR(8) NULL;
+
+-- Join code lookup: WHERE join_code = ?
+CREATE UNIQUE INDEX unique_sessions_join_code ON sessions(join_code);
@@ -0,0 +1,19 @@
+-- Organization audit log
+-- Records changes to who owns an organization's sessions: sessions moved
+-- into or out of the library and transfers between members by an admin.
+
+CREATE TABLE IF NOT EXISTS organization_audit_log (
+    id VARCHAR(36) PRIMARY KEY,
+    organization_id VARCHAR(36) NOT NULL,
+    -- User who made the change
+    actor_id VARCHAR(36) NOT NULL,
+    -- session_added, session_removed or session_transferred
+    action VARCHAR(32) NOT NULL,
+    session_id VARCHAR(36) NOT NULL,
+    -- Previous and new owner of a transferred session
+    from_user_id VARCHAR(36) NULL,
+    to_user_id VARCHAR(36) NULL,
+    created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    -- Log of an organization, newest first
+    INDEX idx_organization_audit_log_org (organization_id, created_at)
+);
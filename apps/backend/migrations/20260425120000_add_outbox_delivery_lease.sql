@@ -0,0 +1,6 @@
+-- Delivery leases for the real-time outbox
+-- A dispatcher claims the rows it is about to publish until lease_expires_at,
+-- so several backend instances never deliver the same row at once. A lease
+-- left by a crashed instance simply runs out.
+
+ALTER TABLE realtime_outbox ADD COLUMN lease_expires_at TIMESTAMP(3) NULL;
@@ -0,0 +1,6 @@
+-- Claims on queued grade passback
+-- The instance posting a score holds the row until lease_expires_at, so the
+-- gradebook never receives it from two instances at once. Rows of a crashed
+-- instance become due again once the lease runs out.
+
+ALTER TABLE lti_grade_sync ADD COLUMN lease_expires_at TIMESTAMP(3) NULL;
@@ -0,0 +1,12 @@
+-- Join code lookups that found no live session, per client IP
+-- Whole lecture halls join from behind one campus NAT, so successful
+-- lookups are never limited; only a client that keeps missing is made to
+-- wait, which is what guessing the codes of live sessions looks like.
+
+CREATE TABLE IF NOT EXISTS join_code_misses (
+    client_ip VARCHAR(45) PRIMARY KEY,
+    miss_count INT NOT NULL DEFAULT 0,
+    window_started_at TIMESTAMP(3) NOT NULL,
+    -- Purge of stale rows
+    INDEX idx_join_code_misses_window (window_started_at)
+);
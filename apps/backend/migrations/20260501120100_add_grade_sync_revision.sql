@@ -0,0 +1,7 @@
+-- Revision of a queued score
+-- Stopping a session again re-queues its scores while a dispatcher may be
+-- posting the previous ones. Each re-queue bumps the revision, and the
+-- dispatcher only records the outcome of the revision it posted, so a newer
+-- score is never marked synced by an older post.
+
+ALTER TABLE lti_grade_sync ADD COLUMN revision INT NOT NULL DEFAULT 0;
@@ -0,0 +1,35 @@
+//! Grant the admin role to an existing user (bootstraps the first admin)
+//! Run with: cargo run --bin grant_admin -- user@example.com
+
+use sqlx::mysql::MySqlPoolOptions;
+use dotenvy::dotenv;
+use std::env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    let email = env::args().nth(1).expect("Usage: grant_admin <email>");
+    let database_url = env::var("DATABASE_URL")
+        .expect("DATABASE_URL must be set");
+
+    println!("Connecting to database...");
+
+    let pool = MySqlPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await?;
+
+    let result = sqlx::query("UPDATE users SET role = 'admin' WHERE email = ?")
+        .bind(&email)
+        .execute(&pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        println!("❌ No user with email {} (or already an admin)", email);
+    } else {
+        println!("✅ {} is now an admin; the change applies from their next login or token refresh", email);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,442 @@
+//! Minimal LTI 1.3 platform for testing the tool locally (no LMS needed)
+//! Run with: cargo run --bin mock_lti_platform
+//!
+//! Open http://localhost:9001, register the platform with the printed admin
+//! request, then use "Add a session" (deep linking) and the launch buttons.
+//! The platform signs with an ES256 key generated at startup. Launches offer
+//! the Assignment and Grade Services; scores the tool posts back after the
+//! session is stopped show up on the home page.
+
+use axum::{
+    extract::{Form, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Html,
+    routing::{get, post},
+    Json, Router,
+};
+use dotenvy::dotenv;
+use jsonwebtoken::{decode, decode_header, encode, jwk::JwkSet, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::env;
+use std::sync::{Arc, Mutex};
+
+const CLIENT_ID: &str = "classcolab-mock";
+const DEPLOYMENT_ID: &str = "mock-deployment";
+const KEY_ID: &str = "mock-platform-1";
+const LEARNERS: &[&str] = &["Grace Hopper", "Alan Turing", "Katherine Johnson"];
+const SCOPE_LINEITEM: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/lineitem";
+const SCOPE_SCORE: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/score";
+
+struct Platform {
+    issuer: String,
+    tool_url: String,
+    instructor_email: String,
+    encoding_key: EncodingKey,
+    jwk: Value,
+    /// Course link created by the last deep linking response: (resource link id, custom parameters)
+    course_link: Mutex<Option<(String, Value)>>,
+    /// Bearer tokens issued to the tool for the grade services
+    access_tokens: Mutex<HashSet<String>>,
+    /// Gradebook columns created by the tool
+    line_items: Mutex<Vec<Value>>,
+    /// Scores received: (line item id, user id, score given, score maximum)
+    scores: Mutex<Vec<(String, String, f64, f64)>>,
+}
+
+type Shared = Arc<Platform>;
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn login_url(platform: &Platform, login_hint: &str, message_hint: &str) -> String {
+    format!(
+        "{}/api/lti/login?iss={}&login_hint={}&lti_message_hint={}&client_id={}&lti_deployment_id={}&target_link_uri={}",
+        platform.tool_url,
+        urlencoding::encode(&platform.issuer),
+        urlencoding::encode(login_hint),
+        urlencoding::encode(message_hint),
+        CLIENT_ID,
+        DEPLOYMENT_ID,
+        urlencoding::encode(&format!("{}/api/lti/launch", platform.tool_url)),
+    )
+}
+
+async fn home(State(platform): State<Shared>) -> Html<String> {
+    let registration = json!({
+        "name": "Mock LMS",
+        "issuer": platform.issuer,
+        "clientId": CLIENT_ID,
+        "deploymentId": DEPLOYMENT_ID,
+        "authLoginUrl": format!("{}/auth", platform.issuer),
+        "authTokenUrl": format!("{}/token", platform.issuer),
+        "jwksUrl": format!("{}/jwks", platform.issuer),
+    });
+
+    let link = platform.course_link.lock().unwrap().clone();
+    let launches = match &link {
+        Some((link_id, custom)) => {
+            let mut items = format!(
+                "<p>Course link <code>{}</code> opens session <code>{}</code></p><ul><li><a href=\"{}\">Launch as instructor</a></li>",
+                escape(link_id), escape(custom["session_id"].as_str().unwrap_or_default()), escape(&login_url(&platform, "instructor", "launch"))
+            );
+            for (i, name) in LEARNERS.iter().enumerate() {
+                items.push_str(&format!(
+                    "<li><a href=\"{}\">Launch as {}</a></li>",
+                    escape(&login_url(&platform, &format!("learner-{}", i + 1), "launch")),
+                    escape(name)
+                ));
+            }
+            items + "</ul>"
+        }
+        None => "<p>No course link yet. Add a session first.</p>".to_string(),
+    };
+
+    let line_items = platform.line_items.lock().unwrap().clone();
+    let scores = platform.scores.lock().unwrap().clone();
+    let mut gradebook = String::new();
+    for item in &line_items {
+        let id = item["id"].as_str().unwrap_or_default();
+        gradebook.push_str(&format!("<h3>{}</h3><ul>", escape(item["label"].as_str().unwrap_or("Untitled"))));
+        for (_, user, given, maximum) in scores.iter().filter(|(line_item, ..)| line_item == id) {
+            gradebook.push_str(&format!("<li>{}: {} / {}</li>", escape(user), given, maximum));
+        }
+        gradebook.push_str("</ul>");
+    }
+    if gradebook.is_empty() {
+        gradebook = "<p>No grades yet. Stop the session in ClassColab after learners answered a quiz.</p>".to_string();
+    }
+
+    Html(format!(
+        r#"<!doctype html><html><body style="font-family: sans-serif; max-width: 760px; margin: 2rem auto">
+<h1>Mock LTI 1.3 platform</h1>
+<h2>1. Register</h2>
+<p>As an admin of the tool:</p>
+<pre>curl -X POST {tool}/api/admin/lti/platforms \
+  -H "Authorization: Bearer $ADMIN_TOKEN" -H "Content-Type: application/json" \
+  -d '{registration}'</pre>
+<h2>2. Add a session (deep linking)</h2>
+<p><a href="{deep_link}">Add a ClassColab session as instructor ({email})</a></p>
+<h2>3. Launch</h2>
+{launches}
+<h2>4. Gradebook</h2>
+{gradebook}
+</body></html>"#,
+        tool = escape(&platform.tool_url),
+        registration = escape(&registration.to_string()),
+        deep_link = escape(&login_url(&platform, "instructor", "deep-link")),
+        email = escape(&platform.instructor_email),
+        launches = launches,
+        gradebook = gradebook,
+    ))
+}
+
+async fn jwks(State(platform): State<Shared>) -> Json<Value> {
+    Json(json!({ "keys": [platform.jwk] }))
+}
+
+#[derive(Deserialize)]
+struct AuthRequest {
+    client_id: String,
+    redirect_uri: String,
+    login_hint: String,
+    state: String,
+    nonce: String,
+    lti_message_hint: Option<String>,
+}
+
+/// OIDC authentication endpoint: answers with an auto-submitting form that
+/// posts the signed launch to the tool
+async fn auth(State(platform): State<Shared>, Query(request): Query<AuthRequest>) -> Html<String> {
+    let expected_redirect = format!("{}/api/lti/launch", platform.tool_url);
+    if request.client_id != CLIENT_ID || request.redirect_uri != expected_redirect {
+        return Html(format!("<p>Rejected: unknown client or redirect URI {}</p>", escape(&request.redirect_uri)));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let (sub, name, email, roles) = match request.login_hint.as_str() {
+        "instructor" => (
+            "mock-instructor".to_string(),
+            "Ada Instructor".to_string(),
+            platform.instructor_email.clone(),
+            vec!["http://purl.imsglobal.org/vocab/lis/v2/membership#Instructor"],
+        ),
+        hint => {
+            let index: usize = hint.trim_start_matches("learner-").parse().unwrap_or(1);
+            let name = LEARNERS.get(index.saturating_sub(1)).copied().unwrap_or("Mock Learner");
+            (
+                format!("mock-learner-{}", index),
+                name.to_string(),
+                format!("learner{}@example.com", index),
+                vec!["http://purl.imsglobal.org/vocab/lis/v2/membership#Learner"],
+            )
+        }
+    };
+
+    let mut claims = json!({
+        "iss": platform.issuer,
+        "aud": CLIENT_ID,
+        "sub": sub,
+        "iat": now,
+        "exp": now + 300,
+        "nonce": request.nonce,
+        "name": name,
+        "email": email,
+        "email_verified": true,
+        "https://purl.imsglobal.org/spec/lti/claim/version": "1.3.0",
+        "https://purl.imsglobal.org/spec/lti/claim/deployment_id": DEPLOYMENT_ID,
+        "https://purl.imsglobal.org/spec/lti/claim/roles": roles,
+        "https://purl.imsglobal.org/spec/lti/claim/context": { "id": "mock-course", "title": "Mock Course 101" },
+    });
+
+    if request.lti_message_hint.as_deref() == Some("deep-link") {
+        claims["https://purl.imsglobal.org/spec/lti/claim/message_type"] = json!("LtiDeepLinkingRequest");
+        claims["https://purl.imsglobal.org/spec/lti-dl/claim/deep_linking_settings"] = json!({
+            "deep_link_return_url": format!("{}/deep-link-return", platform.issuer),
+            "accept_types": ["ltiResourceLink"],
+            "accept_presentation_document_targets": ["iframe", "window"],
+            "data": "mock-opaque-data",
+        });
+    } else {
+        let Some((link_id, custom)) = platform.course_link.lock().unwrap().clone() else {
+            return Html("<p>No course link yet. Add a session first.</p>".to_string());
+        };
+        claims["https://purl.imsglobal.org/spec/lti/claim/message_type"] = json!("LtiResourceLinkRequest");
+        claims["https://purl.imsglobal.org/spec/lti/claim/target_link_uri"] = json!(expected_redirect);
+        claims["https://purl.imsglobal.org/spec/lti/claim/resource_link"] = json!({ "id": link_id });
+        claims["https://purl.imsglobal.org/spec/lti/claim/custom"] = custom;
+        claims["https://purl.imsglobal.org/spec/lti-ags/claim/endpoint"] = json!({
+            "scope": [SCOPE_LINEITEM, SCOPE_SCORE],
+            "lineitems": format!("{}/lineitems", platform.issuer),
+        });
+    }
+
+    let mut header = Header::new(Algorithm::ES256);
+    header.kid = Some(KEY_ID.to_string());
+    let id_token = encode(&header, &claims, &platform.encoding_key).expect("sign id_token");
+
+    Html(format!(
+        r#"<!doctype html><html><body onload="document.forms[0].submit()">
+<form method="post" action="{action}">
+<input type="hidden" name="id_token" value="{id_token}">
+<input type="hidden" name="state" value="{state}">
+<noscript><button type="submit">Continue</button></noscript>
+</form></body></html>"#,
+        action = escape(&request.redirect_uri),
+        id_token = escape(&id_token),
+        state = escape(&request.state),
+    ))
+}
+
+#[derive(Deserialize)]
+struct DeepLinkReturn {
+    #[serde(rename = "JWT")]
+    jwt: String,
+}
+
+/// Verify a JWT signed by the tool against its published JWKS
+async fn verify_tool_jwt(platform: &Platform, jwt: &str, audience: &str) -> Result<Value, String> {
+    let keys: JwkSet = reqwest::get(format!("{}/api/lti/jwks", platform.tool_url))
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    let header = decode_header(jwt).map_err(|e| e.to_string())?;
+    let jwk = header
+        .kid
+        .as_deref()
+        .and_then(|kid| keys.find(kid))
+        .ok_or("Tool key not found in its JWKS")?;
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| e.to_string())?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[CLIENT_ID]);
+    validation.set_audience(&[audience]);
+    Ok(decode::<Value>(jwt, &key, &validation).map_err(|e| e.to_string())?.claims)
+}
+
+/// Deep linking return URL: verify the tool's response and create the course link
+async fn deep_link_return(State(platform): State<Shared>, Form(form): Form<DeepLinkReturn>) -> Html<String> {
+    let result: Result<(String, Value), String> = async {
+        let claims = verify_tool_jwt(&platform, &form.jwt, &platform.issuer).await?;
+
+        // Launches of the course link send back all custom parameters of the item
+        let custom = &claims["https://purl.imsglobal.org/spec/lti-dl/claim/content_items"][0]["custom"];
+        custom["session_id"]
+            .as_str()
+            .map(|session_id| (session_id.to_string(), custom.clone()))
+            .ok_or_else(|| "Response has no session".to_string())
+    }
+    .await;
+
+    match result {
+        Ok((session_id, custom)) => {
+            let link_id = format!("mock-link-{}", uuid::Uuid::new_v4());
+            *platform.course_link.lock().unwrap() = Some((link_id, custom));
+            Html(format!("<p>Linked session <code>{}</code>. <a href=\"/\">Back</a></p>", escape(&session_id)))
+        }
+        Err(e) => Html(format!("<p>Deep linking response rejected: {}</p>", escape(&e))),
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    grant_type: String,
+    client_assertion: String,
+    scope: String,
+}
+
+/// OAuth token endpoint: client credentials with the tool's signed assertion
+async fn token(
+    State(platform): State<Shared>,
+    Form(request): Form<TokenRequest>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    if request.grant_type != "client_credentials" {
+        return Err((StatusCode::BAD_REQUEST, "unsupported_grant_type".to_string()));
+    }
+    verify_tool_jwt(&platform, &request.client_assertion, &format!("{}/token", platform.issuer))
+        .await
+        .map_err(|e| (StatusCode::UNAUTHORIZED, e))?;
+
+    let access_token = uuid::Uuid::new_v4().to_string();
+    platform.access_tokens.lock().unwrap().insert(access_token.clone());
+    Ok(Json(json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": 3600,
+        "scope": request.scope,
+    })))
+}
+
+fn authorize(platform: &Platform, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match token {
+        Some(token) if platform.access_tokens.lock().unwrap().contains(token) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "Invalid access token".to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct LineItemFilter {
+    resource_link_id: Option<String>,
+    resource_id: Option<String>,
+}
+
+async fn list_line_items(
+    State(platform): State<Shared>,
+    headers: HeaderMap,
+    Query(filter): Query<LineItemFilter>,
+) -> Result<Json<Vec<Value>>, (StatusCode, String)> {
+    authorize(&platform, &headers)?;
+    let matches = |item: &Value, key: &str, wanted: &Option<String>| {
+        wanted.as_deref().is_none_or(|wanted| item[key].as_str() == Some(wanted))
+    };
+    let items = platform
+        .line_items
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|item| matches(item, "resourceLinkId", &filter.resource_link_id))
+        .filter(|item| matches(item, "resourceId", &filter.resource_id))
+        .cloned()
+        .collect();
+    Ok(Json(items))
+}
+
+async fn create_line_item(
+    State(platform): State<Shared>,
+    headers: HeaderMap,
+    Json(mut item): Json<Value>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    authorize(&platform, &headers)?;
+    let mut items = platform.line_items.lock().unwrap();
+    item["id"] = json!(format!("{}/lineitems/{}", platform.issuer, items.len() + 1));
+    items.push(item.clone());
+    Ok(Json(item))
+}
+
+async fn post_score(
+    State(platform): State<Shared>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(score): Json<Value>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&platform, &headers)?;
+    let line_item = format!("{}/lineitems/{}", platform.issuer, id);
+    if !platform.line_items.lock().unwrap().iter().any(|item| item["id"].as_str() == Some(line_item.as_str())) {
+        return Err((StatusCode::NOT_FOUND, "Unknown line item".to_string()));
+    }
+
+    let user = score["userId"].as_str().unwrap_or_default().to_string();
+    let given = score["scoreGiven"].as_f64().unwrap_or(0.0);
+    let maximum = score["scoreMaximum"].as_f64().unwrap_or(0.0);
+    let mut scores = platform.scores.lock().unwrap();
+    // The latest score of a user replaces earlier ones
+    scores.retain(|(item, existing, ..)| !(item == &line_item && existing == &user));
+    scores.push((line_item, user, given, maximum));
+    Ok(StatusCode::OK)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    let port: u16 = env::var("MOCK_LTI_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(9001);
+    let tool_url = env::var("PUBLIC_API_URL")
+        .unwrap_or_else(|_| format!("http://localhost:{}", env::var("PORT").unwrap_or_else(|_| "8080".to_string())))
+        .trim_end_matches('/')
+        .to_string();
+    let instructor_email = env::var("MOCK_LTI_INSTRUCTOR_EMAIL").unwrap_or_else(|_| "instructor@example.com".to_string());
+
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).map_err(|e| e.to_string())?;
+    let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+        .map_err(|e| e.to_string())?;
+    // Uncompressed point: 0x04 || x || y
+    let point = key_pair.public_key().as_ref();
+    let b64 = |bytes: &[u8]| base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, bytes);
+    let jwk = json!({
+        "kty": "EC",
+        "crv": "P-256",
+        "alg": "ES256",
+        "use": "sig",
+        "kid": KEY_ID,
+        "x": b64(&point[1..33]),
+        "y": b64(&point[33..65]),
+    });
+
+    let platform = Arc::new(Platform {
+        issuer: format!("http://localhost:{}", port),
+        tool_url,
+        instructor_email,
+        encoding_key: EncodingKey::from_ec_der(pkcs8.as_ref()),
+        jwk,
+        course_link: Mutex::new(None),
+        access_tokens: Mutex::new(HashSet::new()),
+        line_items: Mutex::new(Vec::new()),
+        scores: Mutex::new(Vec::new()),
+    });
+
+    let app = Router::new()
+        .route("/", get(home))
+        .route("/jwks", get(jwks))
+        .route("/auth", get(auth))
+        .route("/deep-link-return", post(deep_link_return))
+        .route("/token", post(token))
+        .route("/lineitems", get(list_line_items).post(create_line_item))
+        .route("/lineitems/:id/scores", post(post_score))
+        .with_state(platform.clone());
+
+    println!("Mock LTI platform on {} (tool at {})", platform.issuer, platform.tool_url);
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
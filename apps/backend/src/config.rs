@@ -1,6 +1,46 @@
 use std::env;
 use dotenvy::dotenv;
 
+/// An OpenID Connect identity provider, configured as `OIDC_{ID}_*`
+#[derive(Debug, Clone)]
+pub struct OidcProviderConfig {
+    /// Lowercase id used in URLs (`/api/auth/oidc/{id}/login`)
+    pub id: String,
+    pub display_name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: String,
+    /// Role given to accounts created on first sign-in (never admin)
+    pub default_role: crate::models::user::Role,
+}
+
+impl OidcProviderConfig {
+    fn from_env(id: &str) -> Self {
+        let prefix = format!("OIDC_{}_", id.to_ascii_uppercase());
+        let var = |name: &str| env::var(format!("{}{}", prefix, name)).ok().filter(|v| !v.is_empty());
+        let required = |name: &str| var(name).unwrap_or_else(|| panic!("{}{} must be set", prefix, name));
+
+        let default_role = var("DEFAULT_ROLE")
+            .map(|r| {
+                crate::models::user::Role::parse(&r)
+                    .filter(|role| *role != crate::models::user::Role::Admin)
+                    .unwrap_or_else(|| panic!("{}DEFAULT_ROLE must be student or teacher", prefix))
+            })
+            .unwrap_or(crate::models::user::Role::Student);
+
+        Self {
+            id: id.to_ascii_lowercase(),
+            display_name: var("DISPLAY_NAME").unwrap_or_else(|| id.to_string()),
+            issuer: required("ISSUER").trim_end_matches('/').to_string(),
+            client_id: required("CLIENT_ID"),
+            client_secret: var("CLIENT_SECRET"),
+            scopes: var("SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+            default_role,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
@@ -8,6 +48,37 @@ pub struct Config {
     pub port: u16,
     pub allowed_origins: Vec<String>,
     pub environment: String,
+    pub ably_api_key: Option<String>,
+    pub ably_rest_url: String,
+    pub realtime_backend: String,
+    pub sse_max_subscribers_per_session: usize,
+    pub sse_poll_interval_ms: u64,
+    pub outbox_max_attempts: i32,
+    pub outbox_poll_interval_ms: u64,
+    pub outbox_retention_hours: u64,
+    pub event_log_capacity: i64,
+    pub vote_coalesce_window_ms: u64,
+    pub qa_snapshot_events: bool,
+    pub access_token_ttl_minutes: i64,
+    pub refresh_token_ttl_days: i64,
+    /// (kid, private key PEM); the first one signs access tokens
+    pub jwt_signing_keys: Vec<(String, String)>,
+    pub login_delay_after_failures: i32,
+    pub login_lockout_threshold: i32,
+    pub login_lockout_minutes: i64,
+    pub self_register_roles: Vec<crate::models::user::Role>,
+    pub app_base_url: String,
+    pub mail_backend: String,
+    pub smtp_url: Option<String>,
+    pub mail_from: String,
+    pub mail_file_path: String,
+    pub require_verified_email_to_go_live: bool,
+    pub public_api_url: String,
+    pub oidc_providers: Vec<OidcProviderConfig>,
+    pub lti_private_key: Option<String>,
+    pub lti_key_id: String,
+    pub grade_sync_max_attempts: i32,
+    pub grade_sync_poll_interval_ms: u64,
 }
 
 impl Config {
@@ -16,12 +87,12 @@ impl Config {
 
         let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-        let port = env::var("PORT")
+        let port: u16 = env::var("PORT")
             .unwrap_or_else(|_| "8080".to_string())
             .parse()
             .expect("PORT must be a number");
         
-        let allowed_origins = env::var("ALLOWED_ORIGINS")
+        let allowed_origins: Vec<String> = env::var("ALLOWED_ORIGINS")
             .unwrap_or_else(|_| "http://localhost:3000".to_string())
             .split(',')
             .map(|s| s.trim().to_string())
@@ -30,16 +101,243 @@ impl Config {
         let environment = env::var("ENVIRONMENT")
             .unwrap_or_else(|_| "development".to_string());
 
+        let ably_api_key = env::var("ABLY_API_KEY").ok().filter(|k| !k.is_empty());
+        let ably_rest_url = env::var("ABLY_REST_URL")
+            .unwrap_or_else(|_| crate::services::ably::DEFAULT_ABLY_REST_URL.to_string());
+
+        // Real-time backend: ably | memory | noop
+        let realtime_backend = env::var("REALTIME_BACKEND")
+            .unwrap_or_else(|_| "ably".to_string());
+
+        let sse_max_subscribers_per_session = env::var("SSE_MAX_SUBSCRIBERS_PER_SESSION")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse()
+            .expect("SSE_MAX_SUBSCRIBERS_PER_SESSION must be a number");
+
+        // How often SSE streams pick up events committed on other instances
+        let sse_poll_interval_ms = env::var("SSE_POLL_INTERVAL_MS")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse()
+            .expect("SSE_POLL_INTERVAL_MS must be a number");
+
+        // Real-time outbox delivery
+        let outbox_max_attempts = env::var("OUTBOX_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse()
+            .expect("OUTBOX_MAX_ATTEMPTS must be a number");
+
+        let outbox_poll_interval_ms = env::var("OUTBOX_POLL_INTERVAL_MS")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse()
+            .expect("OUTBOX_POLL_INTERVAL_MS must be a number");
+
+        let outbox_retention_hours = env::var("OUTBOX_RETENTION_HOURS")
+            .unwrap_or_else(|_| "24".to_string())
+            .parse()
+            .expect("OUTBOX_RETENTION_HOURS must be a number");
+
+        // Events per session that reconnecting clients can catch up on
+        let event_log_capacity = env::var("EVENT_LOG_CAPACITY")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse()
+            .expect("EVENT_LOG_CAPACITY must be a number");
+
+        // Votes on a slide within this window share one VOTE_UPDATE
+        let vote_coalesce_window_ms = env::var("VOTE_COALESCE_WINDOW_MS")
+            .unwrap_or_else(|_| "250".to_string())
+            .parse()
+            .expect("VOTE_COALESCE_WINDOW_MS must be a number");
+
+        // Also publish the full question list (QA_UPDATE) for older clients
+        let qa_snapshot_events = env::var("QA_SNAPSHOT_EVENTS")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        // Access tokens are short-lived; refresh tokens rotate on every use
+        let access_token_ttl_minutes = env::var("ACCESS_TOKEN_TTL_MINUTES")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse()
+            .expect("ACCESS_TOKEN_TTL_MINUTES must be a number");
+
+        let refresh_token_ttl_days = env::var("REFRESH_TOKEN_TTL_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .expect("REFRESH_TOKEN_TTL_DAYS must be a number");
+
+        // Access token signing keys as kid=path pairs, signing key first;
+        // without any, access tokens fall back to HS256 with JWT_SECRET
+        let jwt_signing_keys = env::var("JWT_SIGNING_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (kid, path) = entry
+                    .split_once('=')
+                    .unwrap_or_else(|| panic!("JWT_SIGNING_KEYS entry {} must be kid=path", entry));
+                let pem = std::fs::read_to_string(path.trim())
+                    .unwrap_or_else(|e| panic!("Cannot read JWT signing key {}: {}", path, e));
+                (kid.trim().to_string(), pem)
+            })
+            .collect();
+
+        // Per-account login throttling: failures answered without delay,
+        // failures that lock the account, and how long the lock lasts
+        let login_delay_after_failures = env::var("LOGIN_DELAY_AFTER_FAILURES")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse()
+            .expect("LOGIN_DELAY_AFTER_FAILURES must be a number");
+
+        let login_lockout_threshold = env::var("LOGIN_LOCKOUT_THRESHOLD")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .expect("LOGIN_LOCKOUT_THRESHOLD must be a number");
+
+        let login_lockout_minutes = env::var("LOGIN_LOCKOUT_MINUTES")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse()
+            .expect("LOGIN_LOCKOUT_MINUTES must be a number");
+
+        // Roles users may pick when registering (admin is never allowed)
+        let self_register_roles = env::var("SELF_REGISTER_ROLES")
+            .unwrap_or_else(|_| "student,teacher".to_string())
+            .split(',')
+            .map(|r| {
+                crate::models::user::Role::parse(r)
+                    .unwrap_or_else(|| panic!("SELF_REGISTER_ROLES contains unknown role '{}'", r))
+            })
+            .collect();
+
+        // Frontend URL used in emailed links; defaults to the first allowed origin
+        let app_base_url = env::var("APP_BASE_URL")
+            .ok()
+            .filter(|u| !u.is_empty())
+            .or_else(|| allowed_origins.first().cloned())
+            .unwrap_or_else(|| "http://localhost:3000".to_string());
+
+        // Outgoing mail: smtp | file | stdout
+        let mail_backend = env::var("MAIL_BACKEND")
+            .unwrap_or_else(|_| "stdout".to_string());
+        let smtp_url = env::var("SMTP_URL").ok().filter(|u| !u.is_empty());
+        let mail_from = env::var("MAIL_FROM")
+            .unwrap_or_else(|_| "ClassColab <no-reply@classcolab.local>".to_string());
+        let mail_file_path = env::var("MAIL_FILE_PATH")
+            .unwrap_or_else(|_| "mail.log".to_string());
+
+        // Block going live until the creator has verified their email
+        let require_verified_email_to_go_live = env::var("REQUIRE_VERIFIED_EMAIL_TO_GO_LIVE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        // Public URL of this backend, used for redirect URIs registered with identity providers
+        let public_api_url = env::var("PUBLIC_API_URL")
+            .ok()
+            .filter(|u| !u.is_empty())
+            .unwrap_or_else(|| format!("http://localhost:{}", port))
+            .trim_end_matches('/')
+            .to_string();
+
+        // Single sign-on providers, e.g. OIDC_PROVIDERS=campus,google
+        let oidc_providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(OidcProviderConfig::from_env)
+            .collect();
+
+        // LTI tool signing key (PEM), inline with \n escapes or from a file
+        let lti_private_key = env::var("LTI_PRIVATE_KEY")
+            .ok()
+            .filter(|k| !k.is_empty())
+            .map(|k| k.replace("\\n", "\n"))
+            .or_else(|| {
+                env::var("LTI_PRIVATE_KEY_FILE")
+                    .ok()
+                    .filter(|p| !p.is_empty())
+                    .map(|path| {
+                        std::fs::read_to_string(&path)
+                            .unwrap_or_else(|e| panic!("Cannot read LTI_PRIVATE_KEY_FILE {}: {}", path, e))
+                    })
+            });
+        let lti_key_id = env::var("LTI_KEY_ID")
+            .unwrap_or_else(|_| "classcolab-lti-1".to_string());
+
+        // LTI grade passback
+        let grade_sync_max_attempts = env::var("GRADE_SYNC_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .expect("GRADE_SYNC_MAX_ATTEMPTS must be a number");
+
+        let grade_sync_poll_interval_ms = env::var("GRADE_SYNC_POLL_INTERVAL_MS")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse()
+            .expect("GRADE_SYNC_POLL_INTERVAL_MS must be a number");
+
         Self {
             database_url,
             jwt_secret,
             port,
             allowed_origins,
             environment,
+            ably_api_key,
+            ably_rest_url,
+            realtime_backend,
+            sse_max_subscribers_per_session,
+            sse_poll_interval_ms,
+            outbox_max_attempts,
+            outbox_poll_interval_ms,
+            outbox_retention_hours,
+            event_log_capacity,
+            vote_coalesce_window_ms,
+            qa_snapshot_events,
+            access_token_ttl_minutes,
+            refresh_token_ttl_days,
+            jwt_signing_keys,
+            login_delay_after_failures,
+            login_lockout_threshold,
+            login_lockout_minutes,
+            self_register_roles,
+            app_base_url,
+            mail_backend,
+            smtp_url,
+            mail_from,
+            mail_file_path,
+            require_verified_email_to_go_live,
+            public_api_url,
+            oidc_providers,
+            lti_private_key,
+            lti_key_id,
+            grade_sync_max_attempts,
+            grade_sync_poll_interval_ms,
         }
     }
 
     pub fn is_production(&self) -> bool {
         self.environment == "production"
     }
+
+    pub fn outbox_settings(&self) -> crate::services::outbox::OutboxSettings {
+        crate::services::outbox::OutboxSettings {
+            max_attempts: self.outbox_max_attempts,
+            poll_interval: std::time::Duration::from_millis(self.outbox_poll_interval_ms),
+            retention: std::time::Duration::from_secs(self.outbox_retention_hours * 3600),
+            event_log_capacity: self.event_log_capacity,
+        }
+    }
+
+    pub fn login_policy(&self) -> crate::services::login_guard::LoginPolicy {
+        crate::services::login_guard::LoginPolicy {
+            delay_after_failures: self.login_delay_after_failures,
+            lockout_threshold: self.login_lockout_threshold,
+            lockout_duration: chrono::Duration::minutes(self.login_lockout_minutes),
+        }
+    }
+
+    pub fn grade_sync_settings(&self) -> crate::services::ags::GradeSyncSettings {
+        crate::services::ags::GradeSyncSettings {
+            max_attempts: self.grade_sync_max_attempts,
+            poll_interval: std::time::Duration::from_millis(self.grade_sync_poll_interval_ms),
+        }
+    }
 }
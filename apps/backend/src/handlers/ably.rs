@@ -1,10 +1,14 @@
-use axum::{extract::Query, Json, Extension};
+use axum::{extract::{Query, State}, Json, Extension};
 use serde::Deserialize;
 use std::sync::Arc;
 use serde_json::json;
 
-use crate::error::Result;
+use crate::error::{AppError, Result};
 use crate::config::Config;
+use crate::middleware::auth::AuthUser;
+use crate::models::session::SessionRole;
+use crate::services::ably::{session_channel, staff_channel};
+use crate::services::{participant, session_access};
 
 #[derive(Deserialize)]
 pub struct AblyTokenQuery {
@@ -13,58 +17,102 @@ pub struct AblyTokenQuery {
     role: String,
     #[serde(rename = "participantId")]
     participant_id: Option<String>,
+    /// Signed token returned by `register-participant` (students)
+    #[serde(rename = "participantToken")]
+    participant_token: Option<String>,
+    /// Session share token (projectors opened without a staff login)
+    #[serde(rename = "shareToken")]
+    share_token: Option<String>,
 }
 
 /// Generate Ably token request with appropriate permissions
+/// - staff: must be logged in and at least a presenter; also gets the staff channel
+/// - student: session must exist; participant id must carry a valid participant token
+/// - projector: must collaborate on the session or present its share token
 pub async fn get_ably_token(
-    Extension(_config): Extension<Arc<Config>>,
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    auth_user: Option<AuthUser>,
     Query(params): Query<AblyTokenQuery>,
 ) -> Result<Json<serde_json::Value>> {
-    // Get Ably API key from environment
-    let ably_api_key = std::env::var("ABLY_API_KEY")
-        .map_err(|_| crate::error::AppError::Internal("ABLY_API_KEY not configured".to_string()))?;
+    let ably_api_key = config.ably_api_key.as_deref()
+        .ok_or_else(|| AppError::Internal("ABLY_API_KEY not configured".to_string()))?;
 
     // Parse key: "keyName:keySecret"
     let key_parts: Vec<&str> = ably_api_key.split(':').collect();
     if key_parts.len() != 2 {
-        return Err(crate::error::AppError::Internal("Invalid ABLY_API_KEY format".to_string()));
+        return Err(AppError::Internal("Invalid ABLY_API_KEY format".to_string()));
     }
     let key_name = key_parts[0];
     let key_secret = key_parts[1];
 
-    // Define capabilities based on role
-    let capability = match params.role.as_str() {
+    let session = app_state.session_service.find_session(&params.session_id).await?;
+    let session_role = match auth_user.as_ref() {
+        Some(user) => {
+            let pool = app_state.db_pool.pool().await?;
+            session_access::role_of(&pool, &session.id, &user.user_id).await?
+        }
+        None => None,
+    };
+
+    // Define capabilities and client ID based on the verified role
+    let channel = session_channel(&session.id);
+    let (capability, client_id) = match params.role.as_str() {
         "staff" => {
-            json!({
-                format!("session:{}", params.session_id): ["publish", "subscribe", "presence"]
-            })
+            let user = auth_user
+                .as_ref()
+                .ok_or_else(|| AppError::Auth("Login required for staff access".to_string()))?;
+            session_access::authorize(session_role, SessionRole::Presenter)?;
+            let staff = staff_channel(&session.id);
+            (
+                json!({
+                    channel: ["publish", "subscribe", "presence"],
+                    staff: ["subscribe"],
+                }),
+                format!("staff-{}", user.user_id),
+            )
+        }
+        "student" => {
+            let participant_id = params.participant_id.as_deref()
+                .filter(|id| !id.is_empty())
+                .ok_or_else(|| AppError::Input("Missing participantId".to_string()))?;
+            let verified = params.participant_token.as_deref().is_some_and(|token| {
+                participant::verify_token(&config.jwt_secret, &session.id, participant_id, token)
+            });
+            if !verified {
+                tracing::warn!(
+                    "Rejected student Ably token for session {}: unverified participant {}",
+                    session.id, participant_id
+                );
+                return Err(AppError::Auth("Invalid participant token".to_string()));
+            }
+            (
+                json!({ channel: ["subscribe", "presence"] }),
+                participant_id.to_string(),
+            )
         }
-        "student" | "projector" => {
-            json!({
-                format!("session:{}", params.session_id): ["subscribe", "presence"]
-            })
+        "projector" => {
+            let has_share_token = match (&params.share_token, &session.share_token) {
+                (Some(given), Some(expected)) => given == expected,
+                _ => false,
+            };
+            if session_role.is_none() && !has_share_token {
+                return Err(AppError::Auth("Projector access requires the session share token".to_string()));
+            }
+            (
+                json!({ channel: ["subscribe", "presence"] }),
+                format!("projector-{}", session.id),
+            )
         }
         _ => {
-            return Err(crate::error::AppError::Input("Invalid role. Must be 'staff', 'student', or 'projector'".to_string()));
+            return Err(AppError::Input("Invalid role. Must be 'staff', 'student', or 'projector'".to_string()));
         }
     };
 
-    // Set client ID for tracking - CRITICAL: each participant must have a unique ID
-    let client_id = params.participant_id.clone()
-        .unwrap_or_else(|| format!("{}-{}", params.role, params.session_id));
-    
-    // Warn if participant_id is empty (this causes all students to share the same connection)
-    if params.participant_id.as_ref().map(|s| s.is_empty()).unwrap_or(true) {
-        tracing::warn!(
-            "Empty or missing participant_id for session {} role {}. Using fallback: {}. This may cause connection sharing issues!",
-            params.session_id, params.role, client_id
-        );
-    } else {
-        tracing::info!(
-            "Generating Ably token for session {} role {} participant {}",
-            params.session_id, params.role, client_id
-        );
-    }
+    tracing::info!(
+        "Generating Ably token for session {} role {} client {}",
+        session.id, params.role, client_id
+    );
 
     // Generate timestamp (in milliseconds) and nonce
     let timestamp = std::time::SystemTime::now()
@@ -98,7 +146,7 @@ pub async fn get_ably_token(
     type HmacSha256 = Hmac<Sha256>;
     
     let mut mac = HmacSha256::new_from_slice(key_secret.as_bytes())
-        .map_err(|_| crate::error::AppError::Internal("Failed to create HMAC".to_string()))?;
+        .map_err(|_| AppError::Internal("Failed to create HMAC".to_string()))?;
     mac.update(sign_text.as_bytes());
     let result = mac.finalize();
     let mac_base64 = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, result.into_bytes());
@@ -0,0 +1,188 @@
+use axum::{extract::{Extension, State}, Json};
+use axum_extra::extract::cookie::CookieJar;
+use bcrypt::{hash, verify, DEFAULT_COST};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::query_as;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::handlers::auth::{
+    send_verification_email, without_auth_cookies, MAX_EMAIL_LENGTH, MAX_NAME_LENGTH, MAX_PASSWORD_LENGTH,
+    MIN_PASSWORD_LENGTH,
+};
+use crate::middleware::auth::AuthUser;
+use crate::models::response::ApiResponse;
+use crate::models::user::User;
+use crate::services::{account, auth_tokens};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProfileRequest {
+    name: Option<String>,
+    email: Option<String>,
+    /// Required to change the email
+    current_password: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordRequest {
+    current_password: String,
+    new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    password: String,
+}
+
+pub(crate) async fn find_user(pool: &DbPool, user_id: &str) -> Result<User> {
+    query_as("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+/// Check a password the user typed again before a sensitive change
+pub(crate) async fn confirm_password(user: &User, password: &str) -> Result<()> {
+    let password = password.to_string();
+    let password_hash = user.password_hash.clone();
+    let is_valid = tokio::task::spawn_blocking(move || verify(password, &password_hash))
+        .await
+        .map_err(|e| AppError::Internal(format!("Password verify task failed: {}", e)))??;
+    if !is_valid {
+        return Err(AppError::Forbidden("Current password is incorrect".to_string()));
+    }
+    Ok(())
+}
+
+/// The logged-in user, for restoring login state
+pub async fn get_me(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<Json<ApiResponse<User>>> {
+    let pool = app_state.db_pool.pool().await?;
+    Ok(Json(ApiResponse::success(find_user(&pool, &user_id).await?)))
+}
+
+/// Update name and/or email. A new email must be verified again and needs
+/// the current password, since it would receive password reset links.
+pub async fn update_me(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Json(payload): Json<UpdateProfileRequest>,
+) -> Result<Json<ApiResponse<User>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let user = find_user(&pool, &user_id).await?;
+
+    let name = match payload.name.as_deref().map(str::trim) {
+        Some("") => return Err(AppError::Input("Name cannot be empty".to_string())),
+        Some(name) if name.len() > MAX_NAME_LENGTH => return Err(AppError::Input("Name too long".to_string())),
+        Some(name) => name.to_string(),
+        None => user.name.clone(),
+    };
+
+    let new_email = payload
+        .email
+        .as_deref()
+        .map(str::trim)
+        .filter(|email| !email.eq_ignore_ascii_case(&user.email));
+    if let Some(email) = new_email {
+        if email.len() > MAX_EMAIL_LENGTH {
+            return Err(AppError::Input("Email too long".to_string()));
+        }
+        if !email.contains('@') || !email.contains('.') {
+            return Err(AppError::Input("Invalid email format".to_string()));
+        }
+        let password = payload
+            .current_password
+            .as_deref()
+            .ok_or_else(|| AppError::Input("Current password is required to change the email".to_string()))?;
+        confirm_password(&user, password).await?;
+    }
+
+    sqlx::query(
+        "UPDATE users SET name = ?, email = COALESCE(?, email),
+             email_verified_at = IF(? IS NULL, email_verified_at, NULL)
+         WHERE id = ?"
+    )
+    .bind(&name)
+    .bind(new_email)
+    .bind(new_email)
+    .bind(&user_id)
+    .execute(&pool)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("Duplicate entry") {
+            AppError::Input("Email already exists".to_string())
+        } else {
+            AppError::Database(e)
+        }
+    })?;
+
+    if let Some(email) = new_email {
+        tracing::info!("User {} changed their email", user_id);
+        send_verification_email(&app_state, &config, &user_id, email);
+    }
+
+    Ok(Json(ApiResponse::success(find_user(&pool, &user_id).await?)))
+}
+
+/// Change the password after re-checking the current one. Other devices are
+/// logged out; this one stays signed in.
+pub async fn change_password(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, family_id, .. }: AuthUser,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<Json<Value>> {
+    if payload.new_password.len() < MIN_PASSWORD_LENGTH {
+        return Err(AppError::Input("Password must be at least 8 characters".to_string()));
+    }
+    if payload.new_password.len() > MAX_PASSWORD_LENGTH {
+        return Err(AppError::Input("Password too long".to_string()));
+    }
+
+    let pool = app_state.db_pool.pool().await?;
+    let user = find_user(&pool, &user_id).await?;
+    confirm_password(&user, &payload.current_password).await?;
+
+    let password = payload.new_password;
+    let password_hash = tokio::task::spawn_blocking(move || hash(password, DEFAULT_COST))
+        .await
+        .map_err(|e| AppError::Internal(format!("Password hash task failed: {}", e)))??;
+    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+        .bind(&password_hash)
+        .bind(&user_id)
+        .execute(&pool)
+        .await?;
+
+    let revoked = auth_tokens::revoke_other_families(&pool, &user_id, family_id.as_deref()).await?;
+    tracing::info!("User {} changed their password ({} other logins revoked)", user_id, revoked);
+
+    Ok(Json(json!({ "success": true, "message": "Password changed", "revoked": revoked })))
+}
+
+/// Delete the account with all its sessions, slides, votes and questions
+pub async fn delete_me(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    jar: CookieJar,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> Result<(CookieJar, Json<Value>)> {
+    let pool = app_state.db_pool.pool().await?;
+    let user = find_user(&pool, &user_id).await?;
+    confirm_password(&user, &payload.password).await?;
+
+    let deleted = account::delete_account(&pool, &user.id, &user.email).await?;
+    tracing::info!("User {} deleted their account ({} sessions)", user_id, deleted.sessions);
+
+    Ok((
+        without_auth_cookies(jar),
+        Json(json!({ "success": true, "message": "Account deleted", "deletedSessions": deleted.sessions })),
+    ))
+}
@@ -0,0 +1,136 @@
+use axum::{extract::{Path, State}, Json};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::query_as;
+
+use crate::error::{AppError, Result};
+use crate::middleware::auth::{Admin, AuthUser, RequireRole};
+use crate::models::lti::{LtiPlatform, NewLtiPlatform};
+use crate::models::response::ApiResponse;
+use crate::models::user::{Role, User};
+use crate::services::auth_tokens;
+use crate::services::login_guard;
+
+#[derive(Deserialize)]
+pub struct SetUserRoleRequest {
+    role: Role,
+}
+
+/// Change a user's role (admin only)
+/// - admins cannot change their own role
+/// - the last admin cannot be demoted
+/// - a demoted user's logins are revoked so the old role stops working at once
+pub async fn set_user_role(
+    State(app_state): State<crate::AppState>,
+    RequireRole { user: AuthUser { user_id: admin_id, .. }, .. }: RequireRole<Admin>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<SetUserRoleRequest>,
+) -> Result<Json<ApiResponse<User>>> {
+    let pool = app_state.db_pool.pool().await?;
+
+    if user_id == admin_id {
+        return Err(AppError::Input("Admins cannot change their own role".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let user: User = query_as("SELECT * FROM users WHERE id = ? FOR UPDATE")
+        .bind(&user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let previous = user.role();
+
+    if previous == Role::Admin && payload.role != Role::Admin {
+        // Lock the admin rows so two admins demoting each other cannot both pass
+        let admins: Vec<String> = sqlx::query_scalar("SELECT id FROM users WHERE role = 'admin' FOR UPDATE")
+            .fetch_all(&mut *tx)
+            .await?;
+        if admins.len() <= 1 {
+            return Err(AppError::Input("Cannot demote the last admin".to_string()));
+        }
+    }
+
+    sqlx::query("UPDATE users SET role = ? WHERE id = ?")
+        .bind(payload.role.as_str())
+        .bind(&user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    if payload.role < previous {
+        auth_tokens::revoke_all_for_user(&pool, &user_id).await?;
+    }
+
+    tracing::info!(
+        "Admin {} changed role of user {} from {} to {}",
+        admin_id, user_id, previous.as_str(), payload.role.as_str()
+    );
+
+    Ok(Json(ApiResponse::success(User {
+        role: payload.role.as_str().to_string(),
+        ..user
+    })))
+}
+
+/// Lift a login lockout and reset the failed attempt counter (admin only)
+pub async fn unlock_user(
+    State(app_state): State<crate::AppState>,
+    RequireRole { user: AuthUser { user_id: admin_id, .. }, .. }: RequireRole<Admin>,
+    Path(user_id): Path<String>,
+) -> Result<Json<ApiResponse<Value>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let was_throttled = login_guard::clear(&pool, &email).await?;
+    tracing::info!("Admin {} unlocked login of user {}", admin_id, user_id);
+
+    Ok(Json(ApiResponse::success(json!({ "wasThrottled": was_throttled }))))
+}
+
+/// Registered LTI platforms (admin only)
+pub async fn list_lti_platforms(
+    State(app_state): State<crate::AppState>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<ApiResponse<Vec<LtiPlatform>>>> {
+    let pool = app_state.db_pool.pool().await?;
+    Ok(Json(ApiResponse::success(LtiPlatform::list(&pool).await?)))
+}
+
+/// Register an LMS as an LTI 1.3 platform (admin only)
+pub async fn create_lti_platform(
+    State(app_state): State<crate::AppState>,
+    RequireRole { user: AuthUser { user_id: admin_id, .. }, .. }: RequireRole<Admin>,
+    Json(payload): Json<NewLtiPlatform>,
+) -> Result<Json<ApiResponse<LtiPlatform>>> {
+    if payload.name.trim().is_empty() || payload.issuer.trim().is_empty() || payload.client_id.trim().is_empty() {
+        return Err(AppError::Input("Name, issuer and client id are required".to_string()));
+    }
+    for url in [&payload.auth_login_url, &payload.auth_token_url, &payload.jwks_url] {
+        if !(url.starts_with("https://") || url.starts_with("http://")) {
+            return Err(AppError::Input(format!("Invalid URL: {}", url)));
+        }
+    }
+
+    let pool = app_state.db_pool.pool().await?;
+    let platform = LtiPlatform::create(&pool, &payload).await?;
+    tracing::info!("Admin {} registered LTI platform {} ({})", admin_id, platform.id, platform.issuer);
+
+    Ok(Json(ApiResponse::success(platform)))
+}
+
+/// Remove an LTI platform registration (admin only)
+pub async fn delete_lti_platform(
+    State(app_state): State<crate::AppState>,
+    _admin: RequireRole<Admin>,
+    Path(platform_id): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    if !LtiPlatform::delete(&pool, &platform_id).await? {
+        return Err(AppError::NotFound("LTI platform not found".to_string()));
+    }
+    Ok(Json(ApiResponse::success(())))
+}
@@ -3,24 +3,37 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use sqlx::query_as;
 use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{encode, EncodingKey, Header};
 use uuid::Uuid;
 use std::sync::Arc;
-use chrono::{Utc, Duration};
 
 
 use crate::error::{AppError, Result};
-use crate::models::user::User;
+use crate::models::user::{Role, User};
 use crate::config::Config;
-use crate::middleware::auth::Claims;
+use crate::db::DbPool;
+use crate::middleware::auth::AuthUser;
+use crate::services::auth_tokens;
+use crate::services::login_guard;
+use crate::services::mfa;
+use crate::services::mailer::EmailMessage;
+use crate::services::user_tokens::{self, TokenPurpose};
+use chrono::Duration;
 
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 
 // Input validation constants
-const MAX_EMAIL_LENGTH: usize = 255;
-const MAX_PASSWORD_LENGTH: usize = 128;
-const MIN_PASSWORD_LENGTH: usize = 8;
-const MAX_NAME_LENGTH: usize = 100;
+pub(crate) const MAX_EMAIL_LENGTH: usize = 255;
+pub(crate) const MAX_PASSWORD_LENGTH: usize = 128;
+pub(crate) const MIN_PASSWORD_LENGTH: usize = 8;
+pub(crate) const MAX_NAME_LENGTH: usize = 100;
+
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+const MFA_CHALLENGE_TTL_MINUTES: i64 = 5;
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 48;
+
+pub(crate) const ACCESS_COOKIE: &str = "token";
+pub(crate) const REFRESH_COOKIE: &str = "refresh_token";
+const REFRESH_COOKIE_PATH: &str = "/api/auth";
 
 #[derive(Deserialize)]
 pub struct RegisterRequest {
@@ -36,6 +49,14 @@ pub struct LoginRequest {
     password: String,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaLoginRequest {
+    mfa_token: String,
+    /// Authenticator code or recovery code
+    code: String,
+}
+
 #[derive(Serialize)]
 pub struct AuthResponse {
     success: bool,
@@ -43,8 +64,25 @@ pub struct AuthResponse {
     user: User,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaChallengeResponse {
+    success: bool,
+    mfa_required: bool,
+    /// Exchanged with a code at `/api/auth/login/mfa`
+    mfa_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
 pub async fn register(
     State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
     Json(payload): Json<RegisterRequest>,
 ) -> Result<Json<Value>> {
     let pool = app_state.db_pool.pool().await?;
@@ -74,7 +112,15 @@ pub async fn register(
         .await
         .map_err(|e| AppError::Internal(format!("Password hash task failed: {}", e)))??;
     let id = Uuid::new_v4().to_string();
-    let role = payload.role.unwrap_or_else(|| "student".to_string());
+    // Only roles open to self-registration; admin is granted by another admin
+    let role = match payload.role.as_deref() {
+        Some(requested) => Role::parse(requested)
+            .ok_or_else(|| AppError::Input("Invalid role".to_string()))?,
+        None => Role::Student,
+    };
+    if role == Role::Admin || !config.self_register_roles.contains(&role) {
+        return Err(AppError::Forbidden(format!("Cannot register as {}", role.as_str())));
+    }
 
     sqlx::query(
         "INSERT INTO users (id, email, password_hash, name, role) VALUES (?, ?, ?, ?, ?)",
@@ -83,7 +129,7 @@ pub async fn register(
     .bind(&payload.email)
     .bind(&password_hash)
     .bind(&payload.name)
-    .bind(&role)
+    .bind(role.as_str())
     .execute(&pool)
     .await
     .map_err(|e| {
@@ -94,6 +140,8 @@ pub async fn register(
         }
     })?;
 
+    send_verification_email(&app_state, &config, &id, &payload.email);
+
     Ok(Json(json!({ 
         "success": true,
         "message": "User registered successfully", 
@@ -106,58 +154,545 @@ pub async fn login(
     Extension(config): Extension<Arc<Config>>,
     jar: CookieJar,
     Json(payload): Json<LoginRequest>,
-) -> Result<(CookieJar, Json<AuthResponse>)> {
+) -> Result<(CookieJar, Json<LoginResponse>)> {
     let pool = app_state.db_pool.pool().await?;
-    
-    let user: User = query_as("SELECT * FROM users WHERE email = ?")
+
+    // Throttled per email, whether or not an account exists
+    login_guard::reserve_attempt(&pool, &config.login_policy(), &payload.email).await?;
+
+    let user: Option<User> = query_as("SELECT * FROM users WHERE email = ?")
         .bind(&payload.email)
         .fetch_optional(&pool)
-        .await?
-        .ok_or_else(|| AppError::Auth("Invalid email or password".to_string()))?;
+        .await?;
 
+    // Unknown emails still pay for a bcrypt verify so timing does not reveal them
     let password = payload.password;
-    let password_hash = user.password_hash.clone();
+    let password_hash = user
+        .as_ref()
+        .map_or_else(|| login_guard::TIMING_EQUALIZER_HASH.to_string(), |u| u.password_hash.clone());
     let is_valid = tokio::task::spawn_blocking(move || verify(password, &password_hash))
         .await
         .map_err(|e| AppError::Internal(format!("Password verify task failed: {}", e)))??;
 
-    if !is_valid {
-        return Err(AppError::Auth("Invalid email or password".to_string()));
+    let user = match user {
+        Some(user) if is_valid => user,
+        _ => return Err(AppError::Auth("Invalid email or password".to_string())),
+    };
+
+    // With 2FA the password only earns a challenge. The attempt stays counted
+    // until the second factor passes, so codes cannot be guessed unthrottled.
+    if let Some(mfa_token) = mfa_challenge(&pool, &config, &user.id).await? {
+        return Ok((
+            jar,
+            Json(LoginResponse::MfaRequired(MfaChallengeResponse {
+                success: true,
+                mfa_required: true,
+                mfa_token,
+            })),
+        ));
     }
+    login_guard::clear(&pool, &payload.email).await?;
 
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::days(30))
-        .expect("valid timestamp")
-        .timestamp() as usize;
+    let (jar, response) = sign_in(&app_state, &config, jar, user).await?;
+    Ok((jar, Json(LoginResponse::Authenticated(response))))
+}
 
-    let claims = Claims {
-        user_id: user.id.clone(),
-        role: user.role.clone(),
-        exp: expiration,
-    };
+/// Second login step: trade the MFA challenge token and an authenticator or
+/// recovery code for the login tokens. Wrong codes count as failed logins.
+pub async fn login_mfa(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    jar: CookieJar,
+    Json(payload): Json<MfaLoginRequest>,
+) -> Result<(CookieJar, Json<AuthResponse>)> {
+    let pool = app_state.db_pool.pool().await?;
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
-    )?;
+    let user_id = user_tokens::peek(&pool, &config.jwt_secret, &payload.mfa_token, TokenPurpose::MfaChallenge).await?;
+    let user: User = query_as("SELECT * FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_one(&pool)
+        .await?;
 
-    // Build cookie with security attributes
-    // Use SameSite::None for cross-origin requests (frontend on different domain)
-    // This requires Secure flag (HTTPS)
-    let cookie = Cookie::build(("token", token.clone()))
-        .path("/")
-        .http_only(true)
-        .same_site(SameSite::None)
-        .secure(true) // Required for SameSite::None
-        .build();
+    login_guard::reserve_attempt(&pool, &config.login_policy(), &user.email).await?;
+
+    // The challenge and the code are spent together: a code is never used
+    // up by a login that then fails, and a wrong code rolls back to leave
+    // the challenge for another try
+    let mut tx = pool.begin().await?;
+    user_tokens::redeem(&mut tx, &config.jwt_secret, &payload.mfa_token, TokenPurpose::MfaChallenge).await?;
+    if !mfa::verify(&mut tx, &user.id, &payload.code).await? {
+        return Err(AppError::Auth("Invalid authentication code".to_string()));
+    }
+    tx.commit().await?;
+    login_guard::clear(&pool, &user.email).await?;
+
+    let (jar, response) = sign_in(&app_state, &config, jar, user).await?;
+    Ok((jar, Json(response)))
+}
+
+/// Challenge token for the second login step if the user has 2FA enabled.
+/// Every way of signing in (password, single sign-on, LTI) goes through it.
+pub(crate) async fn mfa_challenge(pool: &DbPool, config: &Config, user_id: &str) -> Result<Option<String>> {
+    if !mfa::is_enabled(pool, user_id).await? {
+        return Ok(None);
+    }
+    let mfa_token = user_tokens::issue(
+        pool,
+        &config.jwt_secret,
+        user_id,
+        TokenPurpose::MfaChallenge,
+        Duration::minutes(MFA_CHALLENGE_TTL_MINUTES),
+    )
+    .await?;
+    Ok(Some(mfa_token))
+}
+
+/// Start a token family for a user who passed every login step
+async fn sign_in(
+    app_state: &crate::AppState,
+    config: &Config,
+    jar: CookieJar,
+    user: User,
+) -> Result<(CookieJar, AuthResponse)> {
+    let pool = app_state.db_pool.pool().await?;
+    let tokens = auth_tokens::start_family(
+        &pool,
+        config,
+        &app_state.jwt_keys,
+        &user.id,
+        user.role().as_str(),
+    ).await?;
+
+    Ok((
+        with_auth_cookies(jar, config, &tokens.access_token, &tokens.refresh_token),
+        AuthResponse {
+            success: true,
+            token: tokens.access_token,
+            user,
+        },
+    ))
+}
+
+/// Exchange the refresh token cookie for a new access token; the refresh
+/// token is rotated
+pub async fn refresh(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<AuthResponse>)> {
+    let pool = app_state.db_pool.pool().await?;
+
+    let refresh_token = jar
+        .get(REFRESH_COOKIE)
+        .map(|c| c.value().to_string())
+        .ok_or_else(|| AppError::Auth("Missing refresh token".to_string()))?;
+
+    let rotated = auth_tokens::rotate(&pool, &config, &refresh_token).await?;
+
+    let user: User = query_as("SELECT * FROM users WHERE id = ?")
+        .bind(&rotated.user_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::Auth("Invalid refresh token".to_string()))?;
+
+    let token = auth_tokens::issue_access_token(
+        &config,
+        &app_state.jwt_keys,
+        &user.id,
+        user.role().as_str(),
+        &rotated.family_id,
+    )?;
 
     Ok((
-        jar.add(cookie),
-        Json(AuthResponse { 
+        with_auth_cookies(jar, &config, &token, &rotated.refresh_token),
+        Json(AuthResponse {
             success: true,
-            token, 
-            user 
+            token,
+            user,
         })
     ))
 }
+
+/// Log out this device: revoke its token family and clear the cookies
+pub async fn logout(
+    State(app_state): State<crate::AppState>,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<Value>)> {
+    let pool = app_state.db_pool.pool().await?;
+
+    let family_id = match jar.get(REFRESH_COOKIE) {
+        Some(cookie) => auth_tokens::family_of(&pool, cookie.value()).await?,
+        None => None,
+    }
+    .or(auth_user.and_then(|user| user.family_id));
+
+    if let Some(family_id) = family_id {
+        auth_tokens::revoke_family(&pool, &family_id).await?;
+    }
+
+    Ok((
+        without_auth_cookies(jar),
+        Json(json!({ "success": true, "message": "Logged out" })),
+    ))
+}
+
+/// Log out all devices: revoke every token family of the user
+pub async fn logout_all(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<Value>)> {
+    let pool = app_state.db_pool.pool().await?;
+    let revoked = auth_tokens::revoke_all_for_user(&pool, &user_id).await?;
+    tracing::info!("User {} logged out of {} sessions", user_id, revoked);
+
+    Ok((
+        without_auth_cookies(jar),
+        Json(json!({ "success": true, "message": "Logged out of all devices", "revoked": revoked })),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    token: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    token: String,
+}
+
+/// Email a password reset link. Always succeeds, so the response does not
+/// reveal whether the address has an account.
+pub async fn forgot_password(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<Value>> {
+    let pool = app_state.db_pool.pool().await?;
+
+    let user: Option<User> = query_as("SELECT * FROM users WHERE email = ?")
+        .bind(payload.email.trim())
+        .fetch_optional(&pool)
+        .await?;
+
+    if let Some(user) = user {
+        let token = user_tokens::issue(
+            &pool,
+            &config.jwt_secret,
+            &user.id,
+            TokenPurpose::PasswordReset,
+            Duration::minutes(PASSWORD_RESET_TTL_MINUTES),
+        )
+        .await?;
+        let link = format!("{}/reset-password?token={}", config.app_base_url, urlencoding::encode(&token));
+        send_email(&app_state, EmailMessage {
+            to: user.email,
+            subject: "Reset your ClassColab password".to_string(),
+            body: format!(
+                "Someone asked to reset the password for your ClassColab account.\n\n\
+                 Open this link within {} minutes to choose a new password:\n{}\n\n\
+                 If this wasn't you, you can ignore this email.",
+                PASSWORD_RESET_TTL_MINUTES, link
+            ),
+        });
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "If that email has an account, a reset link has been sent"
+    })))
+}
+
+/// Set a new password with a reset token. Every login of the account is
+/// revoked and other outstanding reset links stop working.
+pub async fn reset_password(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<Value>> {
+    let pool = app_state.db_pool.pool().await?;
+
+    if payload.password.len() < MIN_PASSWORD_LENGTH {
+        return Err(AppError::Input("Password must be at least 8 characters".to_string()));
+    }
+    if payload.password.len() > MAX_PASSWORD_LENGTH {
+        return Err(AppError::Input("Password too long".to_string()));
+    }
+
+    let password = payload.password;
+    let password_hash = tokio::task::spawn_blocking(move || hash(password, DEFAULT_COST))
+        .await
+        .map_err(|e| AppError::Internal(format!("Password hash task failed: {}", e)))??;
+
+    let mut tx = pool.begin().await?;
+    let user_id = user_tokens::redeem(&mut tx, &config.jwt_secret, &payload.token, TokenPurpose::PasswordReset).await?;
+    user_tokens::invalidate_all(&mut tx, &user_id, TokenPurpose::PasswordReset).await?;
+
+    // Receiving the reset email proves the address, so it counts as verified
+    sqlx::query(
+        "UPDATE users SET password_hash = ?, email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = ?"
+    )
+    .bind(&password_hash)
+    .bind(&user_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    auth_tokens::revoke_all_for_user(&pool, &user_id).await?;
+
+    // Proving access to the mailbox lifts a login lockout
+    let email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_one(&pool)
+        .await?;
+    login_guard::clear(&pool, &email).await?;
+    tracing::info!("Password reset for user {}", user_id);
+
+    Ok(Json(json!({ "success": true, "message": "Password has been reset" })))
+}
+
+/// Mark the account's email as verified
+pub async fn verify_email(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<Json<Value>> {
+    let pool = app_state.db_pool.pool().await?;
+
+    let mut tx = pool.begin().await?;
+    let user_id = user_tokens::redeem(&mut tx, &config.jwt_secret, &payload.token, TokenPurpose::EmailVerification).await?;
+    sqlx::query("UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = ?")
+        .bind(&user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Json(json!({ "success": true, "message": "Email verified" })))
+}
+
+/// Send a fresh verification link to the logged-in user
+pub async fn resend_verification(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<Json<Value>> {
+    let pool = app_state.db_pool.pool().await?;
+
+    let user: User = query_as("SELECT * FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if user.email_verified_at.is_some() {
+        return Err(AppError::Input("Email is already verified".to_string()));
+    }
+
+    send_verification_email(&app_state, &config, &user.id, &user.email);
+
+    Ok(Json(json!({ "success": true, "message": "Verification email sent" })))
+}
+
+/// Public keys that verify our access tokens, for services that accept them
+pub async fn jwks(State(app_state): State<crate::AppState>) -> Json<Value> {
+    Json(app_state.jwt_keys.jwks())
+}
+
+/// Issue a verification token and email the link in the background
+pub(crate) fn send_verification_email(app_state: &crate::AppState, config: &Config, user_id: &str, email: &str) {
+    let app_state = app_state.clone();
+    let secret = config.jwt_secret.clone();
+    let base_url = config.app_base_url.clone();
+    let user_id = user_id.to_string();
+    let email = email.to_string();
+
+    tokio::spawn(async move {
+        let token = async {
+            let pool = app_state.db_pool.pool().await?;
+            user_tokens::issue(
+                &pool,
+                &secret,
+                &user_id,
+                TokenPurpose::EmailVerification,
+                Duration::hours(EMAIL_VERIFICATION_TTL_HOURS),
+            )
+            .await
+        }
+        .await;
+
+        match token {
+            Ok(token) => {
+                let link = format!("{}/verify-email?token={}", base_url, urlencoding::encode(&token));
+                let message = EmailMessage {
+                    to: email,
+                    subject: "Verify your ClassColab email".to_string(),
+                    body: format!(
+                        "Welcome to ClassColab!\n\nConfirm your email address by opening this link \
+                         within {} hours:\n{}",
+                        EMAIL_VERIFICATION_TTL_HOURS, link
+                    ),
+                };
+                if let Err(e) = app_state.mailer.send(&message).await {
+                    tracing::error!("Failed to send verification email: {}", e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to issue verification token for user {}: {}", user_id, e),
+        }
+    });
+}
+
+/// Send an email in the background; failures are logged
+pub(crate) fn send_email(app_state: &crate::AppState, message: EmailMessage) {
+    let mailer = app_state.mailer.clone();
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(&message).await {
+            tracing::error!("Failed to send '{}' email: {}", message.subject, e);
+        }
+    });
+}
+
+// Cookies use SameSite::None for cross-origin requests (frontend on a
+// different domain), which requires the Secure flag (HTTPS)
+fn auth_cookie(name: &'static str, value: String, path: &'static str) -> Cookie<'static> {
+    Cookie::build((name, value))
+        .path(path)
+        .http_only(true)
+        .same_site(SameSite::None)
+        .secure(true)
+        .build()
+}
+
+/// Set the access token cookie and the refresh token cookie (sent to auth endpoints only)
+pub(crate) fn with_auth_cookies(jar: CookieJar, config: &Config, access_token: &str, refresh_token: &str) -> CookieJar {
+    let mut access = auth_cookie(ACCESS_COOKIE, access_token.to_string(), "/");
+    access.set_max_age(time::Duration::minutes(config.access_token_ttl_minutes));
+    let mut refresh = auth_cookie(REFRESH_COOKIE, refresh_token.to_string(), REFRESH_COOKIE_PATH);
+    refresh.set_max_age(time::Duration::days(config.refresh_token_ttl_days));
+    jar.add(access).add(refresh)
+}
+
+pub(crate) fn without_auth_cookies(jar: CookieJar) -> CookieJar {
+    jar.remove(auth_cookie(ACCESS_COOKIE, String::new(), "/"))
+        .remove(auth_cookie(REFRESH_COOKIE, String::new(), REFRESH_COOKIE_PATH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::totp;
+    use crate::test_support::{app, create_user, email_of, TestApp, PASSWORD};
+
+    async fn password_login(app: &TestApp, email: &str) -> LoginResponse {
+        let (_, Json(response)) = login(
+            State(app.state.clone()),
+            Extension(app.config.clone()),
+            CookieJar::new(),
+            Json(LoginRequest { email: email.to_string(), password: PASSWORD.to_string() }),
+        )
+        .await
+        .unwrap();
+        response
+    }
+
+    async fn second_factor(app: &TestApp, mfa_token: &str, code: &str) -> Result<(CookieJar, Json<AuthResponse>)> {
+        login_mfa(
+            State(app.state.clone()),
+            Extension(app.config.clone()),
+            CookieJar::new(),
+            Json(MfaLoginRequest { mfa_token: mfa_token.to_string(), code: code.to_string() }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn logging_out_everywhere_revokes_every_family_and_clears_the_cookies() {
+        let app = app().await;
+        let user = create_user(&app.pool, Role::Teacher).await;
+        let mut families = Vec::new();
+        for _ in 0..2 {
+            let issued = auth_tokens::start_family(&app.pool, &app.config, &app.state.jwt_keys, &user.user_id, "teacher")
+                .await
+                .unwrap();
+            families.push(auth_tokens::family_of(&app.pool, &issued.refresh_token).await.unwrap().unwrap());
+        }
+        let jar = CookieJar::new().add(Cookie::new(ACCESS_COOKIE, "access")).add(Cookie::new(REFRESH_COOKIE, "refresh"));
+
+        let (jar, Json(response)) = logout_all(State(app.state.clone()), user, jar).await.unwrap();
+
+        assert_eq!(response["revoked"], 2);
+        for family_id in &families {
+            assert!(!auth_tokens::is_family_active(&app.pool, family_id).await.unwrap());
+        }
+        assert!(jar.get(ACCESS_COOKIE).is_none());
+        assert!(jar.get(REFRESH_COOKIE).is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn unknown_emails_fail_and_count_like_wrong_passwords() {
+        let app = app().await;
+        let user = create_user(&app.pool, Role::Teacher).await;
+        let attempt = |email: String, password: &str| login(
+            State(app.state.clone()),
+            Extension(app.config.clone()),
+            CookieJar::new(),
+            Json(LoginRequest { email, password: password.to_string() }),
+        );
+
+        let unknown_email = format!("nobody-{}@example.com", user.user_id);
+        let unknown = attempt(unknown_email.clone(), PASSWORD).await.err().unwrap();
+        let wrong_password = attempt(email_of(&user.user_id), "not-the-password").await.err().unwrap();
+        assert!(matches!(&unknown, AppError::Auth(message) if message == "Invalid email or password"));
+        assert_eq!(unknown.to_string(), wrong_password.to_string());
+
+        let failed: i32 = sqlx::query_scalar("SELECT failed_count FROM login_attempts WHERE email = ?")
+            .bind(&unknown_email)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(failed, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn password_alone_does_not_sign_in_with_two_factor_enabled() {
+        let app = app().await;
+        let user = create_user(&app.pool, Role::Teacher).await;
+        let email = email_of(&user.user_id);
+        assert!(matches!(password_login(&app, &email).await, LoginResponse::Authenticated(_)));
+
+        let secret = mfa::start_enrollment(&app.pool, &user.user_id).await.unwrap();
+        let now = chrono::Utc::now().timestamp();
+        let recovery_codes = mfa::confirm_enrollment(&app.pool, &user.user_id, &totp::code_at(&secret, now))
+            .await
+            .unwrap();
+
+        let LoginResponse::MfaRequired(challenge) = password_login(&app, &email).await else {
+            panic!("signed in without the second factor");
+        };
+        let wrong = second_factor(&app, &challenge.mfa_token, "000000").await;
+        assert!(matches!(wrong, Err(AppError::Auth(_))));
+
+        let (jar, Json(signed_in)) = second_factor(&app, &challenge.mfa_token, &totp::code_at(&secret, now + 30))
+            .await
+            .unwrap();
+        assert_eq!(signed_in.user.id, user.user_id);
+        assert!(jar.get(ACCESS_COOKIE).is_some());
+        // The challenge is spent, even with a valid recovery code
+        assert!(second_factor(&app, &challenge.mfa_token, &recovery_codes[0]).await.is_err());
+
+        let LoginResponse::MfaRequired(challenge) = password_login(&app, &email).await else {
+            panic!("signed in without the second factor");
+        };
+        assert!(second_factor(&app, &challenge.mfa_token, &recovery_codes[0]).await.is_ok());
+    }
+}
@@ -0,0 +1,77 @@
+use axum::{extract::{Extension, Path, State}, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::models::response::ApiResponse;
+use crate::models::session::SessionRole;
+use crate::services::clicker::{self, ClickerDevice, PairedClicker, Pairing};
+use crate::services::session_access;
+
+#[derive(Deserialize)]
+pub struct PairClickerRequest {
+    code: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClickerPairing {
+    #[serde(flatten)]
+    pairing: Pairing,
+    /// Opens the pairing page with the code filled in (for a QR code)
+    pairing_url: String,
+}
+
+/// Start pairing a phone as a remote clicker (presenters)
+pub async fn start_pairing(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<ClickerPairing>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    let pairing = clicker::start_pairing(&pool, &session_id, &user_id).await?;
+    let pairing_url = format!("{}/clicker/pair?code={}", config.app_base_url, pairing.code);
+    Ok(Json(ApiResponse::success(ClickerPairing { pairing, pairing_url })))
+}
+
+/// Exchange a pairing code for a clicker token (public; the code is the proof)
+pub async fn pair(
+    State(app_state): State<crate::AppState>,
+    Json(payload): Json<PairClickerRequest>,
+) -> Result<Json<ApiResponse<PairedClicker>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let paired = clicker::pair(&pool, &payload.code).await?;
+    tracing::info!("Paired a clicker to session {}", paired.session_id);
+
+    Ok(Json(ApiResponse::success(paired)))
+}
+
+/// Phones paired to a session (presenters)
+pub async fn list_devices(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<Vec<ClickerDevice>>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+    Ok(Json(ApiResponse::success(clicker::list_devices(&pool, &session_id).await?)))
+}
+
+/// Unpair a phone (presenters)
+pub async fn revoke_device(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path((session_id, device_id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    clicker::revoke(&pool, &session_id, &device_id).await?;
+    tracing::info!("User {} unpaired clicker {} from session {}", user_id, device_id, session_id);
+    Ok(Json(ApiResponse::success(())))
+}
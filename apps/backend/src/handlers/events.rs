@@ -0,0 +1,333 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{header::ACCEPT, HeaderMap, HeaderName, HeaderValue},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::models::response::ApiResponse;
+use crate::services::event_log::{self, LoggedEvent};
+use crate::services::sse::{SseEvent, SubscribeError};
+
+/// Interval between SSE heartbeat comments (keeps proxies from closing idle streams)
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    since: Option<i64>,
+}
+
+/// Session events endpoint
+/// - `Accept: text/event-stream`: live SSE stream (fallback when Ably is blocked)
+/// - otherwise: JSON catch-up of events after `?since=N`
+pub async fn session_events(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Path(session_id): Path<String>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let wants_stream = headers
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("text/event-stream"))
+        .unwrap_or(false);
+
+    if wants_stream {
+        // EventSource sends Last-Event-ID on reconnect; `since` covers the first connect
+        let last_event_id = headers
+            .get("last-event-id")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .or(query.since);
+        stream_events(app_state, session_id, last_event_id, config.event_log_capacity).await
+    } else {
+        let since = query
+            .since
+            .ok_or_else(|| AppError::Input("Missing 'since' parameter".to_string()))?;
+        replay_events(app_state, session_id, since, config.event_log_capacity).await
+    }
+}
+
+/// Return the events a client missed, or a resync marker
+async fn replay_events(
+    app_state: crate::AppState,
+    session_id: String,
+    since: i64,
+    capacity: i64,
+) -> Result<Response> {
+    let pool = app_state.db_pool.pool().await?;
+    let replay = event_log::replay(&pool, &session_id, since, capacity).await?;
+
+    if replay.resync_required {
+        tracing::info!(
+            "Client of session {} needs a resync (since {}, latest {})",
+            session_id, since, replay.latest_seq
+        );
+    }
+
+    Ok(Json(ApiResponse::success(replay)).into_response())
+}
+
+fn logged_to_sse(event: LoggedEvent) -> Event {
+    Event::default()
+        .id(event.seq.to_string())
+        .event(event.name)
+        .data(event.data.to_string())
+}
+
+fn live_to_sse(event: SseEvent) -> Event {
+    Event::default()
+        .id(event.seq.to_string())
+        .event(event.name)
+        .data(event.data.to_string())
+}
+
+/// Lets through live events newer than any the client already has, each once
+fn fresh_after(seq: i64) -> impl FnMut(&SseEvent) -> bool {
+    let mut last_sent = seq;
+    move |event| {
+        let fresh = event.seq > last_sent;
+        if fresh {
+            last_sent = event.seq;
+        }
+        fresh
+    }
+}
+
+/// Stream session updates over Server-Sent Events
+/// Carries the same event names and payloads as the Ably channel. Missed
+/// events are replayed from the event log; if the gap is too large a
+/// `RESYNC_REQUIRED` event tells the client to refetch the session state.
+async fn stream_events(
+    app_state: crate::AppState,
+    session_id: String,
+    last_event_id: Option<i64>,
+    capacity: i64,
+) -> Result<Response> {
+    let pool = app_state.db_pool.pool().await?;
+    let latest_seq = event_log::latest_seq(&pool, &session_id).await?;
+
+    // Subscribe before reading the log so nothing falls between the two
+    let subscription = app_state
+        .sse_hub
+        .subscribe(&session_id, latest_seq)
+        .map_err(|e| match e {
+            SubscribeError::TooManySubscribers => {
+                tracing::warn!("SSE subscriber limit reached for session {}", session_id);
+                AppError::TooManyRequests("Too many listeners for this session".to_string())
+            }
+        })?;
+
+    let mut backlog = Vec::new();
+    let mut replayed_until = last_event_id.unwrap_or(latest_seq);
+
+    if let Some(last_event_id) = last_event_id {
+        let replay = event_log::replay(&pool, &session_id, last_event_id, capacity).await?;
+
+        if replay.resync_required {
+            backlog.push(
+                Event::default()
+                    .id(replay.latest_seq.to_string())
+                    .event("RESYNC_REQUIRED")
+                    .data(serde_json::json!({ "latestSeq": replay.latest_seq }).to_string()),
+            );
+            replayed_until = replay.latest_seq;
+        } else {
+            if let Some(last) = replay.events.last() {
+                replayed_until = last.seq;
+            }
+            backlog.extend(replay.events.into_iter().map(logged_to_sse));
+        }
+    }
+
+    tracing::info!(
+        "SSE subscriber connected to session {} (resume from {:?}, {} replayed)",
+        session_id, last_event_id, backlog.len()
+    );
+
+    let guard = subscription.guard;
+
+    // A lagging subscriber is dropped; the browser reconnects with Last-Event-ID
+    let live = stream::unfold(subscription.receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(event) => Some((event, receiver)),
+            Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => None,
+        }
+    })
+    .filter({
+        // Skip live events already covered by the replay
+        let mut is_fresh = fresh_after(replayed_until);
+        move |event| {
+            let fresh = is_fresh(event);
+            async move { fresh }
+        }
+    })
+    .map(live_to_sse);
+
+    let events = stream::iter(backlog).chain(live).map(move |event| {
+        // Hold the guard for as long as the stream lives
+        let _ = &guard;
+        Ok::<_, Infallible>(event)
+    });
+
+    let sse = Sse::new(events).keep_alive(
+        KeepAlive::new()
+            .interval(HEARTBEAT_INTERVAL)
+            .text("heartbeat"),
+    );
+
+    // Disable response buffering on reverse proxies (nginx, Render)
+    Ok((
+        [(HeaderName::from_static("x-accel-buffering"), HeaderValue::from_static("no"))],
+        sse,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::services::ably::session_channel;
+    use crate::services::outbox;
+    use crate::services::realtime::RealtimeEvent;
+    use crate::services::sse::{SseHub, SseTail};
+    use crate::test_support::{app, create_session, create_user};
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    fn live(seq: i64) -> SseEvent {
+        SseEvent { seq, name: "VOTE_UPDATE".to_string(), data: json!({ "seq": seq }) }
+    }
+
+    #[test]
+    fn live_events_covered_by_the_replay_or_already_sent_are_skipped() {
+        let mut is_fresh = fresh_after(3);
+        let sent: Vec<i64> = [2, 3, 4, 4, 5]
+            .into_iter()
+            .map(live)
+            .filter(|event| is_fresh(event))
+            .map(|event| event.seq)
+            .collect();
+        assert_eq!(sent, [4, 5]);
+    }
+
+    async fn log_event(pool: &crate::db::DbPool, session_id: &str) {
+        let mut tx = pool.begin().await.unwrap();
+        let mut event = RealtimeEvent {
+            channel: session_channel(session_id),
+            name: "QA_UPDATE".to_string(),
+            data: json!({ "sessionId": session_id }),
+        };
+        outbox::enqueue(&mut tx, &mut event).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    fn stream_headers(last_event_id: Option<i64>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
+        if let Some(id) = last_event_id {
+            headers.insert("last-event-id", id.to_string().parse().unwrap());
+        }
+        headers
+    }
+
+    /// The next chunk of an SSE body, as text
+    async fn next_chunk(body: &mut (impl futures::Stream<Item = std::result::Result<axum::body::Bytes, axum::Error>> + Unpin)) -> String {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+            .await
+            .expect("no SSE event in time")
+            .expect("stream ended")
+            .unwrap();
+        String::from_utf8(chunk.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn a_gap_beyond_the_log_starts_the_stream_with_a_resync() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        for _ in 0..3 {
+            log_event(&app.pool, &session_id).await;
+        }
+        let mut config = (*app.config).clone();
+        config.event_log_capacity = 1;
+
+        let response = session_events(
+            State(app.state.clone()),
+            Extension(Arc::new(config)),
+            Path(session_id),
+            Query(EventsQuery { since: None }),
+            stream_headers(Some(0)),
+        )
+        .await
+        .unwrap();
+
+        let mut body = response.into_body().into_data_stream();
+        let first = next_chunk(&mut body).await;
+        assert!(first.contains("event: RESYNC_REQUIRED"), "{}", first);
+        assert!(first.contains("id: 3"), "{}", first);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn the_stream_replays_then_follows_the_event_log() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        for _ in 0..2 {
+            log_event(&app.pool, &session_id).await;
+        }
+        // Events committed elsewhere only reach the hub through the tail
+        SseTail::new(app.state.db_pool.clone(), app.state.sse_hub.clone(), Duration::from_millis(20)).spawn();
+
+        let response = session_events(
+            State(app.state.clone()),
+            Extension(app.config.clone()),
+            Path(session_id.clone()),
+            Query(EventsQuery { since: None }),
+            stream_headers(Some(1)),
+        )
+        .await
+        .unwrap();
+        let mut body = response.into_body().into_data_stream();
+        assert!(next_chunk(&mut body).await.contains("id: 2"));
+
+        log_event(&app.pool, &session_id).await;
+        let live = next_chunk(&mut body).await;
+        assert!(live.contains("event: QA_UPDATE") && live.contains("id: 3"), "{}", live);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn listeners_beyond_the_session_limit_are_refused() {
+        let mut app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        app.state.sse_hub = Arc::new(SseHub::new(1));
+
+        let connect = || session_events(
+            State(app.state.clone()),
+            Extension(app.config.clone()),
+            Path(session_id.clone()),
+            Query(EventsQuery { since: None }),
+            stream_headers(None),
+        );
+        let _first = connect().await.unwrap();
+        let refused = connect().await.unwrap_err().into_response();
+        assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -71,3 +71,26 @@ pub async fn health_check(State(app_state): State<crate::AppState>) -> Result<&'
         Err(StatusCode::SERVICE_UNAVAILABLE)
     }
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxStatusResponse {
+    #[serde(flatten)]
+    pub backlog: crate::services::outbox::OutboxBacklog,
+    #[serde(flatten)]
+    pub metrics: crate::services::outbox::OutboxMetricsSnapshot,
+}
+
+/// Real-time outbox status - pending/dead-lettered events and delivery lag
+/// Use for: Alerting when projectors fall behind
+pub async fn outbox_status(
+    State(app_state): State<crate::AppState>,
+) -> crate::error::Result<Json<OutboxStatusResponse>> {
+    let pool = app_state.db_pool.pool().await?;
+    let backlog = crate::services::outbox::backlog(&pool).await?;
+
+    Ok(Json(OutboxStatusResponse {
+        backlog,
+        metrics: app_state.outbox.metrics.snapshot(),
+    }))
+}
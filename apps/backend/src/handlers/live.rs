@@ -1,12 +1,23 @@
-use axum::{extract::{State, Path}, Json};
+use axum::{extract::{State, Path, Extension}, Json};
 use serde::{Deserialize, Serialize};
 use sqlx::query_as;
+use std::sync::Arc;
 
+use crate::config::Config;
 use crate::error::{AppError, Result};
-use crate::models::session::Session;
+use crate::handlers::student::enqueue_qa_events;
+use crate::models::session::{Session, SessionRole};
+use crate::models::student::{ParticipantConnection, Question};
 use crate::models::response::ApiResponse;
 use crate::middleware::auth::AuthUser;
-use crate::services::ably::publish_state_update;
+use crate::services::ably::{
+    question_event, question_hidden_event, question_moderated_event, question_removed_event,
+    state_update_event,
+};
+use crate::services::ags;
+use crate::services::join_codes;
+use crate::services::outbox;
+use crate::services::session_access;
 
 /// State update payload for real-time broadcast
 #[derive(Serialize)]
@@ -34,6 +45,11 @@ pub struct UpdateSlideVisibilityRequest {
     is_hidden: bool,
 }
 
+#[derive(Deserialize)]
+pub struct SetQuestionApprovalRequest {
+    approved: bool,
+}
+
 /// Set current slide for live presentation
 pub async fn set_current_slide(
     State(app_state): State<crate::AppState>,
@@ -42,17 +58,22 @@ pub async fn set_current_slide(
     Json(payload): Json<SetCurrentSlideRequest>,
 ) -> Result<Json<ApiResponse<Session>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    // Leaving the slide: publish its final results before the state change
+    app_state.vote_coalescer.flush_session(&session_id).await;
+
+    let mut tx = pool.begin().await?;
 
     sqlx::query("UPDATE sessions SET current_slide_id = ? WHERE id = ?")
         .bind(&payload.slide_id)
         .bind(&session_id)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
 
     let session = query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
         .bind(&session_id)
-        .fetch_one(&pool)
+        .fetch_one(&mut *tx)
         .await?;
 
     let state_payload = StateUpdatePayload {
@@ -60,10 +81,10 @@ pub async fn set_current_slide(
         is_presentation_active: session.is_presentation_active,
         is_results_visible: session.is_results_visible,
     };
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_state_update(&session_id_for_publish, &state_payload).await;
-    });
+    let mut event = state_update_event(&session_id, &state_payload);
+    outbox::enqueue(&mut tx, &mut event).await?;
+    tx.commit().await?;
+    app_state.outbox.committed(vec![event]);
 
     Ok(Json(ApiResponse::success(session)))
 }
@@ -76,17 +97,19 @@ pub async fn set_results_visibility(
     Json(payload): Json<SetResultsVisibilityRequest>,
 ) -> Result<Json<ApiResponse<Session>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    let mut tx = pool.begin().await?;
 
     sqlx::query("UPDATE sessions SET is_results_visible = ? WHERE id = ?")
         .bind(payload.visible)
         .bind(&session_id)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
 
     let session = query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
         .bind(&session_id)
-        .fetch_one(&pool)
+        .fetch_one(&mut *tx)
         .await?;
 
     let state_payload = StateUpdatePayload {
@@ -94,10 +117,10 @@ pub async fn set_results_visibility(
         is_presentation_active: session.is_presentation_active,
         is_results_visible: session.is_results_visible,
     };
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_state_update(&session_id_for_publish, &state_payload).await;
-    });
+    let mut event = state_update_event(&session_id, &state_payload);
+    outbox::enqueue(&mut tx, &mut event).await?;
+    tx.commit().await?;
+    app_state.outbox.committed(vec![event]);
 
     Ok(Json(ApiResponse::success(session)))
 }
@@ -111,7 +134,7 @@ pub async fn update_slide_visibility(
     Json(payload): Json<UpdateSlideVisibilityRequest>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
 
     sqlx::query("UPDATE slides SET is_hidden = ? WHERE id = ? AND session_id = ?")
         .bind(payload.is_hidden)
@@ -126,20 +149,40 @@ pub async fn update_slide_visibility(
 /// Go live with session
 pub async fn go_live(
     State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
     AuthUser { user_id, .. }: AuthUser,
     Path(session_id): Path<String>,
 ) -> Result<Json<ApiResponse<Session>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    // Collaborators present the creator's session, so it is the creator
+    // whose email has to be verified
+    if config.require_verified_email_to_go_live {
+        let verified: Option<bool> = sqlx::query_scalar(
+            "SELECT u.email_verified_at IS NOT NULL FROM sessions s JOIN users u ON u.id = s.creator_id WHERE s.id = ?"
+        )
+        .bind(&session_id)
+        .fetch_optional(&pool)
+        .await?;
+        if verified != Some(true) {
+            return Err(AppError::Forbidden(
+                "The session creator has to verify their email before it can go live".to_string(),
+            ));
+        }
+    }
+
+    let mut tx = pool.begin().await?;
 
     sqlx::query("UPDATE sessions SET is_presentation_active = TRUE, status = 'published' WHERE id = ?")
         .bind(&session_id)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
+    join_codes::allocate(&mut tx, &session_id).await?;
 
     let session = query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
         .bind(&session_id)
-        .fetch_one(&pool)
+        .fetch_one(&mut *tx)
         .await?;
 
     let state_payload = StateUpdatePayload {
@@ -147,10 +190,10 @@ pub async fn go_live(
         is_presentation_active: session.is_presentation_active,
         is_results_visible: session.is_results_visible,
     };
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_state_update(&session_id_for_publish, &state_payload).await;
-    });
+    let mut event = state_update_event(&session_id, &state_payload);
+    outbox::enqueue(&mut tx, &mut event).await?;
+    tx.commit().await?;
+    app_state.outbox.committed(vec![event]);
 
     Ok(Json(ApiResponse::success(session)))
 }
@@ -162,16 +205,23 @@ pub async fn stop_live(
     Path(session_id): Path<String>,
 ) -> Result<Json<ApiResponse<Session>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    // Voting is over: publish results still in the coalescing window first
+    app_state.vote_coalescer.flush_session(&session_id).await;
+
+    let mut tx = pool.begin().await?;
 
     sqlx::query("UPDATE sessions SET is_presentation_active = FALSE WHERE id = ?")
         .bind(&session_id)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
+    join_codes::release(&mut tx, &session_id).await?;
+    ParticipantConnection::close_open(&mut tx, &session_id).await?;
 
     let session = query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
         .bind(&session_id)
-        .fetch_one(&pool)
+        .fetch_one(&mut *tx)
         .await?;
 
     let state_payload = StateUpdatePayload {
@@ -179,30 +229,184 @@ pub async fn stop_live(
         is_presentation_active: session.is_presentation_active,
         is_results_visible: session.is_results_visible,
     };
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_state_update(&session_id_for_publish, &state_payload).await;
-    });
+    let mut event = state_update_event(&session_id, &state_payload);
+    outbox::enqueue(&mut tx, &mut event).await?;
+
+    // Quiz scores of LMS learners go back to the course gradebook
+    let grades_queued = ags::enqueue_session(&mut tx, &session_id).await?;
+    tx.commit().await?;
+    app_state.outbox.committed(vec![event]);
+    if grades_queued > 0 {
+        tracing::info!("Queued {} grades for passback from session {}", grades_queued, session_id);
+        app_state.grade_sync.committed();
+    }
 
     Ok(Json(ApiResponse::success(session)))
 }
 
-/// Helper function to verify session ownership
-async fn verify_session_ownership(
-    pool: &crate::db::DbPool,
-    session_id: &str,
-    user_id: &str,
-) -> Result<()> {
-    let exists: Option<bool> = sqlx::query_scalar(
-        "SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ? AND creator_id = ?)"
+/// Approve or hide a question (moderation)
+pub async fn set_question_approval(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path((session_id, question_id)): Path<(String, String)>,
+    Json(payload): Json<SetQuestionApprovalRequest>,
+) -> Result<Json<ApiResponse<Question>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    let mut question = find_session_question(&pool, &session_id, &question_id).await?;
+
+    let mut tx = pool.begin().await?;
+    Question::approve(&mut tx, &question_id, payload.approved).await?;
+    question.is_approved = payload.approved;
+
+    // Students only ever see approved questions; staff get the full question
+    let public_event = if question.is_approved {
+        question_event(&session_id, "QUESTION_UPDATED", &question)
+    } else {
+        question_hidden_event(&session_id, &question_id)
+    };
+    let mut events = enqueue_qa_events(&mut tx, &config, &session_id, public_event).await?;
+    let mut moderated = question_moderated_event(&session_id, &question);
+    outbox::enqueue(&mut tx, &mut moderated).await?;
+    events.push(moderated);
+    tx.commit().await?;
+    app_state.outbox.committed(events);
+
+    Ok(Json(ApiResponse::success(question)))
+}
+
+/// Delete a question (moderation)
+pub async fn delete_question(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path((session_id, question_id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<serde_json::Value>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Presenter).await?;
+
+    find_session_question(&pool, &session_id, &question_id).await?;
+
+    let mut tx = pool.begin().await?;
+    Question::delete(&mut tx, &question_id).await?;
+
+    let events = enqueue_qa_events(
+        &mut tx,
+        &config,
+        &session_id,
+        question_removed_event(&session_id, &question_id),
     )
-    .bind(session_id)
-    .bind(user_id)
-    .fetch_optional(pool)
     .await?;
+    tx.commit().await?;
+    app_state.outbox.committed(events);
+
+    Ok(Json(ApiResponse::success(serde_json::json!({ "message": "Question deleted" }))))
+}
+
+/// Load a question, making sure it belongs to the session
+async fn find_session_question(
+    pool: &crate::db::DbPool,
+    session_id: &str,
+    question_id: &str,
+) -> Result<Question> {
+    Question::find_by_id(pool, question_id)
+        .await?
+        .filter(|q| q.session_id == session_id)
+        .ok_or_else(|| AppError::NotFound("Question not found".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::services::ably::session_channel;
+    use crate::test_support::{add_member, app, create_session, create_slide, create_user};
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn changing_the_slide_publishes_the_new_state() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let slide_id = create_slide(&app.pool, &session_id).await;
+
+        let Json(response) = set_current_slide(
+            State(app.state.clone()),
+            teacher,
+            Path(session_id.clone()),
+            Json(SetCurrentSlideRequest { slide_id: Some(slide_id.clone()) }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.data.current_slide_id.as_deref(), Some(slide_id.as_str()));
+
+        let events = app.wait_for_events(&session_channel(&session_id), 1).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "STATE_UPDATE");
+        assert_eq!(events[0].data["payload"]["currentSlideId"], slide_id.as_str());
+        assert_eq!(events[0].data["payload"]["isPresentationActive"], false);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn viewers_cannot_change_the_slide() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let viewer = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let slide_id = create_slide(&app.pool, &session_id).await;
+        add_member(&app.pool, &session_id, &viewer.user_id, SessionRole::Viewer).await;
+
+        let result = set_current_slide(
+            State(app.state.clone()),
+            viewer,
+            Path(session_id.clone()),
+            Json(SetCurrentSlideRequest { slide_id: Some(slide_id) }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(app.published.events_for(&session_channel(&session_id)).is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn going_live_needs_the_creators_email_verified() {
+        let app = app().await;
+        let mut config = (*app.config).clone();
+        config.require_verified_email_to_go_live = true;
+        let config = Arc::new(config);
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let presenter = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        add_member(&app.pool, &session_id, &presenter.user_id, SessionRole::Presenter).await;
+        sqlx::query("UPDATE users SET email_verified_at = NULL WHERE id = ?")
+            .bind(&teacher.user_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        // A verified presenter cannot go live for an unverified creator
+        let refused = go_live(
+            State(app.state.clone()),
+            Extension(config.clone()),
+            presenter,
+            Path(session_id.clone()),
+        )
+        .await;
+        assert!(matches!(refused, Err(AppError::Forbidden(_))));
 
-    match exists {
-        Some(true) => Ok(()),
-        _ => Err(AppError::Auth("Unauthorized access to session".to_string())),
+        sqlx::query("UPDATE users SET email_verified_at = NOW() WHERE id = ?")
+            .bind(&teacher.user_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let Json(response) = go_live(State(app.state.clone()), Extension(config), teacher, Path(session_id))
+            .await
+            .unwrap();
+        assert!(response.data.is_presentation_active);
     }
 }
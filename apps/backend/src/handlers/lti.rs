@@ -0,0 +1,401 @@
+use axum::{
+    extract::{Extension, Form, State},
+    response::Redirect,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::handlers::auth::{mfa_challenge, with_auth_cookies};
+use crate::middleware::auth::AuthUser;
+use crate::models::lti::{LtiParticipant, LtiPlatform, LtiResourceLink};
+use crate::models::response::ApiResponse;
+use crate::models::session::SessionRole;
+use crate::models::student::Participant;
+use crate::models::user::Role;
+use crate::services::auth_tokens;
+use crate::services::identity::{self, ExternalIdentity, ExternalSignIn};
+use crate::services::lti::{self, DeepLinkContext, LaunchClaims, LoginInitiation, PendingAccountLink};
+use crate::services::participant;
+use crate::services::session_access;
+
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Deserialize)]
+pub struct LaunchForm {
+    pub id_token: String,
+    pub state: String,
+}
+
+fn launch_url(config: &Config) -> String {
+    format!("{}/api/lti/launch", config.public_api_url)
+}
+
+/// Send the browser to the web app's LTI page; details travel in the fragment
+/// so they stay out of server logs
+fn to_app(config: &Config, path: &str, params: &[(&str, &str)]) -> Redirect {
+    let fragment = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+    Redirect::to(&format!("{}{}#{}", config.app_base_url, path, fragment))
+}
+
+/// OIDC third-party login initiation (GET or form POST from the platform)
+pub async fn login_initiation(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Form(initiation): Form<LoginInitiation>,
+) -> Result<Redirect> {
+    let pool = app_state.db_pool.pool().await?;
+    let platform = LtiPlatform::find_for_login(&pool, &initiation.iss, initiation.client_id.as_deref()).await?;
+
+    if let (Some(registered), Some(requested)) = (&platform.deployment_id, &initiation.lti_deployment_id) {
+        if registered != requested {
+            return Err(AppError::Forbidden("This LTI deployment is not registered".to_string()));
+        }
+    }
+
+    let authentication_url = lti::begin_login(&pool, &platform, &initiation, &launch_url(&config)).await?;
+    Ok(Redirect::to(&authentication_url))
+}
+
+/// Launch endpoint: the platform form-posts the signed `id_token` here
+pub async fn launch(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    jar: CookieJar,
+    Form(form): Form<LaunchForm>,
+) -> (CookieJar, Redirect) {
+    match handle_launch(&app_state, &config, jar.clone(), form).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!("LTI launch failed: {}", e);
+            let message = match &e {
+                AppError::Auth(msg) | AppError::Forbidden(msg) | AppError::NotFound(msg) | AppError::Input(msg) => msg.clone(),
+                _ => "The launch could not be completed".to_string(),
+            };
+            (jar, to_app(&config, "/lti/launch", &[("error", &message)]))
+        }
+    }
+}
+
+async fn handle_launch(
+    app_state: &crate::AppState,
+    config: &Config,
+    jar: CookieJar,
+    form: LaunchForm,
+) -> Result<(CookieJar, Redirect)> {
+    let pool = app_state.db_pool.pool().await?;
+    let (platform, claims) = lti::verify_launch(&pool, &app_state.jwks, &form.state, &form.id_token).await?;
+
+    match claims.message_type.as_str() {
+        lti::RESOURCE_LINK_REQUEST => resource_link_launch(app_state, config, jar, &platform, &claims).await,
+        lti::DEEP_LINKING_REQUEST => deep_linking_launch(app_state, config, jar, &platform, &claims).await,
+        other => Err(AppError::Input(format!("Unsupported LTI message type {}", other))),
+    }
+}
+
+/// Outcome of matching an instructor launch to a ClassColab account
+enum Instructor {
+    Account(crate::models::user::User),
+    /// The instructor has to sign in and link the course account first
+    NeedsLink(PendingAccountLink),
+}
+
+/// Local account of an LMS instructor. Accounts are created on first launch
+/// for emails the platform vouches for, but an existing account is only used
+/// once its owner has linked it.
+async fn instructor_account(
+    pool: &crate::db::DbPool,
+    platform: &LtiPlatform,
+    claims: &LaunchClaims,
+) -> Result<Instructor> {
+    let subject = claims
+        .sub
+        .as_deref()
+        .ok_or_else(|| AppError::Input("Anonymous launches are not supported for instructors".to_string()))?;
+    let name = claims.display_name();
+
+    let signed_in = identity::find_or_create_user(pool, &ExternalIdentity {
+        provider: &lti::identity_provider(&platform.id),
+        subject,
+        email: claims.email.as_deref(),
+        email_verified: claims.email_verified,
+        name: name.as_deref(),
+        default_role: Role::Teacher,
+    })
+    .await?;
+    let user = match signed_in {
+        ExternalSignIn::User(user) => user,
+        ExternalSignIn::LinkRequired => {
+            return Ok(Instructor::NeedsLink(PendingAccountLink {
+                platform_id: platform.id.clone(),
+                subject: subject.to_string(),
+                email: claims.email.clone(),
+                issued_at: chrono::Utc::now().timestamp(),
+            }));
+        }
+    };
+
+    if user.role() < Role::Teacher {
+        return Err(AppError::Forbidden("Teacher account required".to_string()));
+    }
+    Ok(Instructor::Account(user))
+}
+
+/// Ask the instructor to sign in and link their course account
+fn to_account_link(config: &Config, link: &PendingAccountLink) -> Redirect {
+    to_app(config, "/lti/launch", &[
+        ("link", &link.seal(&config.jwt_secret)),
+        ("email", link.email.as_deref().unwrap_or_default()),
+    ])
+}
+
+async fn resource_link_launch(
+    app_state: &crate::AppState,
+    config: &Config,
+    jar: CookieJar,
+    platform: &LtiPlatform,
+    claims: &LaunchClaims,
+) -> Result<(CookieJar, Redirect)> {
+    let pool = app_state.db_pool.pool().await?;
+    let link = claims
+        .resource_link
+        .as_ref()
+        .ok_or_else(|| AppError::Input("Launch has no resource link".to_string()))?;
+
+    // Bound on the first launch of a deep-linked course link
+    let session_id = match LtiResourceLink::session_for(&pool, &platform.id, &link.id).await? {
+        Some(session_id) => session_id,
+        None => {
+            let session_id = claims.granted_session_id(&config.jwt_secret, &platform.id).ok_or_else(|| {
+                AppError::NotFound("This course link is not connected to a session yet".to_string())
+            })?;
+            app_state.session_service.find_session(session_id).await?;
+            LtiResourceLink::bind(
+                &pool,
+                &platform.id,
+                &link.id,
+                &claims.deployment_id,
+                claims.context.as_ref().map(|c| c.id.as_str()),
+                session_id,
+            )
+            .await?;
+            session_id.to_string()
+        }
+    };
+    let session = app_state.session_service.find_session(&session_id).await?;
+
+    if let Some((lineitems_url, line_item_url)) = claims.ags_endpoint() {
+        LtiResourceLink::set_ags_endpoint(&pool, &platform.id, &link.id, lineitems_url, line_item_url).await?;
+    }
+
+    if claims.is_staff() {
+        let user = match instructor_account(&pool, platform, claims).await? {
+            Instructor::Account(user) => user,
+            Instructor::NeedsLink(link) => return Ok((jar, to_account_link(config, &link))),
+        };
+        let role = session_access::role_of(&pool, &session.id, &user.id).await?;
+        if role.is_none_or(|role| role < SessionRole::Presenter) {
+            return Err(AppError::Forbidden("This session belongs to another ClassColab account".to_string()));
+        }
+        let next = format!("/staff/session/{}", session.id);
+        if let Some(mfa_token) = mfa_challenge(&pool, config, &user.id).await? {
+            return Ok((jar, to_app(config, "/lti/launch", &[("mfaToken", &mfa_token), ("next", &next)])));
+        }
+
+        let tokens = auth_tokens::start_family(
+            &pool,
+            config,
+            &app_state.jwt_keys,
+            &user.id,
+            user.role().as_str(),
+        ).await?;
+        tracing::info!("LTI instructor launch of session {} by user {}", session.id, user.id);
+        return Ok((
+            with_auth_cookies(jar, config, &tokens.access_token, &tokens.refresh_token),
+            to_app(config, "/lti/launch", &[("next", &next)]),
+        ));
+    }
+
+    let subject = claims
+        .sub
+        .as_deref()
+        .ok_or_else(|| AppError::Input("Anonymous launches are not supported".to_string()))?;
+    let participant_id = LtiParticipant::participant_id_for(&pool, &session.id, &platform.id, subject, &link.id).await?;
+
+    let name: String = claims
+        .display_name()
+        .unwrap_or_else(|| "Student".to_string())
+        .chars()
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    let sanitized_name = name.replace('<', "&lt;").replace('>', "&gt;");
+    Participant::create(&pool, &participant_id, &session.id, &sanitized_name).await?;
+
+    let participant_token = participant::sign_token(&config.jwt_secret, &session.id, &participant_id);
+    let share_token = session.share_token.clone().unwrap_or_default();
+
+    Ok((
+        jar,
+        to_app(config, "/lti/launch", &[
+            ("sessionId", &session.id),
+            ("shareToken", &share_token),
+            ("participantId", &participant_id),
+            ("participantToken", &participant_token),
+            ("name", &name),
+        ]),
+    ))
+}
+
+async fn deep_linking_launch(
+    app_state: &crate::AppState,
+    config: &Config,
+    jar: CookieJar,
+    platform: &LtiPlatform,
+    claims: &LaunchClaims,
+) -> Result<(CookieJar, Redirect)> {
+    if !claims.is_staff() {
+        return Err(AppError::Forbidden("Only instructors can add sessions to a course".to_string()));
+    }
+    if app_state.lti_tool_key.is_none() {
+        return Err(AppError::Internal("LTI_PRIVATE_KEY not configured".to_string()));
+    }
+    let settings = claims
+        .deep_linking_settings
+        .as_ref()
+        .ok_or_else(|| AppError::Input("Deep linking settings missing".to_string()))?;
+
+    let pool = app_state.db_pool.pool().await?;
+    let user = match instructor_account(&pool, platform, claims).await? {
+        Instructor::Account(user) => user,
+        Instructor::NeedsLink(link) => return Ok((jar, to_account_link(config, &link))),
+    };
+
+    let context = DeepLinkContext {
+        platform_id: platform.id.clone(),
+        deployment_id: claims.deployment_id.clone(),
+        return_url: settings.deep_link_return_url.clone(),
+        data: settings.data.clone(),
+        user_id: user.id.clone(),
+        issued_at: chrono::Utc::now().timestamp(),
+    };
+
+    Ok((jar, to_app(config, "/lti/deep-link", &[("context", &context.seal(&config.jwt_secret))])))
+}
+
+#[derive(Deserialize)]
+pub struct DeepLinkSessionsRequest {
+    context: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLinkSession {
+    id: String,
+    title: String,
+    status: String,
+}
+
+/// Sessions the instructor of a deep linking request can pick from (those
+/// they can present)
+pub async fn deep_link_sessions(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Json(payload): Json<DeepLinkSessionsRequest>,
+) -> Result<Json<ApiResponse<Vec<DeepLinkSession>>>> {
+    let context = DeepLinkContext::open(&config.jwt_secret, &payload.context)?;
+
+    let sessions = app_state
+        .session_service
+        .get_user_sessions_with_slide_count(&context.user_id)
+        .await?
+        .into_iter()
+        .filter(|s| s.role >= SessionRole::Presenter && s.session.status != "archived")
+        .map(|s| DeepLinkSession {
+            id: s.session.id,
+            title: s.session.title,
+            status: s.session.status,
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(sessions)))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLinkRequest {
+    context: String,
+    session_id: String,
+}
+
+/// Build the signed deep linking response for the picked session; the browser
+/// form-posts it (`JWT`) to `returnUrl`
+pub async fn deep_link_response(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Json(payload): Json<DeepLinkRequest>,
+) -> Result<Json<ApiResponse<Value>>> {
+    let context = DeepLinkContext::open(&config.jwt_secret, &payload.context)?;
+    let key = app_state
+        .lti_tool_key
+        .as_deref()
+        .ok_or_else(|| AppError::Internal("LTI_PRIVATE_KEY not configured".to_string()))?;
+
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &payload.session_id, &context.user_id, SessionRole::Presenter).await?;
+    let session = app_state.session_service.find_session(&payload.session_id).await?;
+
+    let platform = LtiPlatform::find(&pool, &context.platform_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("LTI platform is no longer registered".to_string()))?;
+
+    let jwt = lti::deep_linking_response(key, &config.jwt_secret, &platform, &context, &launch_url(&config), &session.id, &session.title)?;
+
+    Ok(Json(ApiResponse::success(json!({
+        "returnUrl": context.return_url,
+        "jwt": jwt,
+    }))))
+}
+
+#[derive(Deserialize)]
+pub struct AccountLinkRequest {
+    link: String,
+}
+
+/// Link the course account of an instructor launch to the signed-in user;
+/// later launches from the course sign in to this account
+pub async fn link_account(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Json(payload): Json<AccountLinkRequest>,
+) -> Result<Json<ApiResponse<()>>> {
+    let link = PendingAccountLink::open(&config.jwt_secret, &payload.link)?;
+    let pool = app_state.db_pool.pool().await?;
+    LtiPlatform::find(&pool, &link.platform_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("LTI platform is no longer registered".to_string()))?;
+
+    identity::link(
+        &pool,
+        &lti::identity_provider(&link.platform_id),
+        &link.subject,
+        link.email.as_deref(),
+        &user_id,
+    )
+    .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Public keys of this tool, registered with platforms as the tool JWKS URL
+pub async fn jwks(State(app_state): State<crate::AppState>) -> Json<Value> {
+    let keys: Vec<&Value> = app_state.lti_tool_key.iter().map(|key| key.public_jwk()).collect();
+    Json(json!({ "keys": keys }))
+}
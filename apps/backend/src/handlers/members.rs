@@ -0,0 +1,138 @@
+use axum::{extract::{Extension, Path, State}, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::handlers::account::find_user;
+use crate::handlers::auth::{send_email, MAX_EMAIL_LENGTH};
+use crate::middleware::auth::AuthUser;
+use crate::models::response::ApiResponse;
+use crate::models::session::SessionRole;
+use crate::services::mailer::EmailMessage;
+use crate::services::session_access;
+use crate::services::session_members::{self, PendingInvite, SessionMember, INVITE_TTL_DAYS};
+
+#[derive(Deserialize)]
+pub struct InviteMemberRequest {
+    email: String,
+    role: String,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptInviteRequest {
+    token: String,
+}
+
+#[derive(Serialize)]
+pub struct SessionMembers {
+    members: Vec<SessionMember>,
+    invites: Vec<PendingInvite>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptedInvite {
+    session_id: String,
+    role: SessionRole,
+}
+
+/// Collaborators and pending invitations of a session
+pub async fn list_members(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<SessionMembers>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Viewer).await?;
+
+    let (members, invites) = tokio::try_join!(
+        session_members::list_members(&pool, &session_id),
+        session_members::list_invites(&pool, &session_id),
+    )?;
+    Ok(Json(ApiResponse::success(SessionMembers { members, invites })))
+}
+
+/// Invite someone by email with a role (owners only)
+pub async fn invite_member(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(session_id): Path<String>,
+    Json(payload): Json<InviteMemberRequest>,
+) -> Result<Json<ApiResponse<PendingInvite>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Owner).await?;
+
+    let email = payload.email.trim().to_lowercase();
+    if email.len() > MAX_EMAIL_LENGTH || !email.contains('@') || !email.contains('.') {
+        return Err(AppError::Input("Invalid email format".to_string()));
+    }
+    let role = SessionRole::parse(&payload.role)
+        .ok_or_else(|| AppError::Input(format!("Unknown role {}", payload.role)))?;
+
+    let (invite, token) = session_members::invite(&pool, &session_id, &email, role, &user_id).await?;
+    let inviter = find_user(&pool, &user_id).await?;
+    let session = app_state.session_service.find_session(&session_id).await?;
+
+    let link = format!("{}/invites/accept?token={}", config.app_base_url, urlencoding::encode(&token));
+    send_email(&app_state, EmailMessage {
+        to: email,
+        subject: format!("{} invited you to \"{}\" on ClassColab", inviter.name, session.title),
+        body: format!(
+            "{} invited you to collaborate on the session \"{}\" as {}.\n\n\
+             Open this link within {} days and sign in with this email address to accept:\n{}",
+            inviter.name, session.title, role.as_str(), INVITE_TTL_DAYS, link
+        ),
+    });
+    tracing::info!("User {} invited a {} to session {}", user_id, role.as_str(), session_id);
+
+    Ok(Json(ApiResponse::success(invite)))
+}
+
+/// Accept an invitation sent to the caller's email address
+pub async fn accept_invite(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Json(payload): Json<AcceptInviteRequest>,
+) -> Result<Json<ApiResponse<AcceptedInvite>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let user = find_user(&pool, &user_id).await?;
+    let (session_id, role) = session_members::accept(&pool, &payload.token, &user.id, &user.email).await?;
+    tracing::info!("User {} joined session {} as {}", user_id, session_id, role.as_str());
+
+    Ok(Json(ApiResponse::success(AcceptedInvite { session_id, role })))
+}
+
+/// Remove a collaborator (owners), or leave a session (any member)
+pub async fn remove_member(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path((session_id, member_id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let min = if member_id == user_id { SessionRole::Viewer } else { SessionRole::Owner };
+    session_access::require(&pool, &session_id, &user_id, min).await?;
+
+    let session = app_state.session_service.find_session(&session_id).await?;
+    if session.creator_id == member_id {
+        return Err(AppError::Input("The session creator cannot be removed".to_string()));
+    }
+
+    session_members::remove(&pool, &session_id, &member_id).await?;
+    tracing::info!("User {} removed member {} from session {}", user_id, member_id, session_id);
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Withdraw a pending invitation (owners only)
+pub async fn revoke_invite(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path((session_id, invite_id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Owner).await?;
+
+    session_members::revoke_invite(&pool, &session_id, &invite_id).await?;
+    Ok(Json(ApiResponse::success(())))
+}
@@ -0,0 +1,123 @@
+use axum::{extract::State, Extension, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::handlers::account::{confirm_password, find_user};
+use crate::middleware::auth::{AuthUser, RequireRole, Teacher};
+use crate::models::response::ApiResponse;
+use crate::models::user::User;
+use crate::services::mfa::{self, MfaStatus};
+use crate::services::{login_guard, totp};
+
+#[derive(Deserialize)]
+pub struct MfaCodeRequest {
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct DisableMfaRequest {
+    password: String,
+    /// Authenticator code or recovery code
+    code: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollment {
+    /// Base32 secret for manual entry
+    secret: String,
+    /// `otpauth://` URI to show as a QR code
+    provisioning_uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodes {
+    /// Shown once; each works a single time
+    recovery_codes: Vec<String>,
+}
+
+/// Whether 2FA is on and how many recovery codes are left
+pub async fn get_mfa_status(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<Json<ApiResponse<MfaStatus>>> {
+    let pool = app_state.db_pool.pool().await?;
+    Ok(Json(ApiResponse::success(mfa::status(&pool, &user_id).await?)))
+}
+
+/// Start TOTP enrollment (staff only); 2FA is enabled by the confirm step
+pub async fn start_totp_enrollment(
+    State(app_state): State<crate::AppState>,
+    RequireRole { user, .. }: RequireRole<Teacher>,
+) -> Result<Json<ApiResponse<TotpEnrollment>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let account = find_user(&pool, &user.user_id).await?;
+    let secret = mfa::start_enrollment(&pool, &user.user_id).await?;
+
+    Ok(Json(ApiResponse::success(TotpEnrollment {
+        provisioning_uri: totp::provisioning_uri(mfa::TOTP_ISSUER, &account.email, &secret),
+        secret,
+    })))
+}
+
+/// Enable 2FA with a first code from the authenticator app
+pub async fn confirm_totp_enrollment(
+    State(app_state): State<crate::AppState>,
+    RequireRole { user, .. }: RequireRole<Teacher>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<ApiResponse<RecoveryCodes>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let recovery_codes = mfa::confirm_enrollment(&pool, &user.user_id, &payload.code).await?;
+    tracing::info!("User {} enabled two-factor authentication", user.user_id);
+
+    Ok(Json(ApiResponse::success(RecoveryCodes { recovery_codes })))
+}
+
+/// Check a second factor of a signed-in user, throttled like the login
+/// step so a stolen session cannot guess its way through the codes
+async fn verify_code(pool: &DbPool, config: &Config, user: &User, code: &str) -> Result<()> {
+    login_guard::reserve_attempt(pool, &config.login_policy(), &user.email).await?;
+    let mut conn = pool.acquire().await?;
+    if !mfa::verify(&mut conn, &user.id, code).await? {
+        return Err(AppError::Forbidden("Invalid authentication code".to_string()));
+    }
+    login_guard::clear(pool, &user.email).await?;
+    Ok(())
+}
+
+/// Replace the recovery codes; needs a current code
+pub async fn regenerate_recovery_codes(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<ApiResponse<RecoveryCodes>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let user = find_user(&pool, &user_id).await?;
+    verify_code(&pool, &config, &user, &payload.code).await?;
+    let recovery_codes = mfa::regenerate_recovery_codes(&pool, &user_id).await?;
+    tracing::info!("User {} regenerated their recovery codes", user_id);
+
+    Ok(Json(ApiResponse::success(RecoveryCodes { recovery_codes })))
+}
+
+/// Turn 2FA off; needs the password and a current code
+pub async fn disable_mfa(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Json(payload): Json<DisableMfaRequest>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let user = find_user(&pool, &user_id).await?;
+    confirm_password(&user, &payload.password).await?;
+    verify_code(&pool, &config, &user, &payload.code).await?;
+
+    mfa::disable(&pool, &user_id).await?;
+    tracing::info!("User {} disabled two-factor authentication", user_id);
+    Ok(Json(ApiResponse::success(())))
+}
@@ -1,9 +1,20 @@
 pub mod health;
 pub mod auth;
+pub mod account;
 pub mod session;
+pub mod members;
+pub mod organizations;
 pub mod slide;
 pub mod live;
 pub mod public;
+pub mod clicker;
 pub mod ably;
 pub mod stats;
 pub mod student;
+pub mod events;
+pub mod webhooks;
+pub mod admin;
+pub mod oidc;
+pub mod lti;
+pub mod mfa;
+pub mod tokens;
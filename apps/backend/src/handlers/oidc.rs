@@ -0,0 +1,218 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    response::Redirect,
+    Json,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::handlers::auth::{mfa_challenge, with_auth_cookies};
+use crate::middleware::auth::AuthUser;
+use crate::models::response::ApiResponse;
+use crate::services::auth_tokens;
+use crate::services::identity::{self, ExternalIdentity, ExternalSignIn};
+use crate::services::oidc::LoginState;
+
+const LOGIN_STATE_COOKIE: &str = "oidc_login";
+const LOGIN_STATE_COOKIE_PATH: &str = "/api/auth/oidc";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderInfo {
+    pub id: String,
+    pub display_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+fn redirect_uri(config: &Config, provider_id: &str) -> String {
+    format!("{}/api/auth/oidc/{}/callback", config.public_api_url, provider_id)
+}
+
+// The provider redirects back with a top-level GET, which SameSite=Lax allows
+fn login_state_cookie(value: String) -> Cookie<'static> {
+    Cookie::build((LOGIN_STATE_COOKIE, value))
+        .path(LOGIN_STATE_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(true)
+        .max_age(time::Duration::minutes(10))
+        .build()
+}
+
+/// Single sign-on providers to offer on the login page
+pub async fn list_providers(
+    State(app_state): State<crate::AppState>,
+) -> Json<ApiResponse<Vec<ProviderInfo>>> {
+    let providers = app_state
+        .oidc
+        .providers()
+        .iter()
+        .map(|p| ProviderInfo {
+            id: p.id.clone(),
+            display_name: p.display_name.clone(),
+        })
+        .collect();
+    Json(ApiResponse::success(providers))
+}
+
+/// Start sign-in: remember state, nonce and PKCE verifier in a signed cookie
+/// and send the browser to the provider
+pub async fn login(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Path(provider_id): Path<String>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Redirect)> {
+    let provider = app_state.oidc.provider(&provider_id)?;
+    let (authorization_url, login) = app_state
+        .oidc
+        .begin_login(provider, &redirect_uri(&config, &provider.id))
+        .await?;
+
+    Ok((
+        jar.add(login_state_cookie(login.seal(&config.jwt_secret))),
+        Redirect::to(&authorization_url),
+    ))
+}
+
+/// Start linking a provider account to the signed-in user; the provider
+/// round trip is the same as for sign-in
+pub async fn link(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(provider_id): Path<String>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Redirect)> {
+    let provider = app_state.oidc.provider(&provider_id)?;
+    let (authorization_url, mut login) = app_state
+        .oidc
+        .begin_login(provider, &redirect_uri(&config, &provider.id))
+        .await?;
+    login.link_user_id = Some(user_id);
+
+    Ok((
+        jar.add(login_state_cookie(login.seal(&config.jwt_secret))),
+        Redirect::to(&authorization_url),
+    ))
+}
+
+/// What a completed provider round trip leads to
+enum Completion {
+    /// Access and refresh token of the new sign-in
+    SignedIn(String, String),
+    /// The account has 2FA; the challenge token is finished at `/api/auth/login/mfa`
+    MfaRequired(String),
+    Linked,
+    /// No account is linked and none could be created; the user has to sign
+    /// in another way and link the provider account
+    LinkRequired,
+}
+
+/// Provider redirect target: exchange the code, sign the user in with the same
+/// cookies as password login, then hand over to the web app
+pub async fn callback(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    Path(provider_id): Path<String>,
+    Query(query): Query<CallbackQuery>,
+    jar: CookieJar,
+) -> (CookieJar, Redirect) {
+    let sealed = jar.get(LOGIN_STATE_COOKIE).map(|c| c.value().to_string());
+    let jar = jar.remove(login_state_cookie(String::new()));
+
+    match complete_sign_in(&app_state, &config, &provider_id, query, sealed.as_deref()).await {
+        Ok(Completion::SignedIn(access_token, refresh_token)) => (
+            with_auth_cookies(jar, &config, &access_token, &refresh_token),
+            Redirect::to(&format!("{}/login/sso", config.app_base_url)),
+        ),
+        // In the fragment, so the challenge token stays out of server logs
+        Ok(Completion::MfaRequired(mfa_token)) => (
+            jar,
+            Redirect::to(&format!("{}/login#mfaToken={}", config.app_base_url, urlencoding::encode(&mfa_token))),
+        ),
+        Ok(Completion::Linked) => (jar, Redirect::to(&format!("{}/login/sso", config.app_base_url))),
+        Ok(Completion::LinkRequired) => (
+            jar,
+            Redirect::to(&format!(
+                "{}/login?error=sso_link_required&provider={}",
+                config.app_base_url,
+                urlencoding::encode(&provider_id)
+            )),
+        ),
+        Err(e) => {
+            tracing::warn!("Single sign-on with {} failed: {}", provider_id, e);
+            (jar, Redirect::to(&format!("{}/login?error=sso_failed", config.app_base_url)))
+        }
+    }
+}
+
+async fn complete_sign_in(
+    app_state: &crate::AppState,
+    config: &Config,
+    provider_id: &str,
+    query: CallbackQuery,
+    sealed: Option<&str>,
+) -> Result<Completion> {
+    if let Some(error) = query.error {
+        return Err(AppError::Auth(format!("Provider returned error: {}", error)));
+    }
+
+    let provider = app_state.oidc.provider(provider_id)?;
+    let login = sealed
+        .and_then(|s| LoginState::open(&config.jwt_secret, s))
+        .filter(|l| l.provider == provider.id)
+        .ok_or_else(|| AppError::Auth("Sign-in expired or was started in another browser".to_string()))?;
+    if query.state.as_deref() != Some(login.state.as_str()) {
+        return Err(AppError::Auth("State mismatch".to_string()));
+    }
+    let code = query
+        .code
+        .ok_or_else(|| AppError::Input("Missing authorization code".to_string()))?;
+
+    let claims = app_state
+        .oidc
+        .complete_login(provider, &login, &code, &redirect_uri(config, &provider.id))
+        .await?;
+
+    let pool = app_state.db_pool.pool().await?;
+    if let Some(user_id) = &login.link_user_id {
+        identity::link(&pool, &provider.id, &claims.sub, claims.email.as_deref(), user_id).await?;
+        return Ok(Completion::Linked);
+    }
+
+    let signed_in = identity::find_or_create_user(&pool, &ExternalIdentity {
+        provider: &provider.id,
+        subject: &claims.sub,
+        email: claims.email.as_deref(),
+        email_verified: claims.email_verified,
+        name: claims.name.as_deref(),
+        default_role: provider.default_role,
+    })
+    .await?;
+    let user = match signed_in {
+        ExternalSignIn::User(user) => user,
+        ExternalSignIn::LinkRequired => return Ok(Completion::LinkRequired),
+    };
+    if let Some(mfa_token) = mfa_challenge(&pool, config, &user.id).await? {
+        return Ok(Completion::MfaRequired(mfa_token));
+    }
+    let tokens = auth_tokens::start_family(
+        &pool,
+        config,
+        &app_state.jwt_keys,
+        &user.id,
+        user.role().as_str(),
+    ).await?;
+
+    Ok(Completion::SignedIn(tokens.access_token, tokens.refresh_token))
+}
@@ -0,0 +1,265 @@
+use axum::{extract::{Path, State}, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, Result};
+use crate::handlers::auth::MAX_EMAIL_LENGTH;
+use crate::middleware::auth::{AuthUser, RequireRole, Teacher};
+use crate::models::organization::{OrgRole, Organization};
+use crate::models::response::ApiResponse;
+use crate::models::session::SessionWithSlideCount;
+use crate::services::organizations::{self, AuditEntry, MemberOrganization, OrganizationMember};
+
+const MAX_ORGANIZATION_NAME_LENGTH: usize = 200;
+
+#[derive(Deserialize)]
+pub struct OrganizationRequest {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct AddMemberRequest {
+    email: String,
+    role: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetMemberRoleRequest {
+    role: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferSessionsRequest {
+    from_user_id: String,
+    to_user_id: String,
+    /// Omit to transfer all of the user's organization sessions
+    session_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetSessionOrganizationRequest {
+    /// None takes the session out of its organization
+    organization_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TransferredSessions {
+    transferred: u64,
+}
+
+fn organization_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_ORGANIZATION_NAME_LENGTH {
+        return Err(AppError::Input(format!(
+            "Organization name must be 1-{} characters",
+            MAX_ORGANIZATION_NAME_LENGTH
+        )));
+    }
+    Ok(name)
+}
+
+fn org_role(value: &str) -> Result<OrgRole> {
+    OrgRole::parse(value).ok_or_else(|| AppError::Input(format!("Unknown role {}", value)))
+}
+
+/// Organizations the caller belongs to
+pub async fn list_organizations(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<Json<ApiResponse<Vec<MemberOrganization>>>> {
+    let pool = app_state.db_pool.pool().await?;
+    Ok(Json(ApiResponse::success(organizations::list_for_user(&pool, &user_id).await?)))
+}
+
+/// Create an organization; the caller becomes its admin
+pub async fn create_organization(
+    State(app_state): State<crate::AppState>,
+    RequireRole { user, .. }: RequireRole<Teacher>,
+    Json(payload): Json<OrganizationRequest>,
+) -> Result<Json<ApiResponse<Organization>>> {
+    let name = organization_name(&payload.name)?;
+    let pool = app_state.db_pool.pool().await?;
+    let organization = organizations::create(&pool, name, &user.user_id).await?;
+    tracing::info!("User {} created organization {}", user.user_id, organization.id);
+
+    Ok(Json(ApiResponse::success(organization)))
+}
+
+pub async fn get_organization(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Organization>>> {
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &id, &user_id, OrgRole::Member).await?;
+    Ok(Json(ApiResponse::success(organizations::find(&pool, &id).await?)))
+}
+
+/// Rename an organization (admins only)
+pub async fn update_organization(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+    Json(payload): Json<OrganizationRequest>,
+) -> Result<Json<ApiResponse<Organization>>> {
+    let name = organization_name(&payload.name)?;
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &id, &user_id, OrgRole::Admin).await?;
+    Ok(Json(ApiResponse::success(organizations::rename(&pool, &id, name).await?)))
+}
+
+/// The organization's session library, with the caller's role on each
+pub async fn list_organization_sessions(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Vec<SessionWithSlideCount>>>> {
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &id, &user_id, OrgRole::Member).await?;
+
+    let sessions = app_state.session_service
+        .get_organization_sessions(&id, &user_id)
+        .await?;
+    Ok(Json(ApiResponse::success(sessions)))
+}
+
+pub async fn list_members(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Vec<OrganizationMember>>>> {
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &id, &user_id, OrgRole::Member).await?;
+    Ok(Json(ApiResponse::success(organizations::list_members(&pool, &id).await?)))
+}
+
+/// Add an existing account by email (admins only)
+pub async fn add_member(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+    Json(payload): Json<AddMemberRequest>,
+) -> Result<Json<ApiResponse<OrganizationMember>>> {
+    let email = payload.email.trim().to_lowercase();
+    if email.len() > MAX_EMAIL_LENGTH || !email.contains('@') {
+        return Err(AppError::Input("Invalid email format".to_string()));
+    }
+    let role = org_role(&payload.role)?;
+
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &id, &user_id, OrgRole::Admin).await?;
+    let member = organizations::add_member(&pool, &id, &email, role).await?;
+    tracing::info!("User {} added {} to organization {} as {}", user_id, member.user_id, id, role.as_str());
+
+    Ok(Json(ApiResponse::success(member)))
+}
+
+/// Change a member's role (admins only)
+pub async fn set_member_role(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path((organization_id, member_id)): Path<(String, String)>,
+    Json(payload): Json<SetMemberRoleRequest>,
+) -> Result<Json<ApiResponse<OrganizationMember>>> {
+    let role = org_role(&payload.role)?;
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &organization_id, &user_id, OrgRole::Admin).await?;
+    if organizations::role_of(&pool, &organization_id, &member_id).await?.is_none() {
+        return Err(AppError::NotFound("Member not found".to_string()));
+    }
+
+    let member = organizations::set_role(&pool, &organization_id, &member_id, role).await?;
+    Ok(Json(ApiResponse::success(member)))
+}
+
+/// Remove a member (admins), or leave the organization (any member)
+pub async fn remove_member(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path((organization_id, member_id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let min = if member_id == user_id { OrgRole::Member } else { OrgRole::Admin };
+    organizations::require(&pool, &organization_id, &user_id, min).await?;
+
+    organizations::remove_member(&pool, &organization_id, &member_id).await?;
+    tracing::info!("User {} removed {} from organization {}", user_id, member_id, organization_id);
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Hand a (departing) instructor's organization sessions to another member
+/// (admins only)
+pub async fn transfer_sessions(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+    Json(payload): Json<TransferSessionsRequest>,
+) -> Result<Json<ApiResponse<TransferredSessions>>> {
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &id, &user_id, OrgRole::Admin).await?;
+
+    let transferred = organizations::transfer_sessions(
+        &pool,
+        &id,
+        &user_id,
+        &payload.from_user_id,
+        &payload.to_user_id,
+        payload.session_id.as_deref(),
+    )
+    .await?;
+    tracing::info!(
+        "User {} transferred {} sessions of organization {} from {} to {}",
+        user_id, transferred, id, payload.from_user_id, payload.to_user_id
+    );
+
+    Ok(Json(ApiResponse::success(TransferredSessions { transferred })))
+}
+
+/// Ownership changes of the organization's sessions (admins)
+pub async fn audit_log(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Vec<AuditEntry>>>> {
+    let pool = app_state.db_pool.pool().await?;
+    organizations::require(&pool, &id, &user_id, OrgRole::Admin).await?;
+    Ok(Json(ApiResponse::success(organizations::audit_log(&pool, &id).await?)))
+}
+
+/// Move a personal session into an organization's library (its creator, who
+/// must be a member), or a library session out of or between organizations
+/// (admins of its current organization). Invited owners cannot hand someone
+/// else's session to an organization they administer.
+pub async fn set_session_organization(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(session_id): Path<String>,
+    Json(payload): Json<SetSessionOrganizationRequest>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    let session = app_state.session_service.find_session(&session_id).await?;
+
+    match &session.organization_id {
+        Some(current) => {
+            organizations::require(&pool, current, &user_id, OrgRole::Admin).await?;
+        }
+        None if session.creator_id != user_id => {
+            return Err(AppError::Forbidden("Only the creator can move this session into an organization".to_string()));
+        }
+        None => {}
+    }
+    if let Some(target) = &payload.organization_id {
+        organizations::require(&pool, target, &user_id, OrgRole::Member).await?;
+    }
+
+    organizations::set_session_organization(
+        &pool,
+        &session_id,
+        &user_id,
+        session.organization_id.as_deref(),
+        payload.organization_id.as_deref(),
+    )
+    .await?;
+    Ok(Json(ApiResponse::success(())))
+}
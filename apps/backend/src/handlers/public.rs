@@ -1,11 +1,14 @@
-use axum::{extract::{State, Path}, Json};
+use axum::{extract::{Request, State, Path}, http::{header::AUTHORIZATION, HeaderMap}, Json};
 use serde::Deserialize;
 use sqlx::query_as;
+use tower_governor::key_extractor::{KeyExtractor, SmartIpKeyExtractor};
 
 use crate::error::{AppError, Result};
 use crate::models::response::ApiResponse;
 use crate::models::session::{PublicSessionResponse, Session, SessionState};
-use crate::services::ably::publish_state_update;
+use crate::services::ably::state_update_event;
+use crate::services::join_codes::{self, JoinTarget};
+use crate::services::{clicker, outbox};
 
 /// Get session by share token (public endpoint)
 /// Returns session with slides, questions, and stats
@@ -17,6 +20,23 @@ pub async fn get_session_by_share_token(
     Ok(Json(ApiResponse::success(response)))
 }
 
+/// Resolve a live session's numeric join code. Clients that keep entering
+/// unknown codes are made to wait; see `join_codes::resolve_for_client`.
+pub async fn resolve_join_code(
+    State(app_state): State<crate::AppState>,
+    Path(code): Path<String>,
+    request: Request,
+) -> Result<Json<ApiResponse<JoinTarget>>> {
+    // Same client address the rate limiters key on
+    let client_ip = SmartIpKeyExtractor
+        .extract(&request)
+        .map(|ip| ip.to_string())
+        .map_err(|_| AppError::Input("Client address unavailable".to_string()))?;
+    let pool = app_state.db_pool.pool().await?;
+    let target = join_codes::resolve_for_client(&pool, &client_ip, code.trim()).await?;
+    Ok(Json(ApiResponse::success(target)))
+}
+
 /// Get session state (for students/projector real-time sync)
 /// Returns flattened state that matches frontend StateUpdatePayload
 pub async fn get_session_state(
@@ -28,8 +48,19 @@ pub async fn get_session_state(
 }
 
 // ============ Public Clicker Endpoints ============
-// These endpoints allow mobile clicker access without authentication
-// They verify the session exists but don't require ownership proof
+// These endpoints let a paired phone drive the session without logging in.
+// They require the session's clicker token (`Authorization: Bearer ccc_...`)
+// from `POST /api/clicker/pair`.
+
+/// Check the clicker token sent with a request for the session
+async fn authorize_clicker(pool: &crate::db::DbPool, headers: &HeaderMap, session_id: &str) -> Result<()> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| AppError::Auth("Clicker token required".to_string()))?;
+    clicker::authorize(pool, token, session_id).await
+}
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -50,14 +81,16 @@ struct StateUpdatePayload {
     is_results_visible: bool,
 }
 
-/// Public endpoint to set current slide (for mobile clicker)
+/// Set the current slide from a paired clicker
 pub async fn public_set_current_slide(
     State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
     Path(session_id): Path<String>,
     Json(payload): Json<PublicSetSlideRequest>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let pool = app_state.db_pool.pool().await?;
-    
+    authorize_clicker(&pool, &headers, &session_id).await?;
+
     let session: Option<Session> = query_as("SELECT * FROM sessions WHERE id = ?")
         .bind(&session_id)
         .fetch_optional(&pool)
@@ -65,10 +98,15 @@ pub async fn public_set_current_slide(
 
     let session = session.ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
 
+    // Leaving the slide: publish its final results before the state change
+    app_state.vote_coalescer.flush_session(&session_id).await;
+
+    let mut tx = pool.begin().await?;
+
     sqlx::query("UPDATE sessions SET current_slide_id = ? WHERE id = ?")
         .bind(&payload.slide_id)
         .bind(&session_id)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
 
     let state_payload = StateUpdatePayload {
@@ -76,22 +114,24 @@ pub async fn public_set_current_slide(
         is_presentation_active: session.is_presentation_active,
         is_results_visible: session.is_results_visible,
     };
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_state_update(&session_id_for_publish, &state_payload).await;
-    });
+    let mut event = state_update_event(&session_id, &state_payload);
+    outbox::enqueue(&mut tx, &mut event).await?;
+    tx.commit().await?;
+    app_state.outbox.committed(vec![event]);
 
     Ok(Json(ApiResponse::success(serde_json::json!({ "message": "Slide updated" }))))
 }
 
-/// Public endpoint to set results visibility (for mobile clicker)
+/// Show or hide results from a paired clicker
 pub async fn public_set_results_visibility(
     State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
     Path(session_id): Path<String>,
     Json(payload): Json<PublicSetResultsRequest>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let pool = app_state.db_pool.pool().await?;
-    
+    authorize_clicker(&pool, &headers, &session_id).await?;
+
     let session: Option<Session> = query_as("SELECT * FROM sessions WHERE id = ?")
         .bind(&session_id)
         .fetch_optional(&pool)
@@ -99,10 +139,12 @@ pub async fn public_set_results_visibility(
 
     let session = session.ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
 
+    let mut tx = pool.begin().await?;
+
     sqlx::query("UPDATE sessions SET is_results_visible = ? WHERE id = ?")
         .bind(payload.visible)
         .bind(&session_id)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
 
     let state_payload = StateUpdatePayload {
@@ -110,10 +152,10 @@ pub async fn public_set_results_visibility(
         is_presentation_active: session.is_presentation_active,
         is_results_visible: payload.visible,
     };
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_state_update(&session_id_for_publish, &state_payload).await;
-    });
+    let mut event = state_update_event(&session_id, &state_payload);
+    outbox::enqueue(&mut tx, &mut event).await?;
+    tx.commit().await?;
+    app_state.outbox.committed(vec![event]);
 
     Ok(Json(ApiResponse::success(serde_json::json!({ "message": "Results visibility updated" }))))
 }
@@ -4,13 +4,17 @@ use serde::Deserialize;
 use crate::error::Result;
 use crate::models::session::Session;
 use crate::models::response::ApiResponse;
-use crate::middleware::auth::AuthUser;
+use crate::middleware::auth::{AuthUser, RequireRole, Teacher};
+use crate::models::organization::OrgRole;
+use crate::services::organizations;
 
 /// Request DTO for creating a session
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateSessionRequest {
     title: String,
+    /// Create the session in this organization's library
+    organization_id: Option<String>,
     allow_questions: Option<bool>,
     require_name: Option<bool>,
 }
@@ -24,13 +28,13 @@ pub struct UpdateSessionRequest {
     require_name: Option<bool>,
 }
 
-/// PRESENTATION LAYER - Session Handlers
-/// These handlers ONLY handle HTTP concerns:
-/// - Parse requests
-/// - Extract user identity
-/// - Call service layer
-/// - Return HTTP responses
-/// NO business logic or database access here!
+// PRESENTATION LAYER - Session Handlers
+// These handlers ONLY handle HTTP concerns:
+// - Parse requests
+// - Extract user identity
+// - Call service layer
+// - Return HTTP responses
+// NO business logic or database access here!
 
 /// Get all sessions for the authenticated user
 pub async fn get_sessions(
@@ -47,12 +51,18 @@ pub async fn get_sessions(
 /// Create a new session
 pub async fn create_session(
     State(app_state): State<crate::AppState>,
-    AuthUser { user_id, .. }: AuthUser,
+    RequireRole { user: AuthUser { user_id, .. }, .. }: RequireRole<Teacher>,
     Json(payload): Json<CreateSessionRequest>,
 ) -> Result<Json<ApiResponse<Session>>> {
+    if let Some(organization_id) = &payload.organization_id {
+        let pool = app_state.db_pool.pool().await?;
+        organizations::require(&pool, organization_id, &user_id, OrgRole::Member).await?;
+    }
+
     let session = app_state.session_service
         .create_session(
             &user_id,
+            payload.organization_id.as_deref(),
             &payload.title,
             payload.allow_questions.unwrap_or(false),
             payload.require_name.unwrap_or(false),
@@ -98,7 +108,7 @@ pub async fn update_session(
 /// Duplicate a session
 pub async fn duplicate_session(
     State(app_state): State<crate::AppState>,
-    AuthUser { user_id, .. }: AuthUser,
+    RequireRole { user: AuthUser { user_id, .. }, .. }: RequireRole<Teacher>,
     Path(id): Path<String>,
 ) -> Result<Json<ApiResponse<Session>>> {
     let session = app_state.session_service
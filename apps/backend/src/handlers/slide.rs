@@ -5,7 +5,9 @@ use uuid::Uuid;
 use crate::error::{AppError, Result};
 use crate::models::slide::{Slide, CreateSlideRequest, UpdateSlideRequest, ReorderSlidesRequest};
 use crate::models::response::ApiResponse;
+use crate::models::session::SessionRole;
 use crate::middleware::auth::AuthUser;
+use crate::services::session_access;
 
 /// Get all slides for a session
 pub async fn get_slides(
@@ -14,7 +16,7 @@ pub async fn get_slides(
     Path(session_id): Path<String>,
 ) -> Result<Json<ApiResponse<Vec<Slide>>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Viewer).await?;
 
     let slides = query_as::<_, Slide>(
         "SELECT * FROM slides WHERE session_id = ? ORDER BY order_index ASC"
@@ -34,7 +36,7 @@ pub async fn create_slide(
     Json(payload): Json<CreateSlideRequest>,
 ) -> Result<Json<ApiResponse<Slide>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Editor).await?;
 
     let id = Uuid::new_v4().to_string();
     let max_order: Option<i32> = sqlx::query_scalar(
@@ -73,7 +75,7 @@ pub async fn update_slide(
     Json(payload): Json<UpdateSlideRequest>,
 ) -> Result<Json<ApiResponse<Slide>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Editor).await?;
 
     let _slide: Slide = query_as("SELECT * FROM slides WHERE id = ? AND session_id = ?")
         .bind(&slide_id)
@@ -114,7 +116,7 @@ pub async fn delete_slide(
     Path((session_id, slide_id)): Path<(String, String)>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Editor).await?;
 
     let result = query("DELETE FROM slides WHERE id = ? AND session_id = ?")
         .bind(&slide_id)
@@ -137,7 +139,7 @@ pub async fn reorder_slides(
     Json(payload): Json<ReorderSlidesRequest>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let pool = app_state.db_pool.pool().await?;
-    verify_session_ownership(&pool, &session_id, &user_id).await?;
+    session_access::require(&pool, &session_id, &user_id, SessionRole::Editor).await?;
 
     if payload.slide_ids.is_empty() {
         return Err(AppError::Input("No slides to reorder".to_string()));
@@ -154,34 +156,15 @@ pub async fn reorder_slides(
     qb.push("ELSE order_index END WHERE session_id = ");
     qb.push_bind(&session_id);
     qb.push(" AND id IN (");
-    let mut separated = qb.separated(", ");
-    for slide_id in &payload.slide_ids {
-        separated.push_bind(slide_id);
+    {
+        let mut separated = qb.separated(", ");
+        for slide_id in &payload.slide_ids {
+            separated.push_bind(slide_id);
+        }
     }
-    drop(separated);
     qb.push(")");
 
     qb.build().execute(&pool).await?;
 
     Ok(Json(ApiResponse::success(serde_json::json!({ "message": "Slides reordered successfully" }))))
 }
-
-/// Helper function to verify session ownership
-async fn verify_session_ownership(
-    pool: &crate::db::DbPool,
-    session_id: &str,
-    user_id: &str,
-) -> Result<()> {
-    let exists: Option<bool> = sqlx::query_scalar(
-        "SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ? AND creator_id = ?)"
-    )
-    .bind(session_id)
-    .bind(user_id)
-    .fetch_optional(pool)
-    .await?;
-
-    match exists {
-        Some(true) => Ok(()),
-        _ => Err(AppError::Auth("Unauthorized access to session".to_string())),
-    }
-}
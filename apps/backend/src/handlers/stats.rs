@@ -5,9 +5,12 @@ use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
 use crate::error::{AppError, Result};
-use crate::models::session::Session;
+use crate::models::session::{Session, SessionRole};
 use crate::models::slide::Slide;
+use crate::models::student::{ParticipantAttendance, ParticipantConnection};
 use crate::middleware::auth::AuthUser;
+use crate::services::ags::{self, GradeSyncEntry};
+use crate::services::session_access;
 
 #[derive(Debug, Serialize)]
 pub struct Participant {
@@ -15,6 +18,23 @@ pub struct Participant {
     pub name: String,
     #[serde(rename = "joinedAt")]
     pub joined_at: String,
+    /// Only in the collaborator view; the public dashboard doesn't expose attendance
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub attendance: Option<Attendance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Attendance {
+    /// First presence enter on the session channel
+    #[serde(rename = "firstConnectedAt", skip_serializing_if = "Option::is_none")]
+    pub first_connected_at: Option<String>,
+    /// Most recent presence leave
+    #[serde(rename = "lastDisconnectedAt", skip_serializing_if = "Option::is_none")]
+    pub last_disconnected_at: Option<String>,
+    #[serde(rename = "connectedSeconds")]
+    pub connected_seconds: i64,
+    #[serde(rename = "isConnected")]
+    pub is_connected: bool,
 }
 
 #[derive(Debug, FromRow)]
@@ -67,23 +87,11 @@ pub struct Question {
     pub slide_id: Option<String>,
 }
 
-#[derive(Debug, FromRow)]
-struct DbQuestion {
-    id: String,
-    content: String,
-    upvotes: i32,
-    participant_id: String,
-    created_at: Option<DateTime<Utc>>,
-    slide_id: Option<String>,
-}
-
 #[derive(Debug, FromRow)]
 struct DbQuestionWithAuthor {
     id: String,
     content: String,
     upvotes: i32,
-    #[allow(dead_code)]
-    participant_id: String,
     created_at: Option<DateTime<Utc>>,
     author_name: String,
     slide_id: Option<String>,
@@ -107,96 +115,68 @@ struct VoteInteraction {
 #[derive(Debug, Serialize)]
 pub struct SessionStats {
     pub participants: Vec<Participant>,
+    /// Participants with at least one open connection right now
+    #[serde(rename = "connectedCount", skip_serializing_if = "Option::is_none")]
+    pub connected_count: Option<usize>,
     pub slides: Vec<SlideStats>,
     pub questions: Vec<Question>,
 }
 
-/// Get session stats (authenticated - for session owner)
-pub async fn get_session_stats(
-    State(app_state): State<crate::AppState>,
-    AuthUser { user_id, .. }: AuthUser,
-    Path(id): Path<String>,
-) -> Result<Json<SessionStats>> {
-    let pool = app_state.db_pool.pool().await?;
-    
-    // Verify session exists and user owns it
-    let session = query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
-        .bind(&id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
-
-    if session.creator_id != user_id {
-        return Err(AppError::Auth("Unauthorized access to session".to_string()));
-    }
-
-    // Run independent reads in parallel to reduce tail latency
-    let slides_fut = query_as::<_, Slide>(
-        "SELECT * FROM slides WHERE session_id = ? ORDER BY order_index"
-    )
-    .bind(&id)
-    .fetch_all(&pool);
-
-    let vote_counts_fut = async {
-        sqlx::query_as::<_, VoteCount>(
-            "SELECT slide_id, option_id, COUNT(*) as count FROM votes WHERE session_id = ? GROUP BY slide_id, option_id"
-        )
-        .bind(&id)
-        .fetch_all(&pool)
-        .await
-        .unwrap_or_default()
-    };
-
-    let vote_interactions_fut = async {
-        sqlx::query_as::<_, VoteInteraction>(
-            "SELECT v.slide_id, v.option_id, COALESCE(p.name, 'Anonymous') as participant_name, v.created_at 
-             FROM votes v 
-             LEFT JOIN participants p ON v.participant_id = p.id AND v.session_id = p.session_id
-             WHERE v.session_id = ?
-             ORDER BY v.created_at DESC"
-        )
-        .bind(&id)
-        .fetch_all(&pool)
-        .await
-        .unwrap_or_default()
-    };
-
-    let participants_fut = async {
-        sqlx::query_as::<_, DbParticipant>(
-            "SELECT id, name, joined_at FROM participants WHERE session_id = ? ORDER BY joined_at DESC"
-        )
-        .bind(&id)
-        .fetch_all(&pool)
-        .await
-        .unwrap_or_default()
-    };
-
-    let questions_fut = async {
-        sqlx::query_as::<_, DbQuestionWithAuthor>(
-            "SELECT q.id, q.content, q.upvotes, q.participant_id, q.created_at, q.slide_id,
-                    COALESCE(p.name, 'Anonymous') as author_name
-             FROM questions q 
-             LEFT JOIN participants p ON q.participant_id = p.id AND q.session_id = p.session_id
-             WHERE q.session_id = ? 
-             ORDER BY q.upvotes DESC, q.created_at DESC"
-        )
-        .bind(&id)
-        .fetch_all(&pool)
-        .await
-        .unwrap_or_default()
-    };
-
-    let (slides, vote_counts, vote_interactions, db_participants, questions) =
-        tokio::join!(slides_fut, vote_counts_fut, vote_interactions_fut, participants_fut, questions_fut);
+/// Which parts of a session the stats are built from
+#[derive(Debug, Clone, Copy)]
+enum StatsView {
+    /// Session collaborators: every slide, plus presence-based attendance
+    Collaborator,
+    /// Shared dashboard: visible slides only, no attendance
+    Public,
+}
 
-    let slides = slides?;
+/// Merge registered participants with their presence-based attendance, if loaded
+fn build_participants(
+    db_participants: Vec<DbParticipant>,
+    attendance: Option<Vec<ParticipantAttendance>>,
+) -> Vec<Participant> {
+    let mut attendance: Option<HashMap<String, ParticipantAttendance>> = attendance.map(|rows| {
+        rows.into_iter()
+            .map(|a| (a.participant_id.clone(), a))
+            .collect()
+    });
+
+    db_participants.into_iter().map(|p| {
+        let attendance = attendance.as_mut().map(|by_participant| {
+            let connections = by_participant.remove(&p.id);
+            Attendance {
+                first_connected_at: connections.as_ref()
+                    .and_then(|a| a.first_entered_at)
+                    .map(|dt| dt.to_rfc3339()),
+                last_disconnected_at: connections.as_ref()
+                    .and_then(|a| a.last_left_at)
+                    .map(|dt| dt.to_rfc3339()),
+                connected_seconds: connections.as_ref().map_or(0, |a| a.connected_seconds),
+                is_connected: connections.as_ref().is_some_and(|a| a.open_connections > 0),
+            }
+        });
+        Participant {
+            id: p.id,
+            name: p.name,
+            joined_at: p.joined_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
+            attendance,
+        }
+    }).collect()
+}
 
+/// Turn slides and their votes into per-slide stats
+fn build_slide_stats(
+    slides: Vec<Slide>,
+    vote_counts: Vec<VoteCount>,
+    vote_interactions: Vec<VoteInteraction>,
+) -> Vec<SlideStats> {
     // Build vote maps
     let mut vote_map: HashMap<String, HashMap<String, i32>> = HashMap::new();
     for vc in vote_counts {
         vote_map
             .entry(vc.slide_id)
-            .or_insert_with(HashMap::new)
+            .or_default()
             .insert(vc.option_id, vc.count as i32);
     }
 
@@ -205,7 +185,7 @@ pub async fn get_session_stats(
     for vi in vote_interactions {
         interaction_map
             .entry(vi.slide_id.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(SlideInteraction {
                 name: vi.participant_name,
                 answer: vi.option_id,
@@ -214,15 +194,14 @@ pub async fn get_session_stats(
             });
     }
 
-    // Convert slides to SlideStats
-    let slide_stats: Vec<SlideStats> = slides.into_iter().map(|slide| {
+    slides.into_iter().map(|slide| {
         let content = slide.content.0;
-        
+
         // Extract question text from content
         let question = content.get("question")
             .and_then(|q| q.as_str())
             .map(|s| s.to_string());
-        
+
         // Extract options from content
         let options = content.get("options")
             .and_then(|opts| opts.as_array())
@@ -237,7 +216,7 @@ pub async fn get_session_stats(
                 }).collect()
             });
 
-        let votes = vote_map.get(&slide.id).cloned();
+        let votes = vote_map.remove(&slide.id);
         let interactions = interaction_map.remove(&slide.id);
 
         SlideStats {
@@ -248,56 +227,27 @@ pub async fn get_session_stats(
             votes: Some(votes.unwrap_or_default()),
             interactions: Some(interactions.unwrap_or_default()),
         }
-    }).collect();
-
-    let participants: Vec<Participant> = db_participants.into_iter().map(|p| Participant {
-        id: p.id,
-        name: p.name,
-        joined_at: p.joined_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
-    }).collect();
-
-    let questions: Vec<Question> = questions.into_iter().map(|q| Question {
-        id: q.id,
-        content: q.content,
-        upvotes: q.upvotes,
-        author: q.author_name,
-        created_at: q.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
-        slide_id: q.slide_id,
-    }).collect();
-
-    Ok(Json(SessionStats {
-        participants,
-        slides: slide_stats,
-        questions,
-    }))
+    }).collect()
 }
 
-/// Get public session stats (for shared sessions)
-pub async fn get_public_session_stats(
-    State(app_state): State<crate::AppState>,
-    Path(id): Path<String>,
-) -> Result<Json<SessionStats>> {
-    let pool = app_state.db_pool.pool().await?;
-    
-    // Verify session exists
-    let _session = query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
-        .bind(&id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+/// Load and assemble a session's stats for the given audience
+async fn session_stats(pool: &crate::db::DbPool, id: &str, view: StatsView) -> Result<SessionStats> {
+    let slides_sql = match view {
+        StatsView::Collaborator => "SELECT * FROM slides WHERE session_id = ? ORDER BY order_index",
+        StatsView::Public => "SELECT * FROM slides WHERE session_id = ? AND is_hidden = FALSE ORDER BY order_index",
+    };
 
-    let slides_fut = query_as::<_, Slide>(
-        "SELECT * FROM slides WHERE session_id = ? AND is_hidden = FALSE ORDER BY order_index"
-    )
-    .bind(&id)
-    .fetch_all(&pool);
+    // Run independent reads in parallel to reduce tail latency
+    let slides_fut = query_as::<_, Slide>(slides_sql)
+        .bind(id)
+        .fetch_all(pool);
 
     let vote_counts_fut = async {
         sqlx::query_as::<_, VoteCount>(
             "SELECT slide_id, option_id, COUNT(*) as count FROM votes WHERE session_id = ? GROUP BY slide_id, option_id"
         )
-        .bind(&id)
-        .fetch_all(&pool)
+        .bind(id)
+        .fetch_all(pool)
         .await
         .unwrap_or_default()
     };
@@ -310,8 +260,8 @@ pub async fn get_public_session_stats(
              WHERE v.session_id = ?
              ORDER BY v.created_at DESC"
         )
-        .bind(&id)
-        .fetch_all(&pool)
+        .bind(id)
+        .fetch_all(pool)
         .await
         .unwrap_or_default()
     };
@@ -320,94 +270,53 @@ pub async fn get_public_session_stats(
         sqlx::query_as::<_, DbParticipant>(
             "SELECT id, name, joined_at FROM participants WHERE session_id = ? ORDER BY joined_at DESC"
         )
-        .bind(&id)
-        .fetch_all(&pool)
+        .bind(id)
+        .fetch_all(pool)
         .await
         .unwrap_or_default()
     };
 
     let questions_fut = async {
         sqlx::query_as::<_, DbQuestionWithAuthor>(
-            "SELECT q.id, q.content, q.upvotes, q.participant_id, q.created_at, q.slide_id,
+            "SELECT q.id, q.content, q.upvotes, q.created_at, q.slide_id,
                     COALESCE(p.name, 'Anonymous') as author_name
              FROM questions q 
              LEFT JOIN participants p ON q.participant_id = p.id AND q.session_id = p.session_id
              WHERE q.session_id = ? 
              ORDER BY q.upvotes DESC, q.created_at DESC"
         )
-        .bind(&id)
-        .fetch_all(&pool)
+        .bind(id)
+        .fetch_all(pool)
         .await
         .unwrap_or_default()
     };
 
-    let (slides, vote_counts, vote_interactions, db_participants, questions) =
-        tokio::join!(slides_fut, vote_counts_fut, vote_interactions_fut, participants_fut, questions_fut);
-
-    let slides = slides?;
-
-    // Build vote maps
-    let mut vote_map: HashMap<String, HashMap<String, i32>> = HashMap::new();
-    for vc in vote_counts {
-        vote_map
-            .entry(vc.slide_id)
-            .or_insert_with(HashMap::new)
-            .insert(vc.option_id, vc.count as i32);
-    }
-
-    // Build interaction maps
-    let mut interaction_map: HashMap<String, Vec<SlideInteraction>> = HashMap::new();
-    for vi in vote_interactions {
-        interaction_map
-            .entry(vi.slide_id.clone())
-            .or_insert_with(Vec::new)
-            .push(SlideInteraction {
-                name: vi.participant_name,
-                answer: vi.option_id,
-                text_answer: None,
-                answered_at: vi.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
-            });
-    }
-
-    // Convert slides to SlideStats
-    let slide_stats: Vec<SlideStats> = slides.into_iter().map(|slide| {
-        let content = slide.content.0;
-        
-        let question = content.get("question")
-            .and_then(|q| q.as_str())
-            .map(|s| s.to_string());
-        
-        let options = content.get("options")
-            .and_then(|opts| opts.as_array())
-            .map(|arr| {
-                arr.iter().filter_map(|opt| {
-                    let id = opt.get("id").and_then(|v| v.as_str())?;
-                    let text = opt.get("text").and_then(|v| v.as_str())?;
-                    Some(SlideOption {
-                        id: id.to_string(),
-                        text: text.to_string(),
-                    })
-                }).collect()
-            });
+    let attendance_fut = async {
+        match view {
+            StatsView::Collaborator => Some(
+                ParticipantConnection::attendance_by_session(pool, id)
+                    .await
+                    .unwrap_or_default(),
+            ),
+            StatsView::Public => None,
+        }
+    };
 
-        let votes = vote_map.get(&slide.id).cloned();
-        let interactions = interaction_map.remove(&slide.id);
+    let (slides, vote_counts, vote_interactions, db_participants, questions, attendance) = tokio::join!(
+        slides_fut, vote_counts_fut, vote_interactions_fut, participants_fut, questions_fut, attendance_fut
+    );
 
-        SlideStats {
-            id: slide.id,
-            slide_type: slide.slide_type,
-            question,
-            options,
-            votes: Some(votes.unwrap_or_default()),
-            interactions: Some(interactions.unwrap_or_default()), // Now include interactions for public dashboard
-        }
-    }).collect();
+    let slide_stats = build_slide_stats(slides?, vote_counts, vote_interactions);
 
-    let participants: Vec<Participant> = db_participants.into_iter().map(|p| Participant {
-        id: p.id,
-        name: p.name,
-        joined_at: p.joined_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
-    }).collect();
+    let participants = build_participants(db_participants, attendance);
+    let connected_count = match view {
+        StatsView::Collaborator => Some(
+            participants.iter()
+                .filter(|p| p.attendance.as_ref().is_some_and(|a| a.is_connected))
+                .count(),
+        ),
+        StatsView::Public => None,
+    };
 
     let questions: Vec<Question> = questions.into_iter().map(|q| Question {
         id: q.id,
@@ -418,9 +327,131 @@ pub async fn get_public_session_stats(
         slide_id: q.slide_id,
     }).collect();
 
-    Ok(Json(SessionStats {
+    Ok(SessionStats {
         participants,
+        connected_count,
         slides: slide_stats,
         questions,
-    }))
+    })
+}
+
+/// Get session stats (authenticated - for session collaborators)
+pub async fn get_session_stats(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<SessionStats>> {
+    let pool = app_state.db_pool.pool().await?;
+    
+    session_access::require(&pool, &id, &user_id, SessionRole::Viewer).await?;
+
+    Ok(Json(session_stats(&pool, &id, StatsView::Collaborator).await?))
+}
+
+/// Get public session stats (for shared sessions)
+pub async fn get_public_session_stats(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SessionStats>> {
+    let pool = app_state.db_pool.pool().await?;
+    
+    // Verify session exists
+    let _session = query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+
+    Ok(Json(session_stats(&pool, &id, StatsView::Public).await?))
+}
+
+/// Grade passback state of a session's LMS learners
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeSyncReport {
+    pub pending: usize,
+    pub synced: usize,
+    pub failed: usize,
+    pub entries: Vec<GradeSyncEntry>,
+}
+
+async fn grade_sync_report(pool: &crate::db::DbPool, session_id: &str) -> Result<GradeSyncReport> {
+    let entries = ags::session_report(pool, session_id).await?;
+    let count = |status: &str| entries.iter().filter(|e| e.status == status).count();
+    Ok(GradeSyncReport {
+        pending: count("pending"),
+        synced: count("synced"),
+        failed: count("failed"),
+        entries,
+    })
+}
+
+/// Get LMS grade passback status (authenticated - for session collaborators)
+pub async fn get_grade_sync_status(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<GradeSyncReport>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &id, &user_id, SessionRole::Viewer).await?;
+
+    Ok(Json(grade_sync_report(&pool, &id).await?))
+}
+
+/// Retry grades that exhausted their passback attempts
+pub async fn retry_grade_sync(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<GradeSyncReport>> {
+    let pool = app_state.db_pool.pool().await?;
+    session_access::require(&pool, &id, &user_id, SessionRole::Editor).await?;
+
+    if ags::retry_failed(&pool, &id).await? > 0 {
+        app_state.grade_sync.committed();
+    }
+
+    Ok(Json(grade_sync_report(&pool, &id).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participant(id: &str) -> DbParticipant {
+        DbParticipant {
+            id: id.to_string(),
+            name: format!("Student {}", id),
+            joined_at: None,
+        }
+    }
+
+    #[test]
+    fn public_participants_carry_no_attendance() {
+        let participants = build_participants(vec![participant("p1")], None);
+        let json = serde_json::to_value(&participants).unwrap();
+
+        let fields = json[0].as_object().unwrap();
+        assert_eq!(fields.len(), 3);
+        assert!(!fields.contains_key("connectedSeconds"));
+        assert!(!fields.contains_key("isConnected"));
+    }
+
+    #[test]
+    fn collaborator_participants_default_to_no_connection_time() {
+        let attendance = vec![ParticipantAttendance {
+            participant_id: "p1".to_string(),
+            first_entered_at: None,
+            last_left_at: None,
+            connected_seconds: 90,
+            open_connections: 1,
+        }];
+        let participants = build_participants(vec![participant("p1"), participant("p2")], Some(attendance));
+        let json = serde_json::to_value(&participants).unwrap();
+
+        assert_eq!(json[0]["connectedSeconds"], 90);
+        assert_eq!(json[0]["isConnected"], true);
+        assert_eq!(json[1]["connectedSeconds"], 0);
+        assert_eq!(json[1]["isConnected"], false);
+    }
 }
@@ -1,15 +1,23 @@
-use axum::{extract::{State, Path}, Json};
+use axum::{extract::{State, Path, Extension}, Json};
 use serde::{Deserialize, Serialize};
+use sqlx::MySqlConnection;
 use uuid::Uuid;
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::config::Config;
 use crate::error::{AppError, Result};
 use crate::models::response::ApiResponse;
 use crate::models::student::{Vote, Question, Participant};
-use crate::services::ably::{publish_vote_update, publish_qa_update};
+use crate::services::ably::{qa_update_event, question_event};
+use crate::services::outbox;
+use crate::services::participant;
+use crate::services::realtime::RealtimeEvent;
+use crate::services::vote_coalescer::SubmittedAnswer;
 
 const MAX_QUESTION_LENGTH: usize = 1000;
 const MAX_NAME_LENGTH: usize = 100;
+const MAX_PARTICIPANT_ID_LENGTH: usize = 36;
 const MAX_OPTION_IDS: usize = 10;
 
 #[derive(Deserialize)]
@@ -69,8 +77,9 @@ pub async fn submit_vote(
         }
     }
 
+    let mut conn = pool.acquire().await?;
     Vote::create_many(
-        &pool,
+        &mut conn,
         &session_id,
         &payload.slide_id,
         &payload.participant_id,
@@ -82,13 +91,13 @@ pub async fn submit_vote(
         AppError::Internal(format!("Failed to save vote: {}", e))
     })?;
 
-    let vote_counts = Vote::get_vote_counts(&pool, &payload.slide_id).await.unwrap_or_default();
-    let results: HashMap<String, i32> = vote_counts.into_iter().map(|(option_id, count)| (option_id, count as i32)).collect();
-    let session_id_for_publish = session_id.clone();
-    let slide_id_for_publish = payload.slide_id.clone();
-    tokio::spawn(async move {
-        publish_vote_update(&session_id_for_publish, &slide_id_for_publish, &results).await;
-    });
+    // Results and the per-student answers for presenters are broadcast once
+    // per coalescing window
+    app_state.vote_coalescer.record_vote(
+        &session_id,
+        &payload.slide_id,
+        SubmittedAnswer { participant_id: payload.participant_id, option_ids },
+    );
 
     Ok(Json(ApiResponse::success(serde_json::json!({ "message": "Vote submitted successfully" }))))
 }
@@ -124,9 +133,34 @@ impl From<Question> for QuestionResponse {
 }
 
 
+/// Enqueue a Q&A delta event, followed by the full question list when
+/// snapshot events are enabled for older clients.
+/// Returns the events to hand to `Outbox::committed` after commit.
+pub(crate) async fn enqueue_qa_events(
+    conn: &mut MySqlConnection,
+    config: &Config,
+    session_id: &str,
+    mut delta: RealtimeEvent,
+) -> Result<Vec<RealtimeEvent>> {
+    outbox::enqueue(conn, &mut delta).await?;
+    let mut events = vec![delta];
+
+    if config.qa_snapshot_events {
+        // The snapshot goes to the public channel: leave out hidden questions
+        let mut all_questions = Question::find_by_session(conn, session_id).await?;
+        all_questions.retain(|q| q.is_approved);
+        let mut snapshot = qa_update_event(session_id, &all_questions);
+        outbox::enqueue(conn, &mut snapshot).await?;
+        events.push(snapshot);
+    }
+
+    Ok(events)
+}
+
 /// Submit a question
 pub async fn submit_question(
     State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
     Path(session_id): Path<String>,
     Json(payload): Json<SubmitQuestionRequest>,
 ) -> Result<Json<ApiResponse<QuestionResponse>>> {
@@ -154,14 +188,19 @@ pub async fn submit_question(
     }
 
     let question_id = Uuid::new_v4().to_string();
-    let question = Question::create(&pool, &question_id, &session_id, payload.slide_id.as_deref(), &payload.participant_id, &sanitized_content)
+    let mut tx = pool.begin().await?;
+    let question = Question::create(&mut tx, &question_id, &session_id, payload.slide_id.as_deref(), &payload.participant_id, &sanitized_content)
         .await.map_err(|e| AppError::Internal(format!("Failed to save question: {}", e)))?;
 
-    let all_questions = Question::find_by_session(&pool, &session_id).await.unwrap_or_default();
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_qa_update(&session_id_for_publish, &all_questions).await;
-    });
+    let events = enqueue_qa_events(
+        &mut tx,
+        &config,
+        &session_id,
+        question_event(&session_id, "QUESTION_ADDED", &question),
+    )
+    .await?;
+    tx.commit().await?;
+    app_state.outbox.committed(events);
 
     Ok(Json(ApiResponse::success(question.into())))
 }
@@ -175,15 +214,16 @@ pub struct UpvoteQuestionRequest {
 /// Upvote a question
 pub async fn upvote_question(
     State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
     Path((session_id, question_id)): Path<(String, String)>,
     body: Option<Json<UpvoteQuestionRequest>>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let pool = app_state.db_pool.pool().await?;
     
-    let question = Question::find_by_id(&pool, &question_id).await?;
-    if question.is_none() {
-        return Err(AppError::NotFound("Question not found".to_string()));
-    }
+    let mut question = Question::find_by_id(&pool, &question_id)
+        .await?
+        .filter(|q| q.session_id == session_id)
+        .ok_or_else(|| AppError::NotFound("Question not found".to_string()))?;
 
     let participant_id = body.and_then(|b| b.participant_id.clone()).unwrap_or_else(|| "anonymous".to_string());
 
@@ -195,15 +235,32 @@ pub async fn upvote_question(
         return Err(AppError::Input("You have already upvoted this question".to_string()));
     }
 
+    let mut tx = pool.begin().await?;
+
+    // A hidden question stays hidden: the upvote event would republish it to
+    // students. Locked so a moderator hiding it meanwhile waits for the upvote.
+    let is_approved: bool = sqlx::query_scalar("SELECT is_approved FROM questions WHERE id = ? FOR UPDATE")
+        .bind(&question_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !is_approved {
+        return Err(AppError::NotFound("Question not found".to_string()));
+    }
+
     sqlx::query("INSERT INTO question_upvotes (question_id, participant_id) VALUES (?, ?) ON DUPLICATE KEY UPDATE created_at = created_at")
-        .bind(&question_id).bind(&participant_id).execute(&pool).await.ok();
+        .bind(&question_id).bind(&participant_id).execute(&mut *tx).await.ok();
 
-    let new_upvotes = Question::upvote(&pool, &question_id).await?;
-    let all_questions = Question::find_by_session(&pool, &session_id).await.unwrap_or_default();
-    let session_id_for_publish = session_id.clone();
-    tokio::spawn(async move {
-        publish_qa_update(&session_id_for_publish, &all_questions).await;
-    });
+    let new_upvotes = Question::upvote(&mut tx, &question_id).await?;
+    question.upvotes = new_upvotes;
+    let events = enqueue_qa_events(
+        &mut tx,
+        &config,
+        &session_id,
+        question_event(&session_id, "QUESTION_UPVOTED", &question),
+    )
+    .await?;
+    tx.commit().await?;
+    app_state.outbox.committed(events);
 
     Ok(Json(ApiResponse::success(serde_json::json!({ "message": "Question upvoted", "upvotes": new_upvotes }))))
 }
@@ -213,18 +270,28 @@ pub async fn upvote_question(
 #[serde(rename_all = "camelCase")]
 pub struct RegisterParticipantRequest {
     participant_id: String,
+    #[serde(default)]
     name: String,
+    /// Token from an earlier registration, proving this browser owns the id
+    participant_token: Option<String>,
 }
 
 /// Register a participant in a session
+/// Returns a signed participant token that binds the id to the session;
+/// students need it to obtain an Ably token.
 pub async fn register_participant(
     State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
     Path(session_id): Path<String>,
     Json(payload): Json<RegisterParticipantRequest>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let pool = app_state.db_pool.pool().await?;
     
     let name = payload.name.trim();
+
+    if payload.participant_id.is_empty() || payload.participant_id.len() > MAX_PARTICIPANT_ID_LENGTH {
+        return Err(AppError::Input("Invalid participant id".to_string()));
+    }
     
     // Check if session exists and get require_name setting
     let session_info: Option<(bool,)> = sqlx::query_as(
@@ -238,6 +305,19 @@ pub async fn register_participant(
         Some((require_name,)) => require_name,
         None => return Err(AppError::NotFound("Session not found".to_string())),
     };
+
+    // An id already registered by someone else can only be reused with its token
+    let holds_token = payload.participant_token.as_deref().is_some_and(|token| {
+        participant::verify_token(&config.jwt_secret, &session_id, &payload.participant_id, token)
+    });
+    if !holds_token && Participant::exists(&pool, &payload.participant_id, &session_id).await? {
+        tracing::warn!(
+            "Participant registration rejected: id {} already claimed in session {}",
+            payload.participant_id, session_id
+        );
+        return Err(AppError::Auth("Participant id already in use".to_string()));
+    }
+    let participant_token = participant::sign_token(&config.jwt_secret, &session_id, &payload.participant_id);
     
     // If session requires name, reject empty names
     let is_anonymous = name.eq_ignore_ascii_case("anonymous");
@@ -250,7 +330,8 @@ pub async fn register_participant(
     if name.is_empty() {
         return Ok(Json(ApiResponse::success(serde_json::json!({ 
             "message": "Participant joined anonymously",
-            "participantId": payload.participant_id
+            "participantId": payload.participant_id,
+            "participantToken": participant_token
         }))));
     }
     
@@ -263,7 +344,8 @@ pub async fn register_participant(
 
     Ok(Json(ApiResponse::success(serde_json::json!({ 
         "message": "Participant registered",
-        "participantId": payload.participant_id
+        "participantId": payload.participant_id,
+        "participantToken": participant_token
     }))))
 }
 
@@ -313,3 +395,111 @@ pub async fn get_my_votes(
     
     Ok(Json(ApiResponse::success(MyVotesResponse { votes: votes_map })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::services::ably::{session_channel, staff_channel};
+    use crate::test_support::{app, create_session, create_slide, create_user};
+
+    async fn ask(app: &crate::test_support::TestApp, session_id: &str, content: &str) -> QuestionResponse {
+        let Json(response) = submit_question(
+            State(app.state.clone()),
+            Extension(app.config.clone()),
+            Path(session_id.to_string()),
+            Json(SubmitQuestionRequest {
+                content: content.to_string(),
+                participant_id: "participant-1".to_string(),
+                slide_id: None,
+            }),
+        )
+        .await
+        .unwrap();
+        response.data
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn submitted_question_is_published_escaped_with_a_sequence_number() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+
+        let question = ask(&app, &session_id, "Is <b>this</b> on the exam?").await;
+
+        let events = app.wait_for_events(&session_channel(&session_id), 1).await;
+        let added = events.iter().find(|e| e.name == "QUESTION_ADDED").expect("QUESTION_ADDED published");
+        assert_eq!(added.data["payload"]["question"]["id"], question.id.as_str());
+        assert_eq!(added.data["payload"]["question"]["content"], "Is &lt;b&gt;this&lt;/b&gt; on the exam?");
+        assert!(added.data["seq"].as_i64().is_some_and(|seq| seq > 0));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn upvoting_a_hidden_question_is_rejected_without_publishing() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let question = ask(&app, &session_id, "Can we get the slides?").await;
+        sqlx::query("UPDATE questions SET is_approved = FALSE WHERE id = ?")
+            .bind(&question.id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        app.wait_for_events(&session_channel(&session_id), 1).await;
+        app.published.clear();
+
+        let result = upvote_question(
+            State(app.state.clone()),
+            Extension(app.config.clone()),
+            Path((session_id.clone(), question.id.clone())),
+            Some(Json(UpvoteQuestionRequest { participant_id: Some("participant-2".to_string()) })),
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(app.published.events_for(&session_channel(&session_id)).is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn votes_within_a_window_reach_presenters_as_one_event() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let slide_id = create_slide(&app.pool, &session_id).await;
+
+        for (participant_id, option_id) in [("participant-1", "yes"), ("participant-2", "no")] {
+            let _ = submit_vote(
+                State(app.state.clone()),
+                Path(session_id.clone()),
+                Json(SubmitVoteRequest {
+                    slide_id: slide_id.clone(),
+                    option_id: Some(option_id.to_string()),
+                    option_ids: None,
+                    participant_id: participant_id.to_string(),
+                }),
+            )
+            .await
+            .unwrap();
+        }
+
+        app.wait_for_events(&staff_channel(&session_id), 1).await;
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let events = app.published.events_for(&staff_channel(&session_id));
+        assert_eq!(events.len(), 1, "{:?}", events);
+        assert_eq!(events[0].name, "ANSWERS_SUBMITTED");
+        assert_eq!(events[0].data["slideId"], slide_id.as_str());
+        assert_eq!(
+            events[0].data["answers"],
+            serde_json::json!([
+                { "participantId": "participant-1", "optionIds": ["yes"] },
+                { "participantId": "participant-2", "optionIds": ["no"] },
+            ])
+        );
+        let updates = app.published.events_for(&session_channel(&session_id));
+        assert_eq!(updates.iter().filter(|e| e.name == "VOTE_UPDATE").count(), 1);
+    }
+}
@@ -0,0 +1,90 @@
+use axum::{extract::{Path, State}, Json};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, Result};
+use crate::middleware::auth::AuthUser;
+use crate::models::response::ApiResponse;
+use crate::services::personal_tokens::{self, PersonalToken, TokenScope};
+
+const MAX_TOKEN_NAME_LENGTH: usize = 100;
+const MAX_TOKEN_TTL_DAYS: i64 = 365;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTokenRequest {
+    name: String,
+    scopes: Vec<String>,
+    /// Omit for a token that does not expire
+    expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedToken {
+    #[serde(flatten)]
+    token: PersonalToken,
+    /// Shown once; only a hash is stored
+    secret: String,
+}
+
+/// List the caller's personal access tokens
+pub async fn list_tokens(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<Json<ApiResponse<Vec<PersonalToken>>>> {
+    let pool = app_state.db_pool.pool().await?;
+    Ok(Json(ApiResponse::success(personal_tokens::list(&pool, &user_id).await?)))
+}
+
+/// Create a named, scoped personal access token
+pub async fn create_token(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Json(payload): Json<CreateTokenRequest>,
+) -> Result<Json<ApiResponse<CreatedToken>>> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TOKEN_NAME_LENGTH {
+        return Err(AppError::Input(format!("Token name must be 1-{} characters", MAX_TOKEN_NAME_LENGTH)));
+    }
+
+    let mut scopes = Vec::new();
+    for scope in &payload.scopes {
+        let scope = TokenScope::parse(scope)
+            .ok_or_else(|| AppError::Input(format!("Unknown scope {}", scope)))?;
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(AppError::Input("At least one scope is required".to_string()));
+    }
+
+    let ttl = match payload.expires_in_days {
+        Some(days) if (1..=MAX_TOKEN_TTL_DAYS).contains(&days) => Some(Duration::days(days)),
+        Some(_) => {
+            return Err(AppError::Input(format!("Expiry must be between 1 and {} days", MAX_TOKEN_TTL_DAYS)));
+        }
+        None => None,
+    };
+
+    let pool = app_state.db_pool.pool().await?;
+    let (token, secret) = personal_tokens::issue(&pool, &user_id, name, &scopes, ttl).await?;
+    tracing::info!("User {} created API token {}", user_id, token.id);
+
+    Ok(Json(ApiResponse::success(CreatedToken { token, secret })))
+}
+
+/// Revoke one of the caller's tokens
+pub async fn revoke_token(
+    State(app_state): State<crate::AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Path(token_id): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    let pool = app_state.db_pool.pool().await?;
+    if !personal_tokens::revoke(&pool, &user_id, &token_id).await? {
+        return Err(AppError::NotFound("Token not found".to_string()));
+    }
+    tracing::info!("User {} revoked API token {}", user_id, token_id);
+    Ok(Json(ApiResponse::success(())))
+}
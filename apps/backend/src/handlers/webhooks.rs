@@ -0,0 +1,131 @@
+use axum::{
+    body::Bytes,
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::models::student::ParticipantConnection;
+use crate::services::attendance::{self, PresenceAction, PresenceWebhook};
+
+/// Receive Ably presence webhooks and record participant connection intervals.
+/// Requests must carry a valid `X-Ably-Signature` for the configured API key.
+pub async fn ably_presence(
+    State(app_state): State<crate::AppState>,
+    Extension(config): Extension<Arc<Config>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode> {
+    let api_key = config.ably_api_key.as_deref()
+        .ok_or_else(|| AppError::Internal("ABLY_API_KEY not configured".to_string()))?;
+
+    let key_name = headers
+        .get("x-ably-key")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let signature = headers
+        .get("x-ably-signature")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !attendance::verify_webhook_signature(api_key, key_name, signature, &body) {
+        tracing::warn!("Rejected Ably webhook with invalid signature");
+        return Err(AppError::Auth("Invalid webhook signature".to_string()));
+    }
+
+    let webhook: PresenceWebhook = serde_json::from_slice(&body)
+        .map_err(|e| AppError::Input(format!("Invalid webhook payload: {}", e)))?;
+
+    let pool = app_state.db_pool.pool().await?;
+    for change in webhook.into_changes() {
+        let recorded = match change.action {
+            PresenceAction::Enter => {
+                ParticipantConnection::record_enter(
+                    &pool, &change.session_id, &change.participant_id, &change.connection_id, change.at,
+                ).await?
+            }
+            PresenceAction::Leave => {
+                ParticipantConnection::record_leave(
+                    &pool, &change.session_id, &change.participant_id, &change.connection_id, change.at,
+                ).await?
+            }
+        };
+        if !recorded {
+            tracing::debug!(
+                "Ignored presence {:?} for participant {} in session {}",
+                change.action, change.participant_id, change.session_id
+            );
+        }
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::student::Participant;
+    use crate::models::user::Role;
+    use crate::services::attendance::tests::{sign, API_KEY};
+    use crate::test_support::{app, create_session, create_user};
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    fn signed(body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ably-key", HeaderValue::from_static("appid.keyid"));
+        headers.insert("x-ably-signature", sign(body).parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn signed_presence_webhooks_record_attendance() {
+        let app = app().await;
+        let mut config = (*app.config).clone();
+        config.ably_api_key = Some(API_KEY.to_string());
+        let config = Arc::new(config);
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let participant_id = uuid::Uuid::new_v4().to_string();
+        Participant::create(&app.pool, &participant_id, &session_id, "Alice").await.unwrap();
+
+        // The leave overtook the enter; an unregistered client is ignored
+        let body = serde_json::to_vec(&json!({
+            "items": [{
+                "source": "channel.presence",
+                "data": {
+                    "channelId": format!("session:{}", session_id),
+                    "presence": [
+                        { "action": 3, "clientId": participant_id, "connectionId": "c1", "timestamp": 1_772_442_060_000i64 },
+                        { "action": 2, "clientId": participant_id, "connectionId": "c1", "timestamp": 1_772_442_000_000i64 },
+                        { "action": 2, "clientId": "someone-else", "connectionId": "c2", "timestamp": 1_772_442_000_000i64 }
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let status = ably_presence(
+            State(app.state.clone()),
+            Extension(config.clone()),
+            signed(&body),
+            Bytes::from(body.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let attendance = ParticipantConnection::attendance_by_session(&app.pool, &session_id).await.unwrap();
+        assert_eq!(attendance.len(), 1);
+        assert_eq!(attendance[0].connected_seconds, 60);
+        assert_eq!(attendance[0].open_connections, 0);
+
+        // Tampered bodies are rejected
+        let mut headers = signed(&body);
+        headers.insert("x-ably-signature", sign(b"{}").parse().unwrap());
+        let rejected = ably_presence(State(app.state.clone()), Extension(config), headers, Bytes::from(body)).await;
+        assert!(matches!(rejected, Err(AppError::Auth(_))));
+    }
+}
@@ -18,18 +18,40 @@ mod middleware;
 mod models;
 mod repositories;
 mod services;
+#[cfg(test)]
+mod test_support;
 
 use config::Config;
 use db::LazyDbPool;
 use repositories::session::SessionRepository;
 use repositories::sqlx_session::SqlxSessionRepository;
+use services::ags::{AgsClient, GradeSync, GradeSyncDispatcher};
+use services::jwks::JwksCache;
+use services::jwt_keys::JwtKeys;
+use services::lti::ToolKey;
+use services::mailer::Mailer;
+use services::oidc::OidcService;
+use services::outbox::{Outbox, OutboxDispatcher};
+use services::realtime::RealtimePublisher;
 use services::session::SessionService;
+use services::sse::{SseHub, SseTail};
+use services::vote_coalescer::VoteCoalescer;
 
 /// Application state shared across all handlers
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: LazyDbPool,
     pub session_service: Arc<SessionService>,
+    pub realtime: Arc<dyn RealtimePublisher>,
+    pub sse_hub: Arc<SseHub>,
+    pub outbox: Outbox,
+    pub vote_coalescer: Arc<VoteCoalescer>,
+    pub mailer: Arc<dyn Mailer>,
+    pub oidc: Arc<OidcService>,
+    pub jwks: Arc<JwksCache>,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub lti_tool_key: Option<Arc<ToolKey>>,
+    pub grade_sync: GradeSync,
 }
 
 #[tokio::main]
@@ -63,11 +85,61 @@ async fn main() -> anyhow::Result<()> {
     let session_repository: Arc<dyn SessionRepository> = 
         Arc::new(SqlxSessionRepository::new_lazy(lazy_pool.clone()));
     let session_service = Arc::new(SessionService::new(session_repository));
+
+    // Real-time: events go through the outbox to the primary backend (Ably);
+    // the self-hosted SSE hub gets this instance's events once a transaction
+    // commits and tails the event log for the rest
+    let realtime = services::realtime::publisher_from_config(&config);
+    let sse_hub = Arc::new(SseHub::new(config.sse_max_subscribers_per_session));
+    let outbox = Outbox::new(sse_hub.clone());
+    let vote_coalescer = Arc::new(VoteCoalescer::new(
+        lazy_pool.clone(),
+        outbox.clone(),
+        std::time::Duration::from_millis(config.vote_coalesce_window_ms),
+    ));
+    let mailer = services::mailer::mailer_from_config(&config).map_err(anyhow::Error::msg)?;
+    let jwks = Arc::new(JwksCache::new());
+    let oidc = Arc::new(OidcService::new(config.oidc_providers.clone(), jwks.clone()));
+    let jwt_keys = Arc::new(JwtKeys::from_config(&config));
+    let lti_tool_key = services::lti::tool_key_from_config(&config);
     
     let app_state = AppState {
         db_pool: lazy_pool,
         session_service,
+        realtime,
+        sse_hub,
+        outbox,
+        vote_coalescer,
+        mailer,
+        oidc,
+        jwks,
+        jwt_keys,
+        lti_tool_key,
+        grade_sync: GradeSync::default(),
     };
+
+    OutboxDispatcher::new(
+        app_state.db_pool.clone(),
+        app_state.outbox.clone(),
+        app_state.realtime.clone(),
+        config.outbox_settings(),
+    )
+    .spawn();
+
+    SseTail::new(
+        app_state.db_pool.clone(),
+        app_state.sse_hub.clone(),
+        std::time::Duration::from_millis(config.sse_poll_interval_ms),
+    )
+    .spawn();
+
+    GradeSyncDispatcher::new(
+        app_state.db_pool.clone(),
+        app_state.grade_sync.clone(),
+        AgsClient::new(app_state.lti_tool_key.clone()),
+        config.grade_sync_settings(),
+    )
+    .spawn();
     
     tracing::info!("App state created in {:?}", startup_time.elapsed());
 
@@ -114,26 +186,114 @@ async fn main() -> anyhow::Result<()> {
         ])
         .allow_credentials(true);
 
-    // Routes
-    let app = Router::new()
+    let app = routes()
+        // Cookie-authenticated mutations must come from an allowed origin
+        .layer(axum::middleware::from_fn(middleware::csrf::require_trusted_origin))
+        
+        // Rate limiting layers
+        .layer(GovernorLayer { config: strict_governor_conf })
+        .layer(GovernorLayer { config: general_governor_conf })
+        .layer(cors)
+        .layer(TraceLayer::new_for_http())
+        .layer(Extension(config_arc))
+        .with_state(app_state.clone());
+
+    // Start server IMMEDIATELY (don't wait for DB)
+    let addr = format!("0.0.0.0:{}", config.port);
+    tracing::info!("Server listening on {} (startup: {:?})", addr, startup_time.elapsed());
+    
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    // Publish vote results still waiting in the coalescing window
+    app_state.vote_coalescer.flush_all().await;
+
+    Ok(())
+}
+
+/// Every route of the API, before the layers `main` adds
+fn routes() -> Router<AppState> {
+    Router::new()
         // Health endpoints (no rate limiting, no auth)
         .route("/health", get(handlers::health::health_check))
         .route("/health/live", get(handlers::health::liveness))
         .route("/health/ready", get(handlers::health::readiness))
+        .route("/health/outbox", get(handlers::health::outbox_status))
         
         // Authentication
         .route("/api/auth/register", post(handlers::auth::register))
         .route("/api/auth/login", post(handlers::auth::login))
+        .route("/api/auth/login/mfa", post(handlers::auth::login_mfa))
+        .route("/api/auth/refresh", post(handlers::auth::refresh))
+        .route("/api/auth/logout", post(handlers::auth::logout))
+        .route("/api/auth/logout-all", post(handlers::auth::logout_all))
+        .route("/api/auth/forgot-password", post(handlers::auth::forgot_password))
+        .route("/api/auth/reset-password", post(handlers::auth::reset_password))
+        .route("/api/auth/verify-email", post(handlers::auth::verify_email))
+        .route("/api/auth/resend-verification", post(handlers::auth::resend_verification))
+        .route("/api/auth/oidc/providers", get(handlers::oidc::list_providers))
+        .route("/api/auth/oidc/:provider/login", get(handlers::oidc::login))
+        .route("/api/auth/oidc/:provider/link", get(handlers::oidc::link))
+        .route("/api/auth/oidc/:provider/callback", get(handlers::oidc::callback))
+        .route("/.well-known/jwks.json", get(handlers::auth::jwks))
         .route("/api/auth/ably", get(handlers::ably::get_ably_token))
+        .route("/api/auth/tokens",
+            get(handlers::tokens::list_tokens)
+            .post(handlers::tokens::create_token))
+        .route("/api/auth/tokens/:id",
+            axum::routing::delete(handlers::tokens::revoke_token))
+        
+        // Account self-service
+        .route("/api/me",
+            get(handlers::account::get_me)
+            .put(handlers::account::update_me)
+            .delete(handlers::account::delete_me))
+        .route("/api/me/password", post(handlers::account::change_password))
+        .route("/api/me/mfa",
+            get(handlers::mfa::get_mfa_status)
+            .delete(handlers::mfa::disable_mfa))
+        .route("/api/me/mfa/totp", post(handlers::mfa::start_totp_enrollment))
+        .route("/api/me/mfa/totp/confirm", post(handlers::mfa::confirm_totp_enrollment))
+        .route("/api/me/mfa/recovery-codes", post(handlers::mfa::regenerate_recovery_codes))
+        
+        // Administration (admin role)
+        .route("/api/admin/users/:id/role", put(handlers::admin::set_user_role))
+        .route("/api/admin/users/:id/unlock", post(handlers::admin::unlock_user))
+        
+        .route("/api/admin/lti/platforms",
+            get(handlers::admin::list_lti_platforms)
+            .post(handlers::admin::create_lti_platform))
+        .route("/api/admin/lti/platforms/:id",
+            axum::routing::delete(handlers::admin::delete_lti_platform))
+        
+        // LTI 1.3 tool (launched from an LMS)
+        .route("/api/lti/login", get(handlers::lti::login_initiation).post(handlers::lti::login_initiation))
+        .route("/api/lti/launch", post(handlers::lti::launch))
+        .route("/api/lti/jwks", get(handlers::lti::jwks))
+        .route("/api/lti/deep-link/sessions", post(handlers::lti::deep_link_sessions))
+        .route("/api/lti/deep-link", post(handlers::lti::deep_link_response))
+        .route("/api/lti/link", post(handlers::lti::link_account))
+        
+        // Webhooks (signed by the sender)
+        .route("/api/webhooks/ably/presence", post(handlers::webhooks::ably_presence))
         
         // Public endpoints (no auth required)
         .route("/api/share/:token", get(handlers::public::get_session_by_share_token))
         .route("/api/session-by-token/:token", get(handlers::public::get_session_by_share_token))
         .route("/api/sessions/:id/state", get(handlers::public::get_session_state))
+        .route("/api/join/:code", get(handlers::public::resolve_join_code))
+        .route("/api/sessions/:id/events", get(handlers::events::session_events))
         
-        // Public clicker endpoints
+        // Clicker endpoints (paired phones carry a clicker token)
         .route("/api/sessions/:id/clicker/slide", put(handlers::public::public_set_current_slide))
         .route("/api/sessions/:id/clicker/results", put(handlers::public::public_set_results_visibility))
+        .route("/api/sessions/:id/clicker/pairings", post(handlers::clicker::start_pairing))
+        .route("/api/sessions/:id/clicker/devices", get(handlers::clicker::list_devices))
+        .route("/api/sessions/:session_id/clicker/devices/:device_id",
+            axum::routing::delete(handlers::clicker::revoke_device))
+        .route("/api/clicker/pair", post(handlers::clicker::pair))
         
         // Session stats
         .route("/api/sessions/public/:id/stats", get(handlers::stats::get_public_session_stats))
@@ -149,15 +309,49 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/sessions/:id/duplicate", post(handlers::session::duplicate_session))
         .route("/api/sessions/:id/archive", put(handlers::session::archive_session))
         .route("/api/sessions/:id/restore", put(handlers::session::restore_session))
+
+        // Session collaborators
+        .route("/api/sessions/:id/members",
+            get(handlers::members::list_members)
+            .post(handlers::members::invite_member))
+        .route("/api/sessions/:session_id/members/:user_id",
+            axum::routing::delete(handlers::members::remove_member))
+        .route("/api/sessions/:session_id/invites/:invite_id",
+            axum::routing::delete(handlers::members::revoke_invite))
+        .route("/api/session-invites/accept", post(handlers::members::accept_invite))
+        .route("/api/sessions/:id/organization", put(handlers::organizations::set_session_organization))
+
+        // Organizations
+        .route("/api/organizations",
+            get(handlers::organizations::list_organizations)
+            .post(handlers::organizations::create_organization))
+        .route("/api/organizations/:id",
+            get(handlers::organizations::get_organization)
+            .put(handlers::organizations::update_organization))
+        .route("/api/organizations/:id/sessions", get(handlers::organizations::list_organization_sessions))
+        .route("/api/organizations/:id/transfer-sessions", post(handlers::organizations::transfer_sessions))
+        .route("/api/organizations/:id/audit-log", get(handlers::organizations::audit_log))
+        .route("/api/organizations/:id/members",
+            get(handlers::organizations::list_members)
+            .post(handlers::organizations::add_member))
+        .route("/api/organizations/:organization_id/members/:user_id",
+            put(handlers::organizations::set_member_role)
+            .delete(handlers::organizations::remove_member))
         
         // Session stats
         .route("/api/sessions/:id/stats", get(handlers::stats::get_session_stats))
+        .route("/api/sessions/:id/stats/grade-sync", get(handlers::stats::get_grade_sync_status))
+        .route("/api/sessions/:id/stats/grade-sync/retry", post(handlers::stats::retry_grade_sync))
         
         // Live session controls
         .route("/api/sessions/:id/current-slide", put(handlers::live::set_current_slide))
         .route("/api/sessions/:id/results-visibility", put(handlers::live::set_results_visibility))
         .route("/api/sessions/:id/go-live", post(handlers::live::go_live))
         .route("/api/sessions/:id/stop", post(handlers::live::stop_live))
+        .route("/api/sessions/:session_id/questions/:question_id",
+            axum::routing::delete(handlers::live::delete_question))
+        .route("/api/sessions/:session_id/questions/:question_id/approval",
+            put(handlers::live::set_question_approval))
         
         // Slide management
         .route("/api/sessions/:id/slides", 
@@ -179,21 +373,34 @@ async fn main() -> anyhow::Result<()> {
             post(handlers::student::upvote_question))
         .route("/api/sessions/:id/register-participant",
             post(handlers::student::register_participant))
-        
-        // Rate limiting layers
-        .layer(GovernorLayer { config: strict_governor_conf })
-        .layer(GovernorLayer { config: general_governor_conf })
-        .layer(cors)
-        .layer(TraceLayer::new_for_http())
-        .layer(Extension(config_arc))
-        .with_state(app_state);
+}
 
-    // Start server IMMEDIATELY (don't wait for DB)
-    let addr = format!("0.0.0.0:{}", config.port);
-    tracing::info!("Server listening on {} (startup: {:?})", addr, startup_time.elapsed());
-    
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
+/// Resolves on Ctrl+C or SIGTERM (sent by the platform on redeploy)
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
 
-    Ok(())
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to install SIGTERM handler: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    tracing::info!("Shutdown signal received");
 }
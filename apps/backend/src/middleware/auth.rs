@@ -1,50 +1,122 @@
 use axum::{
     async_trait,
-    extract::FromRequestParts,
-    http::{header::AUTHORIZATION, request::Parts},
-    Extension,
+    extract::{FromRef, FromRequestParts, MatchedPath},
+    http::{header::AUTHORIZATION, request::Parts, Method},
     RequestPartsExt,
 };
 use axum_extra::extract::cookie::CookieJar;
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use crate::config::Config;
+use std::marker::PhantomData;
 use crate::error::AppError;
+use crate::models::user::Role;
+use crate::handlers::auth::ACCESS_COOKIE;
+use crate::services::auth_tokens;
+use crate::services::personal_tokens::{self, TokenScope};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     #[serde(rename = "userId")]
     pub user_id: String,
     pub role: String,
+    /// Token family (one per login); revoked on logout
+    #[serde(rename = "fam")]
+    pub family_id: String,
     pub exp: usize,
 }
 
 pub struct AuthUser {
     pub user_id: String,
-    pub role: String,
+    pub role: Role,
+    /// Token family of the sign-in; None for personal access tokens
+    pub family_id: Option<String>,
+}
+
+/// Which scope a route needs
+#[derive(Debug, Clone, Copy)]
+enum RouteScope {
+    /// `sessions:read` to read, `sessions:write` for any other method
+    ReadOrWrite,
+    /// `sessions:write` whatever the method
+    Write,
+    StatsRead,
+}
+
+/// Routes personal access tokens may call. Routes not listed here
+/// (account, admin, token management) only accept signed-in users.
+const SCOPED_ROUTES: &[(&str, RouteScope)] = &[
+    ("/api/sessions", RouteScope::ReadOrWrite),
+    ("/api/sessions/:id", RouteScope::ReadOrWrite),
+    ("/api/sessions/:id/slides", RouteScope::ReadOrWrite),
+    ("/api/sessions/:id/members", RouteScope::ReadOrWrite),
+    ("/api/organizations/:id/sessions", RouteScope::ReadOrWrite),
+    ("/api/sessions/:id/duplicate", RouteScope::Write),
+    ("/api/sessions/:id/archive", RouteScope::Write),
+    ("/api/sessions/:id/restore", RouteScope::Write),
+    ("/api/sessions/:id/current-slide", RouteScope::Write),
+    ("/api/sessions/:id/results-visibility", RouteScope::Write),
+    ("/api/sessions/:id/go-live", RouteScope::Write),
+    ("/api/sessions/:id/stop", RouteScope::Write),
+    ("/api/sessions/:session_id/questions/:question_id", RouteScope::Write),
+    ("/api/sessions/:session_id/questions/:question_id/approval", RouteScope::Write),
+    ("/api/sessions/:session_id/slides/:slide_id", RouteScope::Write),
+    ("/api/sessions/:session_id/slides/:slide_id/visibility", RouteScope::Write),
+    ("/api/sessions/:id/slides/reorder", RouteScope::Write),
+    ("/api/sessions/:session_id/members/:user_id", RouteScope::Write),
+    ("/api/sessions/:session_id/invites/:invite_id", RouteScope::Write),
+    ("/api/sessions/:id/organization", RouteScope::Write),
+    ("/api/sessions/:id/stats/grade-sync/retry", RouteScope::Write),
+    ("/api/sessions/:id/stats", RouteScope::StatsRead),
+    ("/api/sessions/:id/stats/grade-sync", RouteScope::StatsRead),
+];
+
+/// Scope a personal access token needs for a route pattern
+fn required_scope(method: &Method, route: &str) -> Option<TokenScope> {
+    let (_, scope) = SCOPED_ROUTES.iter().find(|(pattern, _)| *pattern == route)?;
+    Some(match scope {
+        RouteScope::ReadOrWrite if method == Method::GET => TokenScope::SessionsRead,
+        RouteScope::ReadOrWrite | RouteScope::Write => TokenScope::SessionsWrite,
+        RouteScope::StatsRead => TokenScope::StatsRead,
+    })
+}
+
+/// Authenticate a personal access token for the route being called
+async fn token_user(parts: &Parts, app_state: &crate::AppState, token: &str) -> Result<AuthUser, AppError> {
+    let route = parts
+        .extensions
+        .get::<MatchedPath>()
+        .map(|path| path.as_str())
+        .unwrap_or_default();
+    let scope = required_scope(&parts.method, route)
+        .ok_or_else(|| AppError::Forbidden("API tokens cannot be used for this endpoint".to_string()))?;
+
+    let pool = app_state.db_pool.pool().await?;
+    let grant = personal_tokens::authenticate(&pool, token).await?;
+    if !grant.scopes.contains(&scope) {
+        return Err(AppError::Forbidden(format!("API token lacks the {} scope", scope.as_str())));
+    }
+
+    Ok(AuthUser {
+        user_id: grant.user_id,
+        role: grant.role,
+        family_id: None,
+    })
 }
 
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthUser
 where
     S: Send + Sync,
+    crate::AppState: FromRef<S>,
 {
     type Rejection = AppError;
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        // Extract config first to satisfy borrow checker
-        let Extension(config) = parts
-            .extract::<Extension<Arc<Config>>>()
-            .await
-            .map_err(|_| AppError::Internal("Config missing".to_string()))?;
-
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let jar = parts
             .extract::<CookieJar>()
             .await
             .map_err(|_| AppError::Internal("Cookie extraction failed".to_string()))?;
 
-        let token = if let Some(cookie) = jar.get("token") {
+        let token = if let Some(cookie) = jar.get(ACCESS_COOKIE) {
             cookie.value().to_string()
         } else {
             let auth_header = parts
@@ -60,18 +132,209 @@ where
                 return Err(AppError::Auth("Invalid token format".to_string()));
             }
 
-            auth_str[7..].to_string()
+            let bearer = &auth_str[7..];
+            if bearer.starts_with(personal_tokens::TOKEN_PREFIX) {
+                let app_state = crate::AppState::from_ref(state);
+                return token_user(parts, &app_state, bearer).await;
+            }
+            bearer.to_string()
         };
 
-        let token_data = decode::<Claims>(
-            &token,
-            &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
-            &Validation::default(),
-        )?;
+        let app_state = crate::AppState::from_ref(state);
+        let claims: Claims = app_state.jwt_keys.verify(&token)?;
+
+        // Reject access tokens of logged-out devices before they expire
+        let pool = app_state.db_pool.pool().await?;
+        if !auth_tokens::is_family_active(&pool, &claims.family_id).await? {
+            return Err(AppError::Auth("Session has been revoked".to_string()));
+        }
 
         Ok(AuthUser {
-            user_id: token_data.claims.user_id,
-            role: token_data.claims.role,
+            user_id: claims.user_id,
+            role: Role::parse(&claims.role).unwrap_or(Role::Student),
+            family_id: Some(claims.family_id),
         })
     }
 }
+
+/// Role requirement checked by `RequireRole`
+pub trait RoleRequirement {
+    fn allows(role: Role) -> bool;
+    fn describe() -> &'static str;
+}
+
+/// Teachers and admins (authoring sessions)
+pub struct Teacher;
+
+impl RoleRequirement for Teacher {
+    fn allows(role: Role) -> bool {
+        matches!(role, Role::Teacher | Role::Admin)
+    }
+
+    fn describe() -> &'static str {
+        "Teacher account required"
+    }
+}
+
+/// Admins only (user management)
+pub struct Admin;
+
+impl RoleRequirement for Admin {
+    fn allows(role: Role) -> bool {
+        role == Role::Admin
+    }
+
+    fn describe() -> &'static str {
+        "Admin account required"
+    }
+}
+
+/// Authenticated user whose role satisfies `R`; responds 403 otherwise
+pub struct RequireRole<R: RoleRequirement> {
+    pub user: AuthUser,
+    _requirement: PhantomData<R>,
+}
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    crate::AppState: FromRef<S>,
+    R: RoleRequirement,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        if !R::allows(user.role) {
+            return Err(AppError::Forbidden(R::describe().to_string()));
+        }
+        Ok(RequireRole {
+            user,
+            _requirement: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        middleware::Next,
+        response::Response,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    #[test]
+    fn reading_sessions_needs_read_and_changing_them_needs_write() {
+        for route in ["/api/sessions", "/api/sessions/:id", "/api/sessions/:id/slides", "/api/sessions/:id/members"] {
+            assert_eq!(required_scope(&Method::GET, route), Some(TokenScope::SessionsRead), "{}", route);
+            for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+                assert_eq!(required_scope(&method, route), Some(TokenScope::SessionsWrite), "{} {}", method, route);
+            }
+        }
+    }
+
+    #[test]
+    fn running_a_session_needs_write_whatever_the_method() {
+        for route in ["/api/sessions/:id/go-live", "/api/sessions/:id/current-slide", "/api/sessions/:id/organization"] {
+            assert_eq!(required_scope(&Method::GET, route), Some(TokenScope::SessionsWrite), "{}", route);
+            assert_eq!(required_scope(&Method::POST, route), Some(TokenScope::SessionsWrite), "{}", route);
+        }
+        assert_eq!(required_scope(&Method::GET, "/api/sessions/:id/stats"), Some(TokenScope::StatsRead));
+        assert_eq!(
+            required_scope(&Method::POST, "/api/sessions/:id/stats/grade-sync/retry"),
+            Some(TokenScope::SessionsWrite)
+        );
+    }
+
+    #[test]
+    fn account_admin_and_token_routes_refuse_tokens() {
+        for route in [
+            "/api/me",
+            "/api/me/password",
+            "/api/me/mfa",
+            "/api/auth/tokens",
+            "/api/auth/tokens/:id",
+            "/api/auth/logout-all",
+            "/api/admin/users/:id/role",
+            "/api/organizations",
+            "",
+        ] {
+            assert_eq!(required_scope(&Method::GET, route), None, "{}", route);
+            assert_eq!(required_scope(&Method::POST, route), None, "{}", route);
+        }
+        // Scopes match route patterns, never concrete paths
+        assert_eq!(required_scope(&Method::GET, "/api/sessions/3f2a"), None);
+    }
+
+    #[tokio::test]
+    async fn every_scoped_route_is_registered() {
+        async fn matched_path(request: Request<Body>, next: Next) -> Response {
+            let path = request.extensions().get::<MatchedPath>().map(|path| path.as_str().to_string());
+            let mut response = next.run(request).await;
+            if let Some(path) = path {
+                response.headers_mut().insert("x-matched-path", path.parse().unwrap());
+            }
+            response
+        }
+        let app = crate::routes()
+            .layer(axum::middleware::from_fn(matched_path))
+            .with_state(crate::test_support::offline_state());
+
+        for (route, _) in SCOPED_ROUTES {
+            let path = route
+                .split('/')
+                .map(|segment| if segment.starts_with(':') { "x" } else { segment })
+                .collect::<Vec<_>>()
+                .join("/");
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(&path).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let matched = response.headers().get("x-matched-path").and_then(|value| value.to_str().ok());
+            assert_eq!(matched, Some(*route), "{} is not a route", route);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn tokens_only_reach_routes_their_scopes_cover() {
+        let app = crate::test_support::app().await;
+        let teacher = crate::test_support::create_user(&app.pool, Role::Teacher).await;
+        let (_, secret) = personal_tokens::issue(&app.pool, &teacher.user_id, "ci", &[TokenScope::SessionsRead], None)
+            .await
+            .unwrap();
+
+        async fn whoami(user: AuthUser) -> String {
+            user.user_id
+        }
+        let router = Router::new()
+            .route("/api/sessions/:id", get(whoami).put(whoami))
+            .route("/api/me", get(whoami))
+            .with_state(app.state.clone());
+        let call = |method: &str, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(AUTHORIZATION, format!("Bearer {}", secret))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let read = router.clone().oneshot(call("GET", "/api/sessions/abc")).await.unwrap();
+        assert_eq!(read.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(read.into_body(), 1024).await.unwrap();
+        assert_eq!(body, teacher.user_id.as_bytes());
+
+        let write = router.clone().oneshot(call("PUT", "/api/sessions/abc")).await.unwrap();
+        assert_eq!(write.status(), StatusCode::FORBIDDEN);
+        let account = router.oneshot(call("GET", "/api/me")).await.unwrap();
+        assert_eq!(account.status(), StatusCode::FORBIDDEN);
+    }
+}
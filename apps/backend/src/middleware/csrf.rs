@@ -0,0 +1,136 @@
+use axum::{
+    extract::Request,
+    http::{header::{AUTHORIZATION, ORIGIN, REFERER}, HeaderMap, Method},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use axum_extra::extract::cookie::CookieJar;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::handlers::auth::{ACCESS_COOKIE, REFRESH_COOKIE};
+
+/// Routes other sites legitimately post to: LTI platforms submit forms to
+/// the tool, and webhooks are signed by their sender
+const CROSS_SITE_ROUTES: &[&str] = &["/api/lti/login", "/api/lti/launch", "/api/webhooks/"];
+
+/// Origin of the request, from `Origin` or else the `Referer` URL
+fn request_origin(headers: &HeaderMap) -> Option<String> {
+    if let Some(origin) = headers.get(ORIGIN) {
+        return origin.to_str().ok().map(str::to_string);
+    }
+    let referer = headers.get(REFERER)?.to_str().ok()?;
+    let url = reqwest::Url::parse(referer).ok()?;
+    Some(url.origin().ascii_serialization())
+}
+
+/// Reject state-changing requests that carry the auth cookies but come from
+/// a page outside `ALLOWED_ORIGINS`. The cookies are `SameSite=None`, so the
+/// browser attaches them to requests any site triggers. Bearer requests are
+/// exempt: a page can only set that header if CORS lets it.
+pub async fn require_trusted_origin(
+    Extension(config): Extension<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let headers = request.headers();
+    let is_safe = matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let has_bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Bearer "));
+    let path = request.uri().path();
+
+    if is_safe || has_bearer || CROSS_SITE_ROUTES.iter().any(|route| path.starts_with(route)) {
+        return Ok(next.run(request).await);
+    }
+
+    let jar = CookieJar::from_headers(headers);
+    if jar.get(ACCESS_COOKIE).is_none() && jar.get(REFRESH_COOKIE).is_none() {
+        return Ok(next.run(request).await);
+    }
+
+    match request_origin(headers) {
+        Some(origin) if config.allowed_origins.contains(&origin) => {
+            Ok(next.run(request).await)
+        }
+        origin => {
+            tracing::warn!(
+                "Rejected cross-site {} {} from origin {}",
+                request.method(),
+                path,
+                origin.as_deref().unwrap_or("unknown")
+            );
+            Err(AppError::Forbidden("Cross-site request rejected".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::{header::COOKIE, Request as HttpRequest, StatusCode}, routing::post, Router};
+    use tower::ServiceExt;
+
+    use crate::test_support::{config, ALLOWED_ORIGIN};
+
+    const EVIL_ORIGIN: &str = "https://evil.example.net";
+
+    fn router() -> Router {
+        Router::new()
+            .route("/api/sessions", post(|| async { "created" }).get(|| async { "listed" }))
+            .route("/api/lti/launch", post(|| async { "launched" }))
+            .layer(axum::middleware::from_fn(require_trusted_origin))
+            .layer(Extension(Arc::new(config())))
+    }
+
+    async fn status(method: &str, path: &str, headers: &[(axum::http::HeaderName, &str)]) -> StatusCode {
+        let mut request = HttpRequest::builder().method(method).uri(path);
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        router().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    fn access_cookie() -> String {
+        format!("{}=access-token", ACCESS_COOKIE)
+    }
+
+    #[tokio::test]
+    async fn cookie_mutations_from_other_sites_are_rejected() {
+        let cookie = access_cookie();
+        assert_eq!(status("POST", "/api/sessions", &[(COOKIE, &cookie), (ORIGIN, EVIL_ORIGIN)]).await, StatusCode::FORBIDDEN);
+        let referer = format!("{}/attack.html", EVIL_ORIGIN);
+        assert_eq!(status("POST", "/api/sessions", &[(COOKIE, &cookie), (REFERER, &referer)]).await, StatusCode::FORBIDDEN);
+        // Without Origin or Referer the request cannot be attributed, so it is refused
+        assert_eq!(status("POST", "/api/sessions", &[(COOKIE, &cookie)]).await, StatusCode::FORBIDDEN);
+        let refresh_only = format!("{}=refresh-token", REFRESH_COOKIE);
+        assert_eq!(status("POST", "/api/sessions", &[(COOKIE, &refresh_only), (ORIGIN, EVIL_ORIGIN)]).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn cookie_mutations_from_the_app_pass() {
+        let cookie = access_cookie();
+        assert_eq!(status("POST", "/api/sessions", &[(COOKIE, &cookie), (ORIGIN, ALLOWED_ORIGIN)]).await, StatusCode::OK);
+        let referer = format!("{}/staff/sessions?tab=all", ALLOWED_ORIGIN);
+        assert_eq!(status("POST", "/api/sessions", &[(COOKIE, &cookie), (REFERER, &referer)]).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn requests_a_site_cannot_forge_with_cookies_pass() {
+        let cookie = access_cookie();
+        // Reads, bearer requests, cookieless requests and LTI form posts
+        assert_eq!(status("GET", "/api/sessions", &[(COOKIE, &cookie), (ORIGIN, EVIL_ORIGIN)]).await, StatusCode::OK);
+        assert_eq!(
+            status("POST", "/api/sessions", &[(AUTHORIZATION, "Bearer ccp_token"), (ORIGIN, EVIL_ORIGIN)]).await,
+            StatusCode::OK
+        );
+        assert_eq!(status("POST", "/api/sessions", &[(ORIGIN, EVIL_ORIGIN)]).await, StatusCode::OK);
+        assert_eq!(
+            status("POST", "/api/lti/launch", &[(COOKIE, &cookie), (ORIGIN, "https://lms.example.edu")]).await,
+            StatusCode::OK
+        );
+    }
+}
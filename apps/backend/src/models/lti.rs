@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+
+// ============================================
+// LTI Platform Model (LMS registration)
+// ============================================
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LtiPlatform {
+    pub id: String,
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub deployment_id: Option<String>,
+    pub auth_login_url: String,
+    pub auth_token_url: String,
+    pub jwks_url: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Registration details supplied by an admin
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewLtiPlatform {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub deployment_id: Option<String>,
+    pub auth_login_url: String,
+    pub auth_token_url: String,
+    pub jwks_url: String,
+}
+
+impl LtiPlatform {
+    pub async fn create(pool: &DbPool, new: &NewLtiPlatform) -> Result<Self> {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            r#"
+            INSERT INTO lti_platforms
+                (id, name, issuer, client_id, deployment_id, auth_login_url, auth_token_url, jwks_url)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&id)
+        .bind(&new.name)
+        .bind(&new.issuer)
+        .bind(&new.client_id)
+        .bind(&new.deployment_id)
+        .bind(&new.auth_login_url)
+        .bind(&new.auth_token_url)
+        .bind(&new.jwks_url)
+        .execute(pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                AppError::Input("A platform with this issuer and client id is already registered".to_string())
+            }
+            e => AppError::Database(e),
+        })?;
+
+        Self::find(pool, &id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Platform vanished after insert".to_string()))
+    }
+
+    pub async fn find(pool: &DbPool, id: &str) -> Result<Option<Self>> {
+        let platform = sqlx::query_as::<_, LtiPlatform>("SELECT * FROM lti_platforms WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(platform)
+    }
+
+    /// Platform starting a login. Issuers shared by many tenants (e.g. Canvas
+    /// cloud) are told apart by client id, which they send with the request.
+    pub async fn find_for_login(pool: &DbPool, issuer: &str, client_id: Option<&str>) -> Result<Self> {
+        let mut platforms = sqlx::query_as::<_, LtiPlatform>(
+            "SELECT * FROM lti_platforms WHERE issuer = ? AND (? IS NULL OR client_id = ?)"
+        )
+        .bind(issuer)
+        .bind(client_id)
+        .bind(client_id)
+        .fetch_all(pool)
+        .await?;
+
+        match platforms.len() {
+            0 => Err(AppError::NotFound("LTI platform is not registered".to_string())),
+            1 => Ok(platforms.remove(0)),
+            _ => Err(AppError::Input("Several registrations match this issuer; client_id is required".to_string())),
+        }
+    }
+
+    pub async fn list(pool: &DbPool) -> Result<Vec<Self>> {
+        let platforms = sqlx::query_as::<_, LtiPlatform>("SELECT * FROM lti_platforms ORDER BY created_at")
+            .fetch_all(pool)
+            .await?;
+        Ok(platforms)
+    }
+
+    pub async fn delete(pool: &DbPool, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM lti_platforms WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// ============================================
+// LTI Resource Link Model (course link -> session)
+// ============================================
+
+pub struct LtiResourceLink;
+
+impl LtiResourceLink {
+    /// Session a course link was bound to on an earlier launch
+    pub async fn session_for(pool: &DbPool, platform_id: &str, resource_link_id: &str) -> Result<Option<String>> {
+        let session_id = sqlx::query_scalar(
+            "SELECT session_id FROM lti_resource_links WHERE platform_id = ? AND resource_link_id = ?"
+        )
+        .bind(platform_id)
+        .bind(resource_link_id)
+        .fetch_optional(pool)
+        .await?;
+        Ok(session_id)
+    }
+
+    /// Remember the session of a course link (first launch after deep linking)
+    pub async fn bind(
+        pool: &DbPool,
+        platform_id: &str,
+        resource_link_id: &str,
+        deployment_id: &str,
+        context_id: Option<&str>,
+        session_id: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT IGNORE INTO lti_resource_links
+                (platform_id, resource_link_id, deployment_id, context_id, session_id)
+            VALUES (?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(platform_id)
+        .bind(resource_link_id)
+        .bind(deployment_id)
+        .bind(context_id)
+        .bind(session_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record the gradebook endpoints of the latest launch. A line item the
+    /// platform names wins over one created by an earlier sync.
+    pub async fn set_ags_endpoint(
+        pool: &DbPool,
+        platform_id: &str,
+        resource_link_id: &str,
+        lineitems_url: Option<&str>,
+        line_item_url: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE lti_resource_links
+            SET ags_lineitems_url = ?, line_item_url = COALESCE(?, line_item_url)
+            WHERE platform_id = ? AND resource_link_id = ?
+            "#
+        )
+        .bind(lineitems_url)
+        .bind(line_item_url)
+        .bind(platform_id)
+        .bind(resource_link_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+// ============================================
+// LTI Participant Model (LMS learner -> participant)
+// ============================================
+
+pub struct LtiParticipant;
+
+impl LtiParticipant {
+    /// Stable participant id of an LMS user in a session, created on first
+    /// launch. Scores go back through the course link of the latest launch.
+    pub async fn participant_id_for(
+        pool: &DbPool,
+        session_id: &str,
+        platform_id: &str,
+        subject: &str,
+        resource_link_id: &str,
+    ) -> Result<String> {
+        sqlx::query(
+            r#"
+            INSERT INTO lti_participants (session_id, platform_id, subject, participant_id, resource_link_id)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE resource_link_id = VALUES(resource_link_id)
+            "#
+        )
+        .bind(session_id)
+        .bind(platform_id)
+        .bind(subject)
+        .bind(Uuid::new_v4().to_string())
+        .bind(resource_link_id)
+        .execute(pool)
+        .await?;
+
+        let participant_id = sqlx::query_scalar(
+            "SELECT participant_id FROM lti_participants WHERE session_id = ? AND platform_id = ? AND subject = ?"
+        )
+        .bind(session_id)
+        .bind(platform_id)
+        .bind(subject)
+        .fetch_one(pool)
+        .await?;
+        Ok(participant_id)
+    }
+}
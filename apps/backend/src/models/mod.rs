@@ -3,3 +3,5 @@ pub mod session;
 pub mod slide;
 pub mod response;
 pub mod student;
+pub mod lti;
+pub mod organization;
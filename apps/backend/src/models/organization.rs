@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    pub created_by: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Role in an organization, stored lowercase in `organization_members.role`;
+/// ordered by privilege
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    /// Sees and duplicates the organization's sessions
+    Member,
+    /// Also manages members and owns every organization session
+    Admin,
+}
+
+impl OrgRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Member => "member",
+            OrgRole::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "member" => Some(OrgRole::Member),
+            "admin" => Some(OrgRole::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Decoding of stored roles (`#[sqlx(try_from = "String")]`)
+impl TryFrom<String> for OrgRole {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        OrgRole::parse(&value).ok_or_else(|| format!("Unknown organization role {}", value))
+    }
+}
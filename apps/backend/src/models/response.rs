@@ -16,12 +16,4 @@ impl<T> ApiResponse<T> {
             error: None,
         }
     }
-
-    pub fn error(message: String, data: T) -> Self {
-        Self {
-            success: false,
-            data,
-            error: Some(message),
-        }
-    }
 }
@@ -9,11 +9,19 @@ pub struct Session {
     #[serde(rename = "creatorId")]
     #[sqlx(rename = "creator_id")]
     pub creator_id: String,
+    /// Organization whose library the session belongs to
+    #[serde(rename = "organizationId")]
+    #[sqlx(rename = "organization_id")]
+    pub organization_id: Option<String>,
     pub title: String,
     pub status: String,
     #[serde(rename = "shareToken")]
     #[sqlx(rename = "share_token")]
     pub share_token: Option<String>,
+    /// Numeric code students join with; only set while the session is live
+    #[serde(rename = "joinCode")]
+    #[sqlx(rename = "join_code")]
+    pub join_code: Option<String>,
     #[serde(rename = "currentSlideId")]
     #[sqlx(rename = "current_slide_id")]
     pub current_slide_id: Option<String>,
@@ -44,6 +52,53 @@ pub struct SessionWithSlideCount {
     #[serde(flatten)]
     pub session: Session,
     pub slide_count: i64,
+    /// The caller's role on the session
+    pub role: SessionRole,
+}
+
+/// Role of a collaborator on a session, stored lowercase in
+/// `session_members.role`; ordered by privilege. The creator is always owner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionRole {
+    /// Sees slides, results and statistics
+    Viewer,
+    /// Also runs the live presentation and moderates questions
+    Presenter,
+    /// Also edits the session and its slides
+    Editor,
+    /// Also manages collaborators, archives and deletes the session
+    Owner,
+}
+
+impl SessionRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionRole::Viewer => "viewer",
+            SessionRole::Presenter => "presenter",
+            SessionRole::Editor => "editor",
+            SessionRole::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Some(SessionRole::Viewer),
+            "presenter" => Some(SessionRole::Presenter),
+            "editor" => Some(SessionRole::Editor),
+            "owner" => Some(SessionRole::Owner),
+            _ => None,
+        }
+    }
+}
+
+/// Decoding of stored roles (`#[sqlx(try_from = "String")]`)
+impl TryFrom<String> for SessionRole {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        SessionRole::parse(&value).ok_or_else(|| format!("Unknown session role {}", value))
+    }
 }
 
 use crate::models::slide::Slide;
@@ -78,6 +133,8 @@ pub struct PublicSessionResponse {
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionState {
+    /// Sequence number of the last event reflected in this state
+    pub seq: i64,
     pub current_slide_id: Option<String>,
     pub is_presentation_active: bool,
     pub is_results_visible: bool,
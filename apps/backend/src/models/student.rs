@@ -4,7 +4,7 @@ use chrono::{DateTime, Utc};
 use crate::db::DbPool;
 use crate::error::Result;
 use uuid::Uuid;
-use sqlx::MySql;
+use sqlx::{MySql, MySqlConnection};
 
 // ============================================
 // Participant Model
@@ -45,6 +45,17 @@ impl Participant {
         })
     }
 
+    pub async fn exists(pool: &DbPool, id: &str, session_id: &str) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM participants WHERE id = ? AND session_id = ?)"
+        )
+        .bind(id)
+        .bind(session_id)
+        .fetch_one(pool)
+        .await?;
+        Ok(exists)
+    }
+
     pub async fn find_by_session(pool: &DbPool, session_id: &str) -> Result<Vec<Self>> {
         let participants = sqlx::query_as::<_, Participant>(
             "SELECT id, session_id, name, joined_at FROM participants WHERE session_id = ?"
@@ -66,6 +77,200 @@ impl Participant {
     }
 }
 
+// ============================================
+// Participant Connection Model (attendance)
+// ============================================
+
+/// Connected time of one participant, aggregated over their presence intervals
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticipantAttendance {
+    pub participant_id: String,
+    pub first_entered_at: Option<DateTime<Utc>>,
+    pub last_left_at: Option<DateTime<Utc>>,
+    /// Time covered by at least one interval; tabs open side by side count once
+    pub connected_seconds: i64,
+    /// Presence intervals without a leave yet, while the session is live
+    pub open_connections: i64,
+}
+
+#[derive(Debug, FromRow)]
+struct ConnectionInterval {
+    participant_id: String,
+    entered_at: DateTime<Utc>,
+    left_at: Option<DateTime<Utc>>,
+}
+
+pub struct ParticipantConnection;
+
+impl ParticipantConnection {
+    /// Open a presence interval; ignored for ids not registered in the session
+    /// and for re-delivered enter messages. A leave of the connection that
+    /// arrived first (see `record_leave`) closes the interval right away.
+    pub async fn record_enter(
+        pool: &DbPool,
+        session_id: &str,
+        participant_id: &str,
+        connection_id: &str,
+        entered_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT IGNORE INTO participant_connections (session_id, participant_id, connection_id, entered_at, left_at)
+            SELECT p.session_id, p.id, ?, ?, (
+                SELECT MIN(early.left_at) FROM participant_connections early
+                WHERE early.session_id = p.session_id AND early.participant_id = p.id
+                  AND early.connection_id = ? AND early.entered_at = early.left_at AND early.left_at >= ?
+            )
+            FROM participants p WHERE p.session_id = ? AND p.id = ?
+            "#
+        )
+        .bind(connection_id)
+        .bind(entered_at)
+        .bind(connection_id)
+        .bind(entered_at)
+        .bind(session_id)
+        .bind(participant_id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Close the open interval of a connection. Webhooks can arrive out of
+    /// order, so a leave with nothing to close is kept as an empty interval
+    /// for its enter to pick up. Returns false if it was ignored.
+    pub async fn record_leave(
+        pool: &DbPool,
+        session_id: &str,
+        participant_id: &str,
+        connection_id: &str,
+        left_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let closed = sqlx::query(
+            r#"
+            UPDATE participant_connections
+            SET left_at = GREATEST(entered_at, ?)
+            WHERE session_id = ? AND participant_id = ? AND connection_id = ? AND left_at IS NULL
+            "#
+        )
+        .bind(left_at)
+        .bind(session_id)
+        .bind(participant_id)
+        .bind(connection_id)
+        .execute(pool)
+        .await?;
+        if closed.rows_affected() > 0 {
+            return Ok(true);
+        }
+
+        let early = sqlx::query(
+            r#"
+            INSERT IGNORE INTO participant_connections (session_id, participant_id, connection_id, entered_at, left_at)
+            SELECT p.session_id, p.id, ?, ?, ?
+            FROM participants p
+            WHERE p.session_id = ? AND p.id = ?
+              AND NOT EXISTS (
+                  SELECT 1 FROM participant_connections c
+                  WHERE c.session_id = p.session_id AND c.participant_id = p.id
+                    AND c.connection_id = ? AND c.entered_at <= ?
+              )
+            "#
+        )
+        .bind(connection_id)
+        .bind(left_at)
+        .bind(left_at)
+        .bind(session_id)
+        .bind(participant_id)
+        .bind(connection_id)
+        .bind(left_at)
+        .execute(pool)
+        .await?;
+        Ok(early.rows_affected() > 0)
+    }
+
+    /// Close every open interval when the session stops, so a leave that
+    /// never arrived doesn't keep a participant connected
+    pub async fn close_open(conn: &mut MySqlConnection, session_id: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE participant_connections SET left_at = GREATEST(entered_at, NOW(3))
+             WHERE session_id = ? AND left_at IS NULL"
+        )
+        .bind(session_id)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Per-participant connected time for a session
+    pub async fn attendance_by_session(pool: &DbPool, session_id: &str) -> Result<Vec<ParticipantAttendance>> {
+        let is_live: Option<bool> = sqlx::query_scalar("SELECT is_presentation_active FROM sessions WHERE id = ?")
+            .bind(session_id)
+            .fetch_optional(pool)
+            .await?;
+        let intervals = sqlx::query_as::<_, ConnectionInterval>(
+            "SELECT participant_id, entered_at, left_at FROM participant_connections
+             WHERE session_id = ? ORDER BY participant_id, entered_at"
+        )
+        .bind(session_id)
+        .fetch_all(pool)
+        .await?;
+
+        let open_until = is_live.unwrap_or(false).then(Utc::now);
+        Ok(summarize_attendance(intervals, open_until))
+    }
+}
+
+/// Merge each participant's intervals (sorted by participant, then enter
+/// time) into the time they were connected. Open intervals run until
+/// `open_until` while the session is live; otherwise they only arrived after
+/// `close_open` and count for nothing.
+fn summarize_attendance(intervals: Vec<ConnectionInterval>, open_until: Option<DateTime<Utc>>) -> Vec<ParticipantAttendance> {
+    let mut summaries: Vec<ParticipantAttendance> = Vec::new();
+    // Merged interval of the current participant not yet added to their total
+    let mut pending: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+
+    for interval in intervals {
+        if summaries.last().is_none_or(|last| last.participant_id != interval.participant_id) {
+            if let (Some(last), Some((start, end))) = (summaries.last_mut(), pending.take()) {
+                last.connected_seconds += (end - start).num_seconds();
+            }
+            summaries.push(ParticipantAttendance {
+                participant_id: interval.participant_id.clone(),
+                first_entered_at: Some(interval.entered_at),
+                last_left_at: None,
+                connected_seconds: 0,
+                open_connections: 0,
+            });
+        }
+        let summary = summaries.last_mut().expect("pushed above");
+
+        let end = match (interval.left_at, open_until) {
+            (Some(left_at), _) => {
+                summary.last_left_at = summary.last_left_at.max(Some(left_at));
+                left_at
+            }
+            (None, Some(now)) => {
+                summary.open_connections += 1;
+                now
+            }
+            (None, None) => continue,
+        }
+        .max(interval.entered_at);
+
+        pending = match pending {
+            Some((start, pending_end)) if interval.entered_at <= pending_end => Some((start, pending_end.max(end))),
+            Some((start, pending_end)) => {
+                summary.connected_seconds += (pending_end - start).num_seconds();
+                Some((interval.entered_at, end))
+            }
+            None => Some((interval.entered_at, end)),
+        };
+    }
+    if let (Some(last), Some((start, end))) = (summaries.last_mut(), pending) {
+        last.connected_seconds += (end - start).num_seconds();
+    }
+    summaries
+}
+
 // ============================================
 // Vote Model
 // ============================================
@@ -87,41 +292,8 @@ pub struct Vote {
 }
 
 impl Vote {
-    pub async fn create(
-        pool: &DbPool,
-        id: &str,
-        session_id: &str,
-        slide_id: &str,
-        participant_id: &str,
-        option_id: &str,
-    ) -> Result<Self> {
-        sqlx::query(
-            r#"
-            INSERT INTO votes (id, session_id, slide_id, participant_id, option_id)
-            VALUES (?, ?, ?, ?, ?)
-            ON DUPLICATE KEY UPDATE option_id = VALUES(option_id)
-            "#
-        )
-        .bind(id)
-        .bind(session_id)
-        .bind(slide_id)
-        .bind(participant_id)
-        .bind(option_id)
-        .execute(pool)
-        .await?;
-
-        Ok(Vote {
-            id: id.to_string(),
-            session_id: session_id.to_string(),
-            slide_id: slide_id.to_string(),
-            participant_id: participant_id.to_string(),
-            option_id: option_id.to_string(),
-            created_at: Some(Utc::now()),
-        })
-    }
-
     pub async fn create_many(
-        pool: &DbPool,
+        conn: &mut MySqlConnection,
         session_id: &str,
         slide_id: &str,
         participant_id: &str,
@@ -146,52 +318,19 @@ impl Vote {
 
         query.push(" ON DUPLICATE KEY UPDATE option_id = VALUES(option_id)");
 
-        query.build().execute(pool).await?;
+        query.build().execute(conn).await?;
         Ok(())
     }
 
-    pub async fn find_by_slide(pool: &DbPool, slide_id: &str) -> Result<Vec<Self>> {
-        let votes = sqlx::query_as::<_, Vote>(
-            "SELECT id, session_id, slide_id, participant_id, option_id, created_at 
-             FROM votes WHERE slide_id = ?"
-        )
-        .bind(slide_id)
-        .fetch_all(pool)
-        .await?;
-        Ok(votes)
-    }
-
-    pub async fn count_by_option(pool: &DbPool, slide_id: &str, option_id: &str) -> Result<i64> {
-        let count: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM votes WHERE slide_id = ? AND option_id = ?"
-        )
-        .bind(slide_id)
-        .bind(option_id)
-        .fetch_one(pool)
-        .await?;
-        Ok(count.0)
-    }
-
-    pub async fn get_vote_counts(pool: &DbPool, slide_id: &str) -> Result<Vec<(String, i64)>> {
+    pub async fn get_vote_counts(conn: &mut MySqlConnection, slide_id: &str) -> Result<Vec<(String, i64)>> {
         let counts: Vec<(String, i64)> = sqlx::query_as(
             "SELECT option_id, COUNT(*) as count FROM votes WHERE slide_id = ? GROUP BY option_id"
         )
         .bind(slide_id)
-        .fetch_all(pool)
+        .fetch_all(conn)
         .await?;
         Ok(counts)
     }
-
-    pub async fn has_voted(pool: &DbPool, slide_id: &str, participant_id: &str) -> Result<bool> {
-        let count: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM votes WHERE slide_id = ? AND participant_id = ?"
-        )
-        .bind(slide_id)
-        .bind(participant_id)
-        .fetch_one(pool)
-        .await?;
-        Ok(count.0 > 0)
-    }
 }
 
 // ============================================
@@ -218,7 +357,7 @@ pub struct Question {
 
 impl Question {
     pub async fn create(
-        pool: &DbPool,
+        conn: &mut MySqlConnection,
         id: &str,
         session_id: &str,
         slide_id: Option<&str>,
@@ -236,7 +375,7 @@ impl Question {
         .bind(slide_id)
         .bind(participant_id)
         .bind(content)
-        .execute(pool)
+        .execute(conn)
         .await?;
 
         Ok(Question {
@@ -251,13 +390,13 @@ impl Question {
         })
     }
 
-    pub async fn find_by_session(pool: &DbPool, session_id: &str) -> Result<Vec<Self>> {
+    pub async fn find_by_session(conn: &mut MySqlConnection, session_id: &str) -> Result<Vec<Self>> {
         let questions = sqlx::query_as::<_, Question>(
             "SELECT id, session_id, slide_id, participant_id, content, upvotes, is_approved, created_at 
              FROM questions WHERE session_id = ? ORDER BY upvotes DESC, created_at DESC"
         )
         .bind(session_id)
-        .fetch_all(pool)
+        .fetch_all(conn)
         .await?;
         Ok(questions)
     }
@@ -273,33 +412,152 @@ impl Question {
         Ok(question)
     }
 
-    pub async fn upvote(pool: &DbPool, id: &str) -> Result<i32> {
+    pub async fn upvote(conn: &mut MySqlConnection, id: &str) -> Result<i32> {
         sqlx::query("UPDATE questions SET upvotes = upvotes + 1 WHERE id = ?")
             .bind(id)
-            .execute(pool)
+            .execute(&mut *conn)
             .await?;
 
         let question: (i32,) = sqlx::query_as("SELECT upvotes FROM questions WHERE id = ?")
             .bind(id)
-            .fetch_one(pool)
+            .fetch_one(conn)
             .await?;
         Ok(question.0)
     }
 
-    pub async fn approve(pool: &DbPool, id: &str, approved: bool) -> Result<()> {
+    pub async fn approve(conn: &mut MySqlConnection, id: &str, approved: bool) -> Result<()> {
         sqlx::query("UPDATE questions SET is_approved = ? WHERE id = ?")
             .bind(approved)
             .bind(id)
-            .execute(pool)
+            .execute(conn)
             .await?;
         Ok(())
     }
 
-    pub async fn delete(pool: &DbPool, id: &str) -> Result<()> {
+    pub async fn delete(conn: &mut MySqlConnection, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM question_upvotes WHERE question_id = ?")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+
         sqlx::query("DELETE FROM questions WHERE id = ?")
             .bind(id)
-            .execute(pool)
+            .execute(conn)
             .await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::test_support::{app, create_session, create_user};
+    use chrono::{Duration, TimeZone};
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap() + Duration::minutes(minute)
+    }
+
+    fn interval(participant_id: &str, entered: i64, left: Option<i64>) -> ConnectionInterval {
+        ConnectionInterval {
+            participant_id: participant_id.to_string(),
+            entered_at: at(entered),
+            left_at: left.map(at),
+        }
+    }
+
+    #[test]
+    fn overlapping_tabs_and_reconnects_count_once() {
+        let summaries = summarize_attendance(
+            vec![
+                // Two tabs open side by side, then a reconnect after a gap
+                interval("alice", 0, Some(10)),
+                interval("alice", 5, Some(15)),
+                interval("alice", 6, Some(8)),
+                interval("alice", 20, Some(25)),
+                interval("bob", 0, Some(1)),
+            ],
+            None,
+        );
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].participant_id, "alice");
+        assert_eq!(summaries[0].connected_seconds, 20 * 60);
+        assert_eq!(summaries[0].first_entered_at, Some(at(0)));
+        assert_eq!(summaries[0].last_left_at, Some(at(25)));
+        assert_eq!(summaries[0].open_connections, 0);
+        assert_eq!(summaries[1].connected_seconds, 60);
+    }
+
+    #[test]
+    fn open_intervals_run_until_now_only_while_live() {
+        let intervals = || vec![interval("alice", 0, Some(10)), interval("alice", 5, None)];
+
+        let live = summarize_attendance(intervals(), Some(at(30)));
+        assert_eq!(live[0].connected_seconds, 30 * 60);
+        assert_eq!(live[0].open_connections, 1);
+
+        // A leave that never arrived doesn't keep counting after the session
+        let stopped = summarize_attendance(intervals(), None);
+        assert_eq!(stopped[0].connected_seconds, 10 * 60);
+        assert_eq!(stopped[0].open_connections, 0);
+    }
+
+    #[test]
+    fn leaves_recorded_before_their_enter_count_nothing() {
+        // Kept as an empty interval until the enter picks it up
+        let summaries = summarize_attendance(vec![interval("alice", 10, Some(10))], Some(at(30)));
+        assert_eq!(summaries[0].connected_seconds, 0);
+        assert_eq!(summaries[0].open_connections, 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn presence_webhooks_in_any_order_give_closed_intervals() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let participant_id = Uuid::new_v4().to_string();
+        Participant::create(&app.pool, &participant_id, &session_id, "Alice").await.unwrap();
+        sqlx::query("UPDATE sessions SET is_presentation_active = TRUE WHERE id = ?")
+            .bind(&session_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        // Two overlapping tabs
+        assert!(ParticipantConnection::record_enter(&app.pool, &session_id, &participant_id, "tab-1", at(0)).await.unwrap());
+        assert!(ParticipantConnection::record_enter(&app.pool, &session_id, &participant_id, "tab-2", at(5)).await.unwrap());
+        assert!(ParticipantConnection::record_leave(&app.pool, &session_id, &participant_id, "tab-1", at(10)).await.unwrap());
+        assert!(ParticipantConnection::record_leave(&app.pool, &session_id, &participant_id, "tab-2", at(15)).await.unwrap());
+        // A re-delivered enter is ignored
+        assert!(!ParticipantConnection::record_enter(&app.pool, &session_id, &participant_id, "tab-1", at(0)).await.unwrap());
+
+        // The leave of a third tab overtakes its enter
+        assert!(ParticipantConnection::record_leave(&app.pool, &session_id, &participant_id, "tab-3", at(30)).await.unwrap());
+        assert!(ParticipantConnection::record_enter(&app.pool, &session_id, &participant_id, "tab-3", at(20)).await.unwrap());
+
+        let attendance = ParticipantConnection::attendance_by_session(&app.pool, &session_id).await.unwrap();
+        assert_eq!(attendance.len(), 1);
+        assert_eq!(attendance[0].connected_seconds, 25 * 60);
+        assert_eq!(attendance[0].open_connections, 0);
+        assert_eq!(attendance[0].last_left_at, Some(at(30)));
+
+        // A fourth tab whose leave is lost is closed when the session stops
+        ParticipantConnection::record_enter(&app.pool, &session_id, &participant_id, "tab-4", Utc::now()).await.unwrap();
+        let live = ParticipantConnection::attendance_by_session(&app.pool, &session_id).await.unwrap();
+        assert_eq!(live[0].open_connections, 1);
+
+        let mut tx = app.pool.begin().await.unwrap();
+        sqlx::query("UPDATE sessions SET is_presentation_active = FALSE WHERE id = ?")
+            .bind(&session_id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        ParticipantConnection::close_open(&mut tx, &session_id).await.unwrap();
+        tx.commit().await.unwrap();
+        let stopped = ParticipantConnection::attendance_by_session(&app.pool, &session_id).await.unwrap();
+        assert_eq!(stopped[0].open_connections, 0);
+    }
+}
@@ -11,4 +11,41 @@ pub struct User {
     pub name: String,
     pub role: String,
     pub created_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+/// Account role, stored lowercase in `users.role`; ordered by privilege
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Student,
+    Teacher,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Student => "student",
+            Role::Teacher => "teacher",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Parse a stored or requested role; "staff" is the legacy name for teacher
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "student" => Some(Role::Student),
+            "teacher" | "staff" => Some(Role::Teacher),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl User {
+    /// Role of the account; unknown stored values get the least privilege
+    pub fn role(&self) -> Role {
+        Role::parse(&self.role).unwrap_or(Role::Student)
+    }
 }
@@ -1,19 +1,27 @@
 use async_trait::async_trait;
 use crate::error::Result;
-use crate::models::session::Session;
+use crate::models::session::{Session, SessionRole};
 
 /// Repository trait - defines the contract for data access
 /// The Application Layer depends on this trait, not the implementation
 #[async_trait]
 pub trait SessionRepository: Send + Sync {
-    async fn find_by_creator(&self, creator_id: &str) -> Result<Vec<Session>>;
-    async fn find_by_creator_with_slide_count(&self, creator_id: &str) -> Result<Vec<(Session, i64)>>;
+    /// Sessions the user created or collaborates on
+    async fn find_by_member(&self, user_id: &str) -> Result<Vec<Session>>;
+    async fn find_by_member_with_slide_count(&self, user_id: &str) -> Result<Vec<(Session, i64, SessionRole)>>;
+    /// An organization's session library, with the user's role on each
+    async fn find_by_organization_with_slide_count(
+        &self,
+        organization_id: &str,
+        user_id: &str,
+    ) -> Result<Vec<(Session, i64, SessionRole)>>;
     async fn find_by_id(&self, id: &str) -> Result<Option<Session>>;
     async fn find_by_share_token(&self, token: &str) -> Result<Option<Session>>;
     async fn create(&self, session: &NewSession) -> Result<Session>;
     async fn update(&self, id: &str, updates: &SessionUpdates) -> Result<Session>;
     async fn delete(&self, id: &str) -> Result<u64>;
-    async fn verify_ownership(&self, session_id: &str, user_id: &str) -> Result<bool>;
+    async fn find_role(&self, session_id: &str, user_id: &str) -> Result<Option<SessionRole>>;
+    async fn get_event_seq(&self, session_id: &str) -> Result<Option<i64>>;
     
     // Related data methods
     async fn get_slides(&self, session_id: &str) -> Result<Vec<crate::models::slide::Slide>>;
@@ -27,6 +35,7 @@ pub trait SessionRepository: Send + Sync {
 pub struct NewSession {
     pub id: String,
     pub creator_id: String,
+    pub organization_id: Option<String>,
     pub title: String,
     pub share_token: String,
     pub allow_questions: bool,
@@ -2,19 +2,22 @@ use async_trait::async_trait;
 use sqlx::{query_as, query_scalar, MySql, Pool};
 
 use crate::db::LazyDbPool;
-use crate::error::{AppError, Result};
-use crate::models::session::Session;
+use crate::error::Result;
+use crate::models::session::{Session, SessionRole};
 use crate::repositories::session::{NewSession, SessionRepository, SessionUpdates};
 use crate::models::slide::Slide;
 use crate::models::student::{Question, Participant};
+use crate::services::session_access;
 
 #[derive(sqlx::FromRow)]
 struct SessionWithSlideCountRow {
     id: String,
     creator_id: String,
+    organization_id: Option<String>,
     title: String,
     status: String,
     share_token: Option<String>,
+    join_code: Option<String>,
     current_slide_id: Option<String>,
     is_results_visible: bool,
     is_presentation_active: bool,
@@ -23,61 +26,45 @@ struct SessionWithSlideCountRow {
     created_at: Option<chrono::DateTime<chrono::Utc>>,
     updated_at: Option<chrono::DateTime<chrono::Utc>>,
     slide_count: i64,
+    member_role: Option<String>,
+    org_role: Option<String>,
 }
 
 /// SQLx implementation of SessionRepository
 /// This is the Infrastructure Layer - it knows about databases
 pub struct SqlxSessionRepository {
-    pool: Option<Pool<MySql>>,
-    lazy_pool: Option<LazyDbPool>,
+    lazy_pool: LazyDbPool,
 }
 
 impl SqlxSessionRepository {
-    pub fn new(pool: Pool<MySql>) -> Self {
-        Self { pool: Some(pool), lazy_pool: None }
-    }
-
     pub fn new_lazy(lazy_pool: LazyDbPool) -> Self {
-        Self { pool: None, lazy_pool: Some(lazy_pool) }
+        Self { lazy_pool }
     }
 
     async fn get_pool(&self) -> Result<Pool<MySql>> {
-        if let Some(ref pool) = self.pool {
-            return Ok(pool.clone());
-        }
-        
-        if let Some(ref lazy) = self.lazy_pool {
-            lazy.get_or_wait().await
-        } else {
-            Err(AppError::Internal("No database pool configured".to_string()))
-        }
-    }
-}
-
-#[async_trait]
-impl SessionRepository for SqlxSessionRepository {
-    async fn find_by_creator(&self, creator_id: &str) -> Result<Vec<Session>> {
-        let pool = self.get_pool().await?;
-        let sessions = query_as::<_, Session>(
-            "SELECT * FROM sessions WHERE creator_id = ? ORDER BY created_at DESC"
-        )
-        .bind(creator_id)
-        .fetch_all(&pool)
-        .await?;
-
-        Ok(sessions)
+        self.lazy_pool.get_or_wait().await
     }
 
-    async fn find_by_creator_with_slide_count(&self, creator_id: &str) -> Result<Vec<(Session, i64)>> {
+    /// Sessions matching `filter` (a condition on `s`, the session, and `m`,
+    /// the user's membership, with one `?` for `filter_arg`), with slide
+    /// counts and the user's role on each
+    async fn list_with_slide_count(
+        &self,
+        user_id: &str,
+        filter: &str,
+        filter_arg: &str,
+    ) -> Result<Vec<(Session, i64, SessionRole)>> {
         let pool = self.get_pool().await?;
-        let rows = query_as::<_, SessionWithSlideCountRow>(
+        let rows = query_as::<_, SessionWithSlideCountRow>(&format!(
             r#"
             SELECT
                 s.id,
                 s.creator_id,
+                s.organization_id,
                 s.title,
                 s.status,
                 s.share_token,
+                s.join_code,
                 s.current_slide_id,
                 s.is_results_visible,
                 s.is_presentation_active,
@@ -85,31 +72,46 @@ impl SessionRepository for SqlxSessionRepository {
                 s.require_name,
                 s.created_at,
                 s.updated_at,
-                COALESCE(sc.slide_count, 0) as slide_count
+                COALESCE(sc.slide_count, 0) as slide_count,
+                m.role as member_role,
+                om.role as org_role
             FROM sessions s
             LEFT JOIN (
                 SELECT session_id, COUNT(*) as slide_count
                 FROM slides
                 GROUP BY session_id
             ) sc ON sc.session_id = s.id
-            WHERE s.creator_id = ?
+            LEFT JOIN session_members m ON m.session_id = s.id AND m.user_id = ?
+            LEFT JOIN organization_members om ON om.organization_id = s.organization_id AND om.user_id = ?
+            WHERE {}
             ORDER BY s.created_at DESC
-            "#
-        )
-        .bind(creator_id)
+            "#,
+            filter
+        ))
+        .bind(user_id)
+        .bind(user_id)
+        .bind(filter_arg)
         .fetch_all(&pool)
         .await?;
 
         Ok(rows
             .into_iter()
-            .map(|r| {
-                (
+            .filter_map(|r| {
+                let role = session_access::effective_role(
+                    user_id,
+                    &r.creator_id,
+                    r.member_role.as_deref(),
+                    r.org_role.as_deref(),
+                )?;
+                Some((
                     Session {
                         id: r.id,
                         creator_id: r.creator_id,
+                        organization_id: r.organization_id,
                         title: r.title,
                         status: r.status,
                         share_token: r.share_token,
+                        join_code: r.join_code,
                         current_slide_id: r.current_slide_id,
                         is_results_visible: r.is_results_visible,
                         is_presentation_active: r.is_presentation_active,
@@ -119,10 +121,41 @@ impl SessionRepository for SqlxSessionRepository {
                         updated_at: r.updated_at,
                     },
                     r.slide_count,
-                )
+                    role,
+                ))
             })
             .collect())
     }
+}
+
+#[async_trait]
+impl SessionRepository for SqlxSessionRepository {
+    async fn find_by_member(&self, user_id: &str) -> Result<Vec<Session>> {
+        let pool = self.get_pool().await?;
+        let sessions = query_as::<_, Session>(
+            "SELECT * FROM sessions
+             WHERE creator_id = ? OR id IN (SELECT session_id FROM session_members WHERE user_id = ?)
+             ORDER BY created_at DESC"
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn find_by_member_with_slide_count(&self, user_id: &str) -> Result<Vec<(Session, i64, SessionRole)>> {
+        self.list_with_slide_count(user_id, "s.creator_id = ? OR m.user_id IS NOT NULL", user_id).await
+    }
+
+    async fn find_by_organization_with_slide_count(
+        &self,
+        organization_id: &str,
+        user_id: &str,
+    ) -> Result<Vec<(Session, i64, SessionRole)>> {
+        self.list_with_slide_count(user_id, "s.organization_id = ?", organization_id).await
+    }
 
     async fn find_by_id(&self, id: &str) -> Result<Option<Session>> {
         let pool = self.get_pool().await?;
@@ -147,11 +180,12 @@ impl SessionRepository for SqlxSessionRepository {
     async fn create(&self, new_session: &NewSession) -> Result<Session> {
         let pool = self.get_pool().await?;
         sqlx::query(
-            "INSERT INTO sessions (id, creator_id, title, share_token, allow_questions, require_name) 
-             VALUES (?, ?, ?, ?, ?, ?)",
+            "INSERT INTO sessions (id, creator_id, organization_id, title, share_token, allow_questions, require_name) 
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&new_session.id)
         .bind(&new_session.creator_id)
+        .bind(&new_session.organization_id)
         .bind(&new_session.title)
         .bind(&new_session.share_token)
         .bind(new_session.allow_questions)
@@ -207,25 +241,33 @@ impl SessionRepository for SqlxSessionRepository {
 
     async fn delete(&self, id: &str) -> Result<u64> {
         let pool = self.get_pool().await?;
+        let mut tx = pool.begin().await?;
+        // Queued grade passback has no foreign key to cascade from
+        sqlx::query("DELETE FROM lti_grade_sync WHERE session_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
         let result = sqlx::query("DELETE FROM sessions WHERE id = ?")
             .bind(id)
-            .execute(&pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
 
         Ok(result.rows_affected())
     }
 
-    async fn verify_ownership(&self, session_id: &str, user_id: &str) -> Result<bool> {
+    async fn find_role(&self, session_id: &str, user_id: &str) -> Result<Option<SessionRole>> {
         let pool = self.get_pool().await?;
-        let exists: Option<bool> = query_scalar(
-            "SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ? AND creator_id = ?)"
-        )
-        .bind(session_id)
-        .bind(user_id)
-        .fetch_optional(&pool)
-        .await?;
+        session_access::role_of(&pool, session_id, user_id).await
+    }
 
-        Ok(exists.unwrap_or(false))
+    async fn get_event_seq(&self, session_id: &str) -> Result<Option<i64>> {
+        let pool = self.get_pool().await?;
+        let seq = query_scalar("SELECT event_seq FROM sessions WHERE id = ?")
+            .bind(session_id)
+            .fetch_optional(&pool)
+            .await?;
+        Ok(seq)
     }
 
     async fn get_slides(&self, session_id: &str) -> Result<Vec<Slide>> {
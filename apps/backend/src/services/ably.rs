@@ -1,8 +1,14 @@
+use async_trait::async_trait;
 use once_cell::sync::Lazy;
 use serde::Serialize;
-use std::env;
+use serde_json::Value;
 use std::time::Duration;
 
+use crate::services::realtime::{RealtimeEvent, RealtimePublisher};
+
+/// Default Ably REST endpoint
+pub const DEFAULT_ABLY_REST_URL: &str = "https://rest.ably.io";
+
 // Shared HTTP client for connection pooling (reuses connections)
 static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
     reqwest::Client::builder()
@@ -13,101 +19,199 @@ static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
         .expect("Failed to create HTTP client")
 });
 
-/// Publish a message to an Ably channel
-pub async fn publish_to_channel<T: Serialize>(
-    channel: &str,
-    event_name: &str,
-    data: &T,
-) -> Result<(), String> {
-    let ably_api_key = match env::var("ABLY_API_KEY") {
-        Ok(key) => key,
-        Err(_) => {
-            tracing::warn!("ABLY_API_KEY not set, skipping real-time publish");
-            return Ok(());
+/// Publishes events through the Ably REST API
+pub struct AblyPublisher {
+    base_url: String,
+    key_name: String,
+    key_secret: String,
+}
+
+impl AblyPublisher {
+    /// Create a publisher from an API key ("keyName:keySecret") and REST base URL
+    pub fn new(api_key: &str, base_url: &str) -> Result<Self, String> {
+        // Parse key: "keyName:keySecret" for basic auth
+        let key_parts: Vec<&str> = api_key.split(':').collect();
+        if key_parts.len() != 2 {
+            return Err("Invalid ABLY_API_KEY format, expected 'keyName:keySecret'".to_string());
         }
-    };
 
-    // Parse key: "keyName:keySecret" for basic auth
-    let key_parts: Vec<&str> = ably_api_key.split(':').collect();
-    if key_parts.len() != 2 {
-        tracing::error!("Invalid ABLY_API_KEY format, expected 'keyName:keySecret'");
-        return Err("Invalid ABLY_API_KEY format".to_string());
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            key_name: key_parts[0].to_string(),
+            key_secret: key_parts[1].to_string(),
+        })
     }
-    let key_name = key_parts[0];
-    let key_secret = key_parts[1];
-
-    let url = format!(
-        "https://rest.ably.io/channels/{}/messages",
-        urlencoding::encode(channel)
-    );
-
-    let payload = serde_json::json!({
-        "name": event_name,
-        "data": data
-    });
-
-    tracing::info!("Publishing {} to Ably channel: {}", event_name, channel);
-
-    match HTTP_CLIENT
-        .post(&url)
-        .basic_auth(key_name, Some(key_secret))
-        .json(&payload)
-        .send()
-        .await
-    {
-        Ok(response) => {
-            if response.status().is_success() {
-                tracing::info!("Successfully published {} to channel {}", event_name, channel);
-                Ok(())
-            } else {
-                let status = response.status();
-                let body = response.text().await.unwrap_or_default();
-                tracing::error!("Ably publish failed: {} - {}", status, body);
-                Err(format!("Ably publish failed: {}", status))
+}
+
+#[async_trait]
+impl RealtimePublisher for AblyPublisher {
+    async fn publish(&self, channel: &str, event_name: &str, data: &Value) -> Result<(), String> {
+        let url = format!(
+            "{}/channels/{}/messages",
+            self.base_url,
+            urlencoding::encode(channel)
+        );
+
+        let payload = serde_json::json!({
+            "name": event_name,
+            "data": data
+        });
+
+        tracing::info!("Publishing {} to Ably channel: {}", event_name, channel);
+
+        match HTTP_CLIENT
+            .post(&url)
+            .basic_auth(&self.key_name, Some(&self.key_secret))
+            .json(&payload)
+            .send()
+            .await
+        {
+            Ok(response) => {
+                if response.status().is_success() {
+                    tracing::info!("Successfully published {} to channel {}", event_name, channel);
+                    Ok(())
+                } else {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    tracing::error!("Ably publish failed: {} - {}", status, body);
+                    Err(format!("Ably publish failed: {}", status))
+                }
+            }
+            Err(e) => {
+                tracing::error!("Ably request failed: {}", e);
+                Err(format!("Ably request failed: {}", e))
             }
-        }
-        Err(e) => {
-            tracing::error!("Ably request failed: {}", e);
-            Err(format!("Ably request failed: {}", e))
         }
     }
 }
 
-/// Publish a state update to a session channel
-pub async fn publish_state_update(session_id: &str, state: &impl Serialize) {
-    let channel = format!("session:{}", session_id);
-    let payload = serde_json::json!({
-        "payload": state
-    });
-    
-    if let Err(e) = publish_to_channel(&channel, "STATE_UPDATE", &payload).await {
-        tracing::error!("Failed to publish state update: {}", e);
+/// Name of the public channel for a session
+pub fn session_channel(session_id: &str) -> String {
+    format!("session:{}", session_id)
+}
+
+/// Name of the presenter-only channel for a session.
+/// Only session owners get a token for it; its events carry no session
+/// sequence number and are not mirrored over SSE.
+pub fn staff_channel(session_id: &str) -> String {
+    format!("session:{}:staff", session_id)
+}
+
+/// Session id of a public session channel ("session:{id}"), if it is one
+pub fn session_id_from_channel(channel: &str) -> Option<&str> {
+    channel
+        .strip_prefix("session:")
+        .filter(|id| !id.is_empty() && !id.contains(':'))
+}
+
+/// Build a state update event for a session channel
+pub fn state_update_event(session_id: &str, state: &impl Serialize) -> RealtimeEvent {
+    RealtimeEvent {
+        channel: session_channel(session_id),
+        name: "STATE_UPDATE".to_string(),
+        data: serde_json::json!({
+            "payload": state
+        }),
     }
 }
 
-/// Publish a vote update to a session channel
-pub async fn publish_vote_update(session_id: &str, slide_id: &str, results: &std::collections::HashMap<String, i32>) {
-    let channel = format!("session:{}", session_id);
-    let payload = serde_json::json!({
-        "slideId": slide_id,
-        "results": results
-    });
-    
-    if let Err(e) = publish_to_channel(&channel, "VOTE_UPDATE", &payload).await {
-        tracing::error!("Failed to publish vote update: {}", e);
+/// Build a vote update event for a session channel
+pub fn vote_update_event(session_id: &str, slide_id: &str, results: &std::collections::HashMap<String, i32>) -> RealtimeEvent {
+    RealtimeEvent {
+        channel: session_channel(session_id),
+        name: "VOTE_UPDATE".to_string(),
+        data: serde_json::json!({
+            "slideId": slide_id,
+            "results": results
+        }),
     }
 }
 
-/// Publish a Q&A update to a session channel
-pub async fn publish_qa_update(session_id: &str, questions: &impl Serialize) {
-    let channel = format!("session:{}", session_id);
-    let payload = serde_json::json!({
-        "payload": {
-            "questions": questions
-        }
-    });
-    
-    if let Err(e) = publish_to_channel(&channel, "QA_UPDATE", &payload).await {
-        tracing::error!("Failed to publish Q&A update: {}", e);
+/// Build a Q&A update event (full question list) for a session channel
+pub fn qa_update_event(session_id: &str, questions: &impl Serialize) -> RealtimeEvent {
+    RealtimeEvent {
+        channel: session_channel(session_id),
+        name: "QA_UPDATE".to_string(),
+        data: serde_json::json!({
+            "payload": {
+                "questions": questions
+            }
+        }),
     }
 }
+
+/// Build a Q&A delta event (`QUESTION_ADDED`, `QUESTION_UPVOTED`, `QUESTION_UPDATED`)
+/// carrying only the changed question
+pub fn question_event(session_id: &str, event_name: &str, question: &impl Serialize) -> RealtimeEvent {
+    RealtimeEvent {
+        channel: session_channel(session_id),
+        name: event_name.to_string(),
+        data: serde_json::json!({
+            "payload": {
+                "question": question
+            }
+        }),
+    }
+}
+
+/// Build a `QUESTION_REMOVED` event
+pub fn question_removed_event(session_id: &str, question_id: &str) -> RealtimeEvent {
+    RealtimeEvent {
+        channel: session_channel(session_id),
+        name: "QUESTION_REMOVED".to_string(),
+        data: serde_json::json!({
+            "payload": {
+                "questionId": question_id
+            }
+        }),
+    }
+}
+
+/// Build an event for the staff channel of a session
+pub fn staff_event(session_id: &str, event_name: &str, data: serde_json::Value) -> RealtimeEvent {
+    RealtimeEvent {
+        channel: staff_channel(session_id),
+        name: event_name.to_string(),
+        data,
+    }
+}
+
+/// Build a `QUESTION_MODERATED` staff event carrying the question, approved or not
+pub fn question_moderated_event(session_id: &str, question: &impl Serialize) -> RealtimeEvent {
+    staff_event(
+        session_id,
+        "QUESTION_MODERATED",
+        serde_json::json!({
+            "payload": {
+                "question": question
+            }
+        }),
+    )
+}
+
+/// Build a `QUESTION_HIDDEN` event telling students to drop a question;
+/// staff clients keep it and rely on `QUESTION_MODERATED` instead
+pub fn question_hidden_event(session_id: &str, question_id: &str) -> RealtimeEvent {
+    RealtimeEvent {
+        channel: session_channel(session_id),
+        name: "QUESTION_HIDDEN".to_string(),
+        data: serde_json::json!({
+            "payload": {
+                "questionId": question_id
+            }
+        }),
+    }
+}
+
+/// Build an `ANSWERS_SUBMITTED` staff event with the answers a slide received
+/// since the previous one
+pub fn answers_submitted_event(session_id: &str, slide_id: &str, answers: &impl Serialize) -> RealtimeEvent {
+    staff_event(
+        session_id,
+        "ANSWERS_SUBMITTED",
+        serde_json::json!({
+            "slideId": slide_id,
+            "answers": answers
+        }),
+    )
+}
@@ -0,0 +1,229 @@
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::services::organizations;
+
+/// Session-scoped tables, children first. Each is cleared for every personal
+/// session the deleted user created.
+const SESSION_TABLES: &[&str] = &[
+    "lti_grade_sync",
+    "lti_participants",
+    "lti_resource_links",
+    "participant_connections",
+    "votes",
+    "questions",
+    "participants",
+    "slides",
+    "realtime_outbox",
+    "session_members",
+    "session_invites",
+    "clicker_devices",
+];
+
+/// Tables holding the user's credentials, logins and memberships, keyed by `user_id`
+const USER_TABLES: &[&str] = &[
+    "mfa_recovery_codes",
+    "user_mfa",
+    "personal_access_tokens",
+    "user_identities",
+    "user_tokens",
+    "token_families",
+    "session_members",
+    "organization_members",
+];
+
+/// What an account deletion removed
+pub struct AccountDeletion {
+    pub sessions: u64,
+}
+
+/// Delete a user and everything they own in one transaction: their sessions
+/// with all slides, participants, votes and questions, and their logins and
+/// tokens. Access tokens stop working at once because their family is gone.
+/// Sessions in an organization's library stay for its admins to transfer.
+/// The last platform admin and the last admin of an organization have to
+/// hand over the role first.
+pub async fn delete_account(pool: &DbPool, user_id: &str, email: &str) -> Result<AccountDeletion> {
+    let mut tx = pool.begin().await?;
+
+    // Locked like a demotion in `set_user_role`, so the last two admins
+    // cannot both leave at once
+    let admins: Vec<String> = sqlx::query_scalar("SELECT id FROM users WHERE role = 'admin' FOR UPDATE")
+        .fetch_all(&mut *tx)
+        .await?;
+    if admins.len() == 1 && admins[0] == user_id {
+        return Err(AppError::Input(
+            "Make another user an admin before deleting the last admin account".to_string(),
+        ));
+    }
+    let administered: Vec<String> = sqlx::query_scalar(
+        "SELECT organization_id FROM organization_members WHERE user_id = ? AND role = 'admin'"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    for organization_id in &administered {
+        match organizations::ensure_other_admin(&mut tx, organization_id, user_id).await {
+            Err(AppError::Input(_)) => {
+                return Err(AppError::Input(
+                    "Make another member an admin of each organization you are the only admin of before deleting your account".to_string(),
+                ));
+            }
+            result => result?,
+        }
+    }
+
+    sqlx::query(
+        "DELETE FROM question_upvotes WHERE question_id IN (
+             SELECT q.id FROM questions q JOIN sessions s ON s.id = q.session_id WHERE s.creator_id = ? AND s.organization_id IS NULL
+         )"
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    // Staff channel rows carry no session_id
+    sqlx::query(
+        "DELETE FROM realtime_outbox WHERE session_id IS NULL AND channel IN (
+             SELECT CONCAT('session:', id, ':staff') FROM sessions WHERE creator_id = ? AND organization_id IS NULL
+         )"
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    for table in SESSION_TABLES {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE session_id IN (SELECT id FROM sessions WHERE creator_id = ? AND organization_id IS NULL)",
+            table
+        ))
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let sessions = sqlx::query("DELETE FROM sessions WHERE creator_id = ? AND organization_id IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    sqlx::query("DELETE FROM refresh_tokens WHERE family_id IN (SELECT id FROM token_families WHERE user_id = ?)")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    // Clickers they paired to sessions that stay
+    sqlx::query("DELETE FROM clicker_devices WHERE created_by = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for table in USER_TABLES {
+        sqlx::query(&format!("DELETE FROM {} WHERE user_id = ?", table))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query("DELETE FROM login_attempts WHERE email = ?")
+        .bind(email.trim().to_lowercase())
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM users WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(AccountDeletion { sessions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::organization::OrgRole;
+    use crate::models::session::SessionRole;
+    use crate::models::user::Role;
+    use crate::services::{ably, outbox};
+    use crate::test_support::{add_member, app, create_session, create_slide, create_user, email_of};
+    use serde_json::json;
+
+    async fn count(pool: &DbPool, sql: &str, arg: &str) -> i64 {
+        sqlx::query_scalar(sql).bind(arg).fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn deleting_an_account_removes_its_sessions_and_their_events() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let colleague = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        create_slide(&app.pool, &session_id).await;
+        add_member(&app.pool, &session_id, &colleague.user_id, SessionRole::Presenter).await;
+        let colleagues_session = create_session(&app.pool, &colleague.user_id).await;
+        add_member(&app.pool, &colleagues_session, &teacher.user_id, SessionRole::Viewer).await;
+
+        let mut conn = app.pool.acquire().await.unwrap();
+        outbox::enqueue(&mut conn, &mut ably::state_update_event(&session_id, &json!({}))).await.unwrap();
+        outbox::enqueue(&mut conn, &mut ably::staff_event(&session_id, "ANSWERS_SUBMITTED", json!({}))).await.unwrap();
+        drop(conn);
+
+        let deleted = delete_account(&app.pool, &teacher.user_id, &email_of(&teacher.user_id)).await.unwrap();
+        assert_eq!(deleted.sessions, 1);
+
+        assert_eq!(count(&app.pool, "SELECT COUNT(*) FROM users WHERE id = ?", &teacher.user_id).await, 0);
+        assert_eq!(count(&app.pool, "SELECT COUNT(*) FROM sessions WHERE id = ?", &session_id).await, 0);
+        assert_eq!(count(&app.pool, "SELECT COUNT(*) FROM slides WHERE session_id = ?", &session_id).await, 0);
+        assert_eq!(count(&app.pool, "SELECT COUNT(*) FROM session_members WHERE session_id = ?", &session_id).await, 0);
+        assert_eq!(count(&app.pool, "SELECT COUNT(*) FROM session_members WHERE user_id = ?", &teacher.user_id).await, 0);
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM realtime_outbox WHERE channel IN (?, ?)")
+            .bind(ably::session_channel(&session_id))
+            .bind(ably::staff_channel(&session_id))
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(events, 0);
+        // Other users' sessions stay
+        assert_eq!(count(&app.pool, "SELECT COUNT(*) FROM sessions WHERE id = ?", &colleagues_session).await, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn the_last_admin_of_an_organization_cannot_delete_their_account() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let colleague = create_user(&app.pool, Role::Teacher).await;
+        let organization = organizations::create(&app.pool, "Physics", &teacher.user_id).await.unwrap();
+        organizations::add_member(&app.pool, &organization.id, &email_of(&colleague.user_id), OrgRole::Member)
+            .await
+            .unwrap();
+
+        let refused = delete_account(&app.pool, &teacher.user_id, &email_of(&teacher.user_id)).await;
+        assert!(matches!(refused, Err(AppError::Input(_))));
+        assert_eq!(count(&app.pool, "SELECT COUNT(*) FROM users WHERE id = ?", &teacher.user_id).await, 1);
+
+        organizations::set_role(&app.pool, &organization.id, &colleague.user_id, OrgRole::Admin)
+            .await
+            .unwrap();
+        delete_account(&app.pool, &teacher.user_id, &email_of(&teacher.user_id)).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn the_last_platform_admin_cannot_delete_their_account() {
+        let app = app().await;
+        let admin = create_user(&app.pool, Role::Admin).await;
+        // Leave this admin as the only one
+        sqlx::query("UPDATE users SET role = 'teacher' WHERE role = 'admin' AND id <> ?")
+            .bind(&admin.user_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let refused = delete_account(&app.pool, &admin.user_id, &email_of(&admin.user_id)).await;
+        assert!(matches!(refused, Err(AppError::Input(_))));
+
+        create_user(&app.pool, Role::Admin).await;
+        delete_account(&app.pool, &admin.user_id, &email_of(&admin.user_id)).await.unwrap();
+    }
+}
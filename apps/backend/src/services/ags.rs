@@ -0,0 +1,824 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{FromRow, MySqlConnection};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex};
+use uuid::Uuid;
+
+use crate::db::{DbPool, LazyDbPool};
+use crate::error::Result;
+use crate::models::lti::LtiPlatform;
+use crate::models::slide::Slide;
+use crate::services::jwks::HTTP_CLIENT;
+use crate::services::lti::ToolKey;
+
+pub const SCOPE_LINEITEM: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/lineitem";
+pub const SCOPE_SCORE: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/score";
+
+const LINEITEM_MEDIA_TYPE: &str = "application/vnd.ims.lis.v2.lineitem+json";
+const LINEITEM_CONTAINER_MEDIA_TYPE: &str = "application/vnd.ims.lis.v2.lineitemcontainer+json";
+const SCORE_MEDIA_TYPE: &str = "application/vnd.ims.lis.v1.score+json";
+
+/// Points of a quiz question without an explicit `points` value (editor default)
+const DEFAULT_QUIZ_POINTS: f64 = 1000.0;
+/// Tag of the line items this tool creates
+const LINE_ITEM_TAG: &str = "classcolab-quiz";
+
+/// Delay before the first retry; doubled on every further attempt
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Upper bound for the retry delay
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3600);
+/// Maximum number of scores posted per round
+const SYNC_BATCH_SIZE: i64 = 100;
+/// How long claimed scores stay reserved for the instance posting them;
+/// covers a token request, the line item lookup and the score posts
+const SYNC_LEASE_SECS: i64 = 300;
+/// Platform access tokens are renewed this long before they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Dispatcher tuning, read from `Config`
+#[derive(Debug, Clone)]
+pub struct GradeSyncSettings {
+    pub max_attempts: i32,
+    pub poll_interval: Duration,
+}
+
+/// Handle shared with handlers: wakes the dispatcher once scores are queued
+#[derive(Clone)]
+pub struct GradeSync {
+    wake: Arc<watch::Sender<()>>,
+}
+
+impl Default for GradeSync {
+    fn default() -> Self {
+        let (wake, _) = watch::channel(());
+        Self { wake: Arc::new(wake) }
+    }
+}
+
+impl GradeSync {
+    /// Call after the transaction that queued scores has committed
+    pub fn committed(&self) {
+        self.wake.send_replace(());
+    }
+
+    fn wake_receiver(&self) -> watch::Receiver<()> {
+        self.wake.subscribe()
+    }
+}
+
+// ============================================
+// Scoring
+// ============================================
+
+#[derive(Debug, FromRow)]
+struct VoteRow {
+    participant_id: String,
+    slide_id: String,
+    option_id: String,
+}
+
+/// Quiz results of a session: points earned per participant out of `maximum`
+pub struct QuizScores {
+    pub maximum: f64,
+    pub by_participant: HashMap<String, f64>,
+}
+
+/// A question's points are earned when the participant voted and every
+/// option they picked is marked `isCorrect`
+pub async fn quiz_scores(conn: &mut MySqlConnection, session_id: &str) -> Result<QuizScores> {
+    let slides = sqlx::query_as::<_, Slide>(
+        "SELECT * FROM slides WHERE session_id = ? AND type = 'quiz' AND is_hidden = FALSE"
+    )
+    .bind(session_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let votes = sqlx::query_as::<_, VoteRow>(
+        "SELECT participant_id, slide_id, option_id FROM votes WHERE session_id = ?"
+    )
+    .bind(session_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(score_votes(&slides, &votes))
+}
+
+/// Points each voter earned on the quiz `slides`
+fn score_votes(slides: &[Slide], votes: &[VoteRow]) -> QuizScores {
+    let mut maximum = 0.0;
+    let mut questions: HashMap<&str, (f64, HashSet<&str>)> = HashMap::new();
+    for slide in slides {
+        let points = slide.content.get("points").and_then(Value::as_f64).unwrap_or(DEFAULT_QUIZ_POINTS);
+        let correct = slide
+            .content
+            .get("options")
+            .and_then(Value::as_array)
+            .map(|options| {
+                options
+                    .iter()
+                    .filter(|o| o.get("isCorrect").and_then(Value::as_bool).unwrap_or(false))
+                    .filter_map(|o| o.get("id").and_then(Value::as_str))
+                    .collect()
+            })
+            .unwrap_or_default();
+        maximum += points;
+        questions.insert(&slide.id, (points, correct));
+    }
+
+    // participant -> slide -> all picks correct so far
+    let mut answers: HashMap<&str, HashMap<&str, bool>> = HashMap::new();
+    for vote in votes {
+        let Some((_, correct)) = questions.get(vote.slide_id.as_str()) else {
+            continue;
+        };
+        let is_correct = correct.contains(vote.option_id.as_str());
+        answers
+            .entry(&vote.participant_id)
+            .or_default()
+            .entry(&vote.slide_id)
+            .and_modify(|all_correct| *all_correct &= is_correct)
+            .or_insert(is_correct);
+    }
+
+    let by_participant = answers
+        .into_iter()
+        .map(|(participant_id, slides)| {
+            let earned = slides
+                .into_iter()
+                .filter(|(_, all_correct)| *all_correct)
+                .map(|(slide_id, _)| questions[slide_id].0)
+                .sum();
+            (participant_id.to_string(), earned)
+        })
+        .collect();
+
+    QuizScores { maximum, by_participant }
+}
+
+#[derive(Debug, FromRow)]
+struct GradedLearner {
+    participant_id: String,
+    platform_id: String,
+    resource_link_id: String,
+    subject: String,
+}
+
+/// Queue the quiz score of every LMS learner of a session whose course link
+/// has a gradebook service, as part of the caller's transaction. Stopping a
+/// session again re-queues the scores with the current votes; a score being
+/// posted meanwhile keeps its lease and is sent again once that post ends.
+pub async fn enqueue_session(conn: &mut MySqlConnection, session_id: &str) -> Result<u64> {
+    let learners = sqlx::query_as::<_, GradedLearner>(
+        "SELECT lp.participant_id, lp.platform_id, lp.resource_link_id, lp.subject
+         FROM lti_participants lp
+         JOIN lti_resource_links rl
+           ON rl.platform_id = lp.platform_id AND rl.resource_link_id = lp.resource_link_id
+         WHERE lp.session_id = ? AND rl.session_id = lp.session_id
+           AND (rl.ags_lineitems_url IS NOT NULL OR rl.line_item_url IS NOT NULL)"
+    )
+    .bind(session_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    if learners.is_empty() {
+        return Ok(0);
+    }
+
+    let scores = quiz_scores(&mut *conn, session_id).await?;
+    if scores.maximum <= 0.0 {
+        tracing::info!("Session {} has no quiz questions; no grades to pass back", session_id);
+        return Ok(0);
+    }
+
+    for learner in &learners {
+        let score_given = scores.by_participant.get(&learner.participant_id).copied().unwrap_or(0.0);
+        sqlx::query(
+            r#"
+            INSERT INTO lti_grade_sync
+                (session_id, participant_id, platform_id, resource_link_id, subject, score_given, score_maximum)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                platform_id = VALUES(platform_id),
+                resource_link_id = VALUES(resource_link_id),
+                subject = VALUES(subject),
+                score_given = VALUES(score_given),
+                score_maximum = VALUES(score_maximum),
+                status = 'pending',
+                attempts = 0,
+                last_error = NULL,
+                next_attempt_at = NOW(3),
+                synced_at = NULL,
+                revision = revision + 1
+            "#
+        )
+        .bind(session_id)
+        .bind(&learner.participant_id)
+        .bind(&learner.platform_id)
+        .bind(&learner.resource_link_id)
+        .bind(&learner.subject)
+        .bind(score_given)
+        .bind(scores.maximum)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(learners.len() as u64)
+}
+
+/// Make failed scores of a session eligible again; returns how many
+pub async fn retry_failed(pool: &DbPool, session_id: &str) -> Result<u64> {
+    let result = sqlx::query(
+        "UPDATE lti_grade_sync
+         SET status = 'pending', attempts = 0, next_attempt_at = NOW(3)
+         WHERE session_id = ? AND status = 'failed'"
+    )
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Sync state of one learner's score, for the session owner
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeSyncEntry {
+    pub participant_id: String,
+    pub participant_name: String,
+    pub score_given: f64,
+    pub score_maximum: f64,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub synced_at: Option<DateTime<Utc>>,
+}
+
+pub async fn session_report(pool: &DbPool, session_id: &str) -> Result<Vec<GradeSyncEntry>> {
+    let entries = sqlx::query_as::<_, GradeSyncEntry>(
+        "SELECT g.participant_id, COALESCE(p.name, 'Anonymous') as participant_name,
+                g.score_given, g.score_maximum, g.status, g.attempts, g.last_error,
+                CASE WHEN g.status = 'pending' THEN g.next_attempt_at END as next_attempt_at,
+                g.synced_at
+         FROM lti_grade_sync g
+         LEFT JOIN participants p ON p.id = g.participant_id AND p.session_id = g.session_id
+         WHERE g.session_id = ?
+         ORDER BY participant_name"
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(entries)
+}
+
+// ============================================
+// AGS client
+// ============================================
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct LineItem {
+    id: String,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Talks to platform gradebooks with client-credentials tokens obtained by
+/// signing a JWT assertion with the tool key
+pub struct AgsClient {
+    key: Option<Arc<ToolKey>>,
+    tokens: Mutex<HashMap<String, CachedToken>>,
+}
+
+impl AgsClient {
+    pub fn new(key: Option<Arc<ToolKey>>) -> Self {
+        Self {
+            key,
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn access_token(&self, platform: &LtiPlatform) -> std::result::Result<String, String> {
+        if let Some(token) = self.tokens.lock().await.get(&platform.id) {
+            if token.expires_at > Instant::now() {
+                return Ok(token.access_token.clone());
+            }
+        }
+
+        let key = self.key.as_deref().ok_or("LTI_PRIVATE_KEY not configured")?;
+        let now = Utc::now().timestamp();
+        let assertion = key
+            .sign(&json!({
+                "iss": platform.client_id,
+                "sub": platform.client_id,
+                "aud": platform.auth_token_url,
+                "iat": now,
+                "exp": now + 300,
+                "jti": Uuid::new_v4().to_string(),
+            }))
+            .map_err(|e| e.to_string())?;
+
+        let scope = format!("{} {}", SCOPE_LINEITEM, SCOPE_SCORE);
+        let response = HTTP_CLIENT
+            .post(&platform.auth_token_url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_assertion_type", "urn:ietf:params:oauth:client-assertion-type:jwt-bearer"),
+                ("client_assertion", assertion.as_str()),
+                ("scope", scope.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("Token request failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Token request rejected with {}", response.status()));
+        }
+        let token: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid token response: {}", e))?;
+
+        let lifetime = Duration::from_secs(token.expires_in.unwrap_or(3600)).saturating_sub(TOKEN_EXPIRY_MARGIN);
+        self.tokens.lock().await.insert(platform.id.clone(), CachedToken {
+            access_token: token.access_token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+        Ok(token.access_token)
+    }
+
+    /// Line item of a course link: the one the platform named at launch, one
+    /// created on an earlier sync, or a new one
+    async fn line_item(
+        &self,
+        pool: &DbPool,
+        token: &str,
+        link: &SyncLink,
+    ) -> std::result::Result<String, String> {
+        if let Some(url) = &link.line_item_url {
+            return Ok(url.clone());
+        }
+        let container = link
+            .ags_lineitems_url
+            .as_deref()
+            .ok_or("Course link has no gradebook service")?;
+
+        // A column created by an attempt whose response got lost is reused
+        let mut search = reqwest::Url::parse(container).map_err(|e| format!("Invalid line items URL: {}", e))?;
+        search
+            .query_pairs_mut()
+            .append_pair("resource_link_id", &link.resource_link_id)
+            .append_pair("resource_id", &link.session_id);
+        let existing: Vec<LineItem> = HTTP_CLIENT
+            .get(search)
+            .bearer_auth(token)
+            .header(reqwest::header::ACCEPT, LINEITEM_CONTAINER_MEDIA_TYPE)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Line item lookup failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid line items response: {}", e))?;
+
+        let url = match existing.into_iter().next() {
+            Some(item) => item.id,
+            None => {
+                let created: LineItem = HTTP_CLIENT
+                    .post(container)
+                    .bearer_auth(token)
+                    .header(reqwest::header::CONTENT_TYPE, LINEITEM_MEDIA_TYPE)
+                    .header(reqwest::header::ACCEPT, LINEITEM_MEDIA_TYPE)
+                    .body(json!({
+                        "scoreMaximum": link.score_maximum,
+                        "label": link.session_title,
+                        "resourceId": link.session_id,
+                        "resourceLinkId": link.resource_link_id,
+                        "tag": LINE_ITEM_TAG,
+                    }).to_string())
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("Line item creation failed: {}", e))?
+                    .json()
+                    .await
+                    .map_err(|e| format!("Invalid line item response: {}", e))?;
+                tracing::info!("Created line item {} for session {}", created.id, link.session_id);
+                created.id
+            }
+        };
+
+        // Scores of one link can be posted by several instances; the first
+        // column saved wins so they all end up in it
+        sqlx::query(
+            "UPDATE lti_resource_links SET line_item_url = ?
+             WHERE platform_id = ? AND resource_link_id = ? AND line_item_url IS NULL"
+        )
+        .bind(&url)
+        .bind(&link.platform_id)
+        .bind(&link.resource_link_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save line item: {}", e))?;
+        let saved: Option<String> = sqlx::query_scalar(
+            "SELECT line_item_url FROM lti_resource_links WHERE platform_id = ? AND resource_link_id = ?"
+        )
+        .bind(&link.platform_id)
+        .bind(&link.resource_link_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to load line item: {}", e))?;
+        Ok(saved.unwrap_or(url))
+    }
+
+    async fn post_score(&self, token: &str, line_item_url: &str, row: &SyncRow) -> std::result::Result<(), String> {
+        // Scores live under the line item URL, which may carry a query string
+        let mut url = reqwest::Url::parse(line_item_url).map_err(|e| format!("Invalid line item URL: {}", e))?;
+        url.set_path(&format!("{}/scores", url.path().trim_end_matches('/')));
+
+        let response = HTTP_CLIENT
+            .post(url)
+            .bearer_auth(token)
+            .header(reqwest::header::CONTENT_TYPE, SCORE_MEDIA_TYPE)
+            .body(json!({
+                "userId": row.subject,
+                "scoreGiven": row.score_given,
+                "scoreMaximum": row.score_maximum,
+                "activityProgress": "Completed",
+                "gradingProgress": "FullyGraded",
+                "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            }).to_string())
+            .send()
+            .await
+            .map_err(|e| format!("Score request failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Score rejected with {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+// ============================================
+// Dispatcher
+// ============================================
+
+#[derive(Debug, FromRow)]
+struct SyncRow {
+    session_id: String,
+    participant_id: String,
+    platform_id: String,
+    resource_link_id: String,
+    subject: String,
+    score_given: f64,
+    score_maximum: f64,
+    attempts: i32,
+    /// Queued revision being posted; see `release_superseded`
+    revision: i32,
+    session_title: String,
+    ags_lineitems_url: Option<String>,
+    line_item_url: Option<String>,
+}
+
+/// A course link with scores to post
+struct SyncLink {
+    platform_id: String,
+    resource_link_id: String,
+    session_id: String,
+    session_title: String,
+    score_maximum: f64,
+    ags_lineitems_url: Option<String>,
+    line_item_url: Option<String>,
+}
+
+/// Background task posting queued scores to platform gradebooks, with
+/// exponential backoff until `max_attempts`. Scores are grouped per course
+/// link so each link needs one access token and line item per round. Each
+/// backend instance runs a dispatcher; a round first leases the scores it
+/// will post, and scores leased by another instance are left alone.
+pub struct GradeSyncDispatcher {
+    pool: LazyDbPool,
+    grade_sync: GradeSync,
+    client: AgsClient,
+    settings: GradeSyncSettings,
+}
+
+impl GradeSyncDispatcher {
+    pub fn new(pool: LazyDbPool, grade_sync: GradeSync, client: AgsClient, settings: GradeSyncSettings) -> Self {
+        Self { pool, grade_sync, client, settings }
+    }
+
+    /// Start the sync loop in the background
+    pub fn spawn(self) {
+        tokio::spawn(async move {
+            let pool = match self.pool.get_or_wait().await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    tracing::error!("Grade sync dispatcher not started: {}", e);
+                    return;
+                }
+            };
+            tracing::info!("Grade sync dispatcher started");
+
+            let mut wake = self.grade_sync.wake_receiver();
+            loop {
+                match self.sync_ready(&pool).await {
+                    Ok(synced) if synced > 0 => continue,
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Grade sync round failed: {}", e),
+                }
+
+                tokio::select! {
+                    _ = wake.changed() => {}
+                    _ = tokio::time::sleep(self.settings.poll_interval) => {}
+                }
+            }
+        });
+    }
+
+    /// Lease the due scores no other instance is posting and load them with
+    /// their course links
+    async fn claim_ready(&self, pool: &DbPool) -> Result<Vec<SyncRow>> {
+        let mut tx = pool.begin().await?;
+        let keys: Vec<(String, String)> = sqlx::query_as(
+            "SELECT session_id, participant_id FROM lti_grade_sync
+             WHERE status = 'pending' AND next_attempt_at <= NOW(3)
+               AND (lease_expires_at IS NULL OR lease_expires_at <= NOW(3))
+             ORDER BY next_attempt_at
+             LIMIT ?
+             FOR UPDATE SKIP LOCKED"
+        )
+        .bind(SYNC_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut lease = sqlx::QueryBuilder::<sqlx::MySql>::new(
+            "UPDATE lti_grade_sync SET lease_expires_at = NOW(3) + INTERVAL ",
+        );
+        lease.push_bind(SYNC_LEASE_SECS).push(" SECOND WHERE (session_id, participant_id) IN (");
+        let mut claimed = lease.separated(", ");
+        for (session_id, participant_id) in &keys {
+            claimed.push("(").push_bind_unseparated(session_id);
+            claimed.push_unseparated(", ").push_bind_unseparated(participant_id).push_unseparated(")");
+        }
+        claimed.push_unseparated(")");
+        lease.build().execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        let mut rows = sqlx::QueryBuilder::<sqlx::MySql>::new(
+            "SELECT g.session_id, g.participant_id, g.platform_id, g.resource_link_id, g.subject,
+                    g.score_given, g.score_maximum, g.attempts, g.revision, s.title as session_title,
+                    rl.ags_lineitems_url, rl.line_item_url
+             FROM lti_grade_sync g
+             JOIN sessions s ON s.id = g.session_id
+             JOIN lti_resource_links rl
+               ON rl.platform_id = g.platform_id AND rl.resource_link_id = g.resource_link_id
+             WHERE (g.session_id, g.participant_id) IN (",
+        );
+        let mut selected = rows.separated(", ");
+        for (session_id, participant_id) in &keys {
+            selected.push("(").push_bind_unseparated(session_id);
+            selected.push_unseparated(", ").push_bind_unseparated(participant_id).push_unseparated(")");
+        }
+        selected.push_unseparated(")");
+        let rows = rows.build_query_as::<SyncRow>().fetch_all(pool).await?;
+
+        // Scores whose session or course link is gone would be leased
+        // again every round; give up on them
+        for (session_id, participant_id) in &keys {
+            if rows.iter().any(|row| &row.session_id == session_id && &row.participant_id == participant_id) {
+                continue;
+            }
+            tracing::warn!(
+                "Dropping grade passback for participant {} of session {}: session or course link no longer exists",
+                participant_id, session_id
+            );
+            sqlx::query(
+                "UPDATE lti_grade_sync
+                 SET status = 'failed', last_error = 'Session or course link no longer exists', lease_expires_at = NULL
+                 WHERE session_id = ? AND participant_id = ?"
+            )
+            .bind(session_id)
+            .bind(participant_id)
+            .execute(pool)
+            .await?;
+        }
+        Ok(rows)
+    }
+
+    /// Post every due score; returns how many were accepted
+    async fn sync_ready(&self, pool: &DbPool) -> Result<usize> {
+        let rows = self.claim_ready(pool).await?;
+
+        // One token and line item per course link, then its scores in parallel
+        let mut links: BTreeMap<(String, String), Vec<SyncRow>> = BTreeMap::new();
+        for row in rows {
+            links
+                .entry((row.platform_id.clone(), row.resource_link_id.clone()))
+                .or_default()
+                .push(row);
+        }
+
+        let mut synced = 0;
+        for rows in links.into_values() {
+            let first = &rows[0];
+            let link = SyncLink {
+                platform_id: first.platform_id.clone(),
+                resource_link_id: first.resource_link_id.clone(),
+                session_id: first.session_id.clone(),
+                session_title: first.session_title.clone(),
+                score_maximum: first.score_maximum,
+                ags_lineitems_url: first.ags_lineitems_url.clone(),
+                line_item_url: first.line_item_url.clone(),
+            };
+
+            let prepared = match self.prepare(pool, &link).await {
+                Ok(prepared) => prepared,
+                Err(e) => {
+                    for row in &rows {
+                        self.record_failure(pool, row, &e).await?;
+                    }
+                    continue;
+                }
+            };
+
+            let (token, line_item_url) = prepared;
+            let results = futures::future::join_all(
+                rows.iter().map(|row| self.client.post_score(&token, &line_item_url, row)),
+            )
+            .await;
+
+            for (row, result) in rows.iter().zip(results) {
+                match result {
+                    Ok(()) => {
+                        let recorded = sqlx::query(
+                            "UPDATE lti_grade_sync
+                             SET status = 'synced', synced_at = NOW(3), attempts = attempts + 1, last_error = NULL,
+                                 lease_expires_at = NULL
+                             WHERE session_id = ? AND participant_id = ? AND revision = ?"
+                        )
+                        .bind(&row.session_id)
+                        .bind(&row.participant_id)
+                        .bind(row.revision)
+                        .execute(pool)
+                        .await?;
+                        if recorded.rows_affected() == 0 {
+                            release_superseded(pool, row).await?;
+                        }
+                        synced += 1;
+                    }
+                    Err(e) => self.record_failure(pool, row, &e).await?,
+                }
+            }
+        }
+        Ok(synced)
+    }
+
+    async fn prepare(&self, pool: &DbPool, link: &SyncLink) -> std::result::Result<(String, String), String> {
+        let platform = LtiPlatform::find(pool, &link.platform_id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("LTI platform is no longer registered")?;
+        let token = self.client.access_token(&platform).await?;
+        let line_item_url = self.client.line_item(pool, &token, link).await?;
+        Ok((token, line_item_url))
+    }
+
+    async fn record_failure(&self, pool: &DbPool, row: &SyncRow, error: &str) -> Result<()> {
+        let attempts = row.attempts + 1;
+
+        let recorded = if attempts >= self.settings.max_attempts {
+            tracing::error!(
+                "Giving up on grade passback for participant {} of session {} after {} attempts: {}",
+                row.participant_id, row.session_id, attempts, error
+            );
+            sqlx::query(
+                "UPDATE lti_grade_sync SET status = 'failed', attempts = ?, last_error = ?, lease_expires_at = NULL
+                 WHERE session_id = ? AND participant_id = ? AND revision = ?"
+            )
+            .bind(attempts)
+            .bind(error)
+            .bind(&row.session_id)
+            .bind(&row.participant_id)
+            .bind(row.revision)
+            .execute(pool)
+            .await?
+        } else {
+            let delay = retry_delay(attempts);
+            tracing::warn!(
+                "Grade passback for participant {} of session {} failed (attempt {}), retrying in {:?}: {}",
+                row.participant_id, row.session_id, attempts, delay, error
+            );
+            let next_attempt_at = Utc::now()
+                + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::seconds(5));
+            sqlx::query(
+                "UPDATE lti_grade_sync SET attempts = ?, last_error = ?, next_attempt_at = ?, lease_expires_at = NULL
+                 WHERE session_id = ? AND participant_id = ? AND revision = ?"
+            )
+            .bind(attempts)
+            .bind(error)
+            .bind(next_attempt_at)
+            .bind(&row.session_id)
+            .bind(&row.participant_id)
+            .bind(row.revision)
+            .execute(pool)
+            .await?
+        };
+        if recorded.rows_affected() == 0 {
+            release_superseded(pool, row).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A score re-queued while `row` was being posted keeps the new revision
+/// pending; only the lease is dropped so it is posted right away
+async fn release_superseded(pool: &DbPool, row: &SyncRow) -> Result<()> {
+    sqlx::query("UPDATE lti_grade_sync SET lease_expires_at = NULL WHERE session_id = ? AND participant_id = ?")
+        .bind(&row.session_id)
+        .bind(&row.participant_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Exponential backoff: 5s, 10s, 20s, ... capped at MAX_RETRY_DELAY
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(exponent))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn quiz(id: &str, content: Value) -> Slide {
+        Slide {
+            id: id.to_string(),
+            session_id: "session".to_string(),
+            slide_type: "quiz".to_string(),
+            content: sqlx::types::Json(content),
+            order_index: 0,
+            is_hidden: false,
+        }
+    }
+
+    fn vote(participant_id: &str, slide_id: &str, option_id: &str) -> VoteRow {
+        VoteRow {
+            participant_id: participant_id.to_string(),
+            slide_id: slide_id.to_string(),
+            option_id: option_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn quiz_points_need_every_pick_to_be_correct() {
+        let slides = [
+            quiz("capital", json!({
+                "points": 10,
+                "options": [{ "id": "paris", "isCorrect": true }, { "id": "lyon" }]
+            })),
+            quiz("primes", json!({
+                "options": [{ "id": "2", "isCorrect": true }, { "id": "3", "isCorrect": true }, { "id": "4" }]
+            })),
+        ];
+        let votes = [
+            vote("alice", "capital", "paris"),
+            vote("alice", "primes", "2"),
+            vote("alice", "primes", "3"),
+            vote("bob", "capital", "lyon"),
+            // One wrong pick spoils the question
+            vote("bob", "primes", "2"),
+            vote("bob", "primes", "4"),
+            // Votes on other slides are ignored
+            vote("carol", "poll", "yes"),
+        ];
+
+        let scores = score_votes(&slides, &votes);
+        assert_eq!(scores.maximum, 10.0 + DEFAULT_QUIZ_POINTS);
+        assert_eq!(scores.by_participant["alice"], 10.0 + DEFAULT_QUIZ_POINTS);
+        assert_eq!(scores.by_participant["bob"], 0.0);
+        assert!(!scores.by_participant.contains_key("carol"));
+    }
+
+    #[test]
+    fn sessions_without_quiz_questions_have_no_maximum() {
+        let scores = score_votes(&[], &[vote("alice", "poll", "yes")]);
+        assert_eq!(scores.maximum, 0.0);
+        assert!(scores.by_participant.is_empty());
+    }
+}
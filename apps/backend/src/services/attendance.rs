@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::services::ably::session_id_from_channel;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Ably presence action codes
+const PRESENCE_ENTER: u8 = 2;
+const PRESENCE_LEAVE: u8 = 3;
+
+/// Check an Ably webhook signature: base64 HMAC-SHA256 of the raw body,
+/// keyed with the secret of the API key named in `X-Ably-Key`
+pub fn verify_webhook_signature(api_key: &str, key_name: &str, signature: &str, body: &[u8]) -> bool {
+    let Some((expected_name, secret)) = api_key.split_once(':') else {
+        return false;
+    };
+    if key_name != expected_name {
+        return false;
+    }
+    let Ok(tag) = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, signature) else {
+        return false;
+    };
+    let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&tag).is_ok()
+}
+
+/// Presence webhook body; Ably sends enveloped batches by default
+/// (`items[].data`) and a single flat message when enveloping is disabled
+#[derive(Debug, Deserialize)]
+pub struct PresenceWebhook {
+    #[serde(default)]
+    items: Vec<PresenceWebhookItem>,
+    #[serde(flatten)]
+    flat: PresenceWebhookData,
+}
+
+#[derive(Debug, Deserialize)]
+struct PresenceWebhookItem {
+    #[serde(default)]
+    source: String,
+    data: PresenceWebhookData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PresenceWebhookData {
+    #[serde(rename = "channelId", alias = "channel")]
+    channel_id: Option<String>,
+    #[serde(default)]
+    presence: Vec<PresenceMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PresenceMessage {
+    action: u8,
+    #[serde(rename = "clientId")]
+    client_id: Option<String>,
+    #[serde(rename = "connectionId")]
+    connection_id: Option<String>,
+    /// Milliseconds since the Unix epoch
+    timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceAction {
+    Enter,
+    Leave,
+}
+
+/// A student entering or leaving a session channel
+#[derive(Debug, Clone)]
+pub struct PresenceChange {
+    pub session_id: String,
+    pub participant_id: String,
+    pub connection_id: String,
+    pub action: PresenceAction,
+    pub at: DateTime<Utc>,
+}
+
+impl PresenceWebhook {
+    /// Student enter/leave changes on session channels, oldest first.
+    /// Staff and projector clients, other channels and other actions are skipped.
+    pub fn into_changes(self) -> Vec<PresenceChange> {
+        let batches = self
+            .items
+            .into_iter()
+            .filter(|item| item.source.is_empty() || item.source == "channel.presence")
+            .map(|item| item.data)
+            .chain(std::iter::once(self.flat));
+
+        let mut changes: Vec<PresenceChange> = batches
+            .flat_map(|data| {
+                let session_id = data
+                    .channel_id
+                    .as_deref()
+                    .and_then(session_id_from_channel)
+                    .map(str::to_string);
+                data.presence
+                    .into_iter()
+                    .filter_map(move |msg| presence_change(session_id.as_deref()?, msg))
+            })
+            .collect();
+
+        changes.sort_by_key(|change| change.at);
+        changes
+    }
+}
+
+fn presence_change(session_id: &str, msg: PresenceMessage) -> Option<PresenceChange> {
+    let action = match msg.action {
+        PRESENCE_ENTER => PresenceAction::Enter,
+        PRESENCE_LEAVE => PresenceAction::Leave,
+        _ => return None,
+    };
+    // Students use their participant id as Ably client id (see handlers::ably)
+    let participant_id = msg
+        .client_id
+        .filter(|id| !id.is_empty() && !id.starts_with("staff-") && !id.starts_with("projector-"))?;
+
+    Some(PresenceChange {
+        session_id: session_id.to_string(),
+        participant_id,
+        connection_id: msg.connection_id?,
+        action,
+        at: DateTime::from_timestamp_millis(msg.timestamp)?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    pub(crate) const API_KEY: &str = "appid.keyid:webhook-secret";
+
+    /// `X-Ably-Signature` Ably sends for `body` signed with `API_KEY`
+    pub(crate) fn sign(body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(b"webhook-secret").unwrap();
+        mac.update(body);
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, mac.finalize().into_bytes())
+    }
+
+    fn presence(action: u8, client_id: &str, connection_id: &str, timestamp: i64) -> serde_json::Value {
+        json!({ "action": action, "clientId": client_id, "connectionId": connection_id, "timestamp": timestamp })
+    }
+
+    #[test]
+    fn signatures_must_match_the_body_and_key() {
+        let body = br#"{"items":[]}"#;
+        let signature = sign(body);
+        assert!(verify_webhook_signature(API_KEY, "appid.keyid", &signature, body));
+        assert!(!verify_webhook_signature(API_KEY, "appid.keyid", &signature, br#"{"items":[{}]}"#));
+        assert!(!verify_webhook_signature(API_KEY, "appid.other", &signature, body));
+        assert!(!verify_webhook_signature(API_KEY, "appid.keyid", "not base64!", body));
+        assert!(!verify_webhook_signature("no-secret", "no-secret", &signature, body));
+    }
+
+    #[test]
+    fn enveloped_batches_give_student_changes_oldest_first() {
+        let webhook: PresenceWebhook = serde_json::from_value(json!({
+            "items": [
+                {
+                    "source": "channel.presence",
+                    "data": {
+                        "channelId": "session:s1",
+                        "presence": [
+                            presence(PRESENCE_LEAVE, "p1", "c1", 2_000),
+                            presence(PRESENCE_ENTER, "p1", "c1", 1_000),
+                            // Staff, projectors and updates are not attendance
+                            presence(PRESENCE_ENTER, "staff-u1", "c2", 1_000),
+                            presence(PRESENCE_ENTER, "projector-s1", "c3", 1_000),
+                            presence(4, "p1", "c1", 1_500),
+                        ]
+                    }
+                },
+                {
+                    "source": "channel.lifecycle",
+                    "data": { "channelId": "session:s1", "presence": [presence(PRESENCE_ENTER, "p9", "c9", 1_000)] }
+                },
+                {
+                    "source": "channel.presence",
+                    "data": { "channelId": "session:s2:staff", "presence": [presence(PRESENCE_ENTER, "p2", "c4", 500)] }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let changes = webhook.into_changes();
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| (c.session_id.as_str(), c.participant_id.as_str(), c.action, c.at.timestamp_millis()))
+            .collect();
+        assert_eq!(summary, [("s1", "p1", PresenceAction::Enter, 1_000), ("s1", "p1", PresenceAction::Leave, 2_000)]);
+    }
+
+    #[test]
+    fn flat_messages_are_accepted_without_an_envelope() {
+        let webhook: PresenceWebhook = serde_json::from_value(json!({
+            "channel": "session:s1",
+            "presence": [presence(PRESENCE_ENTER, "p1", "c1", 1_000)]
+        }))
+        .unwrap();
+
+        let changes = webhook.into_changes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].connection_id, "c1");
+    }
+}
@@ -0,0 +1,299 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::middleware::auth::Claims;
+use crate::services::jwt_keys::JwtKeys;
+use crate::services::secrets;
+
+/// A rotated refresh token presented again within this window is treated as
+/// a race between tabs, not as theft: the request fails but the family stays
+const REUSE_GRACE: Duration = Duration::seconds(30);
+
+/// Tokens handed to the client after login or refresh
+pub struct IssuedTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+/// Sign a short-lived access token bound to a token family
+pub fn issue_access_token(
+    config: &Config,
+    keys: &JwtKeys,
+    user_id: &str,
+    role: &str,
+    family_id: &str,
+) -> Result<String> {
+    let expiration = Utc::now()
+        .checked_add_signed(Duration::minutes(config.access_token_ttl_minutes))
+        .expect("valid timestamp")
+        .timestamp() as usize;
+
+    let claims = Claims {
+        user_id: user_id.to_string(),
+        role: role.to_string(),
+        family_id: family_id.to_string(),
+        exp: expiration,
+    };
+
+    keys.sign(&claims)
+}
+
+async fn insert_refresh_token(
+    conn: &mut sqlx::MySqlConnection,
+    config: &Config,
+    family_id: &str,
+) -> Result<String> {
+    let token = secrets::random_token();
+    let expires_at = Utc::now() + Duration::days(config.refresh_token_ttl_days);
+
+    sqlx::query(
+        "INSERT INTO refresh_tokens (id, family_id, token_hash, expires_at) VALUES (?, ?, ?, ?)"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(family_id)
+    .bind(secrets::sha256_hex(&token))
+    .bind(expires_at)
+    .execute(conn)
+    .await?;
+    Ok(token)
+}
+
+/// Start a new token family (one per login) and issue its first token pair
+pub async fn start_family(
+    pool: &DbPool,
+    config: &Config,
+    keys: &JwtKeys,
+    user_id: &str,
+    role: &str,
+) -> Result<IssuedTokens> {
+    let family_id = Uuid::new_v4().to_string();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT INTO token_families (id, user_id) VALUES (?, ?)")
+        .bind(&family_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let refresh_token = insert_refresh_token(&mut tx, config, &family_id).await?;
+    tx.commit().await?;
+
+    Ok(IssuedTokens {
+        access_token: issue_access_token(config, keys, user_id, role, &family_id)?,
+        refresh_token,
+    })
+}
+
+#[derive(Debug, FromRow)]
+struct RefreshTokenRow {
+    id: String,
+    family_id: String,
+    expires_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    user_id: String,
+    family_revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Result of a refresh token rotation; the caller signs the access token
+/// with the user's current role
+pub struct RotatedToken {
+    pub user_id: String,
+    pub family_id: String,
+    pub refresh_token: String,
+}
+
+/// Exchange a refresh token for a new one. The presented token is spent;
+/// reusing a spent token (after the grace window) revokes its family.
+pub async fn rotate(pool: &DbPool, config: &Config, refresh_token: &str) -> Result<RotatedToken> {
+    let invalid = || AppError::Auth("Invalid refresh token".to_string());
+
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query_as::<_, RefreshTokenRow>(
+        "SELECT rt.id, rt.family_id, rt.expires_at, rt.used_at,
+                f.user_id, f.revoked_at AS family_revoked_at
+         FROM refresh_tokens rt
+         JOIN token_families f ON f.id = rt.family_id
+         WHERE rt.token_hash = ?
+         FOR UPDATE"
+    )
+    .bind(secrets::sha256_hex(refresh_token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(invalid)?;
+
+    if row.family_revoked_at.is_some() {
+        return Err(invalid());
+    }
+
+    if let Some(used_at) = row.used_at {
+        if Utc::now() - used_at > REUSE_GRACE {
+            tracing::warn!(
+                "Refresh token reuse for user {}: revoking token family {}",
+                row.user_id, row.family_id
+            );
+            sqlx::query("UPDATE token_families SET revoked_at = NOW(3) WHERE id = ? AND revoked_at IS NULL")
+                .bind(&row.family_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+        return Err(invalid());
+    }
+
+    if row.expires_at < Utc::now() {
+        return Err(AppError::Auth("Refresh token expired".to_string()));
+    }
+
+    sqlx::query("UPDATE refresh_tokens SET used_at = NOW(3) WHERE id = ?")
+        .bind(&row.id)
+        .execute(&mut *tx)
+        .await?;
+    let new_refresh_token = insert_refresh_token(&mut tx, config, &row.family_id).await?;
+    tx.commit().await?;
+
+    Ok(RotatedToken {
+        user_id: row.user_id,
+        family_id: row.family_id,
+        refresh_token: new_refresh_token,
+    })
+}
+
+/// Family a refresh token belongs to, if the token is known
+pub async fn family_of(pool: &DbPool, refresh_token: &str) -> Result<Option<String>> {
+    let family_id = sqlx::query_scalar("SELECT family_id FROM refresh_tokens WHERE token_hash = ?")
+        .bind(secrets::sha256_hex(refresh_token))
+        .fetch_optional(pool)
+        .await?;
+    Ok(family_id)
+}
+
+/// Revoke one token family (log out one device)
+pub async fn revoke_family(pool: &DbPool, family_id: &str) -> Result<()> {
+    sqlx::query("UPDATE token_families SET revoked_at = NOW(3) WHERE id = ? AND revoked_at IS NULL")
+        .bind(family_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Revoke every token family of a user (log out all devices)
+pub async fn revoke_all_for_user(pool: &DbPool, user_id: &str) -> Result<u64> {
+    let result = sqlx::query("UPDATE token_families SET revoked_at = NOW(3) WHERE user_id = ? AND revoked_at IS NULL")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Revoke every token family of a user except the one of the current device
+pub async fn revoke_other_families(pool: &DbPool, user_id: &str, keep_family_id: Option<&str>) -> Result<u64> {
+    let result = sqlx::query(
+        "UPDATE token_families SET revoked_at = NOW(3)
+         WHERE user_id = ? AND revoked_at IS NULL AND id <> COALESCE(?, '')"
+    )
+    .bind(user_id)
+    .bind(keep_family_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Whether access tokens of a family are still honoured
+pub async fn is_family_active(pool: &DbPool, family_id: &str) -> Result<bool> {
+    let active: Option<bool> = sqlx::query_scalar(
+        "SELECT revoked_at IS NULL FROM token_families WHERE id = ?"
+    )
+    .bind(family_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(active == Some(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::test_support::{app, create_user, TestApp};
+
+    async fn login(app: &TestApp, user_id: &str) -> IssuedTokens {
+        start_family(&app.pool, &app.config, &app.state.jwt_keys, user_id, "teacher").await.unwrap()
+    }
+
+    /// Pretend a spent refresh token was used `seconds` ago
+    async fn age_use(pool: &DbPool, refresh_token: &str, seconds: i64) {
+        sqlx::query("UPDATE refresh_tokens SET used_at = ? WHERE token_hash = ?")
+            .bind(Utc::now() - Duration::seconds(seconds))
+            .bind(secrets::sha256_hex(refresh_token))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn rotation_spends_the_token_and_keeps_the_family() {
+        let app = app().await;
+        let user = create_user(&app.pool, Role::Teacher).await;
+        let issued = login(&app, &user.user_id).await;
+        let claims: Claims = app.state.jwt_keys.verify(&issued.access_token).unwrap();
+
+        let rotated = rotate(&app.pool, &app.config, &issued.refresh_token).await.unwrap();
+        assert_eq!(rotated.user_id, user.user_id);
+        assert_eq!(rotated.family_id, claims.family_id);
+        assert_ne!(rotated.refresh_token, issued.refresh_token);
+
+        // The new token rotates in turn; the spent one doesn't
+        let next = rotate(&app.pool, &app.config, &rotated.refresh_token).await.unwrap();
+        assert_eq!(next.family_id, claims.family_id);
+        assert!(matches!(
+            rotate(&app.pool, &app.config, &issued.refresh_token).await,
+            Err(AppError::Auth(_))
+        ));
+        assert!(is_family_active(&app.pool, &claims.family_id).await.unwrap(), "reuse within the grace window");
+        assert!(rotate(&app.pool, &app.config, "not-a-token").await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn reusing_a_spent_token_revokes_its_family() {
+        let app = app().await;
+        let user = create_user(&app.pool, Role::Teacher).await;
+        let issued = login(&app, &user.user_id).await;
+        let other_device = login(&app, &user.user_id).await;
+
+        let rotated = rotate(&app.pool, &app.config, &issued.refresh_token).await.unwrap();
+        age_use(&app.pool, &issued.refresh_token, REUSE_GRACE.num_seconds() + 5).await;
+
+        assert!(rotate(&app.pool, &app.config, &issued.refresh_token).await.is_err());
+        assert!(!is_family_active(&app.pool, &rotated.family_id).await.unwrap());
+        // The legitimate holder's newer token dies with the family
+        assert!(rotate(&app.pool, &app.config, &rotated.refresh_token).await.is_err());
+
+        // Other logins of the user are untouched
+        rotate(&app.pool, &app.config, &other_device.refresh_token).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn revoking_all_families_logs_out_every_device_of_the_user_only() {
+        let app = app().await;
+        let user = create_user(&app.pool, Role::Teacher).await;
+        let someone_else = create_user(&app.pool, Role::Teacher).await;
+        let laptop = login(&app, &user.user_id).await;
+        let phone = login(&app, &user.user_id).await;
+        let unrelated = login(&app, &someone_else.user_id).await;
+
+        assert_eq!(revoke_all_for_user(&app.pool, &user.user_id).await.unwrap(), 2);
+
+        for tokens in [&laptop, &phone] {
+            let claims: Claims = app.state.jwt_keys.verify(&tokens.access_token).unwrap();
+            assert!(!is_family_active(&app.pool, &claims.family_id).await.unwrap());
+            assert!(rotate(&app.pool, &app.config, &tokens.refresh_token).await.is_err());
+        }
+        rotate(&app.pool, &app.config, &unrelated.refresh_token).await.unwrap();
+        assert_eq!(revoke_all_for_user(&app.pool, &user.user_id).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,284 @@
+//! Phones paired as remote clickers. The presenter starts a pairing and the
+//! phone trades the short code for a clicker token that can only move the
+//! live slide of that one session.
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::session::SessionRole;
+use crate::services::{secrets, session_access};
+
+/// Marks a bearer credential as a clicker token
+pub const TOKEN_PREFIX: &str = "ccc_";
+/// How long a pairing code can be entered
+pub const PAIRING_CODE_TTL_MINUTES: i64 = 5;
+/// How long a paired phone keeps working (about a day of teaching)
+pub const CLICKER_TOKEN_TTL_HOURS: i64 = 12;
+/// Without look-alike characters (0/O, 1/I/L) so codes can be typed from
+/// the projector; 8 of them leave ~10^12 guesses within the code's lifetime
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 8;
+/// Clicks within this many seconds of the recorded `last_used_at` don't
+/// move it, so paging through a deck isn't a write per click
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// A pairing waiting for a phone
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pairing {
+    pub id: String,
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// What the phone receives in exchange for a pairing code
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedClicker {
+    pub session_id: String,
+    pub token: String,
+    /// Lets the phone follow the session in real time as a projector
+    pub share_token: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A paired phone as listed for the session's presenters
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ClickerDevice {
+    pub id: String,
+    pub created_by: String,
+    pub paired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+struct DeviceGrant {
+    id: String,
+    session_id: String,
+    created_by: String,
+}
+
+/// Codes are shown in upper case but accepted in any case and with spaces
+/// or dashes a user might type
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+fn pairing_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LENGTH)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Start pairing a phone to the session. A new pairing replaces the user's
+/// pending one; only the hash of the code is stored.
+pub async fn start_pairing(pool: &DbPool, session_id: &str, user_id: &str) -> Result<Pairing> {
+    let pairing = Pairing {
+        id: Uuid::new_v4().to_string(),
+        code: pairing_code(),
+        expires_at: Utc::now() + Duration::minutes(PAIRING_CODE_TTL_MINUTES),
+    };
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM clicker_devices WHERE session_id = ? AND created_by = ? AND paired_at IS NULL")
+        .bind(session_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO clicker_devices (id, session_id, created_by, pairing_code_hash, pairing_expires_at)
+         VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&pairing.id)
+    .bind(session_id)
+    .bind(user_id)
+    .bind(secrets::sha256_hex(&pairing.code))
+    .bind(pairing.expires_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(pairing)
+}
+
+/// Exchange a pairing code for a clicker token. Each code pairs one phone;
+/// the token is returned once and only its hash is kept.
+pub async fn pair(pool: &DbPool, code: &str) -> Result<PairedClicker> {
+    let invalid = || AppError::Input("Invalid or expired pairing code".to_string());
+    let code = normalize_code(code);
+    if code.len() != CODE_LENGTH {
+        return Err(invalid());
+    }
+
+    let token = format!("{}{}", TOKEN_PREFIX, secrets::random_token());
+    let token_hash = secrets::sha256_hex(&token);
+    let expires_at = Utc::now() + Duration::hours(CLICKER_TOKEN_TTL_HOURS);
+
+    let mut tx = pool.begin().await?;
+    let claimed = sqlx::query(
+        "UPDATE clicker_devices
+         SET token_hash = ?, pairing_code_hash = NULL, paired_at = NOW(3), expires_at = ?
+         WHERE pairing_code_hash = ? AND paired_at IS NULL AND pairing_expires_at > NOW(3)
+         LIMIT 1"
+    )
+    .bind(&token_hash)
+    .bind(expires_at)
+    .bind(secrets::sha256_hex(&code))
+    .execute(&mut *tx)
+    .await?;
+    if claimed.rows_affected() != 1 {
+        return Err(invalid());
+    }
+    let (session_id, share_token): (String, Option<String>) = sqlx::query_as(
+        "SELECT s.id, s.share_token FROM clicker_devices d JOIN sessions s ON s.id = d.session_id
+         WHERE d.token_hash = ?"
+    )
+    .bind(&token_hash)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(PairedClicker { session_id, token, share_token, expires_at })
+}
+
+/// Check a clicker token for a session. The token stops working when it is
+/// revoked, expires, or the presenter who paired it loses the presenter role.
+pub async fn authorize(pool: &DbPool, token: &str, session_id: &str) -> Result<()> {
+    let rejected = || AppError::Auth("Invalid or revoked clicker token".to_string());
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Err(rejected());
+    }
+
+    let device = sqlx::query_as::<_, DeviceGrant>(
+        "SELECT id, session_id, created_by FROM clicker_devices
+         WHERE token_hash = ? AND revoked_at IS NULL AND expires_at > NOW(3)"
+    )
+    .bind(secrets::sha256_hex(token))
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(rejected)?;
+    if device.session_id != session_id {
+        return Err(rejected());
+    }
+    let role = session_access::role_of(pool, session_id, &device.created_by).await?;
+    if role.is_none_or(|role| role < SessionRole::Presenter) {
+        return Err(rejected());
+    }
+
+    sqlx::query(
+        "UPDATE clicker_devices SET last_used_at = NOW(3)
+         WHERE id = ? AND (last_used_at IS NULL OR last_used_at < NOW(3) - INTERVAL ? SECOND)"
+    )
+    .bind(&device.id)
+    .bind(LAST_USED_RESOLUTION_SECS)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Phones currently paired to the session
+pub async fn list_devices(pool: &DbPool, session_id: &str) -> Result<Vec<ClickerDevice>> {
+    Ok(sqlx::query_as::<_, ClickerDevice>(
+        "SELECT id, created_by, paired_at, expires_at, last_used_at FROM clicker_devices
+         WHERE session_id = ? AND paired_at IS NOT NULL AND revoked_at IS NULL AND expires_at > NOW(3)
+         ORDER BY paired_at DESC"
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Unpair a phone; its token stops working immediately
+pub async fn revoke(pool: &DbPool, session_id: &str, device_id: &str) -> Result<()> {
+    let revoked = sqlx::query(
+        "UPDATE clicker_devices SET revoked_at = NOW(3)
+         WHERE id = ? AND session_id = ? AND revoked_at IS NULL"
+    )
+    .bind(device_id)
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+    if revoked.rows_affected() == 0 {
+        return Err(AppError::NotFound("Clicker not found".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::test_support::{add_member, app, create_session, create_user};
+
+    #[test]
+    fn codes_avoid_look_alike_characters_and_tolerate_typing() {
+        let code = pairing_code();
+        assert_eq!(code.len(), CODE_LENGTH);
+        assert!(code.bytes().all(|b| CODE_ALPHABET.contains(&b)), "{}", code);
+        assert!(!CODE_ALPHABET.iter().any(|b| b"01ILO".contains(b)));
+
+        assert_eq!(normalize_code(" abcd-2345 "), "ABCD2345");
+        assert_eq!(normalize_code("AB CD 23 45"), "ABCD2345");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn a_code_pairs_one_phone_to_one_session() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let other_session = create_session(&app.pool, &teacher.user_id).await;
+
+        let replaced = start_pairing(&app.pool, &session_id, &teacher.user_id).await.unwrap();
+        let pairing = start_pairing(&app.pool, &session_id, &teacher.user_id).await.unwrap();
+        assert!(pair(&app.pool, &replaced.code).await.is_err(), "a replaced code still pairs");
+
+        let typed = format!("{}-{}", &pairing.code[..4], &pairing.code[4..]).to_lowercase();
+        let paired = pair(&app.pool, &typed).await.unwrap();
+        assert_eq!(paired.session_id, session_id);
+        assert!(paired.token.starts_with(TOKEN_PREFIX));
+        assert!(pair(&app.pool, &pairing.code).await.is_err(), "a code paired twice");
+
+        authorize(&app.pool, &paired.token, &session_id).await.unwrap();
+        assert!(matches!(authorize(&app.pool, &paired.token, &other_session).await, Err(AppError::Auth(_))));
+        assert!(authorize(&app.pool, "ccc_forged", &session_id).await.is_err());
+
+        let devices = list_devices(&app.pool, &session_id).await.unwrap();
+        assert_eq!(devices.len(), 1);
+        revoke(&app.pool, &session_id, &devices[0].id).await.unwrap();
+        assert!(authorize(&app.pool, &paired.token, &session_id).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn a_phone_stops_working_when_its_presenter_loses_the_role() {
+        let app = app().await;
+        let owner = create_user(&app.pool, Role::Teacher).await;
+        let presenter = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &owner.user_id).await;
+        add_member(&app.pool, &session_id, &presenter.user_id, SessionRole::Presenter).await;
+
+        let pairing = start_pairing(&app.pool, &session_id, &presenter.user_id).await.unwrap();
+        let paired = pair(&app.pool, &pairing.code).await.unwrap();
+        authorize(&app.pool, &paired.token, &session_id).await.unwrap();
+
+        sqlx::query("UPDATE session_members SET role = 'viewer' WHERE session_id = ? AND user_id = ?")
+            .bind(&session_id)
+            .bind(&presenter.user_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        assert!(authorize(&app.pool, &paired.token, &session_id).await.is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+
+/// A logged session event, as originally published
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedEvent {
+    pub seq: i64,
+    pub name: String,
+    pub data: serde_json::Value,
+}
+
+/// Events a client missed since a given sequence number
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventReplay {
+    pub events: Vec<LoggedEvent>,
+    pub latest_seq: i64,
+    /// The gap can't be filled from the log; refetch the full session state
+    pub resync_required: bool,
+}
+
+impl EventReplay {
+    fn resync(latest_seq: i64) -> Self {
+        Self {
+            events: Vec::new(),
+            latest_seq,
+            resync_required: true,
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct LoggedEventRow {
+    seq: i64,
+    event_name: String,
+    payload: sqlx::types::Json<serde_json::Value>,
+}
+
+#[derive(FromRow)]
+struct TailRow {
+    session_id: String,
+    seq: i64,
+    event_name: String,
+    payload: sqlx::types::Json<serde_json::Value>,
+}
+
+/// The sequence number of a session's most recent event
+pub async fn latest_seq(pool: &DbPool, session_id: &str) -> Result<i64> {
+    sqlx::query_scalar("SELECT event_seq FROM sessions WHERE id = ?")
+        .bind(session_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Session not found".to_string()))
+}
+
+/// Logged events after each session's cursor, in sequence order per session.
+/// Returns at most `limit` events; call again with advanced cursors for more.
+pub async fn tail(pool: &DbPool, cursors: &[(String, i64)], limit: i64) -> Result<Vec<(String, LoggedEvent)>> {
+    if cursors.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut query = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT session_id, seq, event_name, payload FROM realtime_outbox WHERE ",
+    );
+    for (i, (session_id, after)) in cursors.iter().enumerate() {
+        if i > 0 {
+            query.push(" OR ");
+        }
+        query
+            .push("(session_id = ")
+            .push_bind(session_id)
+            .push(" AND seq > ")
+            .push_bind(after)
+            .push(")");
+    }
+    query.push(" ORDER BY session_id, seq LIMIT ").push_bind(limit);
+
+    let rows = query.build_query_as::<TailRow>().fetch_all(pool).await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            (
+                r.session_id,
+                LoggedEvent {
+                    seq: r.seq,
+                    name: r.event_name,
+                    data: r.payload.0,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Load the events after `since` for a session.
+/// At most `capacity` events are replayed; a larger gap, or one that reaches
+/// into already purged rows, requires a resync instead.
+pub async fn replay(pool: &DbPool, session_id: &str, since: i64, capacity: i64) -> Result<EventReplay> {
+    let latest_seq = latest_seq(pool, session_id).await?;
+
+    // A client ahead of the server saw a different history (e.g. restored DB)
+    if since > latest_seq || since < 0 {
+        return Ok(EventReplay::resync(latest_seq));
+    }
+    if since == latest_seq {
+        return Ok(EventReplay {
+            events: Vec::new(),
+            latest_seq,
+            resync_required: false,
+        });
+    }
+    if latest_seq - since > capacity {
+        return Ok(EventReplay::resync(latest_seq));
+    }
+
+    let rows = sqlx::query_as::<_, LoggedEventRow>(
+        "SELECT seq, event_name, payload FROM realtime_outbox
+         WHERE session_id = ? AND seq > ? AND seq <= ?
+         ORDER BY seq"
+    )
+    .bind(session_id)
+    .bind(since)
+    .bind(latest_seq)
+    .fetch_all(pool)
+    .await?;
+
+    // Every number in (since, latest] must still be in the log
+    let complete = rows.len() as i64 == latest_seq - since
+        && rows.first().map(|r| r.seq) == Some(since + 1);
+    if !complete {
+        return Ok(EventReplay::resync(latest_seq));
+    }
+
+    Ok(EventReplay {
+        events: rows
+            .into_iter()
+            .map(|r| LoggedEvent {
+                seq: r.seq,
+                name: r.event_name,
+                data: r.payload.0,
+            })
+            .collect(),
+        latest_seq,
+        resync_required: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::services::ably::session_channel;
+    use crate::services::outbox;
+    use crate::services::realtime::RealtimeEvent;
+    use crate::test_support::{app, create_session, create_user};
+
+    /// Log `count` events on the session channel, numbered from the next seq
+    async fn log_events(pool: &DbPool, session_id: &str, count: usize) {
+        for _ in 0..count {
+            let mut tx = pool.begin().await.unwrap();
+            let mut event = RealtimeEvent {
+                channel: session_channel(session_id),
+                name: "QA_UPDATE".to_string(),
+                data: serde_json::json!({ "sessionId": session_id }),
+            };
+            outbox::enqueue(&mut tx, &mut event).await.unwrap();
+            tx.commit().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn replay_returns_the_missed_events_in_order() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        log_events(&app.pool, &session_id, 3).await;
+
+        let replay = replay(&app.pool, &session_id, 1, 10).await.unwrap();
+        assert!(!replay.resync_required);
+        assert_eq!(replay.latest_seq, 3);
+        let seqs: Vec<i64> = replay.events.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [2, 3]);
+        assert_eq!(replay.events[0].data["seq"], 2);
+
+        let up_to_date = super::replay(&app.pool, &session_id, 3, 10).await.unwrap();
+        assert!(!up_to_date.resync_required);
+        assert!(up_to_date.events.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn gaps_the_log_cannot_fill_require_a_resync() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        log_events(&app.pool, &session_id, 3).await;
+
+        // Larger than the replay capacity
+        assert!(replay(&app.pool, &session_id, 0, 2).await.unwrap().resync_required);
+        // A client ahead of the server
+        assert!(replay(&app.pool, &session_id, 5, 10).await.unwrap().resync_required);
+
+        // A purged row in the middle of the gap
+        sqlx::query("DELETE FROM realtime_outbox WHERE session_id = ? AND seq = 2")
+            .bind(&session_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let replay = replay(&app.pool, &session_id, 1, 10).await.unwrap();
+        assert!(replay.resync_required);
+        assert_eq!(replay.latest_seq, 3);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn tail_reads_each_session_after_its_cursor() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let first = create_session(&app.pool, &teacher.user_id).await;
+        let second = create_session(&app.pool, &teacher.user_id).await;
+        log_events(&app.pool, &first, 3).await;
+        log_events(&app.pool, &second, 1).await;
+
+        let events = tail(&app.pool, &[(first.clone(), 1), (second.clone(), 0)], 10).await.unwrap();
+        let mut read: Vec<(String, i64)> = events.into_iter().map(|(session_id, e)| (session_id, e.seq)).collect();
+        read.sort();
+        let mut expected = vec![(first.clone(), 2), (first, 3), (second, 1)];
+        expected.sort();
+        assert_eq!(read, expected);
+
+        assert!(tail(&app.pool, &[], 10).await.unwrap().is_empty());
+    }
+}
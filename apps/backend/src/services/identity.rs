@@ -0,0 +1,128 @@
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::user::{Role, User};
+use crate::services::secrets;
+
+/// An account at an external identity source (OIDC provider, LTI platform)
+pub struct ExternalIdentity<'a> {
+    /// Key in `user_identities.provider`, e.g. the OIDC provider id
+    pub provider: &'a str,
+    pub subject: &'a str,
+    pub email: Option<&'a str>,
+    /// Whether the source vouches for the email; only then is an account created for it
+    pub email_verified: bool,
+    pub name: Option<&'a str>,
+    /// Role given to an account created on first sign-in
+    pub default_role: Role,
+}
+
+/// Result of signing in with an external identity
+pub enum ExternalSignIn {
+    /// The account linked to the identity, possibly created just now
+    User(User),
+    /// The identity is not linked and no account can be created for it: its
+    /// email belongs to an existing account or is not verified. The user has
+    /// to sign in to ClassColab and link the identity explicitly.
+    LinkRequired,
+}
+
+/// Find the account linked to an external identity, or create one for a
+/// verified email no account uses yet. An existing account is never linked
+/// on email alone: whoever controls the email at the source would get into
+/// it without its password or second factor.
+pub async fn find_or_create_user(pool: &DbPool, identity: &ExternalIdentity<'_>) -> Result<ExternalSignIn> {
+    let linked: Option<User> = sqlx::query_as(
+        "SELECT u.* FROM users u
+         JOIN user_identities i ON i.user_id = u.id
+         WHERE i.provider = ? AND i.subject = ?"
+    )
+    .bind(identity.provider)
+    .bind(identity.subject)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(user) = linked {
+        return Ok(ExternalSignIn::User(user));
+    }
+
+    let Some(email) = identity.email.filter(|_| identity.email_verified) else {
+        return Ok(ExternalSignIn::LinkRequired);
+    };
+
+    let mut tx = pool.begin().await?;
+    let taken: Option<String> = sqlx::query_scalar("SELECT id FROM users WHERE email = ? FOR UPDATE")
+        .bind(email)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if taken.is_some() {
+        return Ok(ExternalSignIn::LinkRequired);
+    }
+
+    // Externally authenticated accounts get a random password nobody knows
+    let random_password = secrets::random_token();
+    let password_hash = tokio::task::spawn_blocking(move || bcrypt::hash(random_password, bcrypt::DEFAULT_COST))
+        .await
+        .map_err(|e| AppError::Internal(format!("Password hash task failed: {}", e)))??;
+    let user_id = Uuid::new_v4().to_string();
+    let name = identity.name.unwrap_or(email);
+
+    sqlx::query(
+        "INSERT INTO users (id, email, password_hash, name, role, email_verified_at) VALUES (?, ?, ?, ?, ?, NOW())"
+    )
+    .bind(&user_id)
+    .bind(email)
+    .bind(&password_hash)
+    .bind(name)
+    .bind(identity.default_role.as_str())
+    .execute(&mut *tx)
+    .await?;
+    tracing::info!("Created user {} from {} sign-in", user_id, identity.provider);
+
+    sqlx::query("INSERT INTO user_identities (provider, subject, user_id, email) VALUES (?, ?, ?, ?)")
+        .bind(identity.provider)
+        .bind(identity.subject)
+        .bind(&user_id)
+        .bind(email)
+        .execute(&mut *tx)
+        .await?;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(ExternalSignIn::User(user))
+}
+
+/// Link an external identity to the account of a signed-in user
+pub async fn link(pool: &DbPool, provider: &str, subject: &str, email: Option<&str>, user_id: &str) -> Result<()> {
+    let linked = sqlx::query("INSERT INTO user_identities (provider, subject, user_id, email) VALUES (?, ?, ?, ?)")
+        .bind(provider)
+        .bind(subject)
+        .bind(user_id)
+        .bind(email)
+        .execute(pool)
+        .await;
+    match linked {
+        Ok(_) => {
+            tracing::info!("Linked {} identity to user {}", provider, user_id);
+            Ok(())
+        }
+        Err(sqlx::Error::Database(db)) if db.is_unique_violation() => {
+            let owner: Option<String> = sqlx::query_scalar(
+                "SELECT user_id FROM user_identities WHERE provider = ? AND subject = ?"
+            )
+            .bind(provider)
+            .bind(subject)
+            .fetch_optional(pool)
+            .await?;
+            if owner.as_deref() == Some(user_id) {
+                Ok(())
+            } else {
+                Err(AppError::Forbidden("This sign-in is already linked to another ClassColab account".to_string()))
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
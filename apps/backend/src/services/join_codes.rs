@@ -0,0 +1,264 @@
+//! Numeric codes students type in to join a live session. A code belongs to
+//! a session only while it is live, so codes stay short and are reused.
+
+use rand::Rng;
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+
+/// Codes start at this many digits and grow when short ones keep colliding
+const MIN_CODE_DIGITS: u32 = 6;
+const MAX_CODE_DIGITS: u32 = 8;
+/// Random picks per length before moving on to a longer code
+const ATTEMPTS_PER_LENGTH: usize = 5;
+/// Lookups that found nothing a client may make per window before it has
+/// to wait for the window to end
+const MAX_MISSES_PER_WINDOW: i32 = 30;
+const MISS_WINDOW_SECS: i64 = 600;
+/// Stale miss counters removed per successful lookup
+const PURGE_BATCH_SIZE: i64 = 100;
+
+/// What a join code resolves to
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct JoinTarget {
+    pub session_id: String,
+    pub title: String,
+    /// Student links use the share token (`/student/session/:token`)
+    pub share_token: Option<String>,
+}
+
+/// A random code without a leading zero, so it reads and types unambiguously
+fn random_code(digits: u32) -> String {
+    let low = 10u32.pow(digits - 1);
+    rand::thread_rng().gen_range(low..low * 10).to_string()
+}
+
+/// Whether `code` has the shape of a join code
+pub fn is_well_formed(code: &str) -> bool {
+    (MIN_CODE_DIGITS as usize..=MAX_CODE_DIGITS as usize).contains(&code.len())
+        && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Give a session that is going live a join code, or keep the one it has.
+/// The unique key on `sessions.join_code` settles races between sessions
+/// drawing the same code at once.
+pub async fn allocate(conn: &mut sqlx::MySqlConnection, session_id: &str) -> Result<String> {
+    let existing: Option<String> = sqlx::query_scalar("SELECT join_code FROM sessions WHERE id = ?")
+        .bind(session_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if let Some(code) = existing {
+        return Ok(code);
+    }
+
+    for digits in MIN_CODE_DIGITS..=MAX_CODE_DIGITS {
+        for _ in 0..ATTEMPTS_PER_LENGTH {
+            let code = random_code(digits);
+            let assigned = sqlx::query("UPDATE sessions SET join_code = ? WHERE id = ?")
+                .bind(&code)
+                .bind(session_id)
+                .execute(&mut *conn)
+                .await;
+            match assigned {
+                Ok(_) => return Ok(code),
+                Err(sqlx::Error::Database(db)) if db.is_unique_violation() => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        tracing::warn!("Join codes with {} digits are crowded; trying a longer one", digits);
+    }
+    Err(AppError::Internal("No free join code found".to_string()))
+}
+
+/// Free the session's code for other sessions
+pub async fn release(conn: &mut sqlx::MySqlConnection, session_id: &str) -> Result<()> {
+    sqlx::query("UPDATE sessions SET join_code = NULL WHERE id = ?")
+        .bind(session_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// The live session using a code
+pub async fn resolve(pool: &DbPool, code: &str) -> Result<JoinTarget> {
+    let not_found = || AppError::NotFound("No live session uses this code".to_string());
+    if !is_well_formed(code) {
+        return Err(not_found());
+    }
+
+    sqlx::query_as::<_, JoinTarget>(
+        "SELECT id AS session_id, title, share_token FROM sessions
+         WHERE join_code = ? AND is_presentation_active = TRUE"
+    )
+    .bind(code)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(not_found)
+}
+
+/// The live session using a code, for a client identified by `client_ip`.
+/// Only misses are counted, so a lecture hall joining through one address
+/// is never held up; a client that keeps missing is refused until its
+/// window ends.
+pub async fn resolve_for_client(pool: &DbPool, client_ip: &str, code: &str) -> Result<JoinTarget> {
+    let wait: Option<i64> = sqlx::query_scalar(
+        "SELECT TIMESTAMPDIFF(SECOND, NOW(3), window_started_at + INTERVAL ? SECOND) FROM join_code_misses
+         WHERE client_ip = ? AND miss_count >= ? AND window_started_at > NOW(3) - INTERVAL ? SECOND"
+    )
+    .bind(MISS_WINDOW_SECS)
+    .bind(client_ip)
+    .bind(MAX_MISSES_PER_WINDOW)
+    .bind(MISS_WINDOW_SECS)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(seconds) = wait {
+        return Err(AppError::TooManyRequests(format!(
+            "Too many unknown join codes. Try again in {} seconds",
+            seconds.max(1)
+        )));
+    }
+
+    match resolve(pool, code).await {
+        Err(AppError::NotFound(message)) => {
+            record_miss(pool, client_ip).await?;
+            Err(AppError::NotFound(message))
+        }
+        Ok(target) => {
+            sqlx::query("DELETE FROM join_code_misses WHERE window_started_at < NOW(3) - INTERVAL ? SECOND LIMIT ?")
+                .bind(MISS_WINDOW_SECS)
+                .bind(PURGE_BATCH_SIZE)
+                .execute(pool)
+                .await?;
+            Ok(target)
+        }
+        result => result,
+    }
+}
+
+/// Count a lookup that found nothing, starting a new window once the last
+/// one has ended
+async fn record_miss(pool: &DbPool, client_ip: &str) -> Result<()> {
+    // Assignments run left to right: the window check in `miss_count` still
+    // sees the old `window_started_at`
+    sqlx::query(
+        "INSERT INTO join_code_misses (client_ip, miss_count, window_started_at) VALUES (?, 1, NOW(3))
+         ON DUPLICATE KEY UPDATE
+             miss_count = IF(window_started_at > NOW(3) - INTERVAL ? SECOND, miss_count + 1, 1),
+             window_started_at = IF(window_started_at > NOW(3) - INTERVAL ? SECOND, window_started_at, NOW(3))"
+    )
+    .bind(client_ip)
+    .bind(MISS_WINDOW_SECS)
+    .bind(MISS_WINDOW_SECS)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::test_support::{app, create_session, create_user};
+
+    #[test]
+    fn random_codes_have_the_requested_digits_and_no_leading_zero() {
+        for digits in MIN_CODE_DIGITS..=MAX_CODE_DIGITS {
+            for _ in 0..200 {
+                let code = random_code(digits);
+                assert_eq!(code.len(), digits as usize, "{}", code);
+                assert!(!code.starts_with('0'), "{}", code);
+                assert!(is_well_formed(&code), "{}", code);
+            }
+        }
+    }
+
+    #[test]
+    fn only_six_to_eight_digits_are_well_formed() {
+        for code in ["123456", "1234567", "12345678"] {
+            assert!(is_well_formed(code), "{}", code);
+        }
+        for code in ["", "12345", "123456789", "12345a", "123 456", "١٢٣٤٥٦"] {
+            assert!(!is_well_formed(code), "{:?}", code);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn codes_are_kept_while_live_and_freed_afterwards() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let other_session = create_session(&app.pool, &teacher.user_id).await;
+        let mut conn = app.pool.acquire().await.unwrap();
+
+        let code = allocate(&mut conn, &session_id).await.unwrap();
+        assert!(is_well_formed(&code));
+        assert_eq!(allocate(&mut conn, &session_id).await.unwrap(), code);
+
+        // Codes resolve only while the session is presenting
+        assert!(matches!(resolve(&app.pool, &code).await, Err(AppError::NotFound(_))));
+        sqlx::query("UPDATE sessions SET is_presentation_active = TRUE WHERE id = ?")
+            .bind(&session_id)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(resolve(&app.pool, &code).await.unwrap().session_id, session_id);
+
+        // Allocation relies on the unique key to settle collisions
+        let taken = sqlx::query("UPDATE sessions SET join_code = ? WHERE id = ?")
+            .bind(&code)
+            .bind(&other_session)
+            .execute(&mut *conn)
+            .await;
+        assert!(matches!(taken, Err(sqlx::Error::Database(db)) if db.is_unique_violation()));
+        assert_ne!(allocate(&mut conn, &other_session).await.unwrap(), code);
+
+        release(&mut conn, &session_id).await.unwrap();
+        assert!(resolve(&app.pool, &code).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn only_misses_count_towards_the_client_limit() {
+        let app = app().await;
+        let teacher = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &teacher.user_id).await;
+        let mut conn = app.pool.acquire().await.unwrap();
+        let code = allocate(&mut conn, &session_id).await.unwrap();
+        sqlx::query("UPDATE sessions SET is_presentation_active = TRUE WHERE id = ?")
+            .bind(&session_id)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let client = format!("203.0.113.{}", rand::thread_rng().gen_range(1..255));
+        sqlx::query("DELETE FROM join_code_misses WHERE client_ip = ?")
+            .bind(&client)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        // A whole lecture hall behind one address joins without limit
+        for _ in 0..(MAX_MISSES_PER_WINDOW * 2) {
+            resolve_for_client(&app.pool, &client, &code).await.unwrap();
+        }
+
+        for _ in 0..MAX_MISSES_PER_WINDOW {
+            let missed = resolve_for_client(&app.pool, &client, "999999999").await;
+            assert!(matches!(missed, Err(AppError::NotFound(_))));
+        }
+        let refused = resolve_for_client(&app.pool, &client, &code).await;
+        assert!(matches!(refused, Err(AppError::TooManyRequests(_))));
+
+        // Once the window has passed the client starts over
+        sqlx::query("UPDATE join_code_misses SET window_started_at = NOW(3) - INTERVAL ? SECOND WHERE client_ip = ?")
+            .bind(MISS_WINDOW_SECS + 1)
+            .bind(&client)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(resolve_for_client(&app.pool, &client, &code).await.unwrap().session_id, session_id);
+    }
+}
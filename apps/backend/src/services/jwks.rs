@@ -0,0 +1,124 @@
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{AppError, Result};
+
+/// How long a fetched key set is trusted before it is fetched again
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+/// Minimum time between refetches triggered by an unknown key id
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+/// Clock skew tolerated on `exp`/`nbf`/`iat`
+const LEEWAY_SECS: u64 = 60;
+
+/// Asymmetric algorithms accepted for third-party tokens (never HMAC)
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+];
+
+// Shared HTTP client for identity provider requests
+pub static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Caches JSON Web Key Sets by URL and verifies tokens signed with them.
+/// A token with an unknown `kid` triggers one refetch (key rotation).
+#[derive(Default)]
+pub struct JwksCache {
+    sets: Mutex<HashMap<String, CachedKeys>>,
+}
+
+impl JwksCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn fetch(&self, jwks_uri: &str) -> Result<JwkSet> {
+        let keys: JwkSet = HTTP_CLIENT
+            .get(jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Internal(format!("JWKS fetch from {} failed: {}", jwks_uri, e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid JWKS from {}: {}", jwks_uri, e)))?;
+
+        self.sets.lock().unwrap().insert(
+            jwks_uri.to_string(),
+            CachedKeys { keys: keys.clone(), fetched_at: Instant::now() },
+        );
+        Ok(keys)
+    }
+
+    /// Key for `kid` (or the only key when the token names none)
+    async fn decoding_key(&self, jwks_uri: &str, kid: Option<&str>) -> Result<DecodingKey> {
+        let find = |keys: &JwkSet| match kid {
+            Some(kid) => keys.find(kid).cloned(),
+            None if keys.keys.len() == 1 => keys.keys.first().cloned(),
+            None => None,
+        };
+
+        let (cached, may_refresh) = {
+            let sets = self.sets.lock().unwrap();
+            match sets.get(jwks_uri) {
+                Some(entry) if entry.fetched_at.elapsed() < JWKS_TTL => (
+                    find(&entry.keys),
+                    entry.fetched_at.elapsed() >= JWKS_MIN_REFRESH,
+                ),
+                _ => (None, true),
+            }
+        };
+
+        let jwk = match cached {
+            Some(jwk) => jwk,
+            None if may_refresh => find(&self.fetch(jwks_uri).await?)
+                .ok_or_else(|| AppError::Auth("Token signed with an unknown key".to_string()))?,
+            None => return Err(AppError::Auth("Token signed with an unknown key".to_string())),
+        };
+
+        DecodingKey::from_jwk(&jwk).map_err(AppError::from)
+    }
+
+    /// Verify a JWT's signature against the key set and check `iss`, `aud` and `exp`
+    pub async fn verify<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        jwks_uri: &str,
+        issuer: &str,
+        audience: &str,
+    ) -> Result<T> {
+        let header = decode_header(token)?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(AppError::Auth("Unsupported token algorithm".to_string()));
+        }
+
+        let key = self.decoding_key(jwks_uri, header.kid.as_deref()).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = LEEWAY_SECS;
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+
+        Ok(decode::<T>(token, &key, &validation)?.claims)
+    }
+}
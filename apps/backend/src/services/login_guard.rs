@@ -0,0 +1,222 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+
+/// bcrypt hash (default cost) of a throwaway password. Logins for unknown
+/// emails verify against it so they take as long as real ones.
+pub const TIMING_EQUALIZER_HASH: &str = "$2b$12$Q3z06Tv0rdsVF.N0JVChn.TxZOXjTAHwm33p.ZwKO6s/mTGlhMhZK";
+
+/// Upper bound for the wait between attempts before the lockout kicks in
+const MAX_DELAY_SECS: i64 = 60;
+/// Counters of emails without attempts for this long start over
+const FAILURE_MEMORY: Duration = Duration::days(1);
+/// Stale rows removed per successful login
+const PURGE_BATCH_SIZE: i64 = 100;
+
+/// Throttling thresholds, read from `Config`
+#[derive(Debug, Clone)]
+pub struct LoginPolicy {
+    pub delay_after_failures: i32,
+    pub lockout_threshold: i32,
+    pub lockout_duration: Duration,
+}
+
+impl LoginPolicy {
+    /// Wait required after `failed` attempts: 1s, 2s, 4s, ... up to MAX_DELAY_SECS
+    fn delay(&self, failed: i32) -> Duration {
+        if failed < self.delay_after_failures {
+            return Duration::zero();
+        }
+        let exponent = (failed - self.delay_after_failures).clamp(0, 16) as u32;
+        Duration::seconds(2i64.pow(exponent).min(MAX_DELAY_SECS))
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct AttemptRow {
+    failed_count: i32,
+    last_attempt_at: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+fn normalize(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn retry_after(until: DateTime<Utc>, now: DateTime<Utc>) -> AppError {
+    let seconds = (until - now).num_seconds().max(1);
+    AppError::TooManyRequests(format!("Too many login attempts. Try again in {} seconds", seconds))
+}
+
+/// Count a login attempt before the password is checked, or refuse it while
+/// the account is locked or waiting out its delay. Attempts are counted up
+/// front so parallel guesses serialize on the row instead of racing past it.
+pub async fn reserve_attempt(pool: &DbPool, policy: &LoginPolicy, email: &str) -> Result<()> {
+    let email = normalize(email);
+    let now = Utc::now();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT IGNORE INTO login_attempts (email, failed_count, last_attempt_at) VALUES (?, 0, ?)")
+        .bind(&email)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    let row = sqlx::query_as::<_, AttemptRow>(
+        "SELECT failed_count, last_attempt_at, locked_until FROM login_attempts WHERE email = ? FOR UPDATE"
+    )
+    .bind(&email)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(locked_until) = row.locked_until.filter(|until| *until > now) {
+        return Err(retry_after(locked_until, now));
+    }
+
+    let failed = if now - row.last_attempt_at > FAILURE_MEMORY { 0 } else { row.failed_count };
+    let allowed_at = row.last_attempt_at + policy.delay(failed);
+    if failed > 0 && allowed_at > now {
+        return Err(retry_after(allowed_at, now));
+    }
+
+    let failed = failed + 1;
+    let locked_until = (failed >= policy.lockout_threshold).then(|| now + policy.lockout_duration);
+    sqlx::query("UPDATE login_attempts SET failed_count = ?, last_attempt_at = ?, locked_until = ? WHERE email = ?")
+        .bind(failed)
+        .bind(now)
+        .bind(locked_until)
+        .bind(&email)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    if locked_until.is_some() {
+        tracing::warn!("Login for {} locked after {} failed attempts", email, failed);
+    }
+    Ok(())
+}
+
+/// Forget the attempts of an email (successful login, password reset, admin
+/// unlock); returns whether there were any
+pub async fn clear(pool: &DbPool, email: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM login_attempts WHERE email = ?")
+        .bind(normalize(email))
+        .execute(pool)
+        .await?;
+
+    sqlx::query("DELETE FROM login_attempts WHERE last_attempt_at < ? AND (locked_until IS NULL OR locked_until < ?) LIMIT ?")
+        .bind(Utc::now() - FAILURE_MEMORY)
+        .bind(Utc::now())
+        .bind(PURGE_BATCH_SIZE)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::app;
+
+    fn policy(delay_after_failures: i32, lockout_threshold: i32) -> LoginPolicy {
+        LoginPolicy {
+            delay_after_failures,
+            lockout_threshold,
+            lockout_duration: Duration::minutes(15),
+        }
+    }
+
+    fn unique_email() -> String {
+        format!("{}@Example.com", uuid::Uuid::new_v4())
+    }
+
+    /// Pretend the email's last attempt was `seconds` ago
+    async fn age_last_attempt(pool: &DbPool, email: &str, seconds: i64) {
+        sqlx::query("UPDATE login_attempts SET last_attempt_at = ? WHERE email = ?")
+            .bind(Utc::now() - Duration::seconds(seconds))
+            .bind(normalize(email))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn delay_doubles_from_the_threshold_up_to_the_cap() {
+        let policy = policy(3, 10);
+        let delays: Vec<i64> = (0..=10).map(|failed| policy.delay(failed).num_seconds()).collect();
+        assert_eq!(delays, [0, 0, 0, 1, 2, 4, 8, 16, 32, 60, 60]);
+    }
+
+    #[test]
+    fn the_timing_equalizer_costs_as_much_as_a_real_hash_and_matches_nothing() {
+        let cost: u32 = TIMING_EQUALIZER_HASH.split('$').nth(2).unwrap().parse().unwrap();
+        assert_eq!(cost, bcrypt::DEFAULT_COST);
+        assert!(!bcrypt::verify("correct-horse-battery", TIMING_EQUALIZER_HASH).unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn attempts_past_the_threshold_wait_progressively_longer() {
+        let app = app().await;
+        let policy = policy(2, 100);
+        let email = unique_email();
+
+        // Below the threshold attempts are not delayed
+        reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+        reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+
+        // Two failures: the next attempt waits 1s
+        let refused = reserve_attempt(&app.pool, &policy, &email).await;
+        assert!(matches!(refused, Err(AppError::TooManyRequests(_))));
+        age_last_attempt(&app.pool, &email, 1).await;
+        reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+
+        // Three failures: 1s is no longer enough, 2s is
+        age_last_attempt(&app.pool, &email, 1).await;
+        assert!(reserve_attempt(&app.pool, &policy, &email).await.is_err());
+        age_last_attempt(&app.pool, &email, 2).await;
+        reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn the_lockout_holds_until_it_expires() {
+        let app = app().await;
+        let policy = policy(100, 3);
+        let email = unique_email();
+
+        for _ in 0..3 {
+            reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+        }
+        // Counted case-insensitively
+        let locked = reserve_attempt(&app.pool, &policy, &email.to_uppercase()).await;
+        assert!(matches!(locked, Err(AppError::TooManyRequests(message)) if message.contains("Try again in")));
+
+        sqlx::query("UPDATE login_attempts SET locked_until = ? WHERE email = ?")
+            .bind(Utc::now() - Duration::seconds(1))
+            .bind(normalize(&email))
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn clear_forgets_the_failures_of_an_email() {
+        let app = app().await;
+        let policy = policy(1, 100);
+        let email = unique_email();
+
+        reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+        assert!(reserve_attempt(&app.pool, &policy, &email).await.is_err());
+
+        assert!(clear(&app.pool, &email.to_uppercase()).await.unwrap());
+        reserve_attempt(&app.pool, &policy, &email).await.unwrap();
+
+        clear(&app.pool, &email).await.unwrap();
+        assert!(!clear(&app.pool, &email).await.unwrap(), "nothing left to clear");
+    }
+}
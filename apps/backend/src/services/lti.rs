@@ -0,0 +1,423 @@
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::lti::LtiPlatform;
+use crate::services::ags;
+use crate::services::jwks::JwksCache;
+use crate::services::jwt_keys::SigningKey;
+use crate::services::secrets;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const LTI_VERSION: &str = "1.3.0";
+pub const RESOURCE_LINK_REQUEST: &str = "LtiResourceLinkRequest";
+pub const DEEP_LINKING_REQUEST: &str = "LtiDeepLinkingRequest";
+const DEEP_LINKING_RESPONSE: &str = "LtiDeepLinkingResponse";
+
+/// How long a platform has to answer a login initiation with a launch
+const LAUNCH_STATE_TTL_MINUTES: i64 = 10;
+/// Expired launch states removed per login initiation; each initiation adds
+/// one, so the table stays at about the launches of the last few minutes
+const EXPIRED_STATE_CLEANUP_BATCH: u32 = 100;
+/// How long an instructor has to pick a session for a deep link
+const DEEP_LINK_CONTEXT_TTL_SECS: i64 = 1800;
+/// How long an instructor has to sign in and link their course account
+const ACCOUNT_LINK_TTL_SECS: i64 = 1800;
+
+/// Custom parameter a deep-linked course link carries to name its session
+pub const SESSION_CUSTOM_PARAM: &str = "session_id";
+/// Custom parameter proving the tool created the course link for that session
+pub const SESSION_GRANT_CUSTOM_PARAM: &str = "session_grant";
+
+/// Key of a platform's users in `user_identities.provider`
+pub fn identity_provider(platform_id: &str) -> String {
+    format!("lti:{}", platform_id)
+}
+
+// ============================================
+// Tool key (signs messages sent to platforms)
+// ============================================
+
+/// RSA key of this tool. Platforms fetch the public half from `/api/lti/jwks`
+/// to verify deep linking responses and service token requests.
+pub type ToolKey = SigningKey;
+
+/// Tool key from `LTI_PRIVATE_KEY`/`LTI_PRIVATE_KEY_FILE`. Without one, launches
+/// still work but deep linking (and anything else the tool signs) is disabled.
+pub fn tool_key_from_config(config: &Config) -> Option<Arc<ToolKey>> {
+    let pem = config.lti_private_key.as_deref()?;
+    match ToolKey::from_pem(&config.lti_key_id, pem) {
+        Ok(key) if key.algorithm() == Algorithm::RS256 => Some(Arc::new(key)),
+        Ok(_) => {
+            tracing::error!("LTI tool key not loaded: LTI 1.3 requires an RSA key");
+            None
+        }
+        Err(e) => {
+            tracing::error!("LTI tool key not loaded: {}", e);
+            None
+        }
+    }
+}
+
+// ============================================
+// Launch messages
+// ============================================
+
+#[derive(Debug, Deserialize)]
+pub struct ResourceLinkClaim {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContextClaim {
+    pub id: String,
+}
+
+/// Assignment and Grade Services endpoints of the course
+#[derive(Debug, Deserialize)]
+pub struct AgsEndpointClaim {
+    #[serde(default)]
+    pub scope: Vec<String>,
+    pub lineitems: Option<String>,
+    pub lineitem: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeepLinkingSettings {
+    pub deep_link_return_url: String,
+    pub data: Option<String>,
+}
+
+/// The parts of an LTI 1.3 launch `id_token` the tool uses
+#[derive(Debug, Deserialize)]
+pub struct LaunchClaims {
+    pub sub: Option<String>,
+    pub nonce: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub email: Option<String>,
+    /// Standard OIDC claim; most platforms leave it out, which counts as unverified
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/message_type")]
+    pub message_type: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/version")]
+    pub version: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/deployment_id")]
+    pub deployment_id: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/roles", default)]
+    pub roles: Vec<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/resource_link")]
+    pub resource_link: Option<ResourceLinkClaim>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/context")]
+    pub context: Option<ContextClaim>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/custom", default)]
+    pub custom: HashMap<String, Value>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti-dl/claim/deep_linking_settings")]
+    pub deep_linking_settings: Option<DeepLinkingSettings>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti-ags/claim/endpoint")]
+    pub ags_endpoint: Option<AgsEndpointClaim>,
+}
+
+impl LaunchClaims {
+    /// Instructors, course designers and administrators act as staff
+    pub fn is_staff(&self) -> bool {
+        self.roles.iter().any(|role| {
+            role.ends_with("#Instructor")
+                || role.ends_with("#Administrator")
+                || role.ends_with("#ContentDeveloper")
+                || role.contains("membership/Instructor#")
+        })
+    }
+
+    /// Real name as reported by the platform
+    pub fn display_name(&self) -> Option<String> {
+        if let Some(name) = self.name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+            return Some(name.to_string());
+        }
+        let parts: Vec<&str> = [self.given_name.as_deref(), self.family_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Line item service of the launch, if the platform lets this tool post
+    /// scores: `(lineitems, lineitem)` URLs
+    pub fn ags_endpoint(&self) -> Option<(Option<&str>, Option<&str>)> {
+        let endpoint = self.ags_endpoint.as_ref()?;
+        if !endpoint.scope.iter().any(|s| s == ags::SCOPE_SCORE) {
+            return None;
+        }
+        // Creating line items needs the full line item scope
+        let lineitems = endpoint
+            .lineitems
+            .as_deref()
+            .filter(|_| endpoint.scope.iter().any(|s| s == ags::SCOPE_LINEITEM));
+        let lineitem = endpoint.lineitem.as_deref();
+        (lineitems.is_some() || lineitem.is_some()).then_some((lineitems, lineitem))
+    }
+
+    /// Session of a course link created by deep linking with this tool on
+    /// `platform_id`. Custom parameters can be edited in the course, so the
+    /// session only counts together with the grant signed into the link.
+    pub fn granted_session_id(&self, secret: &str, platform_id: &str) -> Option<&str> {
+        let custom = |name| self.custom.get(name).and_then(Value::as_str).filter(|v| !v.is_empty());
+        let session_id = custom(SESSION_CUSTOM_PARAM)?;
+        let grant = custom(SESSION_GRANT_CUSTOM_PARAM)?;
+
+        let tag = base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, grant).ok()?;
+        session_grant_mac(secret, platform_id, &self.deployment_id, session_id)
+            .verify_slice(&tag)
+            .ok()?;
+        Some(session_id)
+    }
+}
+
+fn session_grant_mac(secret: &str, platform_id: &str, deployment_id: &str, session_id: &str) -> HmacSha256 {
+    let payload = serde_json::to_string(&[platform_id, deployment_id, session_id]).expect("grant payload serializes");
+    mac(secret, b"lti-session-grant:", &payload)
+}
+
+/// Parameters of a third-party login initiation, sent by the platform
+#[derive(Debug, Deserialize)]
+pub struct LoginInitiation {
+    pub iss: String,
+    pub login_hint: String,
+    pub lti_message_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub lti_deployment_id: Option<String>,
+}
+
+/// Record a single-use state/nonce pair and build the platform's
+/// authentication request URL (id_token via form_post to `redirect_uri`).
+/// Expired states, used or not, are cleared out on the way.
+pub async fn begin_login(
+    pool: &DbPool,
+    platform: &LtiPlatform,
+    initiation: &LoginInitiation,
+    redirect_uri: &str,
+) -> Result<String> {
+    let state = secrets::random_token();
+    let nonce = secrets::random_token();
+
+    sqlx::query("DELETE FROM lti_launch_states WHERE expires_at < NOW(3) LIMIT ?")
+        .bind(EXPIRED_STATE_CLEANUP_BATCH)
+        .execute(pool)
+        .await?;
+    sqlx::query("INSERT INTO lti_launch_states (state, platform_id, nonce, expires_at) VALUES (?, ?, ?, ?)")
+        .bind(&state)
+        .bind(&platform.id)
+        .bind(&nonce)
+        .bind(Utc::now() + Duration::minutes(LAUNCH_STATE_TTL_MINUTES))
+        .execute(pool)
+        .await?;
+
+    let mut url = reqwest::Url::parse(&platform.auth_login_url)
+        .map_err(|e| AppError::Internal(format!("Invalid platform login URL: {}", e)))?;
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("scope", "openid")
+            .append_pair("response_type", "id_token")
+            .append_pair("response_mode", "form_post")
+            .append_pair("prompt", "none")
+            .append_pair("client_id", &platform.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("login_hint", &initiation.login_hint)
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce);
+        if let Some(hint) = &initiation.lti_message_hint {
+            query.append_pair("lti_message_hint", hint);
+        }
+    }
+    Ok(url.into())
+}
+
+/// Spend a launch state; returns the platform it was issued for and its nonce
+async fn consume_state(pool: &DbPool, state: &str) -> Result<(String, String)> {
+    let invalid = || AppError::Auth("Launch expired or was already used; please try again".to_string());
+
+    let mut tx = pool.begin().await?;
+    let spent = sqlx::query(
+        "UPDATE lti_launch_states SET used_at = NOW(3)
+         WHERE state = ? AND used_at IS NULL AND expires_at > NOW(3)"
+    )
+    .bind(state)
+    .execute(&mut *tx)
+    .await?;
+    if spent.rows_affected() != 1 {
+        return Err(invalid());
+    }
+
+    let row: (String, String) = sqlx::query_as("SELECT platform_id, nonce FROM lti_launch_states WHERE state = ?")
+        .bind(state)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(row)
+}
+
+/// Validate a launch: single-use state, `id_token` signature against the
+/// platform's JWKS, audience, nonce, LTI version and deployment
+pub async fn verify_launch(
+    pool: &DbPool,
+    jwks: &JwksCache,
+    state: &str,
+    id_token: &str,
+) -> Result<(LtiPlatform, LaunchClaims)> {
+    let (platform_id, nonce) = consume_state(pool, state).await?;
+    let platform = LtiPlatform::find(pool, &platform_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("LTI platform is no longer registered".to_string()))?;
+
+    let claims: LaunchClaims = jwks
+        .verify(id_token, &platform.jwks_url, &platform.issuer, &platform.client_id)
+        .await?;
+
+    if claims.nonce.as_deref() != Some(nonce.as_str()) {
+        return Err(AppError::Auth("Launch nonce mismatch".to_string()));
+    }
+    if claims.version != LTI_VERSION {
+        return Err(AppError::Input(format!("Unsupported LTI version {}", claims.version)));
+    }
+    if platform.deployment_id.as_deref().is_some_and(|id| id != claims.deployment_id) {
+        return Err(AppError::Forbidden("This LTI deployment is not registered".to_string()));
+    }
+
+    Ok((platform, claims))
+}
+
+// ============================================
+// Deep linking
+// ============================================
+
+/// What the session picker needs to answer a deep linking request; signed
+/// and handed to the browser so the picker works without third-party cookies
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeepLinkContext {
+    pub platform_id: String,
+    pub deployment_id: String,
+    pub return_url: String,
+    pub data: Option<String>,
+    pub user_id: String,
+    pub issued_at: i64,
+}
+
+/// Sign a value for a round trip through the browser. `purpose` keeps a
+/// value sealed for one use from being accepted as another.
+fn seal<T: Serialize>(secret: &str, purpose: &[u8], value: &T) -> String {
+    let payload = base64::Engine::encode(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+        serde_json::to_vec(value).expect("sealed value serializes"),
+    );
+    let tag = mac(secret, purpose, &payload).finalize().into_bytes();
+    let signature = base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, tag);
+    format!("{}.{}", payload, signature)
+}
+
+/// Verify and decode a value from `seal`; None if it was tampered with
+fn open<T: for<'de> Deserialize<'de>>(secret: &str, purpose: &[u8], sealed: &str) -> Option<T> {
+    let (payload, signature) = sealed.split_once('.')?;
+    let tag = base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, signature).ok()?;
+    mac(secret, purpose, payload).verify_slice(&tag).ok()?;
+
+    let json = base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, payload).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+fn mac(secret: &str, purpose: &[u8], payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(purpose);
+    mac.update(payload.as_bytes());
+    mac
+}
+
+impl DeepLinkContext {
+    pub fn seal(&self, secret: &str) -> String {
+        seal(secret, b"lti-deep-link:", self)
+    }
+
+    pub fn open(secret: &str, sealed: &str) -> Result<Self> {
+        open::<Self>(secret, b"lti-deep-link:", sealed)
+            .filter(|context| Utc::now().timestamp() - context.issued_at <= DEEP_LINK_CONTEXT_TTL_SECS)
+            .ok_or_else(|| AppError::Auth("Deep linking request expired; start again from your course".to_string()))
+    }
+}
+
+/// A course account whose instructor launch could not be matched to a
+/// ClassColab account. Signed and handed to the browser; a signed-in user
+/// redeems it to link the course account to theirs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingAccountLink {
+    pub platform_id: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub issued_at: i64,
+}
+
+impl PendingAccountLink {
+    pub fn seal(&self, secret: &str) -> String {
+        seal(secret, b"lti-account-link:", self)
+    }
+
+    pub fn open(secret: &str, sealed: &str) -> Result<Self> {
+        open::<Self>(secret, b"lti-account-link:", sealed)
+            .filter(|link| Utc::now().timestamp() - link.issued_at <= ACCOUNT_LINK_TTL_SECS)
+            .ok_or_else(|| AppError::Auth("Account link expired; launch again from your course".to_string()))
+    }
+}
+
+/// Signed `LtiDeepLinkingResponse` with one resource link that opens
+/// `session_id`. The link carries a grant for the session, so launches can
+/// only bind course links to sessions picked here.
+pub fn deep_linking_response(
+    key: &ToolKey,
+    secret: &str,
+    platform: &LtiPlatform,
+    context: &DeepLinkContext,
+    launch_url: &str,
+    session_id: &str,
+    title: &str,
+) -> Result<String> {
+    let now = Utc::now().timestamp();
+    let grant = session_grant_mac(secret, &platform.id, &context.deployment_id, session_id)
+        .finalize()
+        .into_bytes();
+    let mut claims = json!({
+        "iss": platform.client_id,
+        "aud": platform.issuer,
+        "iat": now,
+        "exp": now + 300,
+        "nonce": secrets::random_token(),
+        "https://purl.imsglobal.org/spec/lti/claim/message_type": DEEP_LINKING_RESPONSE,
+        "https://purl.imsglobal.org/spec/lti/claim/version": LTI_VERSION,
+        "https://purl.imsglobal.org/spec/lti/claim/deployment_id": context.deployment_id,
+        "https://purl.imsglobal.org/spec/lti-dl/claim/content_items": [{
+            "type": "ltiResourceLink",
+            "title": title,
+            "url": launch_url,
+            "custom": {
+                SESSION_CUSTOM_PARAM: session_id,
+                SESSION_GRANT_CUSTOM_PARAM: base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, grant),
+            },
+        }],
+    });
+    if let Some(data) = &context.data {
+        claims["https://purl.imsglobal.org/spec/lti-dl/claim/data"] = json!(data);
+    }
+    key.sign(&claims)
+}
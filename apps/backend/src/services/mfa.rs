@@ -0,0 +1,249 @@
+use chrono::Utc;
+use rand::Rng;
+use serde::Serialize;
+use sqlx::{FromRow, MySqlConnection};
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::services::{secrets, totp};
+
+/// Name authenticator apps show next to the account
+pub const TOTP_ISSUER: &str = "ClassColab";
+/// Recovery codes handed out per (re)generation
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Characters per half of a recovery code ("abcde-fghjk"); no 0/o, 1/l/i
+const RECOVERY_CODE_HALF_LEN: usize = 5;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Two-factor state of an account, as shown to its owner
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaStatus {
+    pub enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+#[derive(Debug, FromRow)]
+struct MfaRow {
+    totp_secret: String,
+    enabled_at: Option<chrono::DateTime<Utc>>,
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    secrets::sha256_hex(&normalize_recovery_code(code))
+}
+
+fn new_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let mut half = || -> String {
+        (0..RECOVERY_CODE_HALF_LEN)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect()
+    };
+    format!("{}-{}", half(), half())
+}
+
+async fn find(pool: &DbPool, user_id: &str) -> Result<Option<MfaRow>> {
+    Ok(sqlx::query_as::<_, MfaRow>("SELECT totp_secret, enabled_at FROM user_mfa WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// Whether login needs a second factor for this user
+pub async fn is_enabled(pool: &DbPool, user_id: &str) -> Result<bool> {
+    Ok(find(pool, user_id).await?.is_some_and(|row| row.enabled_at.is_some()))
+}
+
+pub async fn status(pool: &DbPool, user_id: &str) -> Result<MfaStatus> {
+    let recovery_codes_remaining: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM mfa_recovery_codes WHERE user_id = ? AND used_at IS NULL"
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(MfaStatus {
+        enabled: is_enabled(pool, user_id).await?,
+        recovery_codes_remaining,
+    })
+}
+
+/// Start (or restart) enrollment with a fresh secret; returns the secret.
+/// Nothing changes at login until `confirm_enrollment` succeeds.
+pub async fn start_enrollment(pool: &DbPool, user_id: &str) -> Result<String> {
+    if is_enabled(pool, user_id).await? {
+        return Err(AppError::Input("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret = totp::generate_secret();
+    sqlx::query(
+        "INSERT INTO user_mfa (user_id, totp_secret) VALUES (?, ?)
+         ON DUPLICATE KEY UPDATE totp_secret = VALUES(totp_secret), last_used_step = NULL"
+    )
+    .bind(user_id)
+    .bind(&secret)
+    .execute(pool)
+    .await?;
+    Ok(secret)
+}
+
+/// Enable 2FA once the user proves their app produces valid codes; returns
+/// the recovery codes, which are only shown this once
+pub async fn confirm_enrollment(pool: &DbPool, user_id: &str, code: &str) -> Result<Vec<String>> {
+    let row = find(pool, user_id)
+        .await?
+        .ok_or_else(|| AppError::Input("Start two-factor enrollment first".to_string()))?;
+    if row.enabled_at.is_some() {
+        return Err(AppError::Input("Two-factor authentication is already enabled".to_string()));
+    }
+    let step = totp::matching_step(&row.totp_secret, code, Utc::now().timestamp())
+        .ok_or_else(|| AppError::Input("Invalid authentication code".to_string()))?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE user_mfa SET enabled_at = NOW(3), last_used_step = ? WHERE user_id = ?")
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let codes = replace_recovery_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(codes)
+}
+
+async fn replace_recovery_codes(conn: &mut sqlx::MySqlConnection, user_id: &str) -> Result<Vec<String>> {
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| new_recovery_code()).collect();
+    for code in &codes {
+        sqlx::query("INSERT INTO mfa_recovery_codes (id, user_id, code_hash) VALUES (?, ?, ?)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(user_id)
+            .bind(hash_recovery_code(code))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(codes)
+}
+
+/// Replace all recovery codes with a new set
+pub async fn regenerate_recovery_codes(pool: &DbPool, user_id: &str) -> Result<Vec<String>> {
+    let mut tx = pool.begin().await?;
+    let codes = replace_recovery_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(codes)
+}
+
+/// Check a second factor: a current authenticator code or an unused
+/// recovery code. Either one is spent by a successful check, as part of the
+/// caller's transaction when there is one.
+pub async fn verify(conn: &mut MySqlConnection, user_id: &str, code: &str) -> Result<bool> {
+    let row = sqlx::query_as::<_, MfaRow>("SELECT totp_secret, enabled_at FROM user_mfa WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let Some(row) = row.filter(|row| row.enabled_at.is_some()) else {
+        return Ok(false);
+    };
+
+    if let Some(step) = totp::matching_step(&row.totp_secret, code, Utc::now().timestamp()) {
+        // Only a step after the last accepted one counts, so a code cannot be replayed
+        let accepted = sqlx::query(
+            "UPDATE user_mfa SET last_used_step = ?
+             WHERE user_id = ? AND (last_used_step IS NULL OR last_used_step < ?)"
+        )
+        .bind(step)
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut *conn)
+        .await?;
+        return Ok(accepted.rows_affected() == 1);
+    }
+
+    let redeemed = sqlx::query(
+        "UPDATE mfa_recovery_codes SET used_at = NOW(3)
+         WHERE user_id = ? AND code_hash = ? AND used_at IS NULL LIMIT 1"
+    )
+    .bind(user_id)
+    .bind(hash_recovery_code(code))
+    .execute(&mut *conn)
+    .await?;
+    if redeemed.rows_affected() == 1 {
+        tracing::info!("User {} signed in with a recovery code", user_id);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Turn 2FA off and forget the secret and recovery codes
+pub async fn disable(pool: &DbPool, user_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM user_mfa WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::test_support::{app, create_user};
+
+    #[test]
+    fn recovery_codes_are_readable_and_forgiving_to_type() {
+        let code = new_recovery_code();
+        let (first, second) = code.split_once('-').unwrap();
+        assert_eq!((first.len(), second.len()), (RECOVERY_CODE_HALF_LEN, RECOVERY_CODE_HALF_LEN));
+        assert!(code.bytes().filter(|b| *b != b'-').all(|b| RECOVERY_CODE_ALPHABET.contains(&b)));
+
+        assert_eq!(hash_recovery_code("abcde-fghjk"), hash_recovery_code(" ABCDE FGHJK "));
+        assert_ne!(hash_recovery_code("abcde-fghjk"), hash_recovery_code("abcde-fghjm"));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn codes_and_recovery_codes_work_once() {
+        let app = app().await;
+        let user = create_user(&app.pool, Role::Teacher).await;
+        let mut conn = app.pool.acquire().await.unwrap();
+        assert!(!verify(&mut conn, &user.user_id, "000000").await.unwrap());
+
+        let secret = start_enrollment(&app.pool, &user.user_id).await.unwrap();
+        let now = Utc::now().timestamp();
+        let recovery_codes = confirm_enrollment(&app.pool, &user.user_id, &totp::code_at(&secret, now)).await.unwrap();
+        assert_eq!(recovery_codes.len(), RECOVERY_CODE_COUNT);
+        assert!(is_enabled(&app.pool, &user.user_id).await.unwrap());
+
+        // The enrollment code was spent; the next step's code is accepted once
+        assert!(!verify(&mut conn, &user.user_id, &totp::code_at(&secret, now)).await.unwrap());
+        let next = totp::code_at(&secret, now + 30);
+        assert!(verify(&mut conn, &user.user_id, &next).await.unwrap());
+        assert!(!verify(&mut conn, &user.user_id, &next).await.unwrap());
+
+        let recovery = recovery_codes[0].to_uppercase();
+        assert!(verify(&mut conn, &user.user_id, &recovery).await.unwrap());
+        assert!(!verify(&mut conn, &user.user_id, &recovery).await.unwrap());
+        assert_eq!(status(&app.pool, &user.user_id).await.unwrap().recovery_codes_remaining, RECOVERY_CODE_COUNT as i64 - 1);
+
+        disable(&app.pool, &user.user_id).await.unwrap();
+        assert!(!verify(&mut conn, &user.user_id, &recovery_codes[1]).await.unwrap());
+    }
+}
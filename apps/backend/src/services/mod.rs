@@ -1,3 +1,29 @@
 pub mod ably;
+pub mod account;
+pub mod ags;
+pub mod attendance;
+pub mod auth_tokens;
+pub mod clicker;
+pub mod event_log;
+pub mod identity;
+pub mod join_codes;
+pub mod jwks;
+pub mod jwt_keys;
+pub mod login_guard;
+pub mod lti;
+pub mod mailer;
+pub mod mfa;
+pub mod organizations;
+pub mod oidc;
+pub mod outbox;
+pub mod participant;
+pub mod personal_tokens;
+pub mod realtime;
+pub mod secrets;
 pub mod session;
-
+pub mod session_access;
+pub mod session_members;
+pub mod sse;
+pub mod totp;
+pub mod user_tokens;
+pub mod vote_coalescer;
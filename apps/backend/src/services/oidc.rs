@@ -0,0 +1,229 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::OidcProviderConfig;
+use crate::error::{AppError, Result};
+use crate::services::jwks::{JwksCache, HTTP_CLIENT};
+use crate::services::secrets;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long discovery documents are cached
+const DISCOVERY_TTL: Duration = Duration::from_secs(3600);
+/// How long a user has to finish signing in at the provider
+const LOGIN_STATE_TTL_SECS: i64 = 600;
+
+/// The parts of `/.well-known/openid-configuration` we use
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Per-login secrets carried in a signed cookie between `login` and `callback`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginState {
+    pub provider: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    /// Set when a signed-in user links the provider account instead of signing in
+    #[serde(default)]
+    pub link_user_id: Option<String>,
+    pub issued_at: i64,
+}
+
+impl LoginState {
+    fn mac(secret: &str, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"oidc-login:");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// Serialize and sign for the login cookie
+    pub fn seal(&self, secret: &str) -> String {
+        let payload = base64::Engine::encode(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+            serde_json::to_vec(self).expect("login state serializes"),
+        );
+        let tag = Self::mac(secret, &payload).finalize().into_bytes();
+        let signature = base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, tag);
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Verify and decode a login cookie; None if tampered with or expired
+    pub fn open(secret: &str, sealed: &str) -> Option<Self> {
+        let (payload, signature) = sealed.split_once('.')?;
+        let tag = base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, signature).ok()?;
+        Self::mac(secret, payload).verify_slice(&tag).ok()?;
+
+        let json = base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, payload).ok()?;
+        let state: Self = serde_json::from_slice(&json).ok()?;
+        (Utc::now().timestamp() - state.issued_at <= LOGIN_STATE_TTL_SECS).then_some(state)
+    }
+}
+
+/// ID token claims used to find or create the account
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// OpenID Connect authorization code flow with PKCE for the configured providers.
+/// Providers are discovered from their issuer URL on first use.
+pub struct OidcService {
+    providers: Vec<OidcProviderConfig>,
+    metadata: Mutex<HashMap<String, (ProviderMetadata, Instant)>>,
+    jwks: Arc<JwksCache>,
+}
+
+impl OidcService {
+    pub fn new(providers: Vec<OidcProviderConfig>, jwks: Arc<JwksCache>) -> Self {
+        Self {
+            providers,
+            metadata: Mutex::new(HashMap::new()),
+            jwks,
+        }
+    }
+
+    pub fn providers(&self) -> &[OidcProviderConfig] {
+        &self.providers
+    }
+
+    pub fn provider(&self, id: &str) -> Result<&OidcProviderConfig> {
+        self.providers
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| AppError::NotFound("Unknown sign-in provider".to_string()))
+    }
+
+    async fn discover(&self, provider: &OidcProviderConfig) -> Result<ProviderMetadata> {
+        if let Some((metadata, fetched_at)) = self.metadata.lock().unwrap().get(&provider.id) {
+            if fetched_at.elapsed() < DISCOVERY_TTL {
+                return Ok(metadata.clone());
+            }
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", provider.issuer);
+        let metadata: ProviderMetadata = HTTP_CLIENT
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Internal(format!("OIDC discovery for {} failed: {}", provider.id, e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid OIDC discovery document for {}: {}", provider.id, e)))?;
+
+        if metadata.issuer.trim_end_matches('/') != provider.issuer {
+            return Err(AppError::Internal(format!(
+                "OIDC issuer mismatch for {}: configured {}, discovered {}",
+                provider.id, provider.issuer, metadata.issuer
+            )));
+        }
+
+        self.metadata
+            .lock()
+            .unwrap()
+            .insert(provider.id.clone(), (metadata.clone(), Instant::now()));
+        Ok(metadata)
+    }
+
+    /// Authorization URL to send the browser to, and the state to keep until the callback
+    pub async fn begin_login(&self, provider: &OidcProviderConfig, redirect_uri: &str) -> Result<(String, LoginState)> {
+        let metadata = self.discover(provider).await?;
+
+        let login = LoginState {
+            provider: provider.id.clone(),
+            state: secrets::random_token(),
+            nonce: secrets::random_token(),
+            code_verifier: secrets::random_token(),
+            link_user_id: None,
+            issued_at: Utc::now().timestamp(),
+        };
+        let code_challenge = base64::Engine::encode(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+            Sha256::digest(login.code_verifier.as_bytes()),
+        );
+
+        let mut url = reqwest::Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| AppError::Internal(format!("Invalid authorization endpoint: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", &provider.scopes)
+            .append_pair("state", &login.state)
+            .append_pair("nonce", &login.nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        Ok((url.into(), login))
+    }
+
+    /// Exchange the authorization code and validate the returned ID token
+    pub async fn complete_login(
+        &self,
+        provider: &OidcProviderConfig,
+        login: &LoginState,
+        code: &str,
+        redirect_uri: &str,
+    ) -> Result<IdTokenClaims> {
+        let metadata = self.discover(provider).await?;
+
+        let mut request = HTTP_CLIENT.post(&metadata.token_endpoint).form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", provider.client_id.as_str()),
+            ("code_verifier", login.code_verifier.as_str()),
+        ]);
+        if let Some(secret) = &provider.client_secret {
+            request = request.basic_auth(&provider.client_id, Some(secret));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("OIDC token request failed: {}", e)))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!("OIDC token exchange with {} failed: {} - {}", provider.id, status, body);
+            return Err(AppError::Auth("Sign-in was not accepted by the provider".to_string()));
+        }
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid OIDC token response: {}", e)))?;
+
+        let claims: IdTokenClaims = self
+            .jwks
+            .verify(&tokens.id_token, &metadata.jwks_uri, &metadata.issuer, &provider.client_id)
+            .await?;
+
+        if claims.nonce.as_deref() != Some(login.nonce.as_str()) {
+            return Err(AppError::Auth("ID token nonce mismatch".to_string()));
+        }
+        Ok(claims)
+    }
+}
@@ -0,0 +1,470 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::organization::{OrgRole, Organization};
+
+/// An organization as listed for one of its members
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberOrganization {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub organization: Organization,
+    /// The caller's role
+    #[sqlx(try_from = "String")]
+    pub role: OrgRole,
+}
+
+/// A change to the ownership of an organization session
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: String,
+    pub actor_id: String,
+    pub action: String,
+    pub session_id: String,
+    pub from_user_id: Option<String>,
+    pub to_user_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Entries returned by `audit_log`
+const AUDIT_LOG_LIMIT: u32 = 200;
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationMember {
+    pub user_id: String,
+    pub name: String,
+    pub email: String,
+    #[sqlx(try_from = "String")]
+    pub role: OrgRole,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Create an organization with its creator as the first admin
+pub async fn create(pool: &DbPool, name: &str, user_id: &str) -> Result<Organization> {
+    let id = Uuid::new_v4().to_string();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT INTO organizations (id, name, created_by) VALUES (?, ?, ?)")
+        .bind(&id)
+        .bind(name)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO organization_members (organization_id, user_id, role) VALUES (?, ?, ?)")
+        .bind(&id)
+        .bind(user_id)
+        .bind(OrgRole::Admin.as_str())
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    find(pool, &id).await
+}
+
+pub async fn find(pool: &DbPool, organization_id: &str) -> Result<Organization> {
+    sqlx::query_as::<_, Organization>("SELECT id, name, created_by, created_at FROM organizations WHERE id = ?")
+        .bind(organization_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))
+}
+
+/// Organizations the user belongs to, with their role in each
+pub async fn list_for_user(pool: &DbPool, user_id: &str) -> Result<Vec<MemberOrganization>> {
+    Ok(sqlx::query_as::<_, MemberOrganization>(
+        "SELECT o.id, o.name, o.created_by, o.created_at, om.role
+         FROM organization_members om JOIN organizations o ON o.id = om.organization_id
+         WHERE om.user_id = ?
+         ORDER BY o.name"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// The user's role in an organization, or None if they are not a member.
+/// Fails with NotFound if the organization does not exist.
+pub async fn role_of(pool: &DbPool, organization_id: &str, user_id: &str) -> Result<Option<OrgRole>> {
+    let row: Option<(Option<String>,)> = sqlx::query_as(
+        "SELECT om.role FROM organizations o
+         LEFT JOIN organization_members om ON om.organization_id = o.id AND om.user_id = ?
+         WHERE o.id = ?"
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let (role,) = row.ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+    Ok(role.as_deref().and_then(OrgRole::parse))
+}
+
+/// Check that the user has at least `min` in the organization
+pub async fn require(pool: &DbPool, organization_id: &str, user_id: &str, min: OrgRole) -> Result<OrgRole> {
+    match role_of(pool, organization_id, user_id).await? {
+        Some(role) if role >= min => Ok(role),
+        Some(_) => Err(AppError::Forbidden(format!(
+            "This requires the {} role in the organization",
+            min.as_str()
+        ))),
+        None => Err(AppError::Auth("Unauthorized access to organization".to_string())),
+    }
+}
+
+pub async fn rename(pool: &DbPool, organization_id: &str, name: &str) -> Result<Organization> {
+    sqlx::query("UPDATE organizations SET name = ? WHERE id = ?")
+        .bind(name)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+    find(pool, organization_id).await
+}
+
+pub async fn list_members(pool: &DbPool, organization_id: &str) -> Result<Vec<OrganizationMember>> {
+    Ok(sqlx::query_as::<_, OrganizationMember>(
+        "SELECT u.id AS user_id, u.name, u.email, om.role, om.created_at
+         FROM organization_members om JOIN users u ON u.id = om.user_id
+         WHERE om.organization_id = ?
+         ORDER BY om.role = 'admin' DESC, u.name"
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Add an existing account by email, or change the role of a member
+pub async fn add_member(pool: &DbPool, organization_id: &str, email: &str, role: OrgRole) -> Result<OrganizationMember> {
+    let user_id: String = sqlx::query_scalar("SELECT id FROM users WHERE email = ?")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No account uses this email address".to_string()))?;
+
+    set_role(pool, organization_id, &user_id, role).await
+}
+
+/// Give a user a role in the organization. The last admin cannot step down.
+pub async fn set_role(pool: &DbPool, organization_id: &str, user_id: &str, role: OrgRole) -> Result<OrganizationMember> {
+    let mut tx = pool.begin().await?;
+    if role != OrgRole::Admin {
+        ensure_other_admin(&mut tx, organization_id, user_id).await?;
+    }
+    sqlx::query(
+        "INSERT INTO organization_members (organization_id, user_id, role) VALUES (?, ?, ?)
+         ON DUPLICATE KEY UPDATE role = VALUES(role)"
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .bind(role.as_str())
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    sqlx::query_as::<_, OrganizationMember>(
+        "SELECT u.id AS user_id, u.name, u.email, om.role, om.created_at
+         FROM organization_members om JOIN users u ON u.id = om.user_id
+         WHERE om.organization_id = ? AND om.user_id = ?"
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Remove a member. Their sessions stay in the organization; admins hand
+/// them to someone else with `transfer_sessions`.
+pub async fn remove_member(pool: &DbPool, organization_id: &str, user_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    ensure_other_admin(&mut tx, organization_id, user_id).await?;
+    let removed = sqlx::query("DELETE FROM organization_members WHERE organization_id = ? AND user_id = ?")
+        .bind(organization_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    if removed.rows_affected() == 0 {
+        return Err(AppError::NotFound("Member not found".to_string()));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Fail if `user_id` is the organization's only admin (locks the admin rows
+/// so concurrent demotions cannot both pass)
+pub async fn ensure_other_admin(conn: &mut sqlx::MySqlConnection, organization_id: &str, user_id: &str) -> Result<()> {
+    let admins: Vec<String> = sqlx::query_scalar(
+        "SELECT user_id FROM organization_members WHERE organization_id = ? AND role = 'admin' FOR UPDATE"
+    )
+    .bind(organization_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    if admins.len() == 1 && admins[0] == user_id {
+        return Err(AppError::Input("An organization needs at least one admin".to_string()));
+    }
+    Ok(())
+}
+
+/// Record a change to an organization session in its audit log
+async fn audit(
+    conn: &mut sqlx::MySqlConnection,
+    organization_id: &str,
+    actor_id: &str,
+    action: &str,
+    session_id: &str,
+    transfer: Option<(&str, &str)>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO organization_audit_log (id, organization_id, actor_id, action, session_id, from_user_id, to_user_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(organization_id)
+    .bind(actor_id)
+    .bind(action)
+    .bind(session_id)
+    .bind(transfer.map(|(from, _)| from))
+    .bind(transfer.map(|(_, to)| to))
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Recent ownership changes of the organization's sessions, newest first
+pub async fn audit_log(pool: &DbPool, organization_id: &str) -> Result<Vec<AuditEntry>> {
+    Ok(sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, actor_id, action, session_id, from_user_id, to_user_id, created_at
+         FROM organization_audit_log WHERE organization_id = ?
+         ORDER BY created_at DESC LIMIT ?"
+    )
+    .bind(organization_id)
+    .bind(AUDIT_LOG_LIMIT)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Hand the organization sessions created by `from_user_id` (or just one of
+/// them) to another member, who becomes their owner. Each transfer is
+/// recorded in the audit log under `actor_id`. Returns how many moved.
+pub async fn transfer_sessions(
+    pool: &DbPool,
+    organization_id: &str,
+    actor_id: &str,
+    from_user_id: &str,
+    to_user_id: &str,
+    session_id: Option<&str>,
+) -> Result<u64> {
+    if role_of(pool, organization_id, to_user_id).await?.is_none() {
+        return Err(AppError::Input("Sessions can only be transferred to a member of the organization".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let session_ids: Vec<String> = sqlx::query_scalar(
+        "SELECT id FROM sessions WHERE organization_id = ? AND creator_id = ? AND (? IS NULL OR id = ?) FOR UPDATE"
+    )
+    .bind(organization_id)
+    .bind(from_user_id)
+    .bind(session_id)
+    .bind(session_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for id in &session_ids {
+        // The new owner's invitation to the session is superseded by ownership
+        sqlx::query("DELETE FROM session_members WHERE user_id = ? AND session_id = ?")
+            .bind(to_user_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE sessions SET creator_id = ? WHERE id = ?")
+            .bind(to_user_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        audit(&mut tx, organization_id, actor_id, "session_transferred", id, Some((from_user_id, to_user_id))).await?;
+    }
+    tx.commit().await?;
+
+    Ok(session_ids.len() as u64)
+}
+
+/// Move a session between organizations (None: out of its organization).
+/// Both the organization it leaves and the one it joins log the move.
+pub async fn set_session_organization(
+    pool: &DbPool,
+    session_id: &str,
+    actor_id: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE sessions SET organization_id = ? WHERE id = ?")
+        .bind(to)
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    if let Some(from) = from {
+        audit(&mut tx, from, actor_id, "session_removed", session_id, None).await?;
+    }
+    if let Some(to) = to {
+        audit(&mut tx, to, actor_id, "session_added", session_id, None).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::session::SessionRole;
+    use crate::models::user::Role;
+    use crate::test_support::{add_member as invite, app, create_session, create_user, email_of};
+
+    async fn organization_session(pool: &DbPool, organization_id: &str, creator_id: &str) -> String {
+        let session_id = create_session(pool, creator_id).await;
+        sqlx::query("UPDATE sessions SET organization_id = ? WHERE id = ?")
+            .bind(organization_id)
+            .bind(&session_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        session_id
+    }
+
+    async fn creator_of(pool: &DbPool, session_id: &str) -> String {
+        sqlx::query_scalar("SELECT creator_id FROM sessions WHERE id = ?")
+            .bind(session_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn the_last_admin_cannot_step_down_or_leave() {
+        let app = app().await;
+        let founder = create_user(&app.pool, Role::Teacher).await;
+        let colleague = create_user(&app.pool, Role::Teacher).await;
+        let organization = create(&app.pool, "Physics", &founder.user_id).await.unwrap();
+        add_member(&app.pool, &organization.id, &email_of(&colleague.user_id), OrgRole::Member).await.unwrap();
+
+        let mut conn = app.pool.acquire().await.unwrap();
+        assert!(matches!(
+            ensure_other_admin(&mut conn, &organization.id, &founder.user_id).await,
+            Err(AppError::Input(_))
+        ));
+        // Members can always go
+        ensure_other_admin(&mut conn, &organization.id, &colleague.user_id).await.unwrap();
+        drop(conn);
+
+        assert!(set_role(&app.pool, &organization.id, &founder.user_id, OrgRole::Member).await.is_err());
+        assert!(remove_member(&app.pool, &organization.id, &founder.user_id).await.is_err());
+
+        set_role(&app.pool, &organization.id, &colleague.user_id, OrgRole::Admin).await.unwrap();
+        set_role(&app.pool, &organization.id, &founder.user_id, OrgRole::Member).await.unwrap();
+        assert!(matches!(
+            remove_member(&app.pool, &organization.id, &colleague.user_id).await,
+            Err(AppError::Input(_))
+        ));
+        remove_member(&app.pool, &organization.id, &founder.user_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn transferring_sessions_changes_owners_and_is_audited() {
+        let app = app().await;
+        let admin = create_user(&app.pool, Role::Teacher).await;
+        let leaving = create_user(&app.pool, Role::Teacher).await;
+        let successor = create_user(&app.pool, Role::Teacher).await;
+        let organization = create(&app.pool, "Chemistry", &admin.user_id).await.unwrap();
+        for member in [&leaving, &successor] {
+            add_member(&app.pool, &organization.id, &email_of(&member.user_id), OrgRole::Member).await.unwrap();
+        }
+
+        let first = organization_session(&app.pool, &organization.id, &leaving.user_id).await;
+        let second = organization_session(&app.pool, &organization.id, &leaving.user_id).await;
+        let personal = create_session(&app.pool, &leaving.user_id).await;
+        invite(&app.pool, &first, &successor.user_id, SessionRole::Editor).await;
+
+        let moved = transfer_sessions(&app.pool, &organization.id, &admin.user_id, &leaving.user_id, &successor.user_id, None)
+            .await
+            .unwrap();
+
+        assert_eq!(moved, 2);
+        assert_eq!(creator_of(&app.pool, &first).await, successor.user_id);
+        assert_eq!(creator_of(&app.pool, &second).await, successor.user_id);
+        assert_eq!(creator_of(&app.pool, &personal).await, leaving.user_id, "personal sessions stay");
+
+        // The owner no longer needs their invitation
+        let invitations: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM session_members WHERE session_id = ? AND user_id = ?")
+            .bind(&first)
+            .bind(&successor.user_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(invitations, 0);
+
+        let log = audit_log(&app.pool, &organization.id).await.unwrap();
+        assert_eq!(log.len(), 2);
+        for entry in &log {
+            assert_eq!(entry.action, "session_transferred");
+            assert_eq!(entry.actor_id, admin.user_id);
+            assert_eq!(entry.from_user_id.as_deref(), Some(leaving.user_id.as_str()));
+            assert_eq!(entry.to_user_id.as_deref(), Some(successor.user_id.as_str()));
+        }
+        let mut logged: Vec<&str> = log.iter().map(|e| e.session_id.as_str()).collect();
+        logged.sort();
+        let mut expected = [first.as_str(), second.as_str()];
+        expected.sort();
+        assert_eq!(logged, expected);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn sessions_only_go_to_members() {
+        let app = app().await;
+        let admin = create_user(&app.pool, Role::Teacher).await;
+        let outsider = create_user(&app.pool, Role::Teacher).await;
+        let organization = create(&app.pool, "Biology", &admin.user_id).await.unwrap();
+        let session_id = organization_session(&app.pool, &organization.id, &admin.user_id).await;
+
+        let refused = transfer_sessions(
+            &app.pool, &organization.id, &admin.user_id, &admin.user_id, &outsider.user_id, Some(&session_id),
+        )
+        .await;
+
+        assert!(matches!(refused, Err(AppError::Input(_))));
+        assert_eq!(creator_of(&app.pool, &session_id).await, admin.user_id);
+        assert!(audit_log(&app.pool, &organization.id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn moving_a_session_is_logged_by_both_organizations() {
+        let app = app().await;
+        let admin = create_user(&app.pool, Role::Teacher).await;
+        let from = create(&app.pool, "Old department", &admin.user_id).await.unwrap();
+        let to = create(&app.pool, "New department", &admin.user_id).await.unwrap();
+        let session_id = organization_session(&app.pool, &from.id, &admin.user_id).await;
+
+        set_session_organization(&app.pool, &session_id, &admin.user_id, Some(&from.id), Some(&to.id)).await.unwrap();
+        // Staying put is not a move
+        set_session_organization(&app.pool, &session_id, &admin.user_id, Some(&to.id), Some(&to.id)).await.unwrap();
+
+        let left = audit_log(&app.pool, &from.id).await.unwrap();
+        let joined = audit_log(&app.pool, &to.id).await.unwrap();
+        assert_eq!(left.iter().map(|e| e.action.as_str()).collect::<Vec<_>>(), ["session_removed"]);
+        assert_eq!(joined.iter().map(|e| e.action.as_str()).collect::<Vec<_>>(), ["session_added"]);
+        assert!(joined[0].from_user_id.is_none() && joined[0].to_user_id.is_none());
+    }
+}
@@ -0,0 +1,449 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, MySqlConnection};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+use crate::db::{DbPool, LazyDbPool};
+use crate::error::Result;
+use crate::services::ably::session_id_from_channel;
+use crate::services::realtime::{RealtimeEvent, RealtimePublisher};
+
+/// Delay before the first retry; doubled on every further attempt
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound for the retry delay
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+/// Maximum number of channel heads delivered per round
+const DELIVERY_BATCH_SIZE: i64 = 100;
+/// How long claimed rows stay reserved for the dispatcher delivering them;
+/// well above the publisher's request timeout
+const DELIVERY_LEASE_SECS: i64 = 120;
+/// How often delivered rows past the retention window are purged
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Dispatcher tuning, read from `Config`
+#[derive(Debug, Clone)]
+pub struct OutboxSettings {
+    pub max_attempts: i32,
+    pub poll_interval: Duration,
+    pub retention: Duration,
+    pub event_log_capacity: i64,
+}
+
+/// Delivery counters exposed on `/health/outbox`
+#[derive(Default)]
+pub struct OutboxMetrics {
+    delivered: AtomicU64,
+    failed_attempts: AtomicU64,
+    dead_lettered: AtomicU64,
+    last_lag_ms: AtomicU64,
+    max_lag_ms: AtomicU64,
+}
+
+/// Point-in-time view of `OutboxMetrics`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxMetricsSnapshot {
+    pub delivered: u64,
+    pub failed_attempts: u64,
+    pub dead_lettered: u64,
+    pub last_delivery_lag_ms: u64,
+    pub max_delivery_lag_ms: u64,
+}
+
+impl OutboxMetrics {
+    fn record_delivery(&self, lag_ms: u64) {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+        self.last_lag_ms.store(lag_ms, Ordering::Relaxed);
+        self.max_lag_ms.fetch_max(lag_ms, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> OutboxMetricsSnapshot {
+        OutboxMetricsSnapshot {
+            delivered: self.delivered.load(Ordering::Relaxed),
+            failed_attempts: self.failed_attempts.load(Ordering::Relaxed),
+            dead_lettered: self.dead_lettered.load(Ordering::Relaxed),
+            last_delivery_lag_ms: self.last_lag_ms.load(Ordering::Relaxed),
+            max_delivery_lag_ms: self.max_lag_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Handle shared with handlers: mirrors committed events to in-process
+/// subscribers (SSE) and wakes the dispatcher so delivery starts immediately.
+#[derive(Clone)]
+pub struct Outbox {
+    wake: Arc<watch::Sender<()>>,
+    local: mpsc::UnboundedSender<RealtimeEvent>,
+    pub metrics: Arc<OutboxMetrics>,
+}
+
+impl Outbox {
+    /// Needs a Tokio runtime: one task hands events to `local` in commit order
+    pub fn new(local: Arc<dyn RealtimePublisher>) -> Self {
+        let (wake, _) = watch::channel(());
+        let (sender, mut receiver) = mpsc::unbounded_channel::<RealtimeEvent>();
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                if let Err(e) = local.publish(&event.channel, &event.name, &event.data).await {
+                    tracing::warn!("Local fan-out of {} failed: {}", event.name, e);
+                }
+            }
+        });
+        Self {
+            wake: Arc::new(wake),
+            local: sender,
+            metrics: Arc::new(OutboxMetrics::default()),
+        }
+    }
+
+    /// Call after the transaction that enqueued `events` has committed
+    pub fn committed(&self, events: Vec<RealtimeEvent>) {
+        for event in events {
+            let _ = self.local.send(event);
+        }
+        self.wake.send_replace(());
+    }
+
+    fn wake_receiver(&self) -> watch::Receiver<()> {
+        self.wake.subscribe()
+    }
+}
+
+/// Write an event to the outbox as part of the caller's transaction.
+/// Events on a public session channel are stamped with the next session
+/// sequence number (`seq` in the payload); the session row stays locked
+/// until the transaction ends, so numbers are assigned in commit order.
+pub async fn enqueue(conn: &mut MySqlConnection, event: &mut RealtimeEvent) -> Result<()> {
+    let session_id = session_id_from_channel(&event.channel).map(|id| id.to_string());
+
+    let seq = match &session_id {
+        Some(session_id) => {
+            sqlx::query("UPDATE sessions SET event_seq = event_seq + 1 WHERE id = ?")
+                .bind(session_id)
+                .execute(&mut *conn)
+                .await?;
+            let seq: i64 = sqlx::query_scalar("SELECT event_seq FROM sessions WHERE id = ?")
+                .bind(session_id)
+                .fetch_one(&mut *conn)
+                .await?;
+
+            if let Some(data) = event.data.as_object_mut() {
+                data.insert("seq".to_string(), seq.into());
+            }
+            Some(seq)
+        }
+        None => None,
+    };
+
+    sqlx::query(
+        "INSERT INTO realtime_outbox (channel, event_name, payload, session_id, seq) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&event.channel)
+    .bind(&event.name)
+    .bind(sqlx::types::Json(&event.data))
+    .bind(&session_id)
+    .bind(seq)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Counts of undelivered rows for monitoring
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxBacklog {
+    pub pending: i64,
+    pub dead: i64,
+    pub oldest_pending_at: Option<DateTime<Utc>>,
+}
+
+pub async fn backlog(pool: &DbPool) -> Result<OutboxBacklog> {
+    let backlog = sqlx::query_as::<_, OutboxBacklog>(
+        "SELECT
+            CAST(COALESCE(SUM(status = 'pending'), 0) AS SIGNED) as pending,
+            CAST(COALESCE(SUM(status = 'dead'), 0) AS SIGNED) as dead,
+            MIN(CASE WHEN status = 'pending' THEN created_at END) as oldest_pending_at
+         FROM realtime_outbox
+         WHERE status IN ('pending', 'dead')"
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(backlog)
+}
+
+#[derive(Debug, FromRow)]
+struct OutboxRow {
+    id: i64,
+    channel: String,
+    event_name: String,
+    payload: sqlx::types::Json<serde_json::Value>,
+    attempts: i32,
+    created_at: DateTime<Utc>,
+}
+
+/// Background task delivering outbox rows through the real-time publisher.
+/// Only the oldest pending row of each channel is eligible, so a channel
+/// stays ordered while it is backing off; other channels keep flowing.
+/// Every backend instance runs one. A dispatcher leases the heads it
+/// delivers, and a channel whose head is leased waits, so instances neither
+/// publish a row twice nor overtake each other within a channel.
+pub struct OutboxDispatcher {
+    pool: LazyDbPool,
+    outbox: Outbox,
+    publisher: Arc<dyn RealtimePublisher>,
+    settings: OutboxSettings,
+}
+
+impl OutboxDispatcher {
+    pub fn new(
+        pool: LazyDbPool,
+        outbox: Outbox,
+        publisher: Arc<dyn RealtimePublisher>,
+        settings: OutboxSettings,
+    ) -> Self {
+        Self { pool, outbox, publisher, settings }
+    }
+
+    /// Start the delivery loop in the background
+    pub fn spawn(self) {
+        tokio::spawn(async move {
+            let pool = match self.pool.get_or_wait().await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    tracing::error!("Outbox dispatcher not started: {}", e);
+                    return;
+                }
+            };
+            tracing::info!("Outbox dispatcher started");
+
+            let mut wake = self.outbox.wake_receiver();
+            let mut last_cleanup = std::time::Instant::now();
+
+            loop {
+                match self.deliver_ready(&pool).await {
+                    // More heads may have become eligible; go again right away
+                    Ok(delivered) if delivered > 0 => continue,
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Outbox delivery round failed: {}", e),
+                }
+
+                if last_cleanup.elapsed() >= CLEANUP_INTERVAL {
+                    if let Err(e) = self.purge_delivered(&pool).await {
+                        tracing::error!("Outbox cleanup failed: {}", e);
+                    }
+                    last_cleanup = std::time::Instant::now();
+                }
+
+                tokio::select! {
+                    _ = wake.changed() => {}
+                    _ = tokio::time::sleep(self.settings.poll_interval) => {}
+                }
+            }
+        });
+    }
+
+    /// Lease the ready head of every channel that no other dispatcher holds.
+    /// Rows another instance is claiming at the same moment are skipped.
+    async fn claim_ready(&self, pool: &DbPool) -> Result<Vec<OutboxRow>> {
+        let mut tx = pool.begin().await?;
+        let heads = sqlx::query_as::<_, OutboxRow>(
+            "SELECT o.id, o.channel, o.event_name, o.payload, o.attempts, o.created_at
+             FROM realtime_outbox o
+             JOIN (
+                SELECT channel, MIN(id) as head_id
+                FROM realtime_outbox
+                WHERE status = 'pending'
+                GROUP BY channel
+             ) h ON h.head_id = o.id
+             WHERE o.next_attempt_at <= NOW(3)
+               AND (o.lease_expires_at IS NULL OR o.lease_expires_at <= NOW(3))
+             ORDER BY o.id
+             LIMIT ?
+             FOR UPDATE SKIP LOCKED"
+        )
+        .bind(DELIVERY_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !heads.is_empty() {
+            let mut lease = sqlx::QueryBuilder::<sqlx::MySql>::new(
+                "UPDATE realtime_outbox SET lease_expires_at = NOW(3) + INTERVAL ",
+            );
+            lease.push_bind(DELIVERY_LEASE_SECS).push(" SECOND WHERE id IN (");
+            let mut ids = lease.separated(", ");
+            for row in &heads {
+                ids.push_bind(row.id);
+            }
+            ids.push_unseparated(")");
+            lease.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(heads)
+    }
+
+    /// Deliver the ready head of every channel; returns how many succeeded
+    async fn deliver_ready(&self, pool: &DbPool) -> Result<usize> {
+        let heads = self.claim_ready(pool).await?;
+
+        let results = futures::future::join_all(
+            heads.into_iter().map(|row| self.deliver(pool, row)),
+        )
+        .await;
+
+        let mut delivered = 0;
+        for result in results {
+            match result {
+                Ok(true) => delivered += 1,
+                Ok(false) => {}
+                Err(e) => tracing::error!("Failed to update outbox row: {}", e),
+            }
+        }
+        Ok(delivered)
+    }
+
+    async fn deliver(&self, pool: &DbPool, row: OutboxRow) -> Result<bool> {
+        let metrics = &self.outbox.metrics;
+
+        match self.publisher.publish(&row.channel, &row.event_name, &row.payload.0).await {
+            Ok(()) => {
+                sqlx::query(
+                    "UPDATE realtime_outbox
+                     SET status = 'delivered', delivered_at = NOW(3), attempts = attempts + 1, lease_expires_at = NULL
+                     WHERE id = ?"
+                )
+                .bind(row.id)
+                .execute(pool)
+                .await?;
+
+                let lag_ms = (Utc::now() - row.created_at).num_milliseconds().max(0) as u64;
+                metrics.record_delivery(lag_ms);
+                Ok(true)
+            }
+            Err(e) => {
+                metrics.failed_attempts.fetch_add(1, Ordering::Relaxed);
+                let attempts = row.attempts + 1;
+
+                if attempts >= self.settings.max_attempts {
+                    tracing::error!(
+                        "Dead-lettering outbox event {} ({} on {}) after {} attempts: {}",
+                        row.id, row.event_name, row.channel, attempts, e
+                    );
+                    metrics.dead_lettered.fetch_add(1, Ordering::Relaxed);
+                    sqlx::query(
+                        "UPDATE realtime_outbox SET status = 'dead', attempts = ?, last_error = ?, lease_expires_at = NULL WHERE id = ?"
+                    )
+                    .bind(attempts)
+                    .bind(&e)
+                    .bind(row.id)
+                    .execute(pool)
+                    .await?;
+                } else {
+                    let delay = retry_delay(attempts);
+                    tracing::warn!(
+                        "Outbox event {} ({} on {}) failed (attempt {}), retrying in {:?}: {}",
+                        row.id, row.event_name, row.channel, attempts, delay, e
+                    );
+                    let next_attempt_at = Utc::now()
+                        + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::seconds(1));
+                    sqlx::query(
+                        "UPDATE realtime_outbox
+                         SET attempts = ?, last_error = ?, next_attempt_at = ?, lease_expires_at = NULL
+                         WHERE id = ?"
+                    )
+                    .bind(attempts)
+                    .bind(&e)
+                    .bind(next_attempt_at)
+                    .bind(row.id)
+                    .execute(pool)
+                    .await?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    async fn purge_delivered(&self, pool: &DbPool) -> Result<()> {
+        let cutoff = Utc::now()
+            - chrono::Duration::from_std(self.settings.retention).unwrap_or_else(|_| chrono::Duration::days(1));
+        let result = sqlx::query(
+            "DELETE FROM realtime_outbox WHERE status = 'delivered' AND delivered_at < ?"
+        )
+        .bind(cutoff)
+        .execute(pool)
+        .await?;
+
+        // Keep each session's event log bounded to the replay capacity
+        let trimmed = sqlx::query(
+            "DELETE o FROM realtime_outbox o
+             JOIN sessions s ON s.id = o.session_id
+             WHERE o.status = 'delivered' AND o.seq <= s.event_seq - ?"
+        )
+        .bind(self.settings.event_log_capacity)
+        .execute(pool)
+        .await?;
+
+        let purged = result.rows_affected() + trimmed.rows_affected();
+        if purged > 0 {
+            tracing::info!("Purged {} delivered outbox events", purged);
+        }
+        Ok(())
+    }
+}
+
+/// Exponential backoff: 0.5s, 1s, 2s, ... capped at MAX_RETRY_DELAY
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(exponent))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::realtime::InMemoryPublisher;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn committed_events_reach_local_subscribers_in_order() {
+        let local = Arc::new(InMemoryPublisher::new());
+        let outbox = Outbox::new(local.clone());
+        let mut wake = outbox.wake_receiver();
+
+        outbox.committed(vec![
+            RealtimeEvent { channel: "session:a".into(), name: "QUESTION_ADDED".into(), data: json!({ "seq": 1 }) },
+            RealtimeEvent { channel: "session:a".into(), name: "QA_UPDATE".into(), data: json!({ "seq": 2 }) },
+        ]);
+        assert!(wake.has_changed().unwrap(), "dispatcher not woken");
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while local.events().len() < 2 && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let names: Vec<_> = local.events_for("session:a").into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["QUESTION_ADDED", "QA_UPDATE"]);
+        let _ = wake.borrow_and_update();
+    }
+
+    #[tokio::test]
+    async fn separate_commits_reach_local_subscribers_in_commit_order() {
+        let local = Arc::new(InMemoryPublisher::new());
+        let outbox = Outbox::new(local.clone());
+
+        for seq in 1..=50 {
+            outbox.committed(vec![
+                RealtimeEvent { channel: "session:a".into(), name: "VOTE_UPDATE".into(), data: json!({ "seq": seq }) },
+            ]);
+        }
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while local.events().len() < 50 && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let seqs: Vec<i64> = local.events().iter().map(|e| e.data["seq"].as_i64().unwrap()).collect();
+        assert_eq!(seqs, (1..=50).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,32 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn participant_mac(secret: &str, session_id: &str, participant_id: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(b"participant:");
+    mac.update(session_id.as_bytes());
+    mac.update(b":");
+    mac.update(participant_id.as_bytes());
+    mac
+}
+
+/// Sign a participant id for a session.
+/// The token proves the server issued the id to this browser; it is
+/// required for student Ably tokens and to re-register a claimed id.
+pub fn sign_token(secret: &str, session_id: &str, participant_id: &str) -> String {
+    let tag = participant_mac(secret, session_id, participant_id).finalize().into_bytes();
+    base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, tag)
+}
+
+/// Check a participant token in constant time
+pub fn verify_token(secret: &str, session_id: &str, participant_id: &str, token: &str) -> bool {
+    let Ok(tag) = base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, token) else {
+        return false;
+    };
+    participant_mac(secret, session_id, participant_id)
+        .verify_slice(&tag)
+        .is_ok()
+}
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::user::Role;
+use crate::services::secrets;
+
+/// Marks a bearer credential as a personal access token rather than a JWT
+pub const TOKEN_PREFIX: &str = "ccp_";
+/// Characters of the token kept in clear so users can recognise it
+const DISPLAY_PREFIX_LEN: usize = 12;
+/// Active tokens a user may hold
+const MAX_TOKENS_PER_USER: i64 = 50;
+/// `last_used_at` is refreshed at most this often per token
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// What a personal access token may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenScope {
+    /// List and read sessions and their slides
+    #[serde(rename = "sessions:read")]
+    SessionsRead,
+    /// Create, edit, run and delete sessions and slides
+    #[serde(rename = "sessions:write")]
+    SessionsWrite,
+    /// Session statistics and grade sync reports
+    #[serde(rename = "stats:read")]
+    StatsRead,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::SessionsRead => "sessions:read",
+            TokenScope::SessionsWrite => "sessions:write",
+            TokenScope::StatsRead => "stats:read",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sessions:read" => Some(TokenScope::SessionsRead),
+            "sessions:write" => Some(TokenScope::SessionsWrite),
+            "stats:read" => Some(TokenScope::StatsRead),
+            _ => None,
+        }
+    }
+}
+
+fn parse_scopes(stored: &str) -> Vec<TokenScope> {
+    stored.split_whitespace().filter_map(TokenScope::parse).collect()
+}
+
+/// A token as shown to its owner (never includes the secret)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalToken {
+    pub id: String,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+struct TokenRow {
+    id: String,
+    name: String,
+    token_prefix: String,
+    scopes: String,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<TokenRow> for PersonalToken {
+    fn from(row: TokenRow) -> Self {
+        PersonalToken {
+            scopes: parse_scopes(&row.scopes),
+            id: row.id,
+            name: row.name,
+            token_prefix: row.token_prefix,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+            expires_at: row.expires_at,
+        }
+    }
+}
+
+/// Create a token; the secret is returned once and only its hash is kept
+pub async fn issue(
+    pool: &DbPool,
+    user_id: &str,
+    name: &str,
+    scopes: &[TokenScope],
+    ttl: Option<Duration>,
+) -> Result<(PersonalToken, String)> {
+    let active: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM personal_access_tokens
+         WHERE user_id = ? AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW(3))"
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    if active >= MAX_TOKENS_PER_USER {
+        return Err(AppError::Input(format!("At most {} active tokens are allowed", MAX_TOKENS_PER_USER)));
+    }
+
+    let secret = format!("{}{}", TOKEN_PREFIX, secrets::random_token());
+    let id = Uuid::new_v4().to_string();
+    let scope_list = scopes.iter().map(TokenScope::as_str).collect::<Vec<_>>().join(" ");
+
+    sqlx::query(
+        "INSERT INTO personal_access_tokens (id, user_id, name, token_hash, token_prefix, scopes, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(name)
+    .bind(secrets::sha256_hex(&secret))
+    .bind(&secret[..DISPLAY_PREFIX_LEN])
+    .bind(&scope_list)
+    .bind(ttl.map(|ttl| Utc::now() + ttl))
+    .execute(pool)
+    .await?;
+
+    let row = sqlx::query_as::<_, TokenRow>(
+        "SELECT id, name, token_prefix, scopes, created_at, last_used_at, expires_at
+         FROM personal_access_tokens WHERE id = ?"
+    )
+    .bind(&id)
+    .fetch_one(pool)
+    .await?;
+    Ok((row.into(), secret))
+}
+
+/// Active tokens of a user, newest first
+pub async fn list(pool: &DbPool, user_id: &str) -> Result<Vec<PersonalToken>> {
+    let rows = sqlx::query_as::<_, TokenRow>(
+        "SELECT id, name, token_prefix, scopes, created_at, last_used_at, expires_at
+         FROM personal_access_tokens
+         WHERE user_id = ? AND revoked_at IS NULL
+         ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(PersonalToken::from).collect())
+}
+
+/// Revoke one of the user's tokens; false if it does not exist
+pub async fn revoke(pool: &DbPool, user_id: &str, token_id: &str) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE personal_access_tokens SET revoked_at = NOW(3)
+         WHERE id = ? AND user_id = ? AND revoked_at IS NULL"
+    )
+    .bind(token_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Owner and grants of a valid token
+pub struct TokenGrant {
+    pub user_id: String,
+    pub role: Role,
+    pub scopes: Vec<TokenScope>,
+}
+
+#[derive(FromRow)]
+struct GrantRow {
+    id: String,
+    user_id: String,
+    role: String,
+    scopes: String,
+}
+
+/// Resolve a presented token. The owner's current role applies, so a
+/// demoted account cannot keep teacher access through an old token.
+pub async fn authenticate(pool: &DbPool, token: &str) -> Result<TokenGrant> {
+    let row = sqlx::query_as::<_, GrantRow>(
+        "SELECT t.id, t.user_id, u.role, t.scopes
+         FROM personal_access_tokens t
+         JOIN users u ON u.id = t.user_id
+         WHERE t.token_hash = ? AND t.revoked_at IS NULL
+           AND (t.expires_at IS NULL OR t.expires_at > NOW(3))"
+    )
+    .bind(secrets::sha256_hex(token))
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::Auth("Invalid or revoked API token".to_string()))?;
+
+    sqlx::query(
+        "UPDATE personal_access_tokens SET last_used_at = NOW(3)
+         WHERE id = ? AND (last_used_at IS NULL OR last_used_at < NOW(3) - INTERVAL ? SECOND)"
+    )
+    .bind(&row.id)
+    .bind(LAST_USED_RESOLUTION_SECS)
+    .execute(pool)
+    .await?;
+
+    Ok(TokenGrant {
+        user_id: row.user_id,
+        role: Role::parse(&row.role).unwrap_or(Role::Student),
+        scopes: parse_scopes(&row.scopes),
+    })
+}
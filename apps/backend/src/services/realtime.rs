@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+use crate::config::Config;
+use crate::services::ably::AblyPublisher;
+
+/// Transport used to push real-time events to connected clients.
+/// Handlers never talk to a concrete backend directly; they go through
+/// the publisher held in `AppState` so it can be swapped or recorded.
+#[async_trait]
+pub trait RealtimePublisher: Send + Sync {
+    /// Publish a single named event to a channel
+    async fn publish(&self, channel: &str, event_name: &str, data: &Value) -> Result<(), String>;
+}
+
+/// A named event addressed to a channel
+#[derive(Debug, Clone)]
+pub struct RealtimeEvent {
+    pub channel: String,
+    pub name: String,
+    pub data: Value,
+}
+
+/// An event captured by `InMemoryPublisher`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublishedEvent {
+    pub channel: String,
+    pub name: String,
+    pub data: Value,
+}
+
+/// Publisher that records every event in memory instead of sending it.
+/// Used by tests and local runs to assert exactly what was emitted.
+#[derive(Default)]
+pub struct InMemoryPublisher {
+    events: Mutex<Vec<PublishedEvent>>,
+}
+
+impl InMemoryPublisher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+impl InMemoryPublisher {
+    /// All events published so far, in order
+    pub fn events(&self) -> Vec<PublishedEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Events published to a single channel, in order
+    pub fn events_for(&self, channel: &str) -> Vec<PublishedEvent> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.channel == channel)
+            .cloned()
+            .collect()
+    }
+
+    /// Forget every recorded event
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl RealtimePublisher for InMemoryPublisher {
+    async fn publish(&self, channel: &str, event_name: &str, data: &Value) -> Result<(), String> {
+        tracing::debug!("Recording {} on channel {}", event_name, channel);
+        self.events.lock().unwrap().push(PublishedEvent {
+            channel: channel.to_string(),
+            name: event_name.to_string(),
+            data: data.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// Publisher that drops every event (real-time disabled)
+pub struct NoopPublisher;
+
+#[async_trait]
+impl RealtimePublisher for NoopPublisher {
+    async fn publish(&self, channel: &str, event_name: &str, _data: &Value) -> Result<(), String> {
+        tracing::debug!("Real-time disabled, dropping {} for channel {}", event_name, channel);
+        Ok(())
+    }
+}
+
+/// Build the publisher selected by `REALTIME_BACKEND`.
+/// Falls back to the no-op publisher when Ably is selected without a key.
+pub fn publisher_from_config(config: &Config) -> Arc<dyn RealtimePublisher> {
+    match config.realtime_backend.as_str() {
+        "memory" => {
+            tracing::info!("Using in-memory real-time publisher");
+            Arc::new(InMemoryPublisher::new())
+        }
+        "noop" => {
+            tracing::info!("Real-time publishing disabled");
+            Arc::new(NoopPublisher)
+        }
+        other => {
+            if other != "ably" {
+                tracing::warn!("Unknown REALTIME_BACKEND '{}', using Ably", other);
+            }
+            let Some(api_key) = config.ably_api_key.as_deref() else {
+                tracing::warn!("ABLY_API_KEY not set, skipping real-time publish");
+                return Arc::new(NoopPublisher);
+            };
+            match AblyPublisher::new(api_key, &config.ably_rest_url) {
+                Ok(publisher) => Arc::new(publisher),
+                Err(e) => {
+                    tracing::error!("{}", e);
+                    Arc::new(NoopPublisher)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn in_memory_publisher_records_events_in_order() {
+        let publisher = InMemoryPublisher::new();
+        publisher.publish("session:a", "STATE_UPDATE", &json!({ "n": 1 })).await.unwrap();
+        publisher.publish("session:b", "VOTE_UPDATE", &json!({ "n": 2 })).await.unwrap();
+        publisher.publish("session:a", "QA_UPDATE", &json!({ "n": 3 })).await.unwrap();
+
+        let names: Vec<_> = publisher.events().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["STATE_UPDATE", "VOTE_UPDATE", "QA_UPDATE"]);
+        assert_eq!(
+            publisher.events_for("session:a"),
+            [
+                PublishedEvent { channel: "session:a".into(), name: "STATE_UPDATE".into(), data: json!({ "n": 1 }) },
+                PublishedEvent { channel: "session:a".into(), name: "QA_UPDATE".into(), data: json!({ "n": 3 }) },
+            ]
+        );
+
+        publisher.clear();
+        assert!(publisher.events().is_empty());
+    }
+}
@@ -2,8 +2,9 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
-use crate::models::session::Session;
+use crate::models::session::{Session, SessionRole};
 use crate::repositories::session::{NewSession, SessionRepository, SessionUpdates};
+use crate::services::session_access;
 
 // Input validation constants
 const MAX_TITLE_LENGTH: usize = 200;
@@ -20,46 +21,60 @@ impl SessionService {
         Self { repository }
     }
 
-    /// Get all sessions for a user
+    /// Get all sessions a user created or collaborates on
     pub async fn get_user_sessions(&self, user_id: &str) -> Result<Vec<Session>> {
-        self.repository.find_by_creator(user_id).await
+        self.repository.find_by_member(user_id).await
     }
 
-    /// Get all sessions for a user with slide counts
+    /// Get all sessions for a user with slide counts and the user's role
     pub async fn get_user_sessions_with_slide_count(&self, user_id: &str) -> Result<Vec<crate::models::session::SessionWithSlideCount>> {
-        let sessions_with_counts = self.repository.find_by_creator_with_slide_count(user_id).await?;
+        let sessions_with_counts = self.repository.find_by_member_with_slide_count(user_id).await?;
         
         let result = sessions_with_counts
             .into_iter()
-            .map(|(session, slide_count)| crate::models::session::SessionWithSlideCount {
+            .map(|(session, slide_count, role)| crate::models::session::SessionWithSlideCount {
                 session,
                 slide_count,
+                role,
             })
             .collect();
         
         Ok(result)
     }
 
+    /// Get an organization's sessions with slide counts and the user's role
+    pub async fn get_organization_sessions(&self, organization_id: &str, user_id: &str) -> Result<Vec<crate::models::session::SessionWithSlideCount>> {
+        let sessions = self.repository
+            .find_by_organization_with_slide_count(organization_id, user_id)
+            .await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|(session, slide_count, role)| crate::models::session::SessionWithSlideCount {
+                session,
+                slide_count,
+                role,
+            })
+            .collect())
+    }
+
     /// Get a specific session by ID
-    /// Validates ownership
+    /// Business Rule: any collaborator may view it
     pub async fn get_session(&self, session_id: &str, user_id: &str) -> Result<Session> {
-        let session = self.repository
+        self.require_role(session_id, user_id, SessionRole::Viewer).await?;
+
+        self.repository
             .find_by_id(session_id)
             .await?
-            .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
-
-        if session.creator_id != user_id {
-            return Err(AppError::Auth("Unauthorized access to session".to_string()));
-        }
-
-        Ok(session)
+            .ok_or_else(|| AppError::NotFound("Session not found".to_string()))
     }
 
-    /// Create a new session
+    /// Create a new session, optionally in an organization's library
     /// Business Rule: Title must be non-empty and within MAX_TITLE_LENGTH
     pub async fn create_session(
         &self,
         user_id: &str,
+        organization_id: Option<&str>,
         title: &str,
         allow_questions: bool,
         require_name: bool,
@@ -83,6 +98,7 @@ impl SessionService {
         let new_session = NewSession {
             id,
             creator_id: user_id.to_string(),
+            organization_id: organization_id.map(str::to_string),
             title: title.to_string(),
             share_token,
             allow_questions,
@@ -93,7 +109,7 @@ impl SessionService {
     }
 
     /// Update a session
-    /// Business Rule: Must verify ownership before update
+    /// Business Rule: Must be an editor
     pub async fn update_session(
         &self,
         session_id: &str,
@@ -102,8 +118,7 @@ impl SessionService {
         allow_questions: Option<bool>,
         require_name: Option<bool>,
     ) -> Result<Session> {
-        // Verify ownership (Business Rule)
-        self.verify_ownership(session_id, user_id).await?;
+        self.require_role(session_id, user_id, SessionRole::Editor).await?;
 
         // Validate title if provided
         if let Some(ref t) = title {
@@ -130,9 +145,9 @@ impl SessionService {
     }
 
     /// Delete a session
-    /// Business Rule: Must verify ownership before deletion
+    /// Business Rule: Must be an owner
     pub async fn delete_session(&self, session_id: &str, user_id: &str) -> Result<()> {
-        self.verify_ownership(session_id, user_id).await?;
+        self.require_role(session_id, user_id, SessionRole::Owner).await?;
 
         let rows_affected = self.repository.delete(session_id).await?;
 
@@ -144,9 +159,9 @@ impl SessionService {
     }
 
     /// Duplicate a session
-    /// Business Rule: Must verify ownership of original session
+    /// Business Rule: Any collaborator may copy it; the copy is theirs alone
     pub async fn duplicate_session(&self, session_id: &str, user_id: &str) -> Result<Session> {
-        self.verify_ownership(session_id, user_id).await?;
+        self.require_role(session_id, user_id, SessionRole::Viewer).await?;
 
         let original = self.repository
             .find_by_id(session_id)
@@ -160,6 +175,7 @@ impl SessionService {
         let new_session = NewSession {
             id: new_id,
             creator_id: user_id.to_string(),
+            organization_id: None,
             title: new_title,
             share_token: new_share_token,
             allow_questions: original.allow_questions,
@@ -169,9 +185,9 @@ impl SessionService {
         self.repository.create(&new_session).await
     }
 
-    /// Archive a session
+    /// Archive a session (owners only)
     pub async fn archive_session(&self, session_id: &str, user_id: &str) -> Result<Session> {
-        self.verify_ownership(session_id, user_id).await?;
+        self.require_role(session_id, user_id, SessionRole::Owner).await?;
 
         let updates = SessionUpdates {
             title: None,
@@ -183,9 +199,9 @@ impl SessionService {
         self.repository.update(session_id, &updates).await
     }
 
-    /// Restore a session
+    /// Restore a session (owners only)
     pub async fn restore_session(&self, session_id: &str, user_id: &str) -> Result<Session> {
-        self.verify_ownership(session_id, user_id).await?;
+        self.require_role(session_id, user_id, SessionRole::Owner).await?;
 
         let updates = SessionUpdates {
             title: None,
@@ -197,16 +213,19 @@ impl SessionService {
         self.repository.update(session_id, &updates).await
     }
 
-    /// Helper: Verify ownership
-    /// Business Rule: Only the creator can modify a session
-    async fn verify_ownership(&self, session_id: &str, user_id: &str) -> Result<()> {
-        let is_owner = self.repository.verify_ownership(session_id, user_id).await?;
-
-        if !is_owner {
-            return Err(AppError::Auth("Unauthorized access to session".to_string()));
-        }
+    /// Helper: Check the user's role on the session
+    /// Business Rule: see `session_access` for what each role may do
+    async fn require_role(&self, session_id: &str, user_id: &str, min: SessionRole) -> Result<SessionRole> {
+        let role = self.repository.find_role(session_id, user_id).await?;
+        session_access::authorize(role, min)
+    }
 
-        Ok(())
+    /// Get a session without checking ownership (callers authorize themselves)
+    pub async fn find_session(&self, session_id: &str) -> Result<Session> {
+        self.repository
+            .find_by_id(session_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Session not found".to_string()))
     }
 
     /// Get public session data by share token
@@ -227,7 +246,7 @@ impl SessionService {
         for (slide_id, option_id, count) in vote_counts_raw {
             vote_map
                 .entry(slide_id)
-                .or_insert_with(std::collections::HashMap::new)
+                .or_default()
                 .insert(option_id, count as i32);
         }
 
@@ -248,7 +267,11 @@ impl SessionService {
     }
 
     /// Get session state for real-time sync
+    /// Business Rule: the sequence number is read first, so replaying events
+    /// after it can only repeat changes already included, never miss one
     pub async fn get_session_state(&self, session_id: &str) -> Result<crate::models::session::SessionState> {
+        let seq = self.repository.get_event_seq(session_id).await?
+             .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
         let session = self.repository.find_by_id(session_id).await?
              .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
 
@@ -263,11 +286,12 @@ impl SessionService {
         for (slide_id, option_id, count) in vote_counts_raw {
             vote_counts
                 .entry(slide_id)
-                .or_insert_with(std::collections::HashMap::new)
+                .or_default()
                 .insert(option_id, count as i32);
         }
 
         Ok(crate::models::session::SessionState {
+            seq,
             current_slide_id: session.current_slide_id,
             is_presentation_active: session.is_presentation_active,
             is_results_visible: session.is_results_visible,
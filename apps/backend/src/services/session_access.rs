@@ -0,0 +1,125 @@
+//! Who may do what on a session. Every staff route checks the caller's
+//! session role against the minimum the route needs.
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::organization::OrgRole;
+use crate::models::session::SessionRole;
+
+/// The caller's role on a session, or None without access. Fails with
+/// NotFound if the session does not exist.
+pub async fn role_of(pool: &DbPool, session_id: &str, user_id: &str) -> Result<Option<SessionRole>> {
+    let row: Option<(String, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT s.creator_id, m.role, om.role FROM sessions s
+         LEFT JOIN session_members m ON m.session_id = s.id AND m.user_id = ?
+         LEFT JOIN organization_members om ON om.organization_id = s.organization_id AND om.user_id = ?
+         WHERE s.id = ?"
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let (creator_id, member_role, org_role) =
+        row.ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+    Ok(effective_role(user_id, &creator_id, member_role.as_deref(), org_role.as_deref()))
+}
+
+/// Combine the ways a user can hold a session: creating it (owner), being
+/// invited to it, or belonging to its organization (admins are owners,
+/// members are viewers). The highest role wins.
+pub fn effective_role(
+    user_id: &str,
+    creator_id: &str,
+    member_role: Option<&str>,
+    org_role: Option<&str>,
+) -> Option<SessionRole> {
+    if creator_id == user_id {
+        return Some(SessionRole::Owner);
+    }
+    let from_org = org_role.and_then(OrgRole::parse).map(|role| match role {
+        OrgRole::Admin => SessionRole::Owner,
+        OrgRole::Member => SessionRole::Viewer,
+    });
+    member_role.and_then(SessionRole::parse).max(from_org)
+}
+
+/// Allow a role if it is at least `min`
+pub fn authorize(role: Option<SessionRole>, min: SessionRole) -> Result<SessionRole> {
+    match role {
+        Some(role) if role >= min => Ok(role),
+        Some(_) => Err(AppError::Forbidden(format!(
+            "This requires the {} role on the session",
+            min.as_str()
+        ))),
+        None => Err(AppError::Auth("Unauthorized access to session".to_string())),
+    }
+}
+
+/// Check that the user has at least `min` on the session; returns their role
+pub async fn require(pool: &DbPool, session_id: &str, user_id: &str, min: SessionRole) -> Result<SessionRole> {
+    authorize(role_of(pool, session_id, user_id).await?, min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::Role;
+    use crate::test_support::{add_member, app, create_session, create_user};
+
+    #[test]
+    fn roles_are_ordered_by_privilege() {
+        assert!(SessionRole::Viewer < SessionRole::Presenter);
+        assert!(SessionRole::Presenter < SessionRole::Editor);
+        assert!(SessionRole::Editor < SessionRole::Owner);
+        assert_eq!(SessionRole::parse(" Editor "), Some(SessionRole::Editor));
+        assert_eq!(SessionRole::parse("admin"), None);
+    }
+
+    #[test]
+    fn the_creator_is_always_owner() {
+        assert_eq!(effective_role("u1", "u1", None, None), Some(SessionRole::Owner));
+        assert_eq!(effective_role("u1", "u1", Some("viewer"), Some("member")), Some(SessionRole::Owner));
+    }
+
+    #[test]
+    fn the_highest_of_membership_and_organization_role_wins() {
+        assert_eq!(effective_role("u1", "u2", None, None), None);
+        assert_eq!(effective_role("u1", "u2", Some("presenter"), None), Some(SessionRole::Presenter));
+        assert_eq!(effective_role("u1", "u2", None, Some("member")), Some(SessionRole::Viewer));
+        assert_eq!(effective_role("u1", "u2", None, Some("admin")), Some(SessionRole::Owner));
+        assert_eq!(effective_role("u1", "u2", Some("editor"), Some("member")), Some(SessionRole::Editor));
+        assert_eq!(effective_role("u1", "u2", Some("viewer"), Some("admin")), Some(SessionRole::Owner));
+        // Unknown stored roles grant nothing
+        assert_eq!(effective_role("u1", "u2", Some("superuser"), Some("guest")), None);
+    }
+
+    #[test]
+    fn authorize_tells_missing_access_from_a_too_low_role() {
+        assert!(matches!(authorize(None, SessionRole::Viewer), Err(AppError::Auth(_))));
+        assert!(matches!(authorize(Some(SessionRole::Viewer), SessionRole::Presenter), Err(AppError::Forbidden(_))));
+        assert_eq!(authorize(Some(SessionRole::Presenter), SessionRole::Presenter).unwrap(), SessionRole::Presenter);
+        assert_eq!(authorize(Some(SessionRole::Owner), SessionRole::Editor).unwrap(), SessionRole::Owner);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MySQL: set TEST_DATABASE_URL and run with --ignored"]
+    async fn roles_come_from_the_session_and_its_members() {
+        let app = app().await;
+        let owner = create_user(&app.pool, Role::Teacher).await;
+        let editor = create_user(&app.pool, Role::Teacher).await;
+        let stranger = create_user(&app.pool, Role::Teacher).await;
+        let session_id = create_session(&app.pool, &owner.user_id).await;
+        add_member(&app.pool, &session_id, &editor.user_id, SessionRole::Editor).await;
+
+        assert_eq!(role_of(&app.pool, &session_id, &owner.user_id).await.unwrap(), Some(SessionRole::Owner));
+        assert_eq!(role_of(&app.pool, &session_id, &editor.user_id).await.unwrap(), Some(SessionRole::Editor));
+        assert_eq!(role_of(&app.pool, &session_id, &stranger.user_id).await.unwrap(), None);
+        assert!(require(&app.pool, &session_id, &editor.user_id, SessionRole::Owner).await.is_err());
+        assert!(matches!(
+            role_of(&app.pool, "no-such-session", &owner.user_id).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::session::SessionRole;
+use crate::services::secrets;
+
+/// How long an emailed invitation can be accepted
+pub const INVITE_TTL_DAYS: i64 = 7;
+
+/// A collaborator as listed on the session
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionMember {
+    pub user_id: String,
+    pub name: String,
+    pub email: String,
+    pub role: SessionRole,
+    /// The creator cannot be removed
+    pub is_creator: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// An invitation that has not been accepted yet
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingInvite {
+    pub id: String,
+    pub email: String,
+    #[sqlx(try_from = "String")]
+    pub role: SessionRole,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+struct MemberRow {
+    user_id: String,
+    name: String,
+    email: String,
+    role: String,
+    is_creator: bool,
+    created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+struct InviteRow {
+    id: String,
+    session_id: String,
+    email: String,
+    role: String,
+}
+
+/// The creator (as owner) followed by everyone who accepted an invitation
+pub async fn list_members(pool: &DbPool, session_id: &str) -> Result<Vec<SessionMember>> {
+    let rows = sqlx::query_as::<_, MemberRow>(
+        "SELECT u.id AS user_id, u.name, u.email, 'owner' AS role, TRUE AS is_creator, s.created_at
+         FROM sessions s JOIN users u ON u.id = s.creator_id
+         WHERE s.id = ?
+         UNION ALL
+         SELECT u.id, u.name, u.email, m.role, FALSE, m.created_at
+         FROM session_members m JOIN users u ON u.id = m.user_id
+         WHERE m.session_id = ?
+         ORDER BY is_creator DESC, created_at"
+    )
+    .bind(session_id)
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(SessionMember {
+                role: SessionRole::parse(&row.role)?,
+                user_id: row.user_id,
+                name: row.name,
+                email: row.email,
+                is_creator: row.is_creator,
+                created_at: row.created_at,
+            })
+        })
+        .collect())
+}
+
+pub async fn list_invites(pool: &DbPool, session_id: &str) -> Result<Vec<PendingInvite>> {
+    Ok(sqlx::query_as::<_, PendingInvite>(
+        "SELECT id, email, role, expires_at, created_at FROM session_invites
+         WHERE session_id = ? AND accepted_at IS NULL AND expires_at > NOW(3)
+         ORDER BY created_at"
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Invite an email address to the session; returns the invitation and the
+/// token to email (only its hash is stored). A new invitation to the same
+/// address replaces the pending one.
+pub async fn invite(
+    pool: &DbPool,
+    session_id: &str,
+    email: &str,
+    role: SessionRole,
+    invited_by: &str,
+) -> Result<(PendingInvite, String)> {
+    let is_creator: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sessions s JOIN users u ON u.id = s.creator_id WHERE s.id = ? AND u.email = ?)"
+    )
+    .bind(session_id)
+    .bind(email)
+    .fetch_one(pool)
+    .await?;
+    if is_creator {
+        return Err(AppError::Input("The session creator is already an owner".to_string()));
+    }
+
+    let token = secrets::random_token();
+    let invite = PendingInvite {
+        id: Uuid::new_v4().to_string(),
+        email: email.to_string(),
+        role,
+        expires_at: Utc::now() + Duration::days(INVITE_TTL_DAYS),
+        created_at: Utc::now(),
+    };
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM session_invites WHERE session_id = ? AND email = ? AND accepted_at IS NULL")
+        .bind(session_id)
+        .bind(email)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO session_invites (id, session_id, email, role, token_hash, invited_by, expires_at, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&invite.id)
+    .bind(session_id)
+    .bind(email)
+    .bind(role.as_str())
+    .bind(secrets::sha256_hex(&token))
+    .bind(invited_by)
+    .bind(invite.expires_at)
+    .bind(invite.created_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((invite, token))
+}
+
+/// Join the session an invitation is for. Only the invited address may accept
+/// it; accepting again with a newer invitation changes the role.
+pub async fn accept(pool: &DbPool, token: &str, user_id: &str, user_email: &str) -> Result<(String, SessionRole)> {
+    let invalid = || AppError::Input("Invalid or expired invitation".to_string());
+
+    let invite = sqlx::query_as::<_, InviteRow>(
+        "SELECT id, session_id, email, role FROM session_invites
+         WHERE token_hash = ? AND accepted_at IS NULL AND expires_at > NOW(3)"
+    )
+    .bind(secrets::sha256_hex(token.trim()))
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(invalid)?;
+    if !invite.email.eq_ignore_ascii_case(user_email) {
+        return Err(AppError::Forbidden("This invitation was sent to another email address".to_string()));
+    }
+    let role = SessionRole::parse(&invite.role).ok_or_else(invalid)?;
+
+    let mut tx = pool.begin().await?;
+    let spent = sqlx::query("UPDATE session_invites SET accepted_at = NOW(3) WHERE id = ? AND accepted_at IS NULL")
+        .bind(&invite.id)
+        .execute(&mut *tx)
+        .await?;
+    if spent.rows_affected() != 1 {
+        return Err(invalid());
+    }
+    sqlx::query(
+        "INSERT INTO session_members (session_id, user_id, role, invited_by)
+         SELECT session_id, ?, role, invited_by FROM session_invites WHERE id = ?
+         ON DUPLICATE KEY UPDATE role = VALUES(role)"
+    )
+    .bind(user_id)
+    .bind(&invite.id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((invite.session_id, role))
+}
+
+/// Take a collaborator off the session
+pub async fn remove(pool: &DbPool, session_id: &str, user_id: &str) -> Result<()> {
+    let removed = sqlx::query("DELETE FROM session_members WHERE session_id = ? AND user_id = ?")
+        .bind(session_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    if removed.rows_affected() == 0 {
+        return Err(AppError::NotFound("Member not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Withdraw an invitation that has not been accepted
+pub async fn revoke_invite(pool: &DbPool, session_id: &str, invite_id: &str) -> Result<()> {
+    let revoked = sqlx::query("DELETE FROM session_invites WHERE id = ? AND session_id = ? AND accepted_at IS NULL")
+        .bind(invite_id)
+        .bind(session_id)
+        .execute(pool)
+        .await?;
+    if revoked.rows_affected() == 0 {
+        return Err(AppError::NotFound("Invitation not found".to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::db::{DbPool, LazyDbPool};
+use crate::services::ably::session_id_from_channel;
+use crate::services::event_log;
+use crate::services::realtime::RealtimePublisher;
+
+/// Capacity of each session's broadcast channel. Subscribers that fall
+/// further behind than this are disconnected and resume via Last-Event-ID.
+const BROADCAST_CAPACITY: usize = 256;
+/// Maximum number of logged events fanned out per tail query
+const TAIL_BATCH_SIZE: i64 = 500;
+
+/// A session event as delivered over Server-Sent Events
+#[derive(Debug, Clone)]
+pub struct SseEvent {
+    /// Session sequence number, used as the SSE event id
+    pub seq: i64,
+    pub name: String,
+    pub data: Value,
+}
+
+/// Per-session fan-out state
+struct SessionStream {
+    sender: broadcast::Sender<SseEvent>,
+    subscribers: Arc<AtomicUsize>,
+    /// Sequence number of the last event sent; only its successor goes next
+    last_seq: i64,
+}
+
+impl SessionStream {
+    fn new(last_seq: i64) -> Self {
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self {
+            sender,
+            subscribers: Arc::new(AtomicUsize::new(0)),
+            last_seq,
+        }
+    }
+}
+
+/// Why a subscription was refused
+#[derive(Debug)]
+pub enum SubscribeError {
+    TooManySubscribers,
+}
+
+/// Decrements the session's subscriber count when the stream is dropped
+pub struct SubscriberGuard {
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl Drop for SubscriberGuard {
+    fn drop(&mut self) {
+        self.subscribers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A live subscription to a session's events
+pub struct SseSubscription {
+    pub receiver: broadcast::Receiver<SseEvent>,
+    pub guard: SubscriberGuard,
+}
+
+/// In-process hub that fans session events out to SSE subscribers.
+/// Events are sent strictly in sequence order: one that doesn't directly
+/// follow the last sent is left to `SseTail`, which reads it from the event
+/// log. Missed events are replayed from the event log, not from the hub.
+pub struct SseHub {
+    sessions: Mutex<HashMap<String, SessionStream>>,
+    max_subscribers: usize,
+}
+
+impl SseHub {
+    pub fn new(max_subscribers: usize) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            max_subscribers,
+        }
+    }
+
+    /// Subscribe to live events for a session. `latest_seq` is the session's
+    /// latest sequence number, read before subscribing; a session nobody
+    /// listened to yet continues from there.
+    pub fn subscribe(&self, session_id: &str, latest_seq: i64) -> Result<SseSubscription, SubscribeError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let stream = sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| SessionStream::new(latest_seq));
+
+        let previous = stream.subscribers.fetch_add(1, Ordering::SeqCst);
+        let guard = SubscriberGuard {
+            subscribers: stream.subscribers.clone(),
+        };
+        if previous >= self.max_subscribers {
+            return Err(SubscribeError::TooManySubscribers);
+        }
+
+        Ok(SseSubscription {
+            receiver: stream.sender.subscribe(),
+            guard,
+        })
+    }
+
+    /// Fan an event out to the session's current subscribers if it directly
+    /// follows the last one sent; returns whether it was sent
+    pub fn broadcast(&self, session_id: &str, seq: i64, event_name: &str, data: &Value) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+
+        // Nobody listening: drop the idle entry instead of keeping it around
+        if let Some(stream) = sessions.get(session_id) {
+            if stream.subscribers.load(Ordering::SeqCst) == 0 {
+                sessions.remove(session_id);
+                return false;
+            }
+        }
+
+        match sessions.get_mut(session_id) {
+            Some(stream) if seq == stream.last_seq + 1 => {
+                stream.last_seq = seq;
+                let _ = stream.sender.send(SseEvent {
+                    seq,
+                    name: event_name.to_string(),
+                    data: data.clone(),
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sessions with subscribers and the last sequence number sent to them
+    fn cursors(&self) -> Vec<(String, i64)> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, stream| stream.subscribers.load(Ordering::SeqCst) > 0);
+        sessions
+            .iter()
+            .map(|(session_id, stream)| (session_id.clone(), stream.last_seq))
+            .collect()
+    }
+}
+
+/// Fast path for events committed on this instance; arrives through the
+/// outbox right after the commit
+#[async_trait]
+impl RealtimePublisher for SseHub {
+    async fn publish(&self, channel: &str, event_name: &str, data: &Value) -> Result<(), String> {
+        // Only the public session channel is mirrored over SSE
+        let seq = data.get("seq").and_then(|s| s.as_i64());
+        if let (Some(session_id), Some(seq)) = (session_id_from_channel(channel), seq) {
+            self.broadcast(session_id, seq, event_name, data);
+        }
+        Ok(())
+    }
+}
+
+/// Background task feeding the hub from the event log, so subscribers get
+/// events committed on every backend instance, and in order. Every instance
+/// runs one; it only reads sessions that have subscribers here.
+pub struct SseTail {
+    pool: LazyDbPool,
+    hub: Arc<SseHub>,
+    poll_interval: Duration,
+}
+
+impl SseTail {
+    pub fn new(pool: LazyDbPool, hub: Arc<SseHub>, poll_interval: Duration) -> Self {
+        Self { pool, hub, poll_interval }
+    }
+
+    /// Start the tail loop in the background
+    pub fn spawn(self) {
+        tokio::spawn(async move {
+            let pool = match self.pool.get_or_wait().await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    tracing::error!("SSE tail not started: {}", e);
+                    return;
+                }
+            };
+
+            loop {
+                match self.catch_up(&pool).await {
+                    // A full batch: more may be waiting
+                    Ok(read) if read as i64 >= TAIL_BATCH_SIZE => continue,
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("SSE tail round failed: {}", e),
+                }
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        });
+    }
+
+    /// Send logged events the hub hasn't sent yet; returns how many were read
+    async fn catch_up(&self, pool: &DbPool) -> crate::error::Result<usize> {
+        let cursors = self.hub.cursors();
+        let events = event_log::tail(pool, &cursors, TAIL_BATCH_SIZE).await?;
+        for (session_id, event) in &events {
+            self.hub.broadcast(session_id, event.seq, &event.name, &event.data);
+        }
+        Ok(events.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn subscribers_beyond_the_limit_are_refused_until_one_leaves() {
+        let hub = SseHub::new(2);
+        let first = hub.subscribe("s1", 0).unwrap();
+        let _second = hub.subscribe("s1", 0).unwrap();
+        assert!(matches!(hub.subscribe("s1", 0), Err(SubscribeError::TooManySubscribers)));
+        // The limit is per session
+        assert!(hub.subscribe("s2", 0).is_ok());
+
+        drop(first);
+        assert!(hub.subscribe("s1", 0).is_ok());
+    }
+
+    #[test]
+    fn only_the_next_sequence_number_is_sent() {
+        let hub = SseHub::new(10);
+        let mut subscription = hub.subscribe("s1", 5).unwrap();
+
+        assert!(!hub.broadcast("s1", 7, "VOTE_UPDATE", &json!({ "seq": 7 })), "gap must wait for the tail");
+        assert!(hub.broadcast("s1", 6, "VOTE_UPDATE", &json!({ "seq": 6 })));
+        assert!(!hub.broadcast("s1", 6, "VOTE_UPDATE", &json!({ "seq": 6 })), "sent twice");
+        assert!(hub.broadcast("s1", 7, "VOTE_UPDATE", &json!({ "seq": 7 })));
+
+        let seqs: Vec<i64> = std::iter::from_fn(|| subscription.receiver.try_recv().ok())
+            .map(|event| event.seq)
+            .collect();
+        assert_eq!(seqs, [6, 7]);
+        assert_eq!(hub.cursors(), [("s1".to_string(), 7)]);
+    }
+
+    #[test]
+    fn a_later_subscriber_keeps_the_session_cursor() {
+        let hub = SseHub::new(10);
+        let _first = hub.subscribe("s1", 3).unwrap();
+        assert!(hub.broadcast("s1", 4, "SLIDE_CHANGED", &json!({})));
+
+        let _second = hub.subscribe("s1", 9).unwrap();
+        assert_eq!(hub.cursors(), [("s1".to_string(), 4)]);
+    }
+
+    #[test]
+    fn sessions_without_subscribers_are_dropped() {
+        let hub = SseHub::new(10);
+        drop(hub.subscribe("s1", 0).unwrap());
+
+        assert!(hub.cursors().is_empty());
+        assert!(!hub.broadcast("s1", 1, "SLIDE_CHANGED", &json!({})));
+    }
+
+    #[tokio::test]
+    async fn only_sequenced_session_channel_events_are_mirrored() {
+        let hub = SseHub::new(10);
+        let mut subscription = hub.subscribe("s1", 0).unwrap();
+
+        hub.publish("session:s1:staff", "ANSWERS_SUBMITTED", &json!({ "seq": 1 })).await.unwrap();
+        hub.publish("session:s1", "QA_UPDATE", &json!({})).await.unwrap();
+        hub.publish("session:s1", "QA_UPDATE", &json!({ "seq": 1 })).await.unwrap();
+
+        let event = subscription.receiver.try_recv().unwrap();
+        assert_eq!((event.seq, event.name.as_str()), (1, "QA_UPDATE"));
+        assert!(subscription.receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a single-use account token may be redeemed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    PasswordReset,
+    EmailVerification,
+    /// Password checked, second factor outstanding
+    MfaChallenge,
+}
+
+impl TokenPurpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::PasswordReset => "password_reset",
+            TokenPurpose::EmailVerification => "email_verification",
+            TokenPurpose::MfaChallenge => "mfa_challenge",
+        }
+    }
+}
+
+fn token_mac(secret: &str, purpose: TokenPurpose, token_id: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(purpose.as_str().as_bytes());
+    mac.update(b":");
+    mac.update(token_id.as_bytes());
+    mac
+}
+
+/// Create a token for a user. The returned string is "{id}.{signature}";
+/// only the id is stored, and redeeming it marks the row used.
+pub async fn issue(
+    pool: &DbPool,
+    secret: &str,
+    user_id: &str,
+    purpose: TokenPurpose,
+    ttl: Duration,
+) -> Result<String> {
+    let token_id = Uuid::new_v4().to_string();
+
+    sqlx::query("INSERT INTO user_tokens (id, user_id, purpose, expires_at) VALUES (?, ?, ?, ?)")
+        .bind(&token_id)
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .bind(Utc::now() + ttl)
+        .execute(pool)
+        .await?;
+
+    let tag = token_mac(secret, purpose, &token_id).finalize().into_bytes();
+    let signature = base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, tag);
+    Ok(format!("{}.{}", token_id, signature))
+}
+
+fn invalid() -> AppError {
+    AppError::Input("Invalid or expired token".to_string())
+}
+
+/// Token id of a correctly signed token
+fn verified_id<'a>(secret: &str, token: &'a str, purpose: TokenPurpose) -> Result<&'a str> {
+    let (token_id, signature) = token.split_once('.').ok_or_else(invalid)?;
+    let tag = base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, signature)
+        .map_err(|_| invalid())?;
+    token_mac(secret, purpose, token_id)
+        .verify_slice(&tag)
+        .map_err(|_| invalid())?;
+    Ok(token_id)
+}
+
+/// The user a valid token was issued to, without spending it (for steps that
+/// may be retried before the token is redeemed)
+pub async fn peek(pool: &DbPool, secret: &str, token: &str, purpose: TokenPurpose) -> Result<String> {
+    let token_id = verified_id(secret, token, purpose)?;
+    sqlx::query_scalar(
+        "SELECT user_id FROM user_tokens
+         WHERE id = ? AND purpose = ? AND used_at IS NULL AND expires_at > NOW(3)"
+    )
+    .bind(token_id)
+    .bind(purpose.as_str())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(invalid)
+}
+
+/// Check the signature and spend the token; returns the user it was issued to.
+/// Fails for unknown, expired, already used or wrong-purpose tokens.
+pub async fn redeem(
+    conn: &mut sqlx::MySqlConnection,
+    secret: &str,
+    token: &str,
+    purpose: TokenPurpose,
+) -> Result<String> {
+    let token_id = verified_id(secret, token, purpose)?;
+
+    let spent = sqlx::query(
+        "UPDATE user_tokens SET used_at = NOW(3)
+         WHERE id = ? AND purpose = ? AND used_at IS NULL AND expires_at > NOW(3)"
+    )
+    .bind(token_id)
+    .bind(purpose.as_str())
+    .execute(&mut *conn)
+    .await?;
+    if spent.rows_affected() != 1 {
+        return Err(invalid());
+    }
+
+    let user_id: String = sqlx::query_scalar("SELECT user_id FROM user_tokens WHERE id = ?")
+        .bind(token_id)
+        .fetch_one(conn)
+        .await?;
+    Ok(user_id)
+}
+
+/// Spend every outstanding token of a purpose (e.g. older reset links after a reset)
+pub async fn invalidate_all(
+    conn: &mut sqlx::MySqlConnection,
+    user_id: &str,
+    purpose: TokenPurpose,
+) -> Result<()> {
+    sqlx::query("UPDATE user_tokens SET used_at = NOW(3) WHERE user_id = ? AND purpose = ? AND used_at IS NULL")
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,373 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::db::LazyDbPool;
+use crate::error::Result;
+use crate::models::student::Vote;
+use crate::services::ably::{answers_submitted_event, vote_update_event};
+use crate::services::outbox::{self, Outbox};
+use crate::services::realtime::RealtimeEvent;
+
+/// Consecutive failed flushes of a slide before its update is dropped
+const MAX_FLUSH_FAILURES: u32 = 5;
+
+/// One participant's committed answer, as carried by `ANSWERS_SUBMITTED`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmittedAnswer {
+    pub participant_id: String,
+    pub option_ids: Vec<String>,
+}
+
+/// Flush bookkeeping for one slide
+struct PendingSlide {
+    session_id: String,
+    /// Votes arrived since the last count query started
+    dirty: bool,
+    /// Answers not yet sent to presenters, latest per participant
+    answers: Vec<SubmittedAnswer>,
+}
+
+impl PendingSlide {
+    /// Clear the flag and take the answers gathered so far
+    fn start_flush(&mut self) -> Vec<SubmittedAnswer> {
+        self.dirty = false;
+        std::mem::take(&mut self.answers)
+    }
+
+    /// Queue answers of a failed flush ahead of those that arrived meanwhile
+    fn restore(&mut self, mut answers: Vec<SubmittedAnswer>) {
+        answers.retain(|a| !self.answers.iter().any(|newer| newer.participant_id == a.participant_id));
+        answers.append(&mut self.answers);
+        self.answers = answers;
+        self.dirty = true;
+    }
+}
+
+/// Counts a slide's votes and enqueues its events for a flush
+#[async_trait]
+pub trait VoteTally: Send + Sync {
+    /// Enqueue one `VOTE_UPDATE`, plus one `ANSWERS_SUBMITTED` with the new
+    /// answers, in a single transaction; returns the committed events
+    async fn flush(&self, session_id: &str, slide_id: &str, answers: &[SubmittedAnswer]) -> Result<Vec<RealtimeEvent>>;
+}
+
+/// Counts votes in MySQL and writes the events to the outbox
+pub struct SqlxVoteTally {
+    pool: LazyDbPool,
+}
+
+#[async_trait]
+impl VoteTally for SqlxVoteTally {
+    async fn flush(&self, session_id: &str, slide_id: &str, answers: &[SubmittedAnswer]) -> Result<Vec<RealtimeEvent>> {
+        let pool = self.pool.pool().await?;
+        let mut tx = pool.begin().await?;
+
+        // Lock the session row before counting so concurrent flushes publish
+        // their results in the same order as their sequence numbers
+        sqlx::query("SELECT id FROM sessions WHERE id = ? FOR UPDATE")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let vote_counts = Vote::get_vote_counts(&mut tx, slide_id).await?;
+        let results: HashMap<String, i32> = vote_counts
+            .into_iter()
+            .map(|(option_id, count)| (option_id, count as i32))
+            .collect();
+
+        let mut event = vote_update_event(session_id, slide_id, &results);
+        outbox::enqueue(&mut tx, &mut event).await?;
+        let mut events = vec![event];
+
+        // Per-student answers are presenter-only analytics
+        if !answers.is_empty() {
+            let mut answered = answers_submitted_event(session_id, slide_id, &answers);
+            outbox::enqueue(&mut tx, &mut answered).await?;
+            events.push(answered);
+        }
+        tx.commit().await?;
+        Ok(events)
+    }
+}
+
+/// Batches vote results per slide: votes arriving within the window share a
+/// single count query, a single `VOTE_UPDATE` and a single `ANSWERS_SUBMITTED`
+/// for presenters. A slide that receives votes while being flushed is flushed
+/// again, so the last published result always reflects the final vote.
+pub struct VoteCoalescer {
+    tally: Arc<dyn VoteTally>,
+    outbox: Outbox,
+    window: Duration,
+    pending: Mutex<HashMap<String, PendingSlide>>,
+}
+
+impl VoteCoalescer {
+    pub fn new(pool: LazyDbPool, outbox: Outbox, window: Duration) -> Self {
+        Self::with_tally(Arc::new(SqlxVoteTally { pool }), outbox, window)
+    }
+
+    fn with_tally(tally: Arc<dyn VoteTally>, outbox: Outbox, window: Duration) -> Self {
+        Self {
+            tally,
+            outbox,
+            window,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Note a committed vote; schedules a flush if none is pending for the slide
+    pub fn record_vote(self: &Arc<Self>, session_id: &str, slide_id: &str, answer: SubmittedAnswer) {
+        {
+            let mut pending = self.pending.lock().unwrap();
+            if let Some(slide) = pending.get_mut(slide_id) {
+                slide.dirty = true;
+                // A changed vote replaces the participant's earlier answer
+                slide.answers.retain(|a| a.participant_id != answer.participant_id);
+                slide.answers.push(answer);
+                return;
+            }
+            pending.insert(
+                slide_id.to_string(),
+                PendingSlide {
+                    session_id: session_id.to_string(),
+                    dirty: true,
+                    answers: vec![answer],
+                },
+            );
+        }
+
+        let coalescer = self.clone();
+        let slide_id = slide_id.to_string();
+        tokio::spawn(async move {
+            coalescer.run_slide(slide_id).await;
+        });
+    }
+
+    /// Flush loop for one slide; ends once a flush completes with no new votes
+    async fn run_slide(&self, slide_id: String) {
+        let mut failures = 0;
+
+        loop {
+            tokio::time::sleep(self.window).await;
+
+            // Clear the flag before counting: votes committed after this
+            // point mark the slide dirty again and trigger another round
+            let flush = {
+                let mut pending = self.pending.lock().unwrap();
+                match pending.get_mut(&slide_id) {
+                    Some(slide) if slide.dirty => Some((slide.session_id.clone(), slide.start_flush())),
+                    Some(_) => None,
+                    None => return,
+                }
+            };
+
+            if let Some((session_id, answers)) = flush {
+                if let Err(e) = self.flush_slide(&session_id, &slide_id, &answers).await {
+                    failures += 1;
+                    tracing::error!(
+                        "Failed to flush vote results for slide {} (attempt {}): {}",
+                        slide_id, failures, e
+                    );
+                    if failures < MAX_FLUSH_FAILURES {
+                        if let Some(slide) = self.pending.lock().unwrap().get_mut(&slide_id) {
+                            slide.restore(answers);
+                        }
+                    }
+                } else {
+                    failures = 0;
+                }
+            }
+
+            let mut pending = self.pending.lock().unwrap();
+            if !pending.get(&slide_id).map(|s| s.dirty).unwrap_or(false) {
+                pending.remove(&slide_id);
+                return;
+            }
+        }
+    }
+
+    /// Publish pending results for a session right away (voting stopped)
+    pub async fn flush_session(&self, session_id: &str) {
+        let slides: Vec<(String, Vec<SubmittedAnswer>)> = {
+            let mut pending = self.pending.lock().unwrap();
+            pending
+                .iter_mut()
+                .filter(|(_, slide)| slide.session_id == session_id && slide.dirty)
+                .map(|(slide_id, slide)| (slide_id.clone(), slide.start_flush()))
+                .collect()
+        };
+
+        for (slide_id, answers) in slides {
+            if let Err(e) = self.flush_slide(session_id, &slide_id, &answers).await {
+                tracing::error!("Failed to flush vote results for slide {}: {}", slide_id, e);
+            }
+        }
+    }
+
+    /// Publish every pending result (shutdown)
+    pub async fn flush_all(&self) {
+        let slides: Vec<(String, String, Vec<SubmittedAnswer>)> = {
+            let mut pending = self.pending.lock().unwrap();
+            pending
+                .drain()
+                .filter(|(_, slide)| slide.dirty)
+                .map(|(slide_id, slide)| (slide.session_id, slide_id, slide.answers))
+                .collect()
+        };
+
+        for (session_id, slide_id, answers) in slides {
+            if let Err(e) = self.flush_slide(&session_id, &slide_id, &answers).await {
+                tracing::error!("Failed to flush vote results for slide {}: {}", slide_id, e);
+            }
+        }
+    }
+
+    /// Count the slide's votes once and publish the flush's events
+    async fn flush_slide(&self, session_id: &str, slide_id: &str, answers: &[SubmittedAnswer]) -> Result<()> {
+        let events = self.tally.flush(session_id, slide_id, answers).await?;
+        self.outbox.committed(events);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ably::session_channel;
+    use crate::services::realtime::InMemoryPublisher;
+
+    const WINDOW: Duration = Duration::from_millis(100);
+
+    /// Records every flush instead of querying MySQL
+    #[derive(Default)]
+    struct RecordingTally {
+        flushes: Mutex<Vec<(String, Vec<SubmittedAnswer>)>>,
+        /// How long each flush takes
+        latency: Duration,
+    }
+
+    impl RecordingTally {
+        fn flushed_slides(&self) -> Vec<String> {
+            self.flushes.lock().unwrap().iter().map(|(slide_id, _)| slide_id.clone()).collect()
+        }
+    }
+
+    #[async_trait]
+    impl VoteTally for RecordingTally {
+        async fn flush(&self, session_id: &str, slide_id: &str, answers: &[SubmittedAnswer]) -> Result<Vec<RealtimeEvent>> {
+            tokio::time::sleep(self.latency).await;
+            self.flushes.lock().unwrap().push((slide_id.to_string(), answers.to_vec()));
+            let mut events = vec![vote_update_event(session_id, slide_id, &HashMap::new())];
+            if !answers.is_empty() {
+                events.push(answers_submitted_event(session_id, slide_id, &answers));
+            }
+            Ok(events)
+        }
+    }
+
+    fn setup(tally: RecordingTally) -> (Arc<VoteCoalescer>, Arc<RecordingTally>, Arc<InMemoryPublisher>) {
+        let tally = Arc::new(tally);
+        let published = Arc::new(InMemoryPublisher::new());
+        let coalescer = Arc::new(VoteCoalescer::with_tally(tally.clone(), Outbox::new(published.clone()), WINDOW));
+        (coalescer, tally, published)
+    }
+
+    fn answer(participant_id: &str, option_id: &str) -> SubmittedAnswer {
+        SubmittedAnswer {
+            participant_id: participant_id.to_string(),
+            option_ids: vec![option_id.to_string()],
+        }
+    }
+
+    fn vote_updates(published: &InMemoryPublisher, session_id: &str) -> usize {
+        published.events_for(&session_channel(session_id)).iter().filter(|e| e.name == "VOTE_UPDATE").count()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn votes_within_a_window_share_one_count_and_one_update() {
+        let (coalescer, tally, published) = setup(RecordingTally::default());
+
+        for participant in ["p1", "p2", "p3", "p1"] {
+            coalescer.record_vote("s1", "slide-1", answer(participant, "yes"));
+            tokio::time::sleep(WINDOW / 10).await;
+        }
+        tokio::time::sleep(WINDOW * 3).await;
+
+        assert_eq!(tally.flushed_slides(), ["slide-1"]);
+        assert_eq!(vote_updates(&published, "s1"), 1);
+        // A participant who voted twice is sent once, with the later answer
+        let (_, answers) = tally.flushes.lock().unwrap()[0].clone();
+        let participants: Vec<&str> = answers.iter().map(|a| a.participant_id.as_str()).collect();
+        assert_eq!(participants, ["p2", "p3", "p1"]);
+        assert!(coalescer.pending.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn each_window_is_flushed_separately() {
+        let (coalescer, tally, published) = setup(RecordingTally::default());
+
+        coalescer.record_vote("s1", "slide-1", answer("p1", "yes"));
+        tokio::time::sleep(WINDOW * 3).await;
+        coalescer.record_vote("s1", "slide-1", answer("p2", "no"));
+        tokio::time::sleep(WINDOW * 3).await;
+
+        assert_eq!(tally.flushed_slides(), ["slide-1", "slide-1"]);
+        assert_eq!(vote_updates(&published, "s1"), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_vote_during_a_flush_is_counted_in_another_round() {
+        let (coalescer, tally, _published) = setup(RecordingTally { latency: WINDOW, ..Default::default() });
+
+        coalescer.record_vote("s1", "slide-1", answer("p1", "yes"));
+        // The first flush has started but not finished
+        tokio::time::sleep(WINDOW + WINDOW / 2).await;
+        coalescer.record_vote("s1", "slide-1", answer("p2", "no"));
+        tokio::time::sleep(WINDOW * 5).await;
+
+        let flushes = tally.flushes.lock().unwrap().clone();
+        assert_eq!(flushes.len(), 2);
+        assert_eq!(flushes[0].1, [answer("p1", "yes")]);
+        assert_eq!(flushes[1].1, [answer("p2", "no")]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flush_session_publishes_that_session_right_away() {
+        let (coalescer, tally, published) = setup(RecordingTally::default());
+
+        coalescer.record_vote("s1", "slide-1", answer("p1", "yes"));
+        coalescer.record_vote("s2", "slide-2", answer("p2", "no"));
+        coalescer.flush_session("s1").await;
+
+        assert_eq!(tally.flushed_slides(), ["slide-1"]);
+        tokio::time::sleep(WINDOW * 3).await;
+
+        // The window ends without a second update for the flushed slide
+        assert_eq!(tally.flushed_slides(), ["slide-1", "slide-2"]);
+        assert_eq!(vote_updates(&published, "s1"), 1);
+        assert_eq!(vote_updates(&published, "s2"), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flush_all_publishes_every_pending_slide_once() {
+        let (coalescer, tally, published) = setup(RecordingTally::default());
+
+        coalescer.record_vote("s1", "slide-1", answer("p1", "yes"));
+        coalescer.record_vote("s2", "slide-2", answer("p2", "no"));
+        coalescer.flush_all().await;
+
+        let mut flushed = tally.flushed_slides();
+        flushed.sort();
+        assert_eq!(flushed, ["slide-1", "slide-2"]);
+        assert!(coalescer.pending.lock().unwrap().is_empty());
+
+        tokio::time::sleep(WINDOW * 3).await;
+        assert_eq!(tally.flushed_slides().len(), 2);
+        assert_eq!(vote_updates(&published, "s1"), 1);
+        assert_eq!(vote_updates(&published, "s2"), 1);
+    }
+}
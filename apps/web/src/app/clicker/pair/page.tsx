@@ -0,0 +1,78 @@
+'use client';
+
+import { FormEvent, useEffect, useState } from 'react';
+import { useRouter } from 'next/navigation';
+import { pairClicker } from '@/lib/api';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Card, CardHeader, CardTitle, CardContent } from '@/components/ui/card';
+import { Loader2, Smartphone } from 'lucide-react';
+
+export default function PairClickerPage() {
+    const router = useRouter();
+    const [code, setCode] = useState('');
+    const [pairing, setPairing] = useState(false);
+    const [error, setError] = useState('');
+
+    const pair = async (value: string) => {
+        setPairing(true);
+        setError('');
+        try {
+            const paired = await pairClicker(value);
+            router.replace(`/staff/session/${paired.sessionId}/clicker`);
+        } catch (err: any) {
+            setError(err.message);
+            setPairing(false);
+        }
+    };
+
+    // Scanned or opened pairing links carry the code
+    useEffect(() => {
+        const fromLink = new URLSearchParams(window.location.search).get('code');
+        if (fromLink) {
+            setCode(fromLink);
+            pair(fromLink);
+        }
+    }, []);
+
+    const handleSubmit = (e: FormEvent) => {
+        e.preventDefault();
+        if (code.trim()) pair(code.trim());
+    };
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-slate-900 p-4">
+            <Card className="w-full max-w-sm shadow-xl">
+                <CardHeader className="text-center space-y-2">
+                    <div className="mx-auto w-10 h-10 bg-blue-600 rounded-xl flex items-center justify-center">
+                        <Smartphone className="w-5 h-5 text-white" />
+                    </div>
+                    <CardTitle className="heading-3">Pair Clicker</CardTitle>
+                    <p className="text-sm text-slate-500">Enter the code shown on the presenter screen.</p>
+                </CardHeader>
+                <CardContent>
+                    <form onSubmit={handleSubmit} className="space-y-4">
+                        <Input
+                            value={code}
+                            onChange={(e) => setCode(e.target.value.toUpperCase())}
+                            placeholder="ABCD2345"
+                            autoComplete="off"
+                            autoCapitalize="characters"
+                            className="text-center text-2xl font-mono tracking-widest h-14"
+                            disabled={pairing}
+                        />
+                        {error && (
+                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                {error}
+                            </div>
+                        )}
+                        <Button type="submit" className="w-full" disabled={pairing || !code.trim()}>
+                            {pairing && <Loader2 className="mr-2 h-4 w-4 animate-spin" />}
+                            Pair
+                        </Button>
+                    </form>
+                </CardContent>
+            </Card>
+        </div>
+    );
+}
@@ -94,7 +94,7 @@ export default function PublicDashboardPage() {
 
             {/* Dashboard Content */}
             <div className="max-w-7xl mx-auto px-4 sm:px-6 lg:px-8 py-8">
-                <WebSocketProvider sessionId={sessionId} role="staff">
+                <WebSocketProvider sessionId={sessionId} role="projector" shareToken={shareToken}>
                     <SessionDashboard sessionId={sessionId} isPublic={true} />
                 </WebSocketProvider>
             </div>
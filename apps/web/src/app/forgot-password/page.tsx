@@ -0,0 +1,81 @@
+'use client';
+
+import { useState } from 'react';
+import Link from 'next/link';
+import { forgotPassword } from '@/lib/api';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Card, CardHeader, CardTitle, CardContent, CardFooter } from '@/components/ui/card';
+import { Label } from '@/components/ui/label';
+import { Loader2, Mail } from 'lucide-react';
+
+export default function ForgotPasswordPage() {
+    const [email, setEmail] = useState('');
+    const [sent, setSent] = useState(false);
+    const [error, setError] = useState('');
+    const [loading, setLoading] = useState(false);
+
+    async function handleSubmit(e: React.FormEvent) {
+        e.preventDefault();
+        setError('');
+        setLoading(true);
+        try {
+            await forgotPassword(email);
+            setSent(true);
+        } catch (err: any) {
+            setError(err.message);
+        } finally {
+            setLoading(false);
+        }
+    }
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-gradient-to-br from-slate-50 via-blue-50 to-slate-50 p-4">
+            <div className="w-full max-w-md relative z-10 animate-scale-in">
+                <Card className="shadow-xl border-slate-200">
+                    <CardHeader className="text-center pb-6 space-y-2">
+                        <CardTitle className="heading-3">Forgot Password</CardTitle>
+                        <p className="text-slate-500 text-sm">We&apos;ll email you a link to choose a new password</p>
+                    </CardHeader>
+                    <CardContent>
+                        {sent ? (
+                            <div className="bg-green-50 border border-green-200 text-green-700 px-4 py-3 rounded-lg text-sm">
+                                If that email has an account, a reset link is on its way. The link expires in one hour.
+                            </div>
+                        ) : (
+                            <form onSubmit={handleSubmit} className="space-y-5">
+                                <div className="space-y-2">
+                                    <Label htmlFor="email" className="text-sm font-semibold text-slate-700">Email Address</Label>
+                                    <Input
+                                        id="email"
+                                        type="email"
+                                        placeholder="you@example.com"
+                                        value={email}
+                                        onChange={(e) => setEmail(e.target.value)}
+                                        required
+                                        disabled={loading}
+                                        className="h-11"
+                                    />
+                                </div>
+
+                                {error && (
+                                    <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm animate-slide-down">
+                                        {error}
+                                    </div>
+                                )}
+
+                                <Button type="submit" className="w-full h-11 text-base font-semibold" disabled={loading}>
+                                    {loading ? <Loader2 className="mr-2 h-4 w-4 animate-spin" /> : <Mail className="mr-2 h-4 w-4" />}
+                                    Send Reset Link
+                                </Button>
+                            </form>
+                        )}
+                    </CardContent>
+                    <CardFooter className="justify-center pb-6">
+                        <Link href="/login" className="text-sm text-blue-600 hover:underline">Back to sign in</Link>
+                    </CardFooter>
+                </Card>
+            </div>
+        </div>
+    );
+}
@@ -0,0 +1,77 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import Link from 'next/link';
+import { acceptSessionInvite } from '@/lib/api';
+import { Card, CardHeader, CardTitle, CardContent, CardFooter } from '@/components/ui/card';
+import { Loader2 } from 'lucide-react';
+
+export default function AcceptInvitePage() {
+    const [status, setStatus] = useState<'accepting' | 'accepted' | 'signed-out' | 'failed'>('accepting');
+    const [sessionId, setSessionId] = useState('');
+    const [error, setError] = useState('');
+
+    useEffect(() => {
+        const token = new URLSearchParams(window.location.search).get('token');
+        if (!token) {
+            setStatus('failed');
+            setError('This invitation link is incomplete.');
+            return;
+        }
+        // Accepting needs the account the invitation was sent to
+        if (!localStorage.getItem('token')) {
+            setStatus('signed-out');
+            return;
+        }
+        acceptSessionInvite(token)
+            .then((accepted) => {
+                setSessionId(accepted.sessionId);
+                setStatus('accepted');
+            })
+            .catch((err: any) => {
+                setStatus('failed');
+                setError(err.message);
+            });
+    }, []);
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-gradient-to-br from-slate-50 via-blue-50 to-slate-50 p-4">
+            <div className="w-full max-w-md relative z-10 animate-scale-in">
+                <Card className="shadow-xl border-slate-200">
+                    <CardHeader className="text-center pb-6 space-y-2">
+                        <CardTitle className="heading-3">Session Invitation</CardTitle>
+                    </CardHeader>
+                    <CardContent>
+                        {status === 'accepting' && (
+                            <div className="flex justify-center text-slate-500">
+                                <Loader2 className="mr-2 h-5 w-5 animate-spin" /> Joining...
+                            </div>
+                        )}
+                        {status === 'accepted' && (
+                            <div className="bg-green-50 border border-green-200 text-green-700 px-4 py-3 rounded-lg text-sm">
+                                You now have access to the session.
+                            </div>
+                        )}
+                        {status === 'signed-out' && (
+                            <div className="bg-blue-50 border border-blue-200 text-blue-700 px-4 py-3 rounded-lg text-sm">
+                                Sign in with the email address this invitation was sent to, then open the link again.
+                            </div>
+                        )}
+                        {status === 'failed' && (
+                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                {error}
+                            </div>
+                        )}
+                    </CardContent>
+                    <CardFooter className="justify-center pb-6">
+                        {status === 'accepted' ? (
+                            <Link href={`/staff/session/${sessionId}`} className="text-sm text-blue-600 hover:underline">Open the session</Link>
+                        ) : (
+                            <Link href="/login" className="text-sm text-blue-600 hover:underline">Go to sign in</Link>
+                        )}
+                    </CardFooter>
+                </Card>
+            </div>
+        </div>
+    );
+}
@@ -1,9 +1,9 @@
 'use client';
 
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import { useRouter } from 'next/navigation';
 import Link from 'next/link';
-import { login } from '@/lib/api';
+import { login, loginWithMfa, getSsoProviders, ssoLoginUrl, ssoLinkUrl, SsoProvider } from '@/lib/api';
 import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { Card, CardHeader, CardTitle, CardContent, CardFooter } from '@/components/ui/card';
@@ -16,13 +16,48 @@ export default function LoginPage() {
     const [password, setPassword] = useState('');
     const [error, setError] = useState('');
     const [loading, setLoading] = useState(false);
+    const [providers, setProviders] = useState<SsoProvider[]>([]);
+    const [mfaToken, setMfaToken] = useState<string | null>(null);
+    const [mfaCode, setMfaCode] = useState('');
+    // Provider account to link once the user has signed in with their password
+    const [linkProvider, setLinkProvider] = useState<string | null>(null);
+
+    useEffect(() => {
+        // Single sign-on into an account with 2FA continues with the code step
+        const ssoMfaToken = new URLSearchParams(window.location.hash.slice(1)).get('mfaToken');
+        if (ssoMfaToken) {
+            window.history.replaceState(null, '', window.location.pathname);
+            setMfaToken(ssoMfaToken);
+        }
+        const params = new URLSearchParams(window.location.search);
+        if (params.get('error') === 'sso_failed') {
+            setError('Single sign-on failed. Please try again.');
+        }
+        if (params.get('error') === 'sso_link_required') {
+            setError('An account with this email already exists, or your email is not verified. Sign in with your password to link single sign-on to it.');
+            setLinkProvider(params.get('provider'));
+        }
+        getSsoProviders().then(setProviders).catch(() => {});
+    }, []);
 
     async function handleLogin(e: React.FormEvent) {
         e.preventDefault();
         setError('');
         setLoading(true);
         try {
-            await login(email, password);
+            if (mfaToken) {
+                await loginWithMfa(mfaToken, mfaCode);
+            } else {
+                const result = await login(email, password);
+                if (result.mfaRequired) {
+                    setMfaToken(result.mfaToken);
+                    return;
+                }
+            }
+            if (linkProvider) {
+                window.location.href = ssoLinkUrl(linkProvider);
+                return;
+            }
             router.push('/');
         } catch (err: any) {
             setError(err.message);
@@ -52,6 +87,25 @@ export default function LoginPage() {
                     </CardHeader>
                     <CardContent>
                         <form onSubmit={handleLogin} className="space-y-5">
+                            {mfaToken ? (
+                            <div className="space-y-2">
+                                <Label htmlFor="mfa-code" className="text-sm font-semibold text-slate-700">Authentication Code</Label>
+                                <Input
+                                    id="mfa-code"
+                                    inputMode="text"
+                                    autoComplete="one-time-code"
+                                    placeholder="123456"
+                                    value={mfaCode}
+                                    onChange={(e) => setMfaCode(e.target.value)}
+                                    required
+                                    autoFocus
+                                    disabled={loading}
+                                    className="h-11"
+                                />
+                                <p className="text-xs text-slate-500">Enter the code from your authenticator app, or one of your recovery codes.</p>
+                            </div>
+                            ) : (
+                            <>
                             <div className="space-y-2">
                                 <Label htmlFor="email" className="text-sm font-semibold text-slate-700">Email Address</Label>
                                 <Input
@@ -66,7 +120,10 @@ export default function LoginPage() {
                                 />
                             </div>
                             <div className="space-y-2">
-                                <Label htmlFor="password" className="text-sm font-semibold text-slate-700">Password</Label>
+                                <div className="flex items-center justify-between">
+                                    <Label htmlFor="password" className="text-sm font-semibold text-slate-700">Password</Label>
+                                    <Link href="/forgot-password" className="text-xs text-blue-600 hover:underline">Forgot password?</Link>
+                                </div>
                                 <Input
                                     id="password"
                                     type="password"
@@ -78,6 +135,8 @@ export default function LoginPage() {
                                     className="h-11"
                                 />
                             </div>
+                            </>
+                            )}
 
                             {error && (
                                 <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm animate-slide-down">
@@ -98,11 +157,23 @@ export default function LoginPage() {
                                 ) : (
                                     <>
                                         <LogIn className="mr-2 h-4 w-4" />
-                                        Sign In
+                                        {mfaToken ? 'Verify' : 'Sign In'}
                                     </>
                                 )}
                             </Button>
                         </form>
+
+                        {providers.length > 0 && (
+                            <div className="mt-5 space-y-3">
+                                {providers.map((provider) => (
+                                    <a key={provider.id} href={ssoLoginUrl(provider.id)} className="block">
+                                        <Button type="button" variant="outline" className="w-full h-11" disabled={loading}>
+                                            Sign in with {provider.displayName}
+                                        </Button>
+                                    </a>
+                                ))}
+                            </div>
+                        )}
                     </CardContent>
                     <CardFooter className="flex-col gap-4 pb-6">
                         <div className="relative w-full">
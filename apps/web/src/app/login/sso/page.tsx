@@ -0,0 +1,48 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import { useRouter } from 'next/navigation';
+import Link from 'next/link';
+import { completeSsoLogin } from '@/lib/api';
+import { Card, CardHeader, CardTitle, CardContent, CardFooter } from '@/components/ui/card';
+import { Loader2 } from 'lucide-react';
+
+export default function SsoCallbackPage() {
+    const router = useRouter();
+    const [failed, setFailed] = useState(false);
+
+    useEffect(() => {
+        completeSsoLogin().then((ok) => {
+            if (ok) router.replace('/');
+            else setFailed(true);
+        });
+    }, [router]);
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-gradient-to-br from-slate-50 via-blue-50 to-slate-50 p-4">
+            <div className="w-full max-w-md relative z-10 animate-scale-in">
+                <Card className="shadow-xl border-slate-200">
+                    <CardHeader className="text-center pb-6 space-y-2">
+                        <CardTitle className="heading-3">Single Sign-On</CardTitle>
+                    </CardHeader>
+                    <CardContent>
+                        {failed ? (
+                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                We could not complete your sign-in. Please try again.
+                            </div>
+                        ) : (
+                            <div className="flex justify-center text-slate-500">
+                                <Loader2 className="mr-2 h-5 w-5 animate-spin" /> Signing you in...
+                            </div>
+                        )}
+                    </CardContent>
+                    {failed && (
+                        <CardFooter className="justify-center pb-6">
+                            <Link href="/login" className="text-sm text-blue-600 hover:underline">Back to sign in</Link>
+                        </CardFooter>
+                    )}
+                </Card>
+            </div>
+        </div>
+    );
+}
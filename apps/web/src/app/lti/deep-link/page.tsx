@@ -0,0 +1,91 @@
+'use client';
+
+import { useEffect, useRef, useState } from 'react';
+import { getDeepLinkSessions, createDeepLink, DeepLinkSession } from '@/lib/api';
+import { Button } from '@/components/ui/button';
+import { Card, CardHeader, CardTitle, CardContent } from '@/components/ui/card';
+import { Loader2 } from 'lucide-react';
+
+// Session picker for LTI deep linking (adding a session to an LMS course)
+export default function LtiDeepLinkPage() {
+    const [context, setContext] = useState('');
+    const [sessions, setSessions] = useState<DeepLinkSession[] | null>(null);
+    const [error, setError] = useState('');
+    const [submitting, setSubmitting] = useState<string | null>(null);
+    const [response, setResponse] = useState<{ returnUrl: string; jwt: string } | null>(null);
+    const formRef = useRef<HTMLFormElement>(null);
+
+    useEffect(() => {
+        const value = new URLSearchParams(window.location.hash.slice(1)).get('context');
+        window.history.replaceState(null, '', window.location.pathname);
+        if (!value) {
+            setError('This link is incomplete. Start again from your course.');
+            return;
+        }
+        setContext(value);
+        getDeepLinkSessions(value)
+            .then(setSessions)
+            .catch((err: any) => setError(err.message));
+    }, []);
+
+    // Hand the signed response back to the LMS
+    useEffect(() => {
+        if (response) formRef.current?.submit();
+    }, [response]);
+
+    async function pick(sessionId: string) {
+        setSubmitting(sessionId);
+        setError('');
+        try {
+            setResponse(await createDeepLink(context, sessionId));
+        } catch (err: any) {
+            setError(err.message);
+            setSubmitting(null);
+        }
+    }
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-gradient-to-br from-slate-50 via-blue-50 to-slate-50 p-4">
+            <div className="w-full max-w-lg relative z-10 animate-scale-in">
+                <Card className="shadow-xl border-slate-200">
+                    <CardHeader className="text-center pb-6 space-y-2">
+                        <CardTitle className="heading-3">Add a Session to Your Course</CardTitle>
+                        <p className="text-slate-500 text-sm">Students who open the course link join this session</p>
+                    </CardHeader>
+                    <CardContent className="space-y-3">
+                        {error && (
+                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                {error}
+                            </div>
+                        )}
+                        {!sessions && !error && (
+                            <div className="flex justify-center text-slate-500">
+                                <Loader2 className="mr-2 h-5 w-5 animate-spin" /> Loading sessions...
+                            </div>
+                        )}
+                        {sessions && sessions.length === 0 && (
+                            <p className="text-center text-sm text-slate-500">You have no sessions yet. Create one in ClassColab first.</p>
+                        )}
+                        {sessions?.map((session) => (
+                            <Button
+                                key={session.id}
+                                variant="outline"
+                                className="w-full h-11 justify-between"
+                                disabled={submitting !== null}
+                                onClick={() => pick(session.id)}
+                            >
+                                <span className="truncate">{session.title}</span>
+                                {submitting === session.id && <Loader2 className="h-4 w-4 animate-spin" />}
+                            </Button>
+                        ))}
+                        {response && (
+                            <form ref={formRef} method="POST" action={response.returnUrl}>
+                                <input type="hidden" name="JWT" value={response.jwt} />
+                            </form>
+                        )}
+                    </CardContent>
+                </Card>
+            </div>
+        </div>
+    );
+}
@@ -0,0 +1,180 @@
+'use client';
+
+import { FormEvent, useEffect, useState } from 'react';
+import { useRouter } from 'next/navigation';
+import { completeSsoLogin, linkLtiAccount, loginWithMfa } from '@/lib/api';
+import { Input } from '@/components/ui/input';
+import { Button } from '@/components/ui/button';
+import { Card, CardHeader, CardTitle, CardContent } from '@/components/ui/card';
+import { Loader2 } from 'lucide-react';
+
+// Landing page of LTI launches; the backend passes details in the URL fragment
+export default function LtiLaunchPage() {
+    const router = useRouter();
+    const [error, setError] = useState('');
+    // Instructor whose course account is not linked to a ClassColab account yet
+    const [pendingLink, setPendingLink] = useState<{ link: string; email: string } | null>(null);
+    const [busy, setBusy] = useState(false);
+    const [linked, setLinked] = useState(false);
+    // Instructor account with 2FA: the launch ends with the code step
+    const [mfa, setMfa] = useState<{ token: string; next: string } | null>(null);
+    const [mfaCode, setMfaCode] = useState('');
+
+    useEffect(() => {
+        const params = new URLSearchParams(window.location.hash.slice(1));
+        window.history.replaceState(null, '', window.location.pathname);
+
+        const launchError = params.get('error');
+        if (launchError) {
+            setError(launchError);
+            return;
+        }
+
+        const link = params.get('link');
+        if (link) {
+            setPendingLink({ link, email: params.get('email') || '' });
+            return;
+        }
+
+        // Learner: adopt the participant identity issued for this LMS user
+        const sessionId = params.get('sessionId');
+        const shareToken = params.get('shareToken');
+        const participantId = params.get('participantId');
+        const participantToken = params.get('participantToken');
+        if (sessionId && shareToken && participantId && participantToken) {
+            const name = params.get('name') || '';
+            localStorage.setItem(`studentParticipantId_${sessionId}`, participantId);
+            localStorage.setItem(`studentParticipantToken_${sessionId}`, participantToken);
+            if (name) {
+                sessionStorage.setItem(`studentName_${shareToken}`, name);
+                localStorage.setItem(`studentName_${shareToken}`, name);
+            }
+            router.replace(`/student/session/${shareToken}`);
+            return;
+        }
+
+        const next = params.get('next');
+        const mfaToken = params.get('mfaToken');
+        if (mfaToken && next && next.startsWith('/')) {
+            setMfa({ token: mfaToken, next });
+            return;
+        }
+
+        // Instructor: the login cookies are set; trade the refresh cookie for a token
+        if (next && next.startsWith('/')) {
+            completeSsoLogin().then((ok) => {
+                if (ok) router.replace(next);
+                else setError('Your browser blocked the sign-in inside the course page. Open the link in a new window and try again.');
+            });
+            return;
+        }
+
+        setError('This launch link is incomplete.');
+    }, [router]);
+
+    // Linking needs a signed-in user; the refresh cookie stands in for the password
+    const handleLink = async () => {
+        if (!pendingLink) return;
+        setBusy(true);
+        setError('');
+        try {
+            if (!(await completeSsoLogin())) {
+                setError('Sign in to ClassColab in a new tab first, then come back and link your account.');
+                return;
+            }
+            await linkLtiAccount(pendingLink.link);
+            setLinked(true);
+        } catch (err: any) {
+            setError(err.message);
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    const handleMfa = async (e: FormEvent) => {
+        e.preventDefault();
+        if (!mfa) return;
+        setBusy(true);
+        setError('');
+        try {
+            await loginWithMfa(mfa.token, mfaCode);
+            router.replace(mfa.next);
+        } catch (err: any) {
+            setError(err.message);
+            setBusy(false);
+        }
+    };
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-gradient-to-br from-slate-50 via-blue-50 to-slate-50 p-4">
+            <div className="w-full max-w-md relative z-10 animate-scale-in">
+                <Card className="shadow-xl border-slate-200">
+                    <CardHeader className="text-center pb-6 space-y-2">
+                        <CardTitle className="heading-3">ClassColab</CardTitle>
+                    </CardHeader>
+                    <CardContent>
+                        {mfa ? (
+                            <form onSubmit={handleMfa} className="space-y-4">
+                                <Input
+                                    inputMode="text"
+                                    autoComplete="one-time-code"
+                                    placeholder="123456"
+                                    value={mfaCode}
+                                    onChange={(e) => setMfaCode(e.target.value)}
+                                    required
+                                    autoFocus
+                                    disabled={busy}
+                                />
+                                <p className="text-xs text-slate-500">Enter the code from your authenticator app, or one of your recovery codes.</p>
+                                {error && (
+                                    <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                        {error}
+                                    </div>
+                                )}
+                                <Button type="submit" className="w-full" disabled={busy || !mfaCode.trim()}>
+                                    {busy && <Loader2 className="mr-2 h-4 w-4 animate-spin" />}
+                                    Verify
+                                </Button>
+                            </form>
+                        ) : pendingLink ? (
+                            <div className="space-y-4 text-sm text-slate-600">
+                                {linked ? (
+                                    <p>Your course account is linked. Open the link in your course again to continue.</p>
+                                ) : (
+                                    <>
+                                        <p>
+                                            Your course account{pendingLink.email && ` (${pendingLink.email})`} is not linked to a ClassColab account yet.
+                                            Sign in to ClassColab in this browser, then link it.
+                                        </p>
+                                        {error && (
+                                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg">
+                                                {error}
+                                            </div>
+                                        )}
+                                        <div className="flex gap-2">
+                                            <a href="/login" target="_blank" rel="noopener noreferrer" className="flex-1">
+                                                <Button variant="outline" className="w-full">Sign In</Button>
+                                            </a>
+                                            <Button className="flex-1" onClick={handleLink} disabled={busy}>
+                                                {busy && <Loader2 className="mr-2 h-4 w-4 animate-spin" />}
+                                                Link Account
+                                            </Button>
+                                        </div>
+                                    </>
+                                )}
+                            </div>
+                        ) : error ? (
+                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                {error}
+                            </div>
+                        ) : (
+                            <div className="flex justify-center text-slate-500">
+                                <Loader2 className="mr-2 h-5 w-5 animate-spin" /> Opening session...
+                            </div>
+                        )}
+                    </CardContent>
+                </Card>
+            </div>
+        </div>
+    );
+}
@@ -4,7 +4,7 @@ import { useEffect, useState } from 'react';
 import Link from 'next/link';
 import { useRouter } from 'next/navigation';
 import { Session } from 'shared';
-import { getSessions, createSession, duplicateSession, archiveSession, restoreSession } from '@/lib/api';
+import { getSessions, getMe, createSession, duplicateSession, archiveSession, restoreSession } from '@/lib/api';
 import { Button } from '@/components/ui/button';
 import { Card, CardHeader, CardTitle, CardContent, CardFooter } from '@/components/ui/card';
 import { Plus, Play, BarChart, Copy, Archive, RotateCcw, LogOut, Loader2 } from 'lucide-react';
@@ -44,6 +44,8 @@ export default function Dashboard() {
     setUser(JSON.parse(userStr));
     setAuthChecking(false);
     loadSessions("active");
+    // The cached user may be stale (renamed on another device)
+    getMe().then(setUser).catch(() => {});
   }, [router]);
 
   function handleLogout() {
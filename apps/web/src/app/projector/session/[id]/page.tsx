@@ -5,13 +5,23 @@ export const runtime = 'edge';
 import { useEffect, useState } from 'react';
 import { useParams } from 'next/navigation';
 import { Slide } from 'shared';
-import { getSlides } from '@/lib/api';
+import { getSession, getSlides } from '@/lib/api';
 import { WebSocketProvider, useWebSocket } from '@/lib/websocket';
 import { SlideRenderer } from '@/components/slide-renderer';
 import { Card } from '@/components/ui/card';
 
-function ProjectorContent({ slides }: { slides: Slide[] }) {
+function ProjectorContent({ sessionId, slides }: { sessionId: string; slides: Slide[] }) {
     const { state } = useWebSocket();
+    const [joinCode, setJoinCode] = useState<string | null>(null);
+
+    // Codes are handed out on go-live and released on stop
+    useEffect(() => {
+        if (!state?.isPresentationActive) {
+            setJoinCode(null);
+            return;
+        }
+        getSession(sessionId).then((session) => setJoinCode(session.joinCode ?? null)).catch(() => setJoinCode(null));
+    }, [sessionId, state?.isPresentationActive]);
 
     const currentSlide = slides.find(s => s.id === state?.currentSlideId);
 
@@ -21,9 +31,9 @@ function ProjectorContent({ slides }: { slides: Slide[] }) {
                 <div className="w-full h-full max-w-[1920px] max-h-[1080px] aspect-video bg-white relative">
                     <SlideRenderer slide={currentSlide} role="projector" />
 
-                    {/* QR Code or Join Info Overlay could go here */}
                     <div className="absolute bottom-4 right-4 bg-black/50 text-white px-4 py-2 rounded-full text-sm backdrop-blur-sm">
-                        Join at <strong>classcolab.com/student</strong>
+                        Join at <strong>classcolab.com/student/join</strong>
+                        {joinCode && <> with code <strong className="font-mono tracking-widest">{joinCode}</strong></>}
                     </div>
                 </div>
             ) : (
@@ -50,7 +60,7 @@ export default function ProjectorPage() {
 
     return (
         <WebSocketProvider sessionId={id} role="projector">
-            <ProjectorContent slides={slides} />
+            <ProjectorContent sessionId={id} slides={slides} />
         </WebSocketProvider>
     );
 }
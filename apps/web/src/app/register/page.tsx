@@ -101,7 +101,7 @@ export default function RegisterPage() {
                                     </SelectTrigger>
                                     <SelectContent>
                                         <SelectItem value="student">Student</SelectItem>
-                                        <SelectItem value="staff">Teacher / Staff</SelectItem>
+                                        <SelectItem value="teacher">Teacher / Staff</SelectItem>
                                     </SelectContent>
                                 </Select>
                             </div>
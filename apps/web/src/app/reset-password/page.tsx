@@ -0,0 +1,87 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import Link from 'next/link';
+import { resetPassword } from '@/lib/api';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Card, CardHeader, CardTitle, CardContent, CardFooter } from '@/components/ui/card';
+import { Label } from '@/components/ui/label';
+import { KeyRound, Loader2 } from 'lucide-react';
+
+export default function ResetPasswordPage() {
+    const [token, setToken] = useState('');
+    const [password, setPassword] = useState('');
+    const [done, setDone] = useState(false);
+    const [error, setError] = useState('');
+    const [loading, setLoading] = useState(false);
+
+    useEffect(() => {
+        setToken(new URLSearchParams(window.location.search).get('token') || '');
+    }, []);
+
+    async function handleSubmit(e: React.FormEvent) {
+        e.preventDefault();
+        setError('');
+        setLoading(true);
+        try {
+            await resetPassword(token, password);
+            setDone(true);
+        } catch (err: any) {
+            setError(err.message);
+        } finally {
+            setLoading(false);
+        }
+    }
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-gradient-to-br from-slate-50 via-blue-50 to-slate-50 p-4">
+            <div className="w-full max-w-md relative z-10 animate-scale-in">
+                <Card className="shadow-xl border-slate-200">
+                    <CardHeader className="text-center pb-6 space-y-2">
+                        <CardTitle className="heading-3">Choose a New Password</CardTitle>
+                        <p className="text-slate-500 text-sm">You&apos;ll be signed out on all devices</p>
+                    </CardHeader>
+                    <CardContent>
+                        {done ? (
+                            <div className="bg-green-50 border border-green-200 text-green-700 px-4 py-3 rounded-lg text-sm">
+                                Your password has been reset. You can now sign in with it.
+                            </div>
+                        ) : (
+                            <form onSubmit={handleSubmit} className="space-y-5">
+                                <div className="space-y-2">
+                                    <Label htmlFor="password" className="text-sm font-semibold text-slate-700">New Password</Label>
+                                    <Input
+                                        id="password"
+                                        type="password"
+                                        placeholder="••••••••"
+                                        minLength={8}
+                                        value={password}
+                                        onChange={(e) => setPassword(e.target.value)}
+                                        required
+                                        disabled={loading || !token}
+                                        className="h-11"
+                                    />
+                                </div>
+
+                                {(error || !token) && (
+                                    <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm animate-slide-down">
+                                        {error || 'This reset link is incomplete. Request a new one.'}
+                                    </div>
+                                )}
+
+                                <Button type="submit" className="w-full h-11 text-base font-semibold" disabled={loading || !token}>
+                                    {loading ? <Loader2 className="mr-2 h-4 w-4 animate-spin" /> : <KeyRound className="mr-2 h-4 w-4" />}
+                                    Reset Password
+                                </Button>
+                            </form>
+                        )}
+                    </CardContent>
+                    <CardFooter className="justify-center pb-6">
+                        <Link href="/login" className="text-sm text-blue-600 hover:underline">Back to sign in</Link>
+                    </CardFooter>
+                </Card>
+            </div>
+        </div>
+    );
+}
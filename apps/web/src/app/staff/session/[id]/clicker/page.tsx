@@ -4,14 +4,15 @@ export const runtime = 'edge';
 
 import { useEffect, useState, useRef, useCallback } from 'react';
 import { useParams } from 'next/navigation';
+import Link from 'next/link';
 import { Slide } from 'shared';
-import { publicGetSlides, publicSetCurrentSlide } from '@/lib/api';
+import { forgetStoredClicker, getStoredClicker, PairedClicker, publicGetSlides, publicSetCurrentSlide } from '@/lib/api';
 import { Button } from '@/components/ui/button';
 import { WebSocketProvider, useWebSocket } from '@/lib/websocket';
 import { ChevronLeft, ChevronRight, Smartphone, Layout } from 'lucide-react';
 import { SlideRenderer } from '@/components/slide-renderer';
 
-function ClickerContent() {
+function ClickerContent({ clickerToken, onUnpaired }: { clickerToken: string; onUnpaired: () => void }) {
     const { state, isConnected, lastSlideUpdate, updateState, initialStateLoaded } = useWebSocket();
     const params = useParams();
     const id = params?.id as string;
@@ -82,11 +83,13 @@ function ClickerContent() {
         // This batches rapid clicks and only sends the final destination
         apiTimeoutRef.current = setTimeout(() => {
             if (pendingSlideRef.current) {
-                publicSetCurrentSlide(id, pendingSlideRef.current);
+                publicSetCurrentSlide(id, pendingSlideRef.current, clickerToken).then((accepted) => {
+                    if (!accepted) onUnpaired();
+                });
                 pendingSlideRef.current = null;
             }
         }, 150);
-    }, [id]);
+    }, [id, clickerToken, onUnpaired]);
 
     const handleNext = useCallback(() => {
         const slides = visibleSlidesRef.current;
@@ -205,15 +208,39 @@ function ClickerContent() {
     );
 }
 
+// Shown until this phone is paired, or after it was unpaired
+function PairingRequired() {
+    return (
+        <div className="fixed inset-0 flex flex-col items-center justify-center gap-4 bg-slate-900 text-white p-6 text-center">
+            <Smartphone className="w-10 h-10 text-blue-400" />
+            <p className="text-sm text-slate-300">This phone is not paired with the session. Open the clicker dialog on the presenter screen to get a pairing code.</p>
+            <Link href="/clicker/pair">
+                <Button className="bg-blue-600 hover:bg-blue-500">Enter Pairing Code</Button>
+            </Link>
+        </div>
+    );
+}
+
 export default function ClickerPage() {
     const params = useParams();
     const id = params?.id as string;
+    const [clicker, setClicker] = useState<PairedClicker | null | undefined>(undefined);
+
+    useEffect(() => {
+        if (id) setClicker(getStoredClicker(id));
+    }, [id]);
+
+    const handleUnpaired = useCallback(() => {
+        forgetStoredClicker(id);
+        setClicker(null);
+    }, [id]);
 
-    if (!id) return null;
+    if (!id || clicker === undefined) return null;
+    if (!clicker) return <PairingRequired />;
 
     return (
-        <WebSocketProvider sessionId={id} role="staff">
-            <ClickerContent />
+        <WebSocketProvider sessionId={id} role="projector" shareToken={clicker.shareToken ?? undefined}>
+            <ClickerContent clickerToken={clicker.token} onUnpaired={handleUnpaired} />
         </WebSocketProvider>
     );
 }
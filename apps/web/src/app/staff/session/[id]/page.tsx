@@ -18,6 +18,7 @@ import { SessionDashboard } from '@/components/session-dashboard';
 import { DragDropContext, Droppable, Draggable, DropResult } from '@hello-pangea/dnd';
 import { toast } from 'sonner';
 import { Breadcrumb } from '@/components/ui/breadcrumb';
+import { ClickerPairingDialog } from '@/components/clicker-pairing-dialog';
 
 function EditorContent({ slides, setSlides, loadSlides, session, loadSession }: { slides: Slide[], setSlides: (slides: Slide[]) => void, loadSlides: () => void, session: Session | null, loadSession: () => void }) {
     const { sendMessage, state, activeParticipants, updateState, initialStateLoaded } = useWebSocket();
@@ -30,6 +31,7 @@ function EditorContent({ slides, setSlides, loadSlides, session, loadSession }:
     const [showDashboard, setShowDashboard] = useState(false);
     const [editTitle, setEditTitle] = useState('');
     const [showShareDialog, setShowShareDialog] = useState(false);
+    const [showClickerDialog, setShowClickerDialog] = useState(false);
 
     // SEPARATE PREVIEW STATE: This is for editor preview only, independent of student view
     const [previewSlideId, setPreviewSlideId] = useState<string | null>(null);
@@ -413,7 +415,7 @@ function EditorContent({ slides, setSlides, loadSlides, session, loadSession }:
                                     <Share2 className="w-4 h-4 text-slate-500" />
                                 </Button>
                             )}
-                            <Button variant="ghost" size="icon" onClick={() => window.open(`/staff/session/${id}/clicker`, '_blank')} title="Mobile Clicker">
+                            <Button variant="ghost" size="icon" onClick={() => setShowClickerDialog(true)} title="Mobile Clicker">
                                 <Smartphone className="w-4 h-4 text-slate-500" />
                             </Button>
                         </div>
@@ -552,6 +554,7 @@ function EditorContent({ slides, setSlides, loadSlides, session, loadSession }:
                     </div>
                 </div>
             )}
+            <ClickerPairingDialog sessionId={id} isOpen={showClickerDialog} onClose={() => setShowClickerDialog(false)} />
             {/* Share Dialog */}
             {showShareDialog && session?.shareToken && (
                 <div className="fixed inset-0 bg-black/60 flex items-center justify-center z-50 p-4 backdrop-blur-sm">
@@ -590,8 +593,11 @@ function EditorContent({ slides, setSlides, loadSlides, session, loadSession }:
                                         <p className="text-xs text-slate-500 mb-2">Students enter this code at:</p>
                                         <p className="text-xs font-mono text-slate-600 mb-3">{window.location.origin}/student/join</p>
                                         <div className="bg-gradient-to-br from-green-100 to-green-50 rounded-lg py-3 px-4 inline-block">
-                                            <p className="text-4xl font-black text-green-700 tracking-widest font-mono">{session.shareToken}</p>
+                                            <p className="text-4xl font-black text-green-700 tracking-widest font-mono">{session.joinCode ?? session.shareToken}</p>
                                         </div>
+                                        {session.joinCode && (
+                                            <p className="text-xs text-slate-500 mt-2">Valid while the session is live</p>
+                                        )}
                                     </div>
                                 </div>
                                 <div className="flex gap-2">
@@ -599,7 +605,7 @@ function EditorContent({ slides, setSlides, loadSlides, session, loadSession }:
                                         variant="outline"
                                         className="flex-1 border-green-300 text-green-700 hover:bg-green-100 hover:border-green-400"
                                         onClick={() => {
-                                            navigator.clipboard.writeText(session.shareToken!);
+                                            navigator.clipboard.writeText(session.joinCode ?? session.shareToken!);
                                             toast.success('Join code copied!');
                                         }}
                                     >
@@ -2,6 +2,7 @@
 
 import { useState } from 'react';
 import { useRouter } from 'next/navigation';
+import { resolveJoinCode } from '@/lib/api';
 import { Button } from '@/components/ui/button';
 import { Card, CardContent, CardHeader, CardTitle, CardDescription } from '@/components/ui/card';
 import { Input } from '@/components/ui/input';
@@ -10,14 +11,33 @@ import { Loader2, ArrowRight } from 'lucide-react';
 export default function StudentJoin() {
     const [code, setCode] = useState('');
     const [isLoading, setIsLoading] = useState(false);
+    const [error, setError] = useState('');
     const router = useRouter();
 
-    function handleJoin(e: React.FormEvent) {
+    async function handleJoin(e: React.FormEvent) {
         e.preventDefault();
-        if (code.trim()) {
-            setIsLoading(true);
-            // Simulate a small delay for better UX or just push
-            router.push(`/student/session/${code.trim()}`);
+        const entered = code.trim().replace(/\s+/g, '');
+        if (!entered) return;
+        setIsLoading(true);
+        setError('');
+
+        // Live sessions have a numeric join code; anything else is a share token
+        if (!/^\d{6,8}$/.test(entered)) {
+            router.push(`/student/session/${entered}`);
+            return;
+        }
+        try {
+            const target = await resolveJoinCode(entered);
+            if (!target.shareToken) throw new Error('This session cannot be joined');
+            router.push(`/student/session/${target.shareToken}`);
+        } catch (err: any) {
+            // Share tokens are 8 hex characters and can happen to be all digits
+            if (entered.length === 8 && err.message === 'No live session uses this code') {
+                router.push(`/student/session/${entered}`);
+                return;
+            }
+            setError(err.message);
+            setIsLoading(false);
         }
     }
 
@@ -35,7 +55,7 @@ export default function StudentJoin() {
                     <form onSubmit={handleJoin} className="space-y-4 mt-4">
                         <div className="space-y-2">
                             <Input
-                                placeholder="e.g. abc-123"
+                                placeholder="e.g. 482913"
                                 value={code}
                                 onChange={(e) => setCode(e.target.value)}
                                 className="text-center text-lg tracking-widest uppercase h-12 border-slate-300 focus:border-blue-500 focus:ring-blue-500"
@@ -43,6 +63,11 @@ export default function StudentJoin() {
                                 disabled={isLoading}
                             />
                         </div>
+                        {error && (
+                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                {error}
+                            </div>
+                        )}
                         <Button
                             type="submit"
                             className="w-full h-12 text-base font-semibold bg-blue-600 hover:bg-blue-700 shadow-lg shadow-blue-600/20 transition-all hover:-translate-y-0.5"
@@ -0,0 +1,59 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import Link from 'next/link';
+import { verifyEmail } from '@/lib/api';
+import { Card, CardHeader, CardTitle, CardContent, CardFooter } from '@/components/ui/card';
+import { Loader2 } from 'lucide-react';
+
+export default function VerifyEmailPage() {
+    const [status, setStatus] = useState<'verifying' | 'verified' | 'failed'>('verifying');
+    const [error, setError] = useState('');
+
+    useEffect(() => {
+        const token = new URLSearchParams(window.location.search).get('token');
+        if (!token) {
+            setStatus('failed');
+            setError('This verification link is incomplete.');
+            return;
+        }
+        verifyEmail(token)
+            .then(() => setStatus('verified'))
+            .catch((err: any) => {
+                setStatus('failed');
+                setError(err.message);
+            });
+    }, []);
+
+    return (
+        <div className="min-h-screen flex items-center justify-center bg-gradient-to-br from-slate-50 via-blue-50 to-slate-50 p-4">
+            <div className="w-full max-w-md relative z-10 animate-scale-in">
+                <Card className="shadow-xl border-slate-200">
+                    <CardHeader className="text-center pb-6 space-y-2">
+                        <CardTitle className="heading-3">Email Verification</CardTitle>
+                    </CardHeader>
+                    <CardContent>
+                        {status === 'verifying' && (
+                            <div className="flex justify-center text-slate-500">
+                                <Loader2 className="mr-2 h-5 w-5 animate-spin" /> Verifying...
+                            </div>
+                        )}
+                        {status === 'verified' && (
+                            <div className="bg-green-50 border border-green-200 text-green-700 px-4 py-3 rounded-lg text-sm">
+                                Your email address is verified.
+                            </div>
+                        )}
+                        {status === 'failed' && (
+                            <div className="bg-red-50 border border-red-200 text-red-700 px-4 py-3 rounded-lg text-sm">
+                                {error}
+                            </div>
+                        )}
+                    </CardContent>
+                    <CardFooter className="justify-center pb-6">
+                        <Link href="/login" className="text-sm text-blue-600 hover:underline">Go to sign in</Link>
+                    </CardFooter>
+                </Card>
+            </div>
+        </div>
+    );
+}
@@ -0,0 +1,120 @@
+'use client';
+
+import { useCallback, useEffect, useState } from 'react';
+import { Copy, Loader2, Smartphone, Trash2 } from 'lucide-react';
+import { toast } from 'sonner';
+import { ClickerDevice, ClickerPairing, createClickerPairing, getClickerDevices, revokeClickerDevice } from '@/lib/api';
+import { Button } from '@/components/ui/button';
+import { Dialog, DialogFooter } from '@/components/ui/dialog';
+
+interface ClickerPairingDialogProps {
+    sessionId: string;
+    isOpen: boolean;
+    onClose: () => void;
+}
+
+export function ClickerPairingDialog({ sessionId, isOpen, onClose }: ClickerPairingDialogProps) {
+    const [pairing, setPairing] = useState<ClickerPairing | null>(null);
+    const [devices, setDevices] = useState<ClickerDevice[]>([]);
+    const [loading, setLoading] = useState(false);
+
+    const loadDevices = useCallback(() => {
+        getClickerDevices(sessionId).then(setDevices).catch((err: any) => toast.error(err.message));
+    }, [sessionId]);
+
+    const startPairing = useCallback(async () => {
+        setLoading(true);
+        try {
+            setPairing(await createClickerPairing(sessionId));
+        } catch (err: any) {
+            toast.error(err.message);
+        } finally {
+            setLoading(false);
+        }
+    }, [sessionId]);
+
+    // New code each time the dialog opens; poll so a phone shows up once it pairs
+    useEffect(() => {
+        if (!isOpen) return;
+        startPairing();
+        loadDevices();
+        const interval = setInterval(loadDevices, 5000);
+        return () => clearInterval(interval);
+    }, [isOpen, startPairing, loadDevices]);
+
+    const handleRevoke = async (deviceId: string) => {
+        try {
+            await revokeClickerDevice(sessionId, deviceId);
+            setDevices((current) => current.filter((device) => device.id !== deviceId));
+            toast.success('Phone unpaired');
+        } catch (err: any) {
+            toast.error(err.message);
+        }
+    };
+
+    return (
+        <Dialog
+            isOpen={isOpen}
+            onClose={onClose}
+            title="Pair a Phone as Clicker"
+            description="Open the link on your phone or enter the code there. The code works once and expires after 5 minutes."
+        >
+            <div className="space-y-6">
+                <div className="bg-slate-50 border border-slate-200 rounded-xl p-4 text-center">
+                    {loading || !pairing ? (
+                        <div className="flex justify-center text-slate-500 py-6">
+                            <Loader2 className="mr-2 h-5 w-5 animate-spin" /> Creating code...
+                        </div>
+                    ) : (
+                        <>
+                            <p className="text-4xl font-black text-slate-800 tracking-widest font-mono">{pairing.code}</p>
+                            <p className="text-xs font-mono text-slate-500 mt-3 break-all select-all">{pairing.pairingUrl}</p>
+                            <div className="flex gap-2 justify-center mt-3">
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    onClick={() => {
+                                        navigator.clipboard.writeText(pairing.pairingUrl);
+                                        toast.success('Pairing link copied!');
+                                    }}
+                                >
+                                    <Copy className="w-4 h-4 mr-2" />
+                                    Copy Link
+                                </Button>
+                                <Button variant="ghost" size="sm" onClick={startPairing}>New Code</Button>
+                            </div>
+                        </>
+                    )}
+                </div>
+
+                <div>
+                    <h3 className="text-sm font-bold text-slate-700 mb-2">Paired phones</h3>
+                    {devices.length === 0 ? (
+                        <p className="text-sm text-slate-500">No phones paired yet.</p>
+                    ) : (
+                        <ul className="space-y-2">
+                            {devices.map((device) => (
+                                <li key={device.id} className="flex items-center justify-between border border-slate-200 rounded-lg px-3 py-2">
+                                    <div className="flex items-center gap-2 text-sm text-slate-700">
+                                        <Smartphone className="w-4 h-4 text-slate-500" />
+                                        <span>
+                                            Paired {new Date(device.pairedAt).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })}
+                                            {device.lastUsedAt && `, last used ${new Date(device.lastUsedAt).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })}`}
+                                        </span>
+                                    </div>
+                                    <Button variant="ghost" size="icon" onClick={() => handleRevoke(device.id)} title="Unpair">
+                                        <Trash2 className="w-4 h-4 text-red-500" />
+                                    </Button>
+                                </li>
+                            ))}
+                        </ul>
+                    )}
+                </div>
+            </div>
+
+            <DialogFooter>
+                <Button onClick={onClose}>Done</Button>
+            </DialogFooter>
+        </Dialog>
+    );
+}
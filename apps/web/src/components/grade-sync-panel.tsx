@@ -0,0 +1,84 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import { GraduationCap, RefreshCw } from 'lucide-react';
+import { Card, CardContent, CardHeader, CardTitle, CardDescription } from '@/components/ui/card';
+import { Button } from '@/components/ui/button';
+import { getGradeSync, retryGradeSync, GradeSyncReport } from '@/lib/api';
+
+const STATUS_STYLES: Record<string, string> = {
+    pending: 'bg-amber-100 text-amber-700',
+    synced: 'bg-green-100 text-green-700',
+    failed: 'bg-red-100 text-red-700',
+};
+
+/** LMS gradebook passback of quiz scores; hidden for sessions without LTI learners */
+export function GradeSyncPanel({ sessionId }: { sessionId: string }) {
+    const [report, setReport] = useState<GradeSyncReport | null>(null);
+    const [retrying, setRetrying] = useState(false);
+
+    useEffect(() => {
+        let cancelled = false;
+        const load = () => getGradeSync(sessionId)
+            .then(r => { if (!cancelled) setReport(r); })
+            .catch(() => {});
+        load();
+        const interval = setInterval(load, 15000);
+        return () => { cancelled = true; clearInterval(interval); };
+    }, [sessionId]);
+
+    if (!report || report.entries.length === 0) return null;
+
+    const handleRetry = async () => {
+        setRetrying(true);
+        try {
+            setReport(await retryGradeSync(sessionId));
+        } catch (error) {
+            console.error('Failed to retry grade sync:', error);
+        } finally {
+            setRetrying(false);
+        }
+    };
+
+    return (
+        <Card>
+            <CardHeader className="flex flex-row items-start justify-between space-y-0">
+                <div>
+                    <CardTitle className="text-lg flex items-center gap-2">
+                        <GraduationCap className="w-5 h-5 text-slate-600" />
+                        LMS Gradebook
+                    </CardTitle>
+                    <CardDescription>
+                        {report.synced} synced, {report.pending} pending, {report.failed} failed
+                    </CardDescription>
+                </div>
+                {report.failed > 0 && (
+                    <Button variant="outline" size="sm" onClick={handleRetry} disabled={retrying}>
+                        <RefreshCw className={`w-4 h-4 mr-2 ${retrying ? 'animate-spin' : ''}`} />
+                        Retry failed
+                    </Button>
+                )}
+            </CardHeader>
+            <CardContent>
+                <ul className="divide-y divide-slate-100">
+                    {report.entries.map(entry => (
+                        <li key={entry.participantId} className="flex items-center justify-between py-2 text-sm">
+                            <div>
+                                <span className="font-medium text-slate-900">{entry.participantName}</span>
+                                {entry.lastError && entry.status !== 'synced' && (
+                                    <p className="text-xs text-red-600">{entry.lastError}</p>
+                                )}
+                            </div>
+                            <div className="flex items-center gap-3">
+                                <span className="text-slate-600">{entry.scoreGiven} / {entry.scoreMaximum}</span>
+                                <span className={`px-2 py-0.5 rounded-full text-xs font-medium ${STATUS_STYLES[entry.status] ?? ''}`}>
+                                    {entry.status}
+                                </span>
+                            </div>
+                        </li>
+                    ))}
+                </ul>
+            </CardContent>
+        </Card>
+    );
+}
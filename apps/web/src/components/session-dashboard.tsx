@@ -8,6 +8,7 @@ import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import dynamic from 'next/dynamic';
 import { Button } from '@/components/ui/button';
 import { LoadingState, EmptyState } from '@/components/ui/loading';
+import { GradeSyncPanel } from '@/components/grade-sync-panel';
 
 // Dynamic imports for Recharts - only loaded when dashboard is viewed
 const BarChart = dynamic(() => import('recharts').then(mod => mod.BarChart), { ssr: false });
@@ -36,7 +37,9 @@ interface SlideStats {
 }
 
 interface Stats {
-    participants: { id: string; name: string; joinedAt: string }[];
+    participants: { id: string; name: string; joinedAt: string; connectedSeconds?: number; isConnected?: boolean }[];
+    // Attendance is only returned to session collaborators
+    connectedCount?: number;
     slides: SlideStats[];
     questions: { id: string; content: string; upvotes: number; author: string; createdAt: string; slideId?: string }[];
 }
@@ -262,6 +265,8 @@ export function SessionDashboard({ sessionId, isPublic = false }: { sessionId: s
                 </Card>
             </div>
 
+            {!isPublic && <GradeSyncPanel sessionId={sessionId} />}
+
             {/* Main Content */}
             <Tabs defaultValue="questions" className="w-full">
                 <TabsList className="grid w-full grid-cols-3 mb-6">
@@ -734,7 +739,10 @@ export function SessionDashboard({ sessionId, isPublic = false }: { sessionId: s
                                 <Users className="w-5 h-5" />
                                 Participants ({stats.participants.length})
                             </CardTitle>
-                            <CardDescription>All students who have joined this session</CardDescription>
+                            <CardDescription>
+                                All students who have joined this session
+                                {stats.connectedCount !== undefined && ` (${stats.connectedCount} connected now)`}
+                            </CardDescription>
                         </CardHeader>
                         <CardContent>
                             {stats.participants.length === 0 ? (
@@ -755,8 +763,12 @@ export function SessionDashboard({ sessionId, isPublic = false }: { sessionId: s
                                                 <p className="text-xs text-slate-500 flex items-center gap-1">
                                                     <Clock className="w-3 h-3" />
                                                     Joined {new Date(p.joinedAt).toLocaleString()}
+                                                    {p.connectedSeconds !== undefined && ` · ${Math.round(p.connectedSeconds / 60)} min connected`}
                                                 </p>
                                             </div>
+                                            {p.isConnected && (
+                                                <span className="w-2 h-2 rounded-full bg-green-500" title="Connected" />
+                                            )}
                                         </div>
                                     ))}
                                 </div>
@@ -1,4 +1,4 @@
-import { ApiResponse, Session, Slide } from 'shared';
+import { ApiResponse, Session, SessionRole, Slide, User } from 'shared';
 
 const API_URL = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:8080/api';
 
@@ -13,10 +13,44 @@ async function sleep(ms: number) {
     return new Promise(resolve => setTimeout(resolve, ms));
 }
 
+// Single in-flight refresh shared by concurrent requests
+let refreshPromise: Promise<boolean> | null = null;
+
+// Exchange the http-only refresh cookie for a new access token
+async function refreshAccessToken(): Promise<boolean> {
+    if (!refreshPromise) {
+        refreshPromise = (async () => {
+            try {
+                const res = await fetch(`${API_URL}/auth/refresh`, {
+                    method: 'POST',
+                    credentials: 'include',
+                });
+                if (!res.ok) return false;
+                const json = await res.json();
+                if (!json.success || !json.token) return false;
+                localStorage.setItem('token', json.token);
+                localStorage.setItem('user', JSON.stringify(json.user));
+                return true;
+            } catch {
+                return false;
+            } finally {
+                refreshPromise = null;
+            }
+        })();
+    }
+    return refreshPromise;
+}
+
+function hasBearerToken(headers: HeadersInit | undefined): boolean {
+    return !!headers && !Array.isArray(headers) && !(headers instanceof Headers)
+        && typeof (headers as Record<string, string>)['Authorization'] === 'string';
+}
+
 async function fetchWithRetry(
     url: string,
     options: RequestInit = {},
-    retries = RETRY_CONFIG.maxRetries
+    retries = RETRY_CONFIG.maxRetries,
+    allowRefresh = true
 ): Promise<Response> {
     let lastError: Error | null = null;
     
@@ -52,6 +86,13 @@ async function fetchWithRetry(
                 await sleep(delay);
                 continue;
             }
+
+            // Access tokens are short-lived: refresh once and replay the request
+            if (response.status === 401 && allowRefresh && hasBearerToken(options.headers)) {
+                if (await refreshAccessToken()) {
+                    return fetchWithRetry(url, { ...options, headers: getHeaders() }, retries, false);
+                }
+            }
             
             return response;
         } catch (error: unknown) {
@@ -131,19 +172,111 @@ export async function getSharedSession(token: string, options?: { signal?: Abort
     return json.data;
 }
 
+export type JoinTarget = { sessionId: string; title: string; shareToken?: string | null };
+
+// Numeric codes of live sessions; lookups are rate limited by the server
+export async function resolveJoinCode(code: string): Promise<JoinTarget> {
+    const res = await fetchWithRetry(`${API_URL}/join/${encodeURIComponent(code)}`, { method: 'GET' }, 1);
+
+    if (res.status === 404) throw new Error('No live session uses this code');
+    if (res.status === 429) throw new Error('Too many attempts. Wait a moment and try again');
+    if (!res.ok) throw new Error('Failed to look up the code');
+
+    const json: ApiResponse<JoinTarget> = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to look up the code');
+    return json.data;
+}
+
 export async function login(email: string, password: string) {
     const res = await fetchWithRetry(`${API_URL}/auth/login`, {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ email, password }),
+        credentials: 'include', // receive the refresh token cookie
     });
     const json = await res.json();
     if (!json.success) throw new Error(json.error || 'Login failed');
+    // Accounts with 2FA get a challenge; finish with loginWithMfa
+    if (json.mfaRequired) return json as { mfaRequired: true; mfaToken: string };
+    localStorage.setItem('token', json.token);
+    localStorage.setItem('user', JSON.stringify(json.user));
+    return json;
+}
+
+// Second login step: authenticator or recovery code for the challenge from login
+export async function loginWithMfa(mfaToken: string, code: string) {
+    const res = await fetchWithRetry(`${API_URL}/auth/login/mfa`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ mfaToken, code }),
+        credentials: 'include',
+    });
+    const json = await res.json();
+    if (!json.success) throw new Error(json.error || 'Verification failed');
     localStorage.setItem('token', json.token);
     localStorage.setItem('user', JSON.stringify(json.user));
     return json;
 }
 
+export type SsoProvider = { id: string; displayName: string };
+
+export async function getSsoProviders(): Promise<SsoProvider[]> {
+    const res = await fetchWithRetry(`${API_URL}/auth/oidc/providers`);
+    const json: ApiResponse<SsoProvider[]> = await res.json();
+    return json.success ? json.data : [];
+}
+
+// Full-page navigation; the backend redirects to the provider and back to /login/sso
+export function ssoLoginUrl(providerId: string) {
+    return `${API_URL}/auth/oidc/${encodeURIComponent(providerId)}/login`;
+}
+
+// Same round trip as sign-in, but links the provider account to the signed-in user
+export function ssoLinkUrl(providerId: string) {
+    return `${API_URL}/auth/oidc/${encodeURIComponent(providerId)}/link`;
+}
+
+// After single sign-on the login cookies are set; trade the refresh cookie for a token
+export async function completeSsoLogin(): Promise<boolean> {
+    return refreshAccessToken();
+}
+
+export type DeepLinkSession = { id: string; title: string; status: string };
+
+// LTI deep linking: the signed context from the launch authorizes these calls
+export async function getDeepLinkSessions(context: string): Promise<DeepLinkSession[]> {
+    const res = await fetchWithRetry(`${API_URL}/lti/deep-link/sessions`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ context }),
+    });
+    const json: ApiResponse<DeepLinkSession[]> = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to load sessions');
+    return json.data;
+}
+
+// Link the course account of an instructor launch to the signed-in user
+export async function linkLtiAccount(link: string): Promise<void> {
+    const res = await fetchWithRetry(`${API_URL}/lti/link`, {
+        method: 'POST',
+        headers: getHeaders(),
+        body: JSON.stringify({ link }),
+    });
+    const json: ApiResponse<null> = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to link account');
+}
+
+export async function createDeepLink(context: string, sessionId: string): Promise<{ returnUrl: string; jwt: string }> {
+    const res = await fetchWithRetry(`${API_URL}/lti/deep-link`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ context, sessionId }),
+    });
+    const json: ApiResponse<{ returnUrl: string; jwt: string }> = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to add session to course');
+    return json.data;
+}
+
 export async function register(email: string, password: string, name: string, role: string) {
     const res = await fetchWithRetry(`${API_URL}/auth/register`, {
         method: 'POST',
@@ -155,12 +288,141 @@ export async function register(email: string, password: string, name: string, ro
     return json;
 }
 
+async function postAuth(path: string, body: unknown, fallbackError: string) {
+    const res = await fetchWithRetry(`${API_URL}/auth/${path}`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body),
+    });
+    const json = await res.json();
+    if (!json.success) throw new Error(json.error || fallbackError);
+    return json;
+}
+
+export async function forgotPassword(email: string) {
+    return postAuth('forgot-password', { email }, 'Failed to request password reset');
+}
+
+export async function resetPassword(token: string, password: string) {
+    return postAuth('reset-password', { token, password }, 'Failed to reset password');
+}
+
+export async function verifyEmail(token: string) {
+    return postAuth('verify-email', { token }, 'Failed to verify email');
+}
+
 export function logout() {
+    // Revoke this device's session server-side; local state is cleared regardless
+    fetch(`${API_URL}/auth/logout`, {
+        method: 'POST',
+        headers: getHeaders(),
+        credentials: 'include',
+        keepalive: true,
+    }).catch(() => {});
+    localStorage.removeItem('token');
+    localStorage.removeItem('user');
+    window.location.href = '/login';
+}
+
+export async function logoutAllDevices() {
+    const res = await fetchWithRetry(`${API_URL}/auth/logout-all`, {
+        method: 'POST',
+        headers: getHeaders(),
+        credentials: 'include',
+    });
+    const json = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to log out all devices');
     localStorage.removeItem('token');
     localStorage.removeItem('user');
     window.location.href = '/login';
 }
 
+// Current account, to restore login state; also refreshes the cached user
+export async function getMe(): Promise<User> {
+    const res = await fetchWithRetry(`${API_URL}/me`, { headers: getHeaders(), credentials: 'include' });
+    const json: ApiResponse<User> = await res.json();
+    if (!json.success) throw new Error(json.error || 'Not logged in');
+    localStorage.setItem('user', JSON.stringify(json.data));
+    return json.data;
+}
+
+// A new email needs the current password and must be verified again
+export async function updateMe(changes: { name?: string; email?: string; currentPassword?: string }): Promise<User> {
+    const res = await fetchWithRetry(`${API_URL}/me`, {
+        method: 'PUT',
+        headers: getHeaders(),
+        credentials: 'include',
+        body: JSON.stringify(changes),
+    });
+    const json: ApiResponse<User> = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to update profile');
+    localStorage.setItem('user', JSON.stringify(json.data));
+    return json.data;
+}
+
+// Other devices are logged out; this one stays signed in
+export async function changePassword(currentPassword: string, newPassword: string) {
+    const res = await fetchWithRetry(`${API_URL}/me/password`, {
+        method: 'POST',
+        headers: getHeaders(),
+        credentials: 'include',
+        body: JSON.stringify({ currentPassword, newPassword }),
+    });
+    const json = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to change password');
+    return json;
+}
+
+// Deletes the account with all its sessions; cannot be undone
+export async function deleteAccount(password: string) {
+    const res = await fetchWithRetry(`${API_URL}/me`, {
+        method: 'DELETE',
+        headers: getHeaders(),
+        credentials: 'include',
+        body: JSON.stringify({ password }),
+    });
+    const json = await res.json();
+    if (!json.success) throw new Error(json.error || 'Failed to delete account');
+    localStorage.removeItem('token');
+    localStorage.removeItem('user');
+    window.location.href = '/login';
+}
+
+export type MfaStatus = { enabled: boolean; recoveryCodesRemaining: number };
+
+async function mfaRequest<T>(path: string, method: string, body: unknown, fallbackError: string): Promise<T> {
+    const res = await fetchWithRetry(`${API_URL}/me/mfa${path}`, {
+        method,
+        headers: getHeaders(),
+        credentials: 'include',
+        body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    const json: ApiResponse<T> = await res.json();
+    if (!json.success) throw new Error(json.error || fallbackError);
+    return json.data;
+}
+
+export async function getMfaStatus(): Promise<MfaStatus> {
+    return mfaRequest('', 'GET', undefined, 'Failed to load two-factor status');
+}
+
+// Staff only; show provisioningUri as a QR code, then confirm with a first code
+export async function startTotpEnrollment(): Promise<{ secret: string; provisioningUri: string }> {
+    return mfaRequest('/totp', 'POST', undefined, 'Failed to start two-factor setup');
+}
+
+export async function confirmTotpEnrollment(code: string): Promise<{ recoveryCodes: string[] }> {
+    return mfaRequest('/totp/confirm', 'POST', { code }, 'Invalid authentication code');
+}
+
+export async function regenerateRecoveryCodes(code: string): Promise<{ recoveryCodes: string[] }> {
+    return mfaRequest('/recovery-codes', 'POST', { code }, 'Failed to regenerate recovery codes');
+}
+
+export async function disableMfa(password: string, code: string): Promise<void> {
+    await mfaRequest('', 'DELETE', { password, code }, 'Failed to disable two-factor authentication');
+}
+
 export async function getSessions(status?: string): Promise<Session[]> {
     const url = status ? `${API_URL}/sessions?status=${status}` : `${API_URL}/sessions`;
     const res = await fetchWithRetry(url, { headers: getHeaders() });
@@ -209,11 +471,11 @@ export async function restoreSession(sessionId: string): Promise<void> {
     if (!json.success) throw new Error(json.error || 'Failed to restore session');
 }
 
-export async function createSession(title: string, allowQuestions = false, requireName = false): Promise<Session> {
+export async function createSession(title: string, allowQuestions = false, requireName = false, organizationId?: string): Promise<Session> {
     const res = await fetchWithRetry(`${API_URL}/sessions`, {
         method: 'POST',
         headers: getHeaders(),
-        body: JSON.stringify({ title, allowQuestions, requireName }),
+        body: JSON.stringify({ title, allowQuestions, requireName, organizationId }),
     });
     if (res.status === 401) { logout(); throw new Error('Unauthorized'); }
     const json: ApiResponse<Session> = await res.json();
@@ -325,42 +587,216 @@ export async function stopSession(sessionId: string): Promise<void> {
     if (!json.success) throw new Error(json.error || 'Failed to stop session');
 }
 
-// ============ Public Clicker API (no auth required) ============
+export type GradeSyncEntry = {
+    participantId: string;
+    participantName: string;
+    scoreGiven: number;
+    scoreMaximum: number;
+    status: 'pending' | 'synced' | 'failed';
+    attempts: number;
+    lastError?: string | null;
+    nextAttemptAt?: string | null;
+    syncedAt?: string | null;
+};
+
+export type GradeSyncReport = { pending: number; synced: number; failed: number; entries: GradeSyncEntry[] };
+
+export async function getGradeSync(sessionId: string): Promise<GradeSyncReport> {
+    const res = await fetchWithRetry(`${API_URL}/sessions/${sessionId}/stats/grade-sync`, { headers: getHeaders() });
+    if (res.status === 401) { logout(); throw new Error('Unauthorized'); }
+    if (!res.ok) throw new Error('Failed to load grade sync status');
+    return res.json();
+}
+
+export async function retryGradeSync(sessionId: string): Promise<GradeSyncReport> {
+    const res = await fetchWithRetry(`${API_URL}/sessions/${sessionId}/stats/grade-sync/retry`, {
+        method: 'POST',
+        headers: getHeaders(),
+    });
+    if (res.status === 401) { logout(); throw new Error('Unauthorized'); }
+    if (!res.ok) throw new Error('Failed to retry grade sync');
+    return res.json();
+}
+
+export type SessionMember = {
+    userId: string;
+    name: string;
+    email: string;
+    role: SessionRole;
+    isCreator: boolean;
+    createdAt?: string | null;
+};
+
+export type SessionInvite = { id: string; email: string; role: SessionRole; expiresAt: string; createdAt: string };
+
+async function membersRequest<T>(path: string, method: string, body: unknown, fallbackError: string): Promise<T> {
+    const res = await fetchWithRetry(`${API_URL}${path}`, {
+        method,
+        headers: getHeaders(),
+        credentials: 'include',
+        body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    if (res.status === 401) { logout(); throw new Error('Unauthorized'); }
+    const json: ApiResponse<T> = await res.json();
+    if (!json.success) throw new Error(json.error || fallbackError);
+    return json.data;
+}
+
+export async function getSessionMembers(sessionId: string): Promise<{ members: SessionMember[]; invites: SessionInvite[] }> {
+    return membersRequest(`/sessions/${sessionId}/members`, 'GET', undefined, 'Failed to load collaborators');
+}
+
+// Owners only; the invitee receives an email link to /invites/accept
+export async function inviteSessionMember(sessionId: string, email: string, role: SessionRole): Promise<SessionInvite> {
+    return membersRequest(`/sessions/${sessionId}/members`, 'POST', { email, role }, 'Failed to send invitation');
+}
+
+export async function acceptSessionInvite(token: string): Promise<{ sessionId: string; role: SessionRole }> {
+    return membersRequest('/session-invites/accept', 'POST', { token }, 'Invalid or expired invitation');
+}
+
+// Pass your own user id to leave a shared session
+export async function removeSessionMember(sessionId: string, userId: string): Promise<void> {
+    await membersRequest(`/sessions/${sessionId}/members/${userId}`, 'DELETE', undefined, 'Failed to remove collaborator');
+}
+
+export async function revokeSessionInvite(sessionId: string, inviteId: string): Promise<void> {
+    await membersRequest(`/sessions/${sessionId}/invites/${inviteId}`, 'DELETE', undefined, 'Failed to withdraw invitation');
+}
+
+export type OrgRole = 'admin' | 'member';
 
-export async function publicSetCurrentSlide(sessionId: string, slideId: string | null): Promise<void> {
+export type Organization = { id: string; name: string; createdBy: string; createdAt?: string | null };
+
+export type OrganizationMember = { userId: string; name: string; email: string; role: OrgRole; createdAt?: string | null };
+
+export async function getOrganizations(): Promise<(Organization & { role: OrgRole })[]> {
+    return membersRequest('/organizations', 'GET', undefined, 'Failed to load organizations');
+}
+
+export async function createOrganization(name: string): Promise<Organization> {
+    return membersRequest('/organizations', 'POST', { name }, 'Failed to create organization');
+}
+
+export async function renameOrganization(organizationId: string, name: string): Promise<Organization> {
+    return membersRequest(`/organizations/${organizationId}`, 'PUT', { name }, 'Failed to rename organization');
+}
+
+export async function getOrganizationSessions(organizationId: string): Promise<Session[]> {
+    return membersRequest(`/organizations/${organizationId}/sessions`, 'GET', undefined, 'Failed to load sessions');
+}
+
+export async function getOrganizationMembers(organizationId: string): Promise<OrganizationMember[]> {
+    return membersRequest(`/organizations/${organizationId}/members`, 'GET', undefined, 'Failed to load members');
+}
+
+// Admins only; the email must belong to an existing account
+export async function addOrganizationMember(organizationId: string, email: string, role: OrgRole): Promise<OrganizationMember> {
+    return membersRequest(`/organizations/${organizationId}/members`, 'POST', { email, role }, 'Failed to add member');
+}
+
+export async function setOrganizationMemberRole(organizationId: string, userId: string, role: OrgRole): Promise<OrganizationMember> {
+    return membersRequest(`/organizations/${organizationId}/members/${userId}`, 'PUT', { role }, 'Failed to change role');
+}
+
+// Pass your own user id to leave the organization
+export async function removeOrganizationMember(organizationId: string, userId: string): Promise<void> {
+    await membersRequest(`/organizations/${organizationId}/members/${userId}`, 'DELETE', undefined, 'Failed to remove member');
+}
+
+// Admins only; omit sessionId to transfer all of the user's organization sessions
+export async function transferOrganizationSessions(organizationId: string, fromUserId: string, toUserId: string, sessionId?: string): Promise<{ transferred: number }> {
+    return membersRequest(`/organizations/${organizationId}/transfer-sessions`, 'POST', { fromUserId, toUserId, sessionId }, 'Failed to transfer sessions');
+}
+
+// null takes the session out of its organization
+export async function setSessionOrganization(sessionId: string, organizationId: string | null): Promise<void> {
+    await membersRequest(`/sessions/${sessionId}/organization`, 'PUT', { organizationId }, 'Failed to move session');
+}
+
+// ============ Remote Clicker ============
+
+export type ClickerPairing = { id: string; code: string; expiresAt: string; pairingUrl: string };
+
+export type PairedClicker = { sessionId: string; token: string; shareToken?: string | null; expiresAt: string };
+
+export type ClickerDevice = { id: string; createdBy: string; pairedAt: string; expiresAt: string; lastUsedAt?: string | null };
+
+// Presenters only; the phone opens pairingUrl (or enters the code) within 5 minutes
+export async function createClickerPairing(sessionId: string): Promise<ClickerPairing> {
+    return membersRequest(`/sessions/${sessionId}/clicker/pairings`, 'POST', undefined, 'Failed to start pairing');
+}
+
+export async function getClickerDevices(sessionId: string): Promise<ClickerDevice[]> {
+    return membersRequest(`/sessions/${sessionId}/clicker/devices`, 'GET', undefined, 'Failed to load paired phones');
+}
+
+export async function revokeClickerDevice(sessionId: string, deviceId: string): Promise<void> {
+    await membersRequest(`/sessions/${sessionId}/clicker/devices/${deviceId}`, 'DELETE', undefined, 'Failed to unpair phone');
+}
+
+const clickerStorageKey = (sessionId: string) => `clicker:${sessionId}`;
+
+// Runs on the phone without a login; the clicker token is kept per session
+export async function pairClicker(code: string): Promise<PairedClicker> {
+    const res = await fetchWithRetry(`${API_URL}/clicker/pair`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ code }),
+    }, 2, false);
+    const json: ApiResponse<PairedClicker> = await res.json();
+    if (!json.success) throw new Error(json.error || 'Invalid or expired pairing code');
+    localStorage.setItem(clickerStorageKey(json.data.sessionId), JSON.stringify(json.data));
+    return json.data;
+}
+
+export function getStoredClicker(sessionId: string): PairedClicker | null {
+    const stored = localStorage.getItem(clickerStorageKey(sessionId));
+    if (!stored) return null;
     try {
-        const res = await fetchWithRetry(`${API_URL}/sessions/${sessionId}/clicker/slide`, {
-            method: 'PUT',
-            headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify({ slideId }),
-        }, 2); // Fewer retries for non-critical
-        if (!res.ok) return;
-        const text = await res.text();
-        if (!text) return;
-        const json: ApiResponse<void> = JSON.parse(text);
-        if (!json.success) console.error(json.error || 'Failed to set slide');
-    } catch (e) {
-        console.error('Error setting slide:', e);
+        const clicker: PairedClicker = JSON.parse(stored);
+        if (new Date(clicker.expiresAt) > new Date()) return clicker;
+    } catch {
+        // Corrupt entry; pair again
     }
+    localStorage.removeItem(clickerStorageKey(sessionId));
+    return null;
+}
+
+export function forgetStoredClicker(sessionId: string): void {
+    localStorage.removeItem(clickerStorageKey(sessionId));
 }
 
-export async function publicSetResultsVisibility(sessionId: string, visible: boolean): Promise<void> {
+// Returns false when the clicker token was rejected (unpaired or expired)
+async function clickerRequest(sessionId: string, path: string, body: unknown, clickerToken: string, fallbackError: string): Promise<boolean> {
     try {
-        const res = await fetchWithRetry(`${API_URL}/sessions/${sessionId}/clicker/results`, {
+        const res = await fetchWithRetry(`${API_URL}/sessions/${sessionId}/clicker/${path}`, {
             method: 'PUT',
-            headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify({ visible }),
-        }, 2);
-        if (!res.ok) return;
+            headers: { 'Content-Type': 'application/json', 'Authorization': `Bearer ${clickerToken}` },
+            body: JSON.stringify(body),
+        }, 2, false); // Fewer retries for non-critical
+        if (res.status === 401) return false;
+        if (!res.ok) return true;
         const text = await res.text();
-        if (!text) return;
+        if (!text) return true;
         const json: ApiResponse<void> = JSON.parse(text);
-        if (!json.success) console.error(json.error || 'Failed to set results visibility');
+        if (!json.success) console.error(json.error || fallbackError);
     } catch (e) {
-        console.error('Error setting results visibility:', e);
+        console.error(`${fallbackError}:`, e);
     }
+    return true;
+}
+
+export async function publicSetCurrentSlide(sessionId: string, slideId: string | null, clickerToken: string): Promise<boolean> {
+    return clickerRequest(sessionId, 'slide', { slideId }, clickerToken, 'Failed to set slide');
 }
 
+export async function publicSetResultsVisibility(sessionId: string, visible: boolean, clickerToken: string): Promise<boolean> {
+    return clickerRequest(sessionId, 'results', { visible }, clickerToken, 'Failed to set results visibility');
+}
+
+// ============ Public Session API (no auth required) ============
+
 export async function publicGetSlides(sessionId: string): Promise<Slide[]> {
     try {
         const res = await fetchWithRetry(`${API_URL}/sessions/${sessionId}/state`);
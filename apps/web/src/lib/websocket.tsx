@@ -38,6 +38,16 @@ const ELECTION_RANDOM_DELAY = 400;
 const leaderStatus = new Map<string, boolean>();
 const broadcastChannels = new Map<string, BroadcastChannel>();
 
+// Apply a Q&A delta, keeping the server's order (most upvoted, then newest first)
+function upsertQuestion(questions: any[], question: any): any[] {
+    const next = questions.filter(q => q.id !== question.id);
+    next.push(question);
+    return next.sort((a, b) =>
+        (b.upvotes ?? 0) - (a.upvotes ?? 0) ||
+        new Date(b.createdAt ?? 0).getTime() - new Date(a.createdAt ?? 0).getTime()
+    );
+}
+
 interface WebSocketContextType {
     isConnected: boolean;
     isConnecting: boolean;
@@ -64,12 +74,15 @@ export function WebSocketProvider({
     children,
     sessionId,
     role,
-    name
+    name,
+    shareToken
 }: {
     children: React.ReactNode;
     sessionId: string;
     role: 'staff' | 'student' | 'projector';
     name?: string;
+    // Share token, for projector views opened without a staff login
+    shareToken?: string;
 }) {
     const [isConnected, setIsConnected] = useState(false);
     const [isConnecting, setIsConnecting] = useState(true);
@@ -208,12 +221,38 @@ export function WebSocketProvider({
             if (payload.payload?.questions) {
                 setQuestions(payload.payload.questions);
             }
+        } else if (
+            messageName === 'QUESTION_ADDED' ||
+            messageName === 'QUESTION_UPVOTED' ||
+            messageName === 'QUESTION_UPDATED'
+        ) {
+            const question = payload.payload?.question;
+            if (question) {
+                setQuestions(prev => upsertQuestion(prev, question));
+            }
+        } else if (messageName === 'QUESTION_MODERATED') {
+            // Staff channel: the question with its moderation state
+            const question = payload.payload?.question;
+            if (question) {
+                setQuestions(prev => upsertQuestion(prev, question));
+            }
+        } else if (messageName === 'QUESTION_HIDDEN') {
+            // Students drop hidden questions; staff keep them via QUESTION_MODERATED
+            const questionId = payload.payload?.questionId;
+            if (questionId && role !== 'staff') {
+                setQuestions(prev => prev.filter(q => q.id !== questionId));
+            }
+        } else if (messageName === 'QUESTION_REMOVED') {
+            const questionId = payload.payload?.questionId;
+            if (questionId) {
+                setQuestions(prev => prev.filter(q => q.id !== questionId));
+            }
         } else if (messageName === 'PARTICIPANT_COUNT_UPDATE') {
             setActiveParticipants(payload.count || 0);
         } else if (messageName === 'SLIDES_UPDATE') {
             setLastSlideUpdate(Date.now());
         }
-    }, []);
+    }, [role]);
 
     // Process buffered messages after failover
     const processBufferedMessages = useCallback(() => {
@@ -239,6 +278,33 @@ export function WebSocketProvider({
         let bc: BroadcastChannel | null = null;
         const channelName = `ably-session-${sessionId}-${role}`;
 
+        // Students register once per mount; the server returns a signed
+        // participant token that the Ably token endpoint requires
+        let participantTokenPromise: Promise<string | null> | null = null;
+        const ensureParticipantToken = (): Promise<string | null> => {
+            if (participantTokenPromise) return participantTokenPromise;
+            const apiBase = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:8080/api';
+            const tokenKey = `studentParticipantToken_${sessionId}`;
+            participantTokenPromise = fetch(`${apiBase}/sessions/${sessionId}/register-participant`, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({
+                    participantId: participantIdRef.current,
+                    name: name?.trim() || '',
+                    participantToken: localStorage.getItem(tokenKey) || undefined
+                })
+            })
+                .then(async res => {
+                    if (!res.ok) return null;
+                    const body = await res.json();
+                    const token = body.data?.participantToken ?? null;
+                    if (token) localStorage.setItem(tokenKey, token);
+                    return token;
+                })
+                .catch(() => null);
+            return participantTokenPromise;
+        };
+
         // Track when we last heard from a leader and their priority
         let lastLeaderTimestamp = 0;
         let currentLeaderSince = 0;
@@ -291,19 +357,9 @@ export function WebSocketProvider({
                     console.error('[DEBUG] Failed to fetch previous votes:', e);
                 }
                 
-                // Only register participant if they have a name
-                // When requireName is true, students must provide a name before joining
-                // When requireName is false, we still register them but only if they provided a name
-                if (name && name.trim()) {
-                    fetch(`${apiBase}/sessions/${sessionId}/register-participant`, {
-                        method: 'POST',
-                        headers: { 'Content-Type': 'application/json' },
-                        body: JSON.stringify({
-                            participantId: participantIdRef.current,
-                            name: name.trim()
-                        })
-                    }).catch(() => { });
-                }
+                // Register (named or anonymous) and obtain the participant token
+                // The server only stores participants who provided a name
+                ensureParticipantToken();
             }
         };
 
@@ -321,8 +377,29 @@ export function WebSocketProvider({
             console.log(`Creating Ably connection for ${role} with participantId: ${participantId}`);
 
             client = new Ably.Realtime({
-                authUrl: `${apiBase}/auth/ably?sessionId=${sessionId}&role=${role}&participantId=${participantId}`,
-                authMethod: 'GET',
+                authCallback: async (_tokenParams, callback) => {
+                    try {
+                        const query = new URLSearchParams({ sessionId, role, participantId });
+                        if (role === 'student') {
+                            const participantToken = await ensureParticipantToken();
+                            if (participantToken) query.set('participantToken', participantToken);
+                        }
+                        if (shareToken) query.set('shareToken', shareToken);
+
+                        const headers: Record<string, string> = {};
+                        const authToken = role !== 'student' ? localStorage.getItem('token') : null;
+                        if (authToken) headers['Authorization'] = `Bearer ${authToken}`;
+
+                        const res = await fetch(`${apiBase}/auth/ably?${query}`, {
+                            headers,
+                            credentials: 'include'
+                        });
+                        if (!res.ok) throw new Error(`Ably token request rejected (${res.status})`);
+                        callback(null, await res.json());
+                    } catch (e) {
+                        callback(e instanceof Error ? e.message : String(e), null);
+                    }
+                },
                 disconnectedRetryTimeout: 5000,
                 suspendedRetryTimeout: 10000,
             });
@@ -381,6 +458,23 @@ export function WebSocketProvider({
                 }
             });
 
+            // Presenter-only events (moderation, per-student answers)
+            if (role === 'staff') {
+                const staffChannel = client.channels.get(`session:${sessionId}:staff`);
+                staffChannel.subscribe((message) => {
+                    handleAblyMessage(message.name || '', message.data);
+
+                    if (bc && isLeaderRef.current) {
+                        bc.postMessage({
+                            type: 'ABLY_MESSAGE',
+                            sessionId,
+                            message: { name: message.name, data: message.data },
+                            timestamp: Date.now()
+                        });
+                    }
+                });
+            }
+
             fetchInitialState();
         };
 
@@ -630,7 +724,7 @@ export function WebSocketProvider({
             ablyClientRef.current = null;
             bcRef.current = null;
         };
-    }, [sessionId, role, name, handleAblyMessage, processBufferedMessages]);
+    }, [sessionId, role, name, shareToken, handleAblyMessage, processBufferedMessages]);
 
     const sendMessage = async (type: string, payload: any) => {
         const apiBase = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:8080/api';
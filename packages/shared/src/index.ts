@@ -86,17 +86,22 @@ export const SlideSchema = z.object({
 
 export const SessionStatusSchema = z.enum(['draft', 'published', 'archived']);
 
+export const SessionRoleSchema = z.enum(['viewer', 'presenter', 'editor', 'owner']);
+
 export const SessionSchema = z.object({
     id: z.string(),
     title: z.string(),
+    organizationId: z.string().nullable().optional(),
     status: SessionStatusSchema,
     shareToken: z.string().optional(),
+    joinCode: z.string().nullable().optional(), // Only while the session is live
     allowQuestions: z.boolean().optional(),
     requireName: z.boolean().optional(),
     isPresentationActive: z.boolean().optional(),
     createdAt: z.string(), // ISO date
     updatedAt: z.string(),
     slideCount: z.number().optional(), // Included in list view
+    role: SessionRoleSchema.optional(), // Caller's role, included in list view
 });
 
 export const ParticipantSchema = z.object({
@@ -127,6 +132,7 @@ export const QuestionSchema = z.object({
 
 export type Slide = z.infer<typeof SlideSchema>;
 export type Session = z.infer<typeof SessionSchema>;
+export type SessionRole = z.infer<typeof SessionRoleSchema>;
 export type Participant = z.infer<typeof ParticipantSchema>;
 export type Interaction = z.infer<typeof InteractionSchema>;
 export type Question = z.infer<typeof QuestionSchema>;